@@ -0,0 +1,38 @@
+// A minimal formatter: reads a document from the file given as argument
+// (or stdin), checks it and prints it back in the canonical pretty style.
+//
+//     cargo run --example fmt -- config.zmr
+
+use std::error::Error;
+use std::io::Read;
+use zmerald::fmt::format_str;
+use zmerald::ser::PrettyConfig;
+
+fn format(source: &str, config: PrettyConfig) -> Result<String, Box<dyn Error>> {
+    Ok(format_str(source, &config)?)
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut source = String::new();
+    match std::env::args().nth(1) {
+        Some(path) => source = std::fs::read_to_string(path)?,
+        None => {
+            std::io::stdin().read_to_string(&mut source)?;
+        }
+    }
+
+    let config = PrettyConfig::new().indentor(String::from("  "));
+    println!("{}", format(&source, config)?);
+    Ok(())
+}
+
+#[test]
+fn test_format() {
+    let source = r#"{"name":"x","list":[1,2]}"#;
+    let formatted = format(source, PrettyConfig::new()).unwrap();
+    assert_eq!(formatted, "{\n    \"name\": \"x\",\n    \"list\": [\n        1,\n        2,\n    ],\n}");
+
+    // formatting is idempotent
+    assert_eq!(format(&formatted, PrettyConfig::new()).unwrap(), formatted);
+    assert!(format("{ \"a\": }", PrettyConfig::new()).is_err());
+}
@@ -0,0 +1,76 @@
+// Flattens `include` lists: every included file is loaded first, so the
+// including file overrides whatever it pulls in. Files are read through a
+// loader function, here an in-memory table. The parser has no `include`
+// keyword yet (see the todo list in the README), so the application does the
+// flattening itself.
+//
+//     cargo run --example include
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Document {
+    include: Vec<String>,
+    keys: HashMap<String, String>,
+}
+
+fn flatten(
+    path: &str,
+    load: &dyn Fn(&str) -> Option<String>,
+    stack: &mut Vec<String>,
+    keys: &mut HashMap<String, String>,
+) -> Result<(), Box<dyn Error>> {
+    if stack.iter().any(|p| p == path) {
+        return Err(format!("include cycle: {} -> {}", stack.join(" -> "), path).into());
+    }
+
+    let source = load(path).ok_or_else(|| format!("{}: not found", path))?;
+    let document: Document = zmerald::from_str(&source).map_err(|e| format!("{}:{}", path, e))?;
+
+    stack.push(path.to_string());
+    for include in &document.include {
+        flatten(include, load, stack, keys)?;
+    }
+    stack.pop();
+
+    keys.extend(document.keys);
+    Ok(())
+}
+
+fn files() -> HashMap<&'static str, &'static str> {
+    let mut files = HashMap::new();
+    files.insert("main.zmr", r#"{ include: ["base.zmr", "media.zmr"], keys: { <q> "quit!" } }"#);
+    files.insert("base.zmr", r#"{ keys: { <q> "quit", <n> "new" } }"#);
+    files.insert("media.zmr", r#"{ keys: { <p> "play" } }"#);
+    files.insert("loop.zmr", r#"{ include: ["loop.zmr"] }"#);
+    files
+}
+
+fn main() {
+    let files = files();
+    let load = |path: &str| files.get(path).map(|s| s.to_string());
+
+    let mut keys = HashMap::new();
+    match flatten("main.zmr", &load, &mut Vec::new(), &mut keys) {
+        Ok(()) => println!("{:#?}", keys),
+        Err(e) => eprintln!("{}", e),
+    }
+}
+
+#[test]
+fn test_include() {
+    let files = files();
+    let load = |path: &str| files.get(path).map(|s| s.to_string());
+
+    let mut keys = HashMap::new();
+    flatten("main.zmr", &load, &mut Vec::new(), &mut keys).unwrap();
+    assert_eq!(keys["q"], "quit!");
+    assert_eq!(keys["n"], "new");
+    assert_eq!(keys["p"], "play");
+
+    assert!(flatten("loop.zmr", &load, &mut Vec::new(), &mut HashMap::new()).is_err());
+    assert!(flatten("missing.zmr", &load, &mut Vec::new(), &mut HashMap::new()).is_err());
+}
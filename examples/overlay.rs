@@ -0,0 +1,78 @@
+// Layers several config sources on top of each other: built-in defaults,
+// a system file and a user file. Every field of a layer is optional and
+// later layers win.
+//
+//     cargo run --example overlay
+
+use serde::Deserialize;
+use std::error::Error;
+
+const SYSTEM: &str = r#"{
+    layout: "us",
+    font_size: 12,
+}"#;
+
+const USER: &str = r#"{
+    font_size: 14,
+    # keep the system layout
+}"#;
+
+#[derive(Debug, PartialEq)]
+struct Config {
+    layout: String,
+    font_size: u32,
+    theme: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Layer {
+    layout: Option<String>,
+    font_size: Option<u32>,
+    theme: Option<String>,
+}
+
+impl Layer {
+    fn over(self, base: Layer) -> Layer {
+        Layer {
+            layout: self.layout.or(base.layout),
+            font_size: self.font_size.or(base.font_size),
+            theme: self.theme.or(base.theme),
+        }
+    }
+
+    fn resolve(self) -> Config {
+        Config {
+            layout: self.layout.unwrap_or_else(|| String::from("us")),
+            font_size: self.font_size.unwrap_or(11),
+            theme: self.theme.unwrap_or_else(|| String::from("light")),
+        }
+    }
+}
+
+fn load(sources: &[&str]) -> Result<Config, Box<dyn Error>> {
+    let mut merged = Layer::default();
+    for source in sources {
+        let layer: Layer = zmerald::from_str(source)?;
+        merged = layer.over(merged);
+    }
+
+    Ok(merged.resolve())
+}
+
+fn main() {
+    match load(&[SYSTEM, USER]) {
+        Ok(config) => println!("{:#?}", config),
+        Err(e) => eprintln!("{}", e),
+    }
+}
+
+#[test]
+fn test_overlay() {
+    assert_eq!(
+        load(&[SYSTEM, USER]).unwrap(),
+        Config { layout: String::from("us"), font_size: 14, theme: String::from("light") }
+    );
+    assert_eq!(load(&[]).unwrap().font_size, 11);
+    assert!(load(&[SYSTEM, "{ font_size: \"big\" }"]).is_err());
+}
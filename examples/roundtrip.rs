@@ -0,0 +1,66 @@
+// Loads a typed config, edits it and writes it back out with its comments.
+//
+//     cargo run --example roundtrip
+
+use serde::{ Deserialize, Serialize };
+use std::collections::HashMap;
+use std::error::Error;
+use zmerald::comments;
+use zmerald::ser::PrettyConfig;
+
+const CONFIG: &str = r#"{
+    # shown in the title bar
+    name: "benten",
+    layout: "us", # keyboard
+    windows: [
+        { title: "main", size: (800, 600) },
+    ],
+    keys: {
+        <ctrl_q> "quit",
+        <ctrl_n> "new",
+    },
+    debug: Some(false),
+}"#;
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+struct Config {
+    name: String,
+    layout: String,
+    windows: Vec<Window>,
+    keys: HashMap<String, String>,
+    debug: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+struct Window {
+    title: String,
+    size: (u32, u32),
+}
+
+// The comments are kept by the path to the items they belong to, so they
+// follow the fields wherever the typed config writes them.
+fn roundtrip(source: &str) -> Result<(Config, String), Box<dyn Error>> {
+    let (_, comments) = comments::from_str(source)?;
+    let mut config: Config = zmerald::from_str(source)?;
+    config.windows.push(Window { title: String::from("scratch"), size: (320, 240) });
+
+    let output = comments::to_string_pretty(&config, &comments, PrettyConfig::new())?;
+
+    Ok((config, output))
+}
+
+fn main() {
+    match roundtrip(CONFIG) {
+        Ok((_, output)) => println!("{}", output),
+        Err(e) => eprintln!("{}", e),
+    }
+}
+
+#[test]
+fn test_roundtrip() {
+    let (config, output) = roundtrip(CONFIG).unwrap();
+    assert_eq!(config.windows.len(), 2);
+    assert_eq!(zmerald::from_str::<Config>(&output), Ok(config));
+    assert!(output.contains("# shown in the title bar\n    name: \"benten\","));
+    assert!(output.contains("layout: \"us\", # keyboard"));
+}
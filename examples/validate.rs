@@ -0,0 +1,68 @@
+// Validates a config in two steps: the shape is checked while deserializing
+// (unknown fields, wrong types and missing fields are reported with their
+// position), the remaining rules are checked on the typed result.
+//
+//     cargo run --example validate
+
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Config {
+    name: String,
+    workers: u8,
+    #[serde(default)]
+    ports: Vec<u16>,
+}
+
+fn validate(source: &str) -> Result<Config, Vec<String>> {
+    let config: Config = zmerald::from_str(source).map_err(|e| vec![e.to_string()])?;
+
+    let mut errors = Vec::new();
+    if config.name.is_empty() {
+        errors.push(String::from("name: must not be empty"));
+    }
+    if config.workers == 0 {
+        errors.push(String::from("workers: must be at least 1"));
+    }
+    for (i, port) in config.ports.iter().enumerate() {
+        if config.ports[..i].contains(port) {
+            errors.push(format!("ports[{}]: duplicate port {}", i, port));
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(config)
+    } else {
+        Err(errors)
+    }
+}
+
+fn main() {
+    let sources = [
+        r#"{ name: "ok", workers: 4, ports: [80, 443] }"#,
+        r#"{ name: "", workers: 0, ports: [80, 80] }"#,
+        "{ name: \"typo\",\n  wrokers: 4 }",
+        r#"{ name: "big", workers: 300 }"#,
+    ];
+
+    for source in sources {
+        match validate(source) {
+            Ok(config) => println!("ok: {:?}", config),
+            Err(errors) => {
+                for error in errors {
+                    println!("error: {}", error);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_validate() {
+    assert!(validate(r#"{ name: "ok", workers: 4, ports: [80, 443] }"#).is_ok());
+    assert_eq!(validate(r#"{ name: "", workers: 0, ports: [80, 80] }"#).unwrap_err().len(), 3);
+
+    let errors = validate("{ name: \"typo\",\n  wrokers: 4 }").unwrap_err();
+    assert!(errors[0].starts_with("2:"), "{:?}", errors);
+}
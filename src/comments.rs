@@ -0,0 +1,89 @@
+// Keeps the comments of a document when it is read into a `Value` and written
+// back out. They are kept in a table keyed by the path to each item, so the
+// value can be edited in between:
+//
+//     let (mut value, comments) = comments::from_str(&source)?;
+//     ...
+//     let source = comments::to_string_pretty(&value, &comments, PrettyConfig::new())?;
+
+use crate::document::Document;
+use crate::error::{ Result, SpannedResult };
+use crate::ser::PrettyConfig;
+use crate::value::Value;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+// A step from a collection to one of its items.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Segment {
+    // A map key or struct field, quoted keys by their contents.
+    Key(String),
+    // An element of a sequence or tuple.
+    Index(usize),
+}
+
+// The comments of one item, each the text after its `#`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct NodeComments {
+    // On the lines above the item.
+    pub leading: Vec<String>,
+    // After the item, on the same line.
+    pub trailing: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Comments {
+    nodes: BTreeMap<Vec<Segment>, NodeComments>,
+}
+
+impl Comments {
+    pub fn get(&self, path: &[Segment]) -> Option<&NodeComments> {
+        self.nodes.get(path)
+    }
+
+    pub fn get_mut(&mut self, path: &[Segment]) -> Option<&mut NodeComments> {
+        self.nodes.get_mut(path)
+    }
+
+    pub fn insert(&mut self, path: Vec<Segment>, comments: NodeComments) -> Option<NodeComments> {
+        self.nodes.insert(path, comments)
+    }
+
+    pub fn remove(&mut self, path: &[Segment]) -> Option<NodeComments> {
+        self.nodes.remove(path)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&[Segment], &NodeComments)> {
+        self.nodes.iter().map(|(path, comments)| (path.as_slice(), comments))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub(crate) fn insert_node(&mut self, path: Vec<Segment>, comments: NodeComments) {
+        if !comments.leading.is_empty() || comments.trailing.is_some() {
+            self.nodes.insert(path, comments);
+        }
+    }
+}
+
+// Reads a `Value` along with the comments around its items. Comments that
+// belong to no item, such as one just before a closing bracket, are dropped.
+pub fn from_str(s: &str) -> SpannedResult<(Value, Comments)> {
+    let value = crate::from_str(s)?;
+    let document: Document = s.parse()?;
+
+    Ok((value, document.comments()))
+}
+
+// Like `ser::to_string_pretty`, with `comments` written next to the items at
+// their paths. Items the config writes on a shared line get no comments.
+pub fn to_string_pretty<T>(value: &T, comments: &Comments, config: PrettyConfig) -> Result<String>
+where T: ?Sized + Serialize {
+    let output = crate::ser::to_string_pretty(value, config)?;
+    let mut document: Document = output.parse()?;
+    document.attach_comments(comments);
+
+    Ok(document.to_string())
+}
@@ -0,0 +1,66 @@
+use crate::error::{ Error, Position, SpannedError, SpannedResult };
+use std::borrow::Cow;
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+const UTF16_LE_BOM: &[u8] = b"\xFF\xFE";
+const UTF16_BE_BOM: &[u8] = b"\xFE\xFF";
+
+// Text encodings told apart by their byte order mark, input without one is
+// taken to be UTF-8.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Encoding {
+    Utf8,
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+}
+
+impl Encoding {
+    pub fn detect(bytes: &[u8]) -> Encoding {
+        if bytes.starts_with(UTF8_BOM) {
+            Encoding::Utf8Bom
+        } else if bytes.starts_with(UTF16_LE_BOM) {
+            Encoding::Utf16Le
+        } else if bytes.starts_with(UTF16_BE_BOM) {
+            Encoding::Utf16Be
+        } else {
+            Encoding::Utf8
+        }
+    }
+
+    // The input as UTF-8 without byte order mark, transcoded if need be. A bad
+    // code unit is reported where it would be in the transcoded text.
+    pub(crate) fn decode(self, bytes: &[u8]) -> SpannedResult<Cow<'_, [u8]>> {
+        let from_units = |units: &[u8], unit: fn([u8; 2]) -> u16| {
+            let mut text = String::with_capacity(units.len() / 2);
+            for c in char::decode_utf16(units.chunks_exact(2).map(|pair| unit([pair[0], pair[1]]))) {
+                match c {
+                    Ok(c) => text.push(c),
+                    Err(_) => return Err(SpannedError { code: Error::InvalidUtf16, position: end_of(&text) }),
+                }
+            }
+            // A lone byte at the end. `is_multiple_of` would need Rust 1.87.
+            #[allow(clippy::manual_is_multiple_of)]
+            if units.len() % 2 != 0 {
+                return Err(SpannedError { code: Error::InvalidUtf16, position: end_of(&text) });
+            }
+
+            Ok(Cow::Owned(text.into_bytes()))
+        };
+
+        match self {
+            Encoding::Utf8 => Ok(Cow::Borrowed(bytes)),
+            Encoding::Utf8Bom => Ok(Cow::Borrowed(&bytes[UTF8_BOM.len()..])),
+            Encoding::Utf16Le => from_units(&bytes[UTF16_LE_BOM.len()..], u16::from_le_bytes),
+            Encoding::Utf16Be => from_units(&bytes[UTF16_BE_BOM.len()..], u16::from_be_bytes),
+        }
+    }
+}
+
+// The position following `text`, counted as `Bytes` counts them.
+fn end_of(text: &str) -> Position {
+    let line = text.rfind('\n').map_or(text, |last| &text[last + 1..]);
+
+    Position { line: 1 + text.matches('\n').count(), col: 1 + line.len() }
+}
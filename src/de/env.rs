@@ -0,0 +1,52 @@
+// The `env` extension: `${NAME}` in a string is replaced by the variable's
+// value, `${NAME:-default}` falls back to `default` when it is not set and
+// `$${` is a literal `${`. Variables are looked up by `Options::environment`.
+
+use std::borrow::Cow;
+
+use super::{ Error, Result };
+use crate::options::Environment;
+
+pub fn interpolate<'a>(s: &'a str, environment: Option<&Environment>) -> Result<Cow<'a, str>> {
+    if !s.contains("${") {
+        return Ok(Cow::Borrowed(s));
+    }
+
+    let mut expanded = String::with_capacity(s.len());
+    let mut rest = s;
+
+    while let Some(start) = rest.find("${") {
+        if rest[..start].ends_with('$') {
+            expanded.push_str(&rest[..start - 1]);
+            expanded.push_str("${");
+            rest = &rest[start + 2..];
+            continue;
+        }
+
+        expanded.push_str(&rest[..start]);
+        rest = &rest[start + 2..];
+
+        let end = rest.find('}').ok_or_else(|| error(rest, "missing closing `}`"))?;
+        let (name, default) = match rest[..end].split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (&rest[..end], None),
+        };
+
+        let environment = environment.ok_or_else(|| error(name, "interpolation is disabled, see `Options::environment`"))?;
+        match (environment.var(name), default) {
+            (Some(value), _) => expanded.push_str(&value),
+            (None, Some(default)) => expanded.push_str(default),
+            (None, None) => return Err(error(name, "variable is not set")),
+        }
+
+        rest = &rest[end + 1..];
+    }
+
+    expanded.push_str(rest);
+
+    Ok(Cow::Owned(expanded))
+}
+
+fn error(name: &str, message: &str) -> Error {
+    Error::Interpolation { name: String::from(name), message: String::from(message) }
+}
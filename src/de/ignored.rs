@@ -0,0 +1,70 @@
+// Records the entries a `Deserialize` impl skips, such as unknown fields of a
+// struct without `#[serde(deny_unknown_fields)]`, so that applications can
+// tell users that `window.widht` was not recognized. Paths are written as
+// `de::from_str_at` takes them.
+
+use super::Deserializer;
+
+#[derive(Default)]
+pub(crate) struct Ignored {
+    // Keys and indices of the value being read.
+    path: Vec<String>,
+    found: Vec<String>,
+    // Set while an ignored value is skipped, its own entries are not
+    // recorded.
+    skipping: bool,
+}
+
+impl<'de> Deserializer<'de> {
+    pub(crate) fn track_ignored(&mut self) {
+        self.ignored = Some(Ignored::default());
+    }
+
+    pub(crate) fn take_ignored(&mut self) -> Vec<String> {
+        self.ignored.take().map(|ignored| ignored.found).unwrap_or_default()
+    }
+
+    // Steps into the entry whose key is written as `segment`.
+    pub(crate) fn enter(&mut self, segment: &[u8]) {
+        if let Some(ignored) = &mut self.ignored {
+            // `"x"` and `x` name the same key
+            let segment = match segment {
+                [b'"', inner @ .., b'"'] => inner,
+                _ => segment,
+            };
+            ignored.path.push(String::from_utf8_lossy(segment).into_owned());
+        }
+    }
+
+    pub(crate) fn enter_index(&mut self, index: usize) {
+        if let Some(ignored) = &mut self.ignored {
+            ignored.path.push(index.to_string());
+        }
+    }
+
+    pub(crate) fn leave(&mut self) {
+        if let Some(ignored) = &mut self.ignored {
+            ignored.path.pop();
+        }
+    }
+
+    // Records the entry being read as ignored, unless it is within one.
+    // Returns whether it was recorded, and so is to be passed to
+    // `end_ignored` once skipped.
+    pub(crate) fn start_ignored(&mut self) -> bool {
+        match &mut self.ignored {
+            Some(ignored) if !ignored.skipping => {
+                ignored.found.push(ignored.path.join("."));
+                ignored.skipping = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub(crate) fn end_ignored(&mut self, started: bool) {
+        if let Some(ignored) = self.ignored.as_mut().filter(|_| started) {
+            ignored.skipping = false;
+        }
+    }
+}
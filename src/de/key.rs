@@ -0,0 +1,141 @@
+use serde::de::{ self, Visitor };
+
+use super::{ Deserializer, Error, Result };
+use crate::parse::ParsedStr;
+use crate::value::VALUE_TOKEN;
+
+// Map keys may be written as quoted strings (see `PrettyConfig::quote_keys`),
+// string-like targets receive the quoted contents as is while every other
+// target re-parses the contents as a standalone value.
+pub struct KeyDeserializer<'a, 'b: 'a> {
+    d: &'a mut Deserializer<'b>,
+}
+
+impl<'a, 'b: 'a> KeyDeserializer<'a, 'b> {
+    pub fn new(d: &'a mut Deserializer<'b>) -> Self {
+        KeyDeserializer { d }
+    }
+
+    // Bare keys are fine under `Options::strict_strings`, which is about
+    // values.
+    fn bare<T>(&mut self, f: impl FnOnce(&mut Deserializer<'b>) -> Result<T>) -> Result<T> {
+        let strict = std::mem::replace(&mut self.d.options.strict_strings, false);
+        let result = f(self.d);
+        self.d.options.strict_strings = strict;
+
+        result
+    }
+
+    fn quoted(&mut self) -> Result<Option<ParsedStr<'b>>> {
+        if self.d.bytes.peek() == Some(b'"') {
+            self.d.bytes.string().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+macro_rules! reparse_quoted {
+    ($($method:ident($($arg:ident: $ty:ty),*);)*) => {
+        $(
+            fn $method<V>(self, $($arg: $ty,)* visitor: V) -> Result<V::Value>
+            where V: Visitor<'b> {
+                match self.quoted()? {
+                    Some(ParsedStr::Slice(s)) => {
+                        let mut d = Deserializer::from_str(s)?;
+                        let value = (&mut d).$method($($arg,)* visitor)?;
+                        d.end()?;
+                        Ok(value)
+                    }
+                    // Escaped contents do not borrow from the input and can
+                    // therefore only be handed out as strings.
+                    Some(ParsedStr::Allocated(s)) => visitor.visit_string(s),
+                    None => self.d.$method($($arg,)* visitor),
+                }
+            }
+        )*
+    };
+}
+
+impl<'a, 'b> de::Deserializer<'b> for &mut KeyDeserializer<'a, 'b> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where V: Visitor<'b> {
+        match self.quoted()? {
+            Some(ParsedStr::Allocated(s)) => visitor.visit_string(s),
+            Some(ParsedStr::Slice(s)) => visitor.visit_borrowed_str(s),
+            None => self.bare(|d| d.deserialize_any(visitor)),
+        }
+    }
+
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value>
+    where V: Visitor<'b> {
+        match self.quoted()? {
+            Some(ParsedStr::Allocated(s)) => visitor.visit_string(s),
+            Some(ParsedStr::Slice(s)) => visitor.visit_borrowed_str(s),
+            None => self.bare(|d| d.deserialize_str(visitor)),
+        }
+    }
+
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
+    where V: Visitor<'b> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value>
+    where V: Visitor<'b> {
+        match self.quoted()? {
+            Some(ParsedStr::Allocated(s)) => visitor.visit_string(s),
+            Some(ParsedStr::Slice(s)) => visitor.visit_borrowed_str(s),
+            None => self.d.deserialize_identifier(visitor),
+        }
+    }
+
+    // `Value` keys keep quoted contents as strings.
+    fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value>
+    where V: Visitor<'b> {
+        match self.quoted()? {
+            Some(ParsedStr::Slice(s)) if name == VALUE_TOKEN => visitor.visit_borrowed_str(s),
+            Some(ParsedStr::Slice(s)) => {
+                let mut d = Deserializer::from_str(s)?;
+                let value = (&mut d).deserialize_newtype_struct(name, visitor)?;
+                d.end()?;
+                Ok(value)
+            }
+            Some(ParsedStr::Allocated(s)) => visitor.visit_string(s),
+            None => self.d.deserialize_newtype_struct(name, visitor),
+        }
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value>
+    where V: Visitor<'b> {
+        self.deserialize_any(visitor)
+    }
+
+    reparse_quoted! {
+        deserialize_bool();
+        deserialize_i8();
+        deserialize_i16();
+        deserialize_i32();
+        deserialize_i64();
+        deserialize_u8();
+        deserialize_u16();
+        deserialize_u32();
+        deserialize_u64();
+        deserialize_f32();
+        deserialize_f64();
+        deserialize_char();
+        deserialize_bytes();
+        deserialize_byte_buf();
+        deserialize_option();
+        deserialize_unit();
+        deserialize_unit_struct(name: &'static str);
+        deserialize_seq();
+        deserialize_tuple(len: usize);
+        deserialize_tuple_struct(name: &'static str, len: usize);
+        deserialize_map();
+        deserialize_struct(name: &'static str, fields: &'static [&'static str]);
+        deserialize_enum(name: &'static str, variants: &'static [&'static str]);
+    }
+}
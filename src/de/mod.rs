@@ -1,5 +1,5 @@
 mod value;
-mod map;
+pub mod map;
 
 #[cfg(test)]
 mod tests;
@@ -10,51 +10,223 @@ pub use id::IdDeserializer;
 mod tag;
 pub use tag::TagDeserializer;
 
-use crate::error::{ Error, SpannedError, Result, SpannedResult };
-use crate::parse::{ AnyNum, Bytes, ParsedStr };
+mod key;
+pub use key::KeyDeserializer;
+
+mod untyped;
+use untyped::VariantMap;
+
+pub(crate) mod recover;
+pub use recover::{ check_str, syntax_errors };
+
+mod env;
+
+mod variables;
+use variables::Variables;
+
+mod path;
+
+mod ignored;
+use ignored::Ignored;
+
+mod tokenizer;
+pub use tokenizer::{ Event, Tokenizer };
+
+mod encoding;
+pub use encoding::Encoding;
+
+use crate::error::{ Error, Position, SpannedError, Result, SpannedResult };
+use crate::options::{ Brackets, Compat, Options, Scalar, StructNames, UnknownExtensions, RON_EXTENSIONS };
+use crate::parse::{ is_ident_first_char, is_ident_other_char, is_keyword, is_reserved_char, is_whitespace_char, AnyNum, Bytes, ParsedBytes, ParsedStr };
+use crate::value::{ Value, RAW_VALUE_TOKEN, VALUE_TOKEN };
+use crate::{ BUILTIN_SYNTAX, EXPANDING_EXTENSIONS };
 use serde::de::{ self, DeserializeSeed, Deserializer as SerdeError, Visitor };
-use std::{ borrow::Cow, io, str };
+use serde::de::value::{ BorrowedStrDeserializer, StrDeserializer };
+use std::{ borrow::Cow, collections::HashMap, io, str };
 
-pub fn from_reader<R, T>(mut rdr: R) -> SpannedResult<T> where R: io::Read, T: de::DeserializeOwned {
-    let mut bytes = Vec::new();
-    rdr.read_to_end(&mut bytes)?;
+pub fn from_reader<R, T>(rdr: R) -> SpannedResult<T> where R: io::Read, T: de::DeserializeOwned {
+    Options::default().from_reader(rdr)
+}
 
-    from_bytes(&bytes)
+pub fn from_path<P, T>(path: P) -> SpannedResult<T> where P: AsRef<std::path::Path>, T: de::DeserializeOwned {
+    Options::default().from_path(path)
 }
 
 pub fn from_str<'a, T>(s: &'a str) -> SpannedResult<T> where T: de::Deserialize<'a> {
-    from_bytes(s.as_bytes())
+    Options::default().from_str(s)
 }
 
 pub fn from_bytes<'a, T>(s: &'a [u8]) -> SpannedResult<T> where T: de::Deserialize<'a> {
-    from_bytes_seed(s, std::marker::PhantomData)
+    Options::default().from_bytes(s)
+}
+
+// Reads only the value at `path`, such as `server.tls`, skipping the rest of
+// the document.
+pub fn from_str_at<'a, T>(s: &'a str, path: &str) -> SpannedResult<T> where T: de::Deserialize<'a> {
+    Options::default().from_str_at(s, path)
+}
+
+// Also returns the warnings of the document, see `Options::warnings`.
+pub fn from_str_with_warnings<'a, T>(s: &'a str) -> SpannedResult<(T, Vec<SpannedError>)> where T: de::Deserialize<'a> {
+    Options::default().from_str_with_warnings(s)
+}
+
+// Also returns the paths of the entries that were skipped, such as unknown
+// fields of a struct, see `Options::from_str_with_ignored`.
+pub fn from_str_with_ignored<'a, T>(s: &'a str) -> SpannedResult<(T, Vec<String>)> where T: de::Deserialize<'a> {
+    Options::default().from_str_with_ignored(s)
+}
+
+pub fn from_bytes_decoded<T>(s: &[u8]) -> (Encoding, SpannedResult<T>) where T: de::DeserializeOwned {
+    Options::default().from_bytes_decoded(s)
+}
+
+pub fn from_bytes_lossy<T>(s: &[u8]) -> SpannedResult<T> where T: de::DeserializeOwned {
+    Options::default().from_bytes_lossy(s)
 }
 
 pub fn from_bytes_seed<'a, S, T>(s: &'a [u8], seed: S) -> SpannedResult<T>
 where S: de::DeserializeSeed<'a, Value = T> {
-    let mut deserializer = Deserializer::from_bytes(s)?;
-    let value = seed.deserialize(&mut deserializer).map_err(|e| deserializer.span_error(e))?;
-    deserializer.end().map_err(|e| deserializer.span_error(e))?;
-    Ok(value)
+    Options::default().from_bytes_seed(s, seed)
+}
+
+// A `ValueRef` borrowing from `s`, with its nodes and unescaped strings
+// allocated in `arena`.
+#[cfg(feature = "arena")]
+pub fn from_str_in<'a>(s: &'a str, arena: &'a bumpalo::Bump) -> SpannedResult<crate::value::ValueRef<'a>> {
+    Options::default().from_str_in(s, arena)
+}
+
+// Reports a grammar decision to the `trace` feature's tracer, if one is set.
+macro_rules! trace {
+    ($de:expr, $($arg:tt)*) => {
+        #[cfg(feature = "trace")]
+        $de.trace(format_args!($($arg)*));
+    };
+}
+
+// Returns what the `ScalarParser` of the options reads the token ahead as,
+// if anything, through `$method` of the `Value` it stands for.
+macro_rules! parsed_scalar {
+    ($de:expr, $method:ident, $visitor:expr) => {
+        if let Some(value) = $de.parsed_scalar()? {
+            return value.$method($visitor);
+        }
+    };
 }
 
 pub struct Deserializer<'de> {
     bytes: Bytes<'de>,
+    options: Options,
+    // Named by the document's `#![enable(...)]` header.
+    extensions: Vec<String>,
+    // Set for RON's `unwrap_variant_newtypes`, the struct in a newtype
+    // variant has its fields directly between the variant's parentheses.
+    unwrapped: bool,
+    variables: Variables<'de>,
+    // Values labelled `&name` so far, for the `anchors` extension, each with
+    // what a copy counts against `Limits::expansion`.
+    anchors: HashMap<&'de str, (Value, usize)>,
+    // Set to record skipped entries, see `from_str_with_ignored`.
+    ignored: Option<Ignored>,
+    // Values the one being read is nested in, see `Limits::depth`.
+    depth: usize,
+    // Bytes read again so far, see `Limits::expansion`.
+    expanded: usize,
 }
 
 impl<'de> Deserializer<'de> {
+    // Cannot implement trait here since output is tied to input lifetime 'de.
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(input: &'de str) -> SpannedResult<Self> {
         Self::from_bytes(input.as_bytes())
     }
 
     pub fn from_bytes(input: &'de [u8]) -> SpannedResult<Self> {
-        let deserializer = Deserializer {
-            bytes: Bytes::new(input)?,
+        Self::from_bytes_with_options(input, Options::default())
+    }
+
+    // A UTF-8 byte order mark is skipped, UTF-16 input is turned away.
+    pub fn from_bytes_with_options(input: &'de [u8], options: Options) -> SpannedResult<Self> {
+        if input.len() > options.limits.input_size {
+            return Err(SpannedError { code: Error::InputTooLarge(options.limits.input_size), position: Position { line: 0, col: 0 } });
+        }
+
+        let input = match Encoding::detect(input) {
+            Encoding::Utf8 => input,
+            Encoding::Utf8Bom => &input[3..],
+            encoding => {
+                return Err(SpannedError { code: Error::UnsupportedEncoding(encoding), position: Position { line: 1, col: 1 } })
+            }
         };
 
+        let mut bytes = Bytes::new(input)?;
+        bytes.set_max_token(options.limits.token_len);
+        bytes.set_compat(options.compat).map_err(|e| bytes.span_error(e))?;
+        let mut extensions = Vec::new();
+        for (position, name) in bytes.extensions().map_err(|e| bytes.span_error(e))? {
+            if EXPANDING_EXTENSIONS.contains(&name) && !options.extensions.iter().any(|e| e == name) {
+                return Err(SpannedError { code: Error::ExtensionNotAllowed(String::from(name)), position });
+            }
+            if !options.supports_extension(name) {
+                let error = SpannedError { code: Error::UnknownExtension(String::from(name)), position };
+                match options.unknown_extensions {
+                    UnknownExtensions::Error => return Err(error),
+                    UnknownExtensions::Warn(ref warn) => warn(&error),
+                    UnknownExtensions::Ignore => (),
+                }
+                continue;
+            }
+            if extensions.iter().any(|e| e == name)
+                || BUILTIN_SYNTAX.contains(&name)
+                || (options.compat == Compat::Ron && RON_EXTENSIONS.contains(&name))
+            {
+                let warning = SpannedError { code: Error::UnusedExtension(String::from(name)), position };
+                if options.warnings_as_errors {
+                    return Err(warning);
+                } else if let Some(warnings) = &options.warnings {
+                    warnings.warn(&warning);
+                }
+                continue;
+            }
+            extensions.push(String::from(name));
+        }
+
+        let variables = match extensions.iter().any(|e| e == "variables") {
+            true => Variables::collect(bytes)?,
+            false => Variables::default(),
+        };
+
+        let deserializer = Deserializer { bytes, options, extensions, unwrapped: false, variables, anchors: HashMap::new(), ignored: None, depth: 0, expanded: 0 };
+        #[cfg(feature = "trace")]
+        for name in &deserializer.extensions {
+            trace!(deserializer, "extension `{}` enabled", name);
+        }
+
         Ok(deserializer)
     }
 
+    // Reads ahead from `bytes` without the document's options or extensions.
+    pub(crate) fn ahead(bytes: Bytes<'de>) -> Self {
+        Deserializer {
+            bytes,
+            options: Options::default(),
+            extensions: Vec::new(),
+            unwrapped: false,
+            variables: Variables::default(),
+            anchors: HashMap::new(),
+            ignored: None,
+            depth: 0,
+            expanded: 0,
+        }
+    }
+
+    // Whether the document enabled the extension `name` in its header. Syntax
+    // that is always read, such as `byte_strings`, is never enabled.
+    pub fn extension_enabled(&self, name: &str) -> bool {
+        self.extensions.iter().any(|e| e == name)
+    }
+
     pub fn remainder(&self) -> Cow<'_, str> {
         String::from_utf8_lossy(self.bytes.bytes())
     }
@@ -76,6 +248,314 @@ impl<'de> Deserializer<'de> {
         }
     }
 
+    fn resolve(&self, path: &str) -> Result<Vec<u8>> {
+        let resolver = self.options.resolver.as_ref().ok_or_else(|| Error::FileReference {
+            path: String::from(path),
+            message: String::from("file references are disabled, see `Options::resolver`"),
+        })?;
+
+        resolver.resolve(path).map_err(|e| Error::FileReference { path: String::from(path), message: e.to_string() })
+    }
+
+    #[cfg(feature = "trace")]
+    fn trace(&self, message: std::fmt::Arguments) {
+        self.trace_at(self.bytes.position(), message)
+    }
+
+    #[cfg(feature = "trace")]
+    fn trace_at(&self, position: Position, message: std::fmt::Arguments) {
+        if let Some(tracer) = &self.options.tracer {
+            tracer.trace(position, &message.to_string());
+        }
+    }
+
+    // Reads a value nested in the current one with `read`, so that deeply
+    // nested input fails rather than overflowing the stack.
+    pub(crate) fn nested<T, F>(&mut self, read: F) -> Result<T> where F: FnOnce(&mut Self) -> Result<T> {
+        if self.depth >= self.options.limits.depth {
+            return Err(Error::DepthLimitExceeded(self.options.limits.depth));
+        }
+
+        self.depth += 1;
+        let result = read(self);
+        self.depth -= 1;
+
+        result
+    }
+
+    // Counts `len` more bytes as read again, see `Limits::expansion`.
+    pub(crate) fn expand(&mut self, len: usize) -> Result<()> {
+        self.expanded += len;
+        if self.expanded > self.options.limits.expansion {
+            return Err(Error::ExpansionTooLarge(self.options.limits.expansion));
+        }
+
+        Ok(())
+    }
+
+    // Passes a lint found at `position` to `Options::warnings`, or fails with
+    // it under `warnings_as_errors`.
+    fn warn(&self, code: Error, position: Position) -> Result<()> {
+        if self.options.warnings_as_errors {
+            return Err(code);
+        }
+        if let Some(warnings) = &self.options.warnings {
+            warnings.warn(&SpannedError { code, position });
+        }
+
+        Ok(())
+    }
+
+    // A float read from an integer, such as `16777217` as an `f32`, that is
+    // not the integer written.
+    fn rounded_integer(&self, start: Bytes<'de>, float: f64) -> Result<()> {
+        let text = &start.bytes()[..start.bytes().len() - self.bytes.bytes().len()];
+        let Ok(text) = str::from_utf8(text) else {
+            return Ok(());
+        };
+
+        match text.replace('_', "").parse::<i128>() {
+            Ok(integer) if float as i128 != integer => self.warn(Error::RoundedInteger(String::from(text)), start.position()),
+            _ => Ok(()),
+        }
+    }
+
+    fn named_newtype_ahead(&self, name: &str) -> bool {
+        let mut bytes = self.bytes;
+        !name.is_empty() && bytes.consume_ident(name) && bytes.skip_ws().is_ok() && bytes.peek() == Some(b'(')
+    }
+
+    // Consumes `Some(`, read whatever the extensions. Anything else, such as
+    // a struct named `Something`, is the content of an implicit `Some`.
+    fn explicit_some(&mut self) -> Result<bool> {
+        let mut bytes = self.bytes;
+        if !bytes.consume_ident("Some") {
+            return Ok(false);
+        }
+        bytes.skip_ws()?;
+        if !bytes.consume("(") {
+            return Ok(false);
+        }
+
+        self.bytes = bytes;
+        Ok(true)
+    }
+
+    // Consumes the Rust type suffix of a number, `u8` of `1u8` or `f32` of
+    // `2.5f32`, with the `number_suffixes` extension. Integers may have float
+    // suffixes, floats not integer ones.
+    fn number_suffix(&mut self, float: bool) -> Result<()> {
+        const INTEGERS: &[&str] = &["i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16", "u32", "u64", "u128", "usize"];
+
+        if !self.extension_enabled("number_suffixes") || self.bytes.consume_ident("f32") || self.bytes.consume_ident("f64") {
+            return Ok(());
+        }
+        let mut bytes = self.bytes;
+        if INTEGERS.iter().any(|suffix| bytes.consume_ident(suffix)) {
+            if float {
+                return Err(Error::ExpectedFloat);
+            }
+            self.bytes = bytes;
+        }
+
+        Ok(())
+    }
+
+    // The unquoted token ahead, as `Options::scalar_parser` reads it.
+    fn parsed_scalar(&mut self) -> Result<Option<Value>> {
+        let Some(parser) = &self.options.scalar_parser else {
+            return Ok(None);
+        };
+
+        let bytes = self.bytes.bytes();
+        let len = bytes.iter().take_while(|&&b| !is_reserved_char(b) && !is_whitespace_char(b) && b != b'"' && b != b'#').count();
+        if len == 0 {
+            return Ok(None);
+        }
+
+        match parser.parse(str::from_utf8(&bytes[..len])?) {
+            Some(Scalar::String(s)) => {
+                self.bytes.advance(len)?;
+                Ok(Some(Value::String(s)))
+            }
+            Some(Scalar::Number(n)) => {
+                self.bytes.advance(len)?;
+                Ok(Some(Value::Number(n)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    // The elements in `[..]` or `(..)`, whichever `opener` begins, or in
+    // either as `Options::brackets` says. Tuples have `len` elements.
+    fn bracketed<V>(&mut self, opener: u8, len: Option<usize>, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
+        let found = match self.bytes.peek() {
+            Some(found @ (b'[' | b'(')) => found,
+            _ => return Err(Error::ExpectedArray),
+        };
+        if found != opener && self.options.brackets == Brackets::Strict {
+            return Err(Error::MismatchedBrackets { expected: opener as char, found: found as char });
+        }
+
+        let closer = if found == b'[' { b']' } else { b')' };
+        self.bytes.advance_single()?;
+
+        let mut elements = CommaSeparated::new(closer, self);
+        if len.is_none() {
+            elements.remaining = count_elements(elements.de.bytes.bytes(), closer);
+        }
+        let value = match (visitor.visit_seq(&mut elements), len) {
+            (Err(Error::ExpectedDifferentLength { found, .. }), Some(expected)) => {
+                return Err(Error::ExpectedArrayLength { expected, found });
+            }
+            (value, _) => value?,
+        };
+        let had_comma = elements.had_comma;
+
+        if self.bytes.comma()? || had_comma {
+            if let Some(expected) = len {
+                let found = expected + self.count_rest(closer)?;
+                if found > expected {
+                    return Err(Error::ExpectedArrayLength { expected, found });
+                }
+            }
+        }
+
+        if self.bytes.peek() == Some(closer) {
+            self.bytes.advance_single()?;
+            Ok(value)
+        } else {
+            Err(Error::ExpectedArrayEnd)
+        }
+    }
+
+    // Skips the elements left before `closer`, if the rest are well formed.
+    fn count_rest(&mut self, closer: u8) -> Result<usize> {
+        let mut count = 0;
+        loop {
+            self.bytes.skip_ws()?;
+            if self.bytes.peek() == Some(closer) {
+                return Ok(count);
+            }
+
+            <de::IgnoredAny as de::Deserialize>::deserialize(&mut *self)?;
+            count += 1;
+            if !self.bytes.comma()? {
+                self.bytes.skip_ws()?;
+                return Ok(count);
+            }
+        }
+    }
+
+    // Consumes the struct name, if any, as the `struct_names` option says.
+    fn struct_name(&mut self, name: &'static str) -> Result<bool> {
+        let position = self.bytes.position();
+
+        match self.bytes.consume_struct_name(name) {
+            Ok(false) if !name.is_empty() && matches!(self.options.struct_names, StructNames::Require) => {
+                Err(Error::ExpectedNamedStruct(name))
+            }
+            Err(code @ Error::ExpectedDifferentStructName { .. }) => match &self.options.struct_names {
+                StructNames::Ignore => Ok(true),
+                StructNames::Warn(warn) => {
+                    warn(&SpannedError { code, position });
+                    Ok(true)
+                }
+                _ => Err(code),
+            },
+            result => result,
+        }
+    }
+
+    // Consumes the identifier ahead if `Options::renames` renames it in `ty`,
+    // returning its new name.
+    fn renamed(&mut self, ty: &str) -> Result<Option<String>> {
+        if self.options.renames.is_empty() {
+            return Ok(None);
+        }
+
+        let mut bytes = self.bytes;
+        let position = bytes.position();
+        let Ok(old) = bytes.identifier() else {
+            return Ok(None);
+        };
+        let old = str::from_utf8(old)?;
+        let Some(new) = self.options.renames.get(ty, old).map(String::from) else {
+            return Ok(None);
+        };
+
+        let code = Error::DeprecatedName { old: String::from(old), new: new.clone(), outer: (!ty.is_empty()).then(|| String::from(ty)) };
+        self.options.renames.deprecated(&SpannedError { code: code.clone(), position });
+        self.warn(code, position)?;
+        self.bytes = bytes;
+        Ok(Some(new))
+    }
+
+    // The fields of the struct `name` up to `terminator`. Errors about its
+    // fields, rather than those of a nested value, name the struct, and a
+    // duplicate field is reported where its key starts.
+    fn struct_fields<V>(&mut self, name: &'static str, terminator: u8, visitor: V) -> Result<V::Value>
+    where V: Visitor<'de> {
+        let mut access = CommaSeparated::new(terminator, self);
+        access.ty = Some(name);
+        let result = visitor.visit_map(&mut access);
+        if access.in_value {
+            return result;
+        }
+        let key_start = access.key_start;
+
+        let outer = (!name.is_empty()).then(|| String::from(name));
+        result.map_err(|e| match e {
+            Error::NoSuchStructField { expected, found, outer: None } => Error::NoSuchStructField { expected, found, outer },
+            Error::MissingStructField { field, outer: None } => Error::MissingStructField { field, outer },
+            Error::DuplicateStructField { field, outer: None } => {
+                self.bytes = key_start;
+                Error::DuplicateStructField { field, outer }
+            }
+            e => e,
+        })
+    }
+
+    // Same as `deserialize_map`, with bare keys read as strings. Duplicate
+    // keys are always linted, the last one wins.
+    fn deserialize_untyped_map<V>(&mut self, visitor: V) -> Result<V::Value>
+    where V: Visitor<'de> {
+        if self.bytes.consume("{") {
+            let mut access = CommaSeparated::new(b'}', self);
+            access.untyped = true;
+            access.keys = Some(HashMap::new());
+            let value = visitor.visit_map(access)?;
+            self.bytes.comma()?;
+
+            if self.bytes.consume("}") {
+                Ok(value)
+            } else {
+                Err(Error::ExpectedMapEnd)
+            }
+        } else {
+            Err(Error::ExpectedMap)
+        }
+    }
+
+    fn ron(&self) -> bool {
+        self.options.compat == Compat::Ron
+    }
+
+    // Under `strict_strings`, bare words are not read as strings or chars.
+    fn check_quoted(&self, quote: u8) -> Result<()> {
+        let quoted = self.bytes.peek() == Some(quote) || (quote == b'"' && self.bytes.check_raw_string());
+        if self.options.strict_strings && !quoted {
+            return Err(Error::UnquotedString);
+        }
+
+        Ok(())
+    }
+
+    // Whether RON's `(field: ..)` struct syntax follows.
+    fn ron_fields_ahead(&self, bytes: Bytes) -> bool {
+        self.ron() && fields_ahead(bytes)
+    }
+
     fn handle_other_structs<V>(&mut self, visitor: V) -> Result<V::Value>
     where V: Visitor<'de> {
         let mut bytes = self.bytes;
@@ -85,6 +565,8 @@ impl<'de> Deserializer<'de> {
 
             if bytes.check_tuple_struct()? {
                 self.deserialize_tuple(0, visitor)
+            } else if self.ron() {
+                self.deserialize_struct("", &[], visitor)
             } else {
                Err(Error::ExpectedTupleStruct) 
             }
@@ -121,38 +603,113 @@ impl<'de> Deserializer<'de> {
 //     }
 // }
 
+// Whether `bytes` hold `(field: ..`, the fields of a struct in parentheses.
+fn fields_ahead(mut bytes: Bytes) -> bool {
+    bytes.consume("(") && bytes.skip_ws().is_ok() && bytes.check_tuple_struct().is_ok_and(|tuple| !tuple)
+}
+
 // need to edit the visit_map() of the below Visitor, how does one do that?
-impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
     type Error = Error;
 
     fn deserialize_any<V>(self, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
+        parsed_scalar!(self, deserialize_any, visitor);
+
         if self.bytes.consume_ident("true") {
             return visitor.visit_bool(true);
         } else if self.bytes.consume_ident("false") {
             return visitor.visit_bool(false);
         } else if self.bytes.check_ident("Some") {
+            // A bare `Some` would be the content of an implicit one, endlessly.
+            let mut bytes = self.bytes;
+            if !(bytes.consume_ident("Some") && bytes.skip_ws().is_ok() && bytes.peek() == Some(b'(')) {
+                return Err(Error::ExpectedOption);
+            }
             return self.deserialize_option(visitor);
         } else if self.bytes.consume_ident("None") {
             return visitor.visit_none();
         } else if self.bytes.consume("()") {
             return visitor.visit_unit();
         } else if self.bytes.consume_ident("inf") {
-            return visitor.visit_f64(std::f64::INFINITY);
+            return visitor.visit_f64(f64::INFINITY);
         } else if self.bytes.consume_ident("-inf") {
-            return visitor.visit_f64(std::f64::NEG_INFINITY);
+            return visitor.visit_f64(f64::NEG_INFINITY);
         } else if self.bytes.consume_ident("NaN") {
-            return visitor.visit_f64(std::f64::NAN);
+            return visitor.visit_f64(f64::NAN);
+        }
+
+        if self.ron() && self.bytes.check_raw_string() {
+            return self.deserialize_str(visitor);
+        }
+        // `::1` or `fe80::1`, which would end at the first `:` otherwise.
+        if self.bytes.check_address() && !self.options.strict_strings {
+            return self.deserialize_str(visitor);
         }
 
         // `identifier` does not change state if it fails
-        if self.bytes.identifier().ok().is_some() {
-            self.bytes.skip_ws()?;
-            return self.handle_other_structs(visitor);
+        let start = self.bytes;
+        let ident = self.bytes.identifier();
+        if let Err(code @ Error::TokenTooLong(_)) = ident {
+            return Err(code);
         }
+        if let Ok(ident) = ident {
+            let ident = str::from_utf8(ident).map_err(Error::from)?;
+            // A bare string such as `ctrl-q` only starts like an identifier.
+            if self.bytes.peek().is_some_and(|b| !is_reserved_char(b) && !is_whitespace_char(b)) {
+                self.bytes = start;
+                return self.deserialize_str(visitor);
+            }
+            let mut bytes = self.bytes;
+            bytes.skip_ws()?;
+
+            return match bytes.peek() {
+                Some(b'(') if self.ron_fields_ahead(bytes) => {
+                    trace!(self, "any: name `{}` of a RON struct skipped", ident);
+                    self.bytes = bytes;
+                    self.deserialize_struct("", &[], visitor)
+                }
+                Some(b'(') => {
+                    trace!(self, "any: variant `{}` read as a map", ident);
+                    self.bytes = bytes;
+                    visitor.visit_map(VariantMap::new(self, ident))
+                }
+                Some(b'{') => {
+                    trace!(self, "any: name `{}` skipped", ident);
+                    self.bytes = bytes;
+                    self.deserialize_any(visitor)
+                }
+                // A bare word is a string, so that it survives being
+                // buffered, as done for `#[serde(flatten)]` fields.
+                _ if self.options.strict_strings => {
+                    self.bytes = start;
+                    Err(Error::UnquotedString)
+                }
+                _ => {
+                    trace!(self, "any: bare word `{}` read as a string", ident);
+                    visitor.visit_borrowed_str(ident)
+                }
+            };
+        }
+
+        trace!(self, "any: `{}`", self.bytes.peek_or_eof()? as char);
 
         match self.bytes.peek_or_eof()? {
             b'0'..=b'9' | b'+' | b'-' => {
-                match self.bytes.any_num()? {
+                let start = self.bytes;
+                let number = self.bytes.any_num().and_then(|number| {
+                    self.number_suffix(matches!(number, AnyNum::F32(_) | AnyNum::F64(_)))?;
+                    Ok(number)
+                });
+                // A bare word such as `1.0.2` only starts like a number, it is
+                // a string as it would be for a `String`.
+                let word = self.bytes.peek().is_some_and(|b| !is_reserved_char(b) && !is_whitespace_char(b) && b != b'"' && b != b'#');
+                if (number.is_err() || word) && !self.options.strict_strings {
+                    self.bytes = start;
+                    trace!(self, "any: bare word read as a string");
+                    return self.deserialize_str(visitor);
+                }
+
+                match number? {
                     AnyNum::F32(x) => visitor.visit_f32(x),
                     AnyNum::F64(x) => visitor.visit_f64(x),
                     AnyNum::I8(x) => visitor.visit_i8(x),
@@ -166,8 +723,9 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
                 }
             },
 
-            b'{' | b'<' => self.deserialize_map(visitor),
+            b'{' | b'<' => self.deserialize_untyped_map(visitor),
             b'"' | b'r' => self.deserialize_string(visitor),
+            b'b' | b'x' | b'@' => self.deserialize_byte_buf(visitor),
             b'(' => self.handle_other_structs(visitor),
             b'[' => self.deserialize_seq(visitor),
             b'.' => self.deserialize_f64(visitor),
@@ -177,55 +735,122 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     }
 
     fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
+        trace!(self, "expecting bool");
         visitor.visit_bool(self.bytes.bool()?)
     }
 
     fn deserialize_i8<V>(self, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
-        visitor.visit_i8(self.bytes.signed_integer()?)
+        trace!(self, "expecting i8");
+        parsed_scalar!(self, deserialize_i8, visitor);
+        let number = self.bytes.signed_integer()?;
+        self.number_suffix(false)?;
+
+        visitor.visit_i8(number)
     }
 
     fn deserialize_i16<V>(self, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
-        visitor.visit_i16(self.bytes.signed_integer()?)
+        trace!(self, "expecting i16");
+        parsed_scalar!(self, deserialize_i16, visitor);
+        let number = self.bytes.signed_integer()?;
+        self.number_suffix(false)?;
+
+        visitor.visit_i16(number)
     }
 
     fn deserialize_i32<V>(self, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
-        visitor.visit_i32(self.bytes.signed_integer()?)
+        trace!(self, "expecting i32");
+        parsed_scalar!(self, deserialize_i32, visitor);
+        let number = self.bytes.signed_integer()?;
+        self.number_suffix(false)?;
+
+        visitor.visit_i32(number)
     }
 
     fn deserialize_i64<V>(self, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
-        visitor.visit_i64(self.bytes.signed_integer()?)
+        trace!(self, "expecting i64");
+        parsed_scalar!(self, deserialize_i64, visitor);
+        let number = self.bytes.signed_integer()?;
+        self.number_suffix(false)?;
+
+        visitor.visit_i64(number)
     }
 
     fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
-        visitor.visit_u8(self.bytes.unsigned_integer()?)
+        trace!(self, "expecting u8");
+        parsed_scalar!(self, deserialize_u8, visitor);
+        let number = self.bytes.unsigned_integer()?;
+        self.number_suffix(false)?;
+
+        visitor.visit_u8(number)
     }
 
     fn deserialize_u16<V>(self, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
-        visitor.visit_u16(self.bytes.unsigned_integer()?)
+        trace!(self, "expecting u16");
+        parsed_scalar!(self, deserialize_u16, visitor);
+        let number = self.bytes.unsigned_integer()?;
+        self.number_suffix(false)?;
+
+        visitor.visit_u16(number)
     }
 
     fn deserialize_u32<V>(self, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
-        visitor.visit_u32(self.bytes.unsigned_integer()?)
+        trace!(self, "expecting u32");
+        parsed_scalar!(self, deserialize_u32, visitor);
+        let number = self.bytes.unsigned_integer()?;
+        self.number_suffix(false)?;
+
+        visitor.visit_u32(number)
     }
 
     fn deserialize_u64<V>(self, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
-        visitor.visit_u64(self.bytes.unsigned_integer()?)
+        trace!(self, "expecting u64");
+        parsed_scalar!(self, deserialize_u64, visitor);
+        let number = self.bytes.unsigned_integer()?;
+        self.number_suffix(false)?;
+
+        visitor.visit_u64(number)
     }
 
     fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
-        visitor.visit_f32(self.bytes.float()?)
+        trace!(self, "expecting f32");
+        parsed_scalar!(self, deserialize_f32, visitor);
+        let start = self.bytes;
+        let number: f32 = self.bytes.float()?;
+        self.rounded_integer(start, f64::from(number))?;
+        self.number_suffix(true)?;
+
+        visitor.visit_f32(number)
     }
 
     fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
-        visitor.visit_f64(self.bytes.float()?)
+        trace!(self, "expecting f64");
+        parsed_scalar!(self, deserialize_f64, visitor);
+        let start = self.bytes;
+        let number: f64 = self.bytes.float()?;
+        self.rounded_integer(start, number)?;
+        self.number_suffix(true)?;
+
+        visitor.visit_f64(number)
     }
 
     fn deserialize_char<V>(self, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
+        trace!(self, "expecting char");
+        self.check_quoted(b'\'')?;
         visitor.visit_char(self.bytes.char()?)
     }
 
     fn deserialize_str<V>(self, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
-        match self.bytes.string()? {
+        trace!(self, "expecting str");
+        parsed_scalar!(self, deserialize_str, visitor);
+        self.check_quoted(b'"')?;
+        let parsed = self.bytes.string()?;
+        if self.extension_enabled("env") {
+            if let Cow::Owned(s) = env::interpolate(parsed.as_str(), self.options.environment.as_ref())? {
+                return visitor.visit_string(s);
+            }
+        }
+
+        match parsed {
             ParsedStr::Allocated(s) => visitor.visit_string(s),
             ParsedStr::Slice(s) => visitor.visit_borrowed_str(s),
         }
@@ -239,7 +864,26 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         self.deserialize_byte_buf(visitor)
     }
 
+    // Bytes are accepted as byte strings, hex strings, integer arrays or
+    // base64 strings, see `PrettyConfig::bytes_format`.
     fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
+        trace!(self, "expecting byte_buf");
+        match self.bytes.peek() {
+            Some(b'b') => {
+                return match self.bytes.byte_string()? {
+                    ParsedBytes::Allocated(b) => visitor.visit_byte_buf(b),
+                    ParsedBytes::Slice(b) => visitor.visit_borrowed_bytes(b),
+                };
+            }
+            Some(b'x') => return visitor.visit_byte_buf(self.bytes.hex_string()?),
+            Some(b'[') => return visitor.visit_byte_buf(de::Deserialize::deserialize(&mut *self)?),
+            Some(b'@') => {
+                let path = self.bytes.file_reference()?;
+                return visitor.visit_byte_buf(self.resolve(&path)?);
+            }
+            _ => {}
+        }
+
         let res = {
             let string = self.bytes.string()?;
             let base64_str = match string {
@@ -257,11 +901,13 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     }
 
     fn deserialize_option<V>(self, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
-        if self.bytes.consume("None") {
+        if self.bytes.consume_ident("None") {
+            trace!(self, "option: None");
             visitor.visit_none()
-        } else if self.bytes.consume("Some") && { self.bytes.skip_ws()?; self.bytes.consume("(") } {
+        } else if self.explicit_some()? {
+            trace!(self, "option: Some(..)");
             self.bytes.skip_ws()?;
-            let v = visitor.visit_some(&mut *self)?;
+            let v = self.nested(|de| visitor.visit_some(de))?;
             self.bytes.skip_ws()?;
 
             if self.bytes.consume(")") {
@@ -270,13 +916,16 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
                 Err(Error::ExpectedOptionEnd)
             }
         } else {
+            trace!(self, "option: implicit Some");
             visitor.visit_some(&mut *self)
         }
     }
 
     // In Serde, unit means an anonymous value containing no data
     fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
-        if self.bytes.consume("{}") {
+        trace!(self, "expecting unit");
+        // RON writes `()`
+        if self.bytes.consume("{}") || (self.ron() && self.bytes.consume("()")) {
             visitor.visit_unit()
         } else {
             Err(Error::ExpectedUnit)
@@ -284,7 +933,10 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     }
 
     fn deserialize_unit_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
-        if self.bytes.consume_struct_name(name)? {
+        trace!(self, "expecting unit struct `{}`", name);
+        if self.struct_name(name)? {
+            // `Name()`, see `PrettyConfig::unit_parens`
+            self.bytes.consume("()");
             visitor.visit_unit()
         } else {
             self.deserialize_unit(visitor)
@@ -293,7 +945,23 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
 
     // HMM
     fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
-        self.bytes.consume_struct_name(name)?;
+        trace!(self, "expecting newtype struct `{}`", name);
+        if name == VALUE_TOKEN {
+            return self.deserialize_value(visitor);
+        }
+        // The text of the value, see `RawValue`.
+        if name == RAW_VALUE_TOKEN {
+            let start = self.bytes.bytes();
+            <de::IgnoredAny as de::Deserialize>::deserialize(&mut *self)?;
+            return visitor.visit_borrowed_str(str::from_utf8(&start[..start.len() - self.bytes.bytes().len()])?);
+        }
+
+        // `Name(..)` is still read as the newtype, anything else as its content
+        if self.extension_enabled("unwrap_newtypes") && !self.named_newtype_ahead(name) {
+            return visitor.visit_newtype_struct(&mut *self);
+        }
+
+        self.struct_name(name)?;
         self.bytes.skip_ws()?;
 
         if self.bytes.consume("(") {
@@ -314,43 +982,37 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     }
 
     fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
-        if self.bytes.consume("[") {
-            let value = visitor.visit_seq(CommaSeparated::new(b']', self))?;
-            self.bytes.comma()?;
-
-            if self.bytes.consume("]") {
-                Ok(value)
-            } else {
-                Err(Error::ExpectedArrayEnd)
-            }
-        } else {
-            Err(Error::ExpectedArray)
-        }
+        trace!(self, "expecting seq");
+        self.bracketed(b'[', None, visitor)
     }
 
-    fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
-        if self.bytes.consume("(") {
-            let value = visitor.visit_seq(CommaSeparated::new(b')', self))?;
-            self.bytes.comma()?;
-
-            if self.bytes.consume(")") {
-                Ok(value)
-            } else {
-                Err(Error::ExpectedArrayEnd)
-            }
-        } else {
-            Err(Error::ExpectedArray)
-        }
+    fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
+        trace!(self, "expecting tuple");
+        self.bracketed(b'(', Some(len), visitor)
     }
 
     fn deserialize_tuple_struct<V>(self, name: &'static str, len: usize, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
-        self.bytes.consume_struct_name(name)?;
+        trace!(self, "expecting tuple struct `{}`", name);
+        self.struct_name(name)?;
         self.deserialize_tuple(len, visitor)
     }
 
     fn deserialize_map<V>(self, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
+        trace!(self, "expecting map");
+        // Structs with flattened fields are read as maps, their name is
+        // skipped.
+        let mut bytes = self.bytes;
+        if bytes.identifier().is_ok() && bytes.skip_ws().is_ok() && bytes.peek() == Some(b'{') {
+            self.bytes = bytes;
+        }
+
         if self.bytes.consume("{") {
-            let value = visitor.visit_map(CommaSeparated::new(b'}', self))?;
+            let mut access = CommaSeparated::new(b'}', self);
+            // Only when someone hears of duplicate keys, typed maps are common.
+            if access.de.options.warnings.is_some() || access.de.options.warnings_as_errors {
+                access.keys = Some(HashMap::new());
+            }
+            let value = visitor.visit_map(access)?;
             self.bytes.comma()?;
 
             if self.bytes.consume("}") {
@@ -364,15 +1026,27 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     }
 
     fn deserialize_struct<V>(self, name: &'static str, _fields: &'static [&'static str], visitor: V) -> Result<V::Value> where V: Visitor<'de> {
-        self.bytes.consume_struct_name(name)?;
+        trace!(self, "expecting struct `{}`", name);
+        if std::mem::take(&mut self.unwrapped) {
+            return self.struct_fields(name, b')', visitor);
+        }
+
+        self.struct_name(name)?;
         self.bytes.skip_ws()?;
 
-        if self.bytes.consume("{") {
-            let value = visitor.visit_map(CommaSeparated::new(b'}', self))?;
+        let closer = if self.bytes.consume("{") {
+            Some("}")
+        } else if self.ron() && self.bytes.consume("(") {
+            Some(")")
+        } else {
+            None
+        };
+
+        if let Some(closer) = closer {
+            let value = self.struct_fields(name, closer.as_bytes()[0], visitor)?;
             self.bytes.comma()?;
 
-            if self.bytes.consume("}") {
-                // duplicate error occurs after this is returned
+            if self.bytes.consume(closer) {
                 Ok(value)
             } else {
                 Err(Error::ExpectedStructEnd)
@@ -384,9 +1058,10 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         }
     }
 
-    fn deserialize_enum<V>(self,_name: &'static str, _variants: &'static [&'static str], visitor: V) -> Result<V::Value>
+    fn deserialize_enum<V>(self, name: &'static str, _variants: &'static [&'static str], visitor: V) -> Result<V::Value>
     where V: Visitor<'de> {
-        visitor.visit_enum(Enum::new(self))
+        trace!(self, "expecting enum `{}`", name);
+        visitor.visit_enum(Enum::new(self, name))
     }
 
     fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value>
@@ -406,21 +1081,148 @@ struct CommaSeparated<'a, 'de: 'a> {
     de: &'a mut Deserializer<'de>,
     terminator: u8,
     had_comma: bool,
+    // Raw text, position and start of the last key.
+    key: &'de [u8],
+    key_position: Position,
+    key_start: Bytes<'de>,
+    // The struct whose fields these are, for `Options::renames`.
+    ty: Option<&'static str>,
+    // Set while a value is read, so that its errors are told apart from
+    // those about the keys.
+    in_value: bool,
+    // Whether this is an untyped map, whose bare keys are strings.
+    untyped: bool,
+    // Keys seen so far in a map, used to lint duplicate keys.
+    keys: Option<HashMap<&'de [u8], SeenKey>>,
+    // Elements left in a sequence, if they were counted.
+    remaining: Option<usize>,
+    // Elements or entries read so far.
+    index: usize,
+}
+
+// The number of elements before `terminator` when they are all numbers,
+// bools or bare words, such as those of large numeric arrays. Counting gives
+// up at anything that could hide a `,` or the terminator.
+fn count_elements(bytes: &[u8], terminator: u8) -> Option<usize> {
+    let mut count = 0;
+    let mut element = false;
+
+    for &b in bytes {
+        match b {
+            _ if b == terminator => return Some(count + usize::from(element)),
+            b',' => {
+                count += usize::from(element);
+                element = false;
+            }
+            b'"' | b'\'' | b'#' | b'/' | b'$' | b'@' | b'&' | b'*' => return None,
+            _ if is_reserved_char(b) => return None,
+            _ if is_whitespace_char(b) => {}
+            _ => element = true,
+        }
+    }
+
+    None
+}
+
+struct SeenKey {
+    position: Position,
+    shape: &'static str,
 }
 
 impl<'a, 'de> CommaSeparated<'a, 'de> {
     fn new(terminator: u8, de: &'a mut Deserializer<'de>) -> Self {
         CommaSeparated {
+            key_start: de.bytes,
             de,
             terminator,
             had_comma: true,
+            key: &[],
+            key_position: Position { line: 0, col: 0 },
+            ty: None,
+            in_value: false,
+            untyped: false,
+            keys: None,
+            remaining: None,
+            index: 0,
+        }
+    }
+
+    // Keys of untyped maps written as bare identifiers, `{ name: .. }` or
+    // `<name> ..`, are strings rather than unit variants.
+    fn bare_key(&mut self) -> Result<Option<&'de str>> {
+        if !self.untyped {
+            return Ok(None);
+        }
+
+        let mut bytes = self.de.bytes;
+        let raw = bytes.bytes().starts_with(b"r#");
+        let ident = match bytes.identifier() {
+            Ok(ident) => str::from_utf8(ident)?,
+            Err(_) => return Ok(None),
+        };
+
+        let after = bytes;
+        bytes.skip_ws()?;
+        if (raw || !is_keyword(ident)) && matches!(bytes.peek(), Some(b':') | Some(b'>')) {
+            self.de.bytes = after;
+            Ok(Some(ident))
+        } else {
+            Ok(None)
         }
     }
 
+    // Roughly classifies the upcoming value without consuming it. `Name(..)`,
+    // `Some(..)` included, is a variant rather than a tuple.
+    fn peek_shape(&self) -> &'static str {
+        let bytes = self.de.bytes.bytes();
+        let ident = bytes.first().copied().is_some_and(is_ident_first_char);
+        let after_ident = if ident {
+            let rest = &bytes[bytes.iter().take_while(|&&b| is_ident_other_char(b)).count()..];
+            &rest[rest.iter().take_while(|b| b.is_ascii_whitespace()).count()..]
+        } else {
+            bytes
+        };
+
+        match after_ident.first() {
+            Some(b'(') if ident => "a variant",
+            Some(b'[') | Some(b'(') => "a sequence",
+            Some(b'{') | Some(b'<') => "a map",
+            _ => "a single value",
+        }
+    }
+
+    fn lint_duplicate_key(&mut self, raw: &'de [u8], position: Position) -> Result<()> {
+        let shape = self.peek_shape();
+        // `"x"` and `x` name the same key
+        let raw = match raw {
+            [b'"', inner @ .., b'"'] => inner,
+            _ => raw,
+        };
+
+        if let Some(keys) = self.keys.as_mut() {
+            if let Some(first) = keys.get(raw) {
+                let key = String::from_utf8_lossy(raw).into_owned();
+                let code = if first.shape != shape {
+                    Error::MismatchedDuplicateKey { key, first: first.position, second: position, expected: first.shape, found: shape }
+                } else {
+                    Error::DuplicateKey { key, first: first.position, second: position }
+                };
+                self.de.warn(code, position)?;
+            } else {
+                keys.insert(raw, SeenKey { position, shape });
+            }
+        }
+
+        Ok(())
+    }
+
     fn has_element(&mut self) -> Result<bool> {
         self.de.bytes.skip_ws()?;
 
         match (self.had_comma, self.de.bytes.peek_or_eof()? != self.terminator) {
+            (true, true) if self.index >= self.de.options.limits.collection_len => {
+                Err(Error::CollectionTooLarge(self.de.options.limits.collection_len))
+            }
             // Trailing comma, maybe has a next element
             (true, has_element) => Ok(has_element),
             // No trailing comma but terminator
@@ -436,31 +1238,73 @@ impl<'de, 'a> de::SeqAccess<'de> for CommaSeparated<'a, 'de> {
 
     fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>> where T: DeserializeSeed<'de> {
         if self.has_element()? {
-            let res = seed.deserialize(&mut *self.de)?;
+            self.de.enter_index(self.index);
+            let res = self.de.value(seed, |seed, de| seed.deserialize(de));
+            self.de.leave();
+            let res = res?;
+            self.index += 1;
             self.had_comma = self.de.bytes.comma()?;
+            self.remaining = self.remaining.map(|n| n.saturating_sub(1));
 
             Ok(Some(res))
         } else {
             Ok(None)
         }
     }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.remaining
+    }
 }
 
 impl<'de, 'a> de::MapAccess<'de> for CommaSeparated<'a, 'de> {
     type Error = Error;
 
     fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>> where K: DeserializeSeed<'de> {
-        if self.has_element()? {
-            if self.de.bytes.consume("<") {
-                return seed.deserialize(&mut *self.de).map(Some);
+        while self.has_element()? {
+            if self.de.skip_definition()? {
+                self.had_comma = self.de.bytes.comma()?;
+                continue;
+            }
+
+            let is_cavetta = self.de.bytes.consume("<");
+            if is_cavetta {
+                self.de.bytes.skip_ws()?;
+            }
+            let start = self.de.bytes.bytes();
+            self.key_start = self.de.bytes;
+            self.key_position = self.de.bytes.position();
+
+            let renamed = match self.ty {
+                Some(ty) => self.de.renamed(ty)?,
+                None => None,
+            };
+            let key = if let Some(field) = self.bare_key()? {
+                seed.deserialize(BorrowedStrDeserializer::<Error>::new(field))
+            } else if let Some(field) = renamed {
+                seed.deserialize(StrDeserializer::<Error>::new(&field))
+            } else if is_cavetta {
+                seed.deserialize(&mut *self.de)
             } else if self.terminator == b')' {
-                return seed.deserialize(&mut IdDeserializer::new(&mut *self.de)).map(Some);
-            } 
+                seed.deserialize(&mut IdDeserializer::new(&mut *self.de))
+            } else {
+                seed.deserialize(&mut KeyDeserializer::new(&mut *self.de))
+            };
+            // An unknown field is reported where its name starts.
+            let key = key.inspect_err(|e| {
+                if matches!(e, Error::NoSuchStructField { .. }) {
+                    self.de.bytes = self.key_start;
+                }
+            })?;
 
-            seed.deserialize(&mut *self.de).map(Some)
-        } else {
-            Ok(None)
+            self.key = &start[..start.len() - self.de.bytes.bytes().len()];
+            #[cfg(feature = "trace")]
+            self.de.trace_at(self.key_position, format_args!("key `{}`", String::from_utf8_lossy(self.key)));
+
+            return Ok(Some(key));
         }
+
+        Ok(None)
     }
 
     fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value> where V: DeserializeSeed<'de> {
@@ -468,7 +1312,14 @@ impl<'de, 'a> de::MapAccess<'de> for CommaSeparated<'a, 'de> {
 
         if self.de.bytes.consume(":") || self.de.bytes.consume(">") {
             self.de.bytes.skip_ws()?;
-            let res = seed.deserialize(&mut TagDeserializer::new(&mut *self.de))?;
+            self.lint_duplicate_key(self.key, self.key_position)?;
+            self.in_value = true;
+            self.de.enter(self.key);
+            let res = self.de.value(seed, |seed, de| seed.deserialize(&mut TagDeserializer::new(de)));
+            self.de.leave();
+            let res = res?;
+            self.in_value = false;
+            self.index += 1;
             self.had_comma = self.de.bytes.comma()?;
 
             Ok(res)
@@ -480,11 +1331,12 @@ impl<'de, 'a> de::MapAccess<'de> for CommaSeparated<'a, 'de> {
 
 struct Enum<'a, 'de: 'a> {
     de: &'a mut Deserializer<'de>,
+    name: &'static str,
 }
 
 impl<'a, 'de> Enum<'a, 'de> {
-    fn new(de: &'a mut Deserializer<'de>) -> Self {
-        Enum { de }
+    fn new(de: &'a mut Deserializer<'de>, name: &'static str) -> Self {
+        Enum { de, name }
     }
 }
 
@@ -496,7 +1348,17 @@ impl<'de, 'a> de::EnumAccess<'de> for Enum<'a, 'de> {
     where V: DeserializeSeed<'de> {
         self.de.bytes.skip_ws()?;
 
-        let value = seed.deserialize(&mut *self.de)?;
+        // An unknown variant is reported where its name starts.
+        if let Some(variant) = self.de.renamed(self.name)? {
+            return Ok((seed.deserialize(StrDeserializer::<Error>::new(&variant))?, self));
+        }
+
+        let before = self.de.bytes;
+        let value = seed.deserialize(&mut *self.de).inspect_err(|e| {
+            if matches!(e, Error::NoSuchEnumVariant { .. }) {
+                self.de.bytes = before;
+            }
+        })?;
 
         Ok((value, self))
     }
@@ -505,7 +1367,10 @@ impl<'de, 'a> de::EnumAccess<'de> for Enum<'a, 'de> {
 impl<'de, 'a> de::VariantAccess<'de> for Enum<'a, 'de> {
     type Error = Error;
 
+    // `Variant()` as well, see `PrettyConfig::unit_parens`.
     fn unit_variant(self) -> Result<()> {
+        self.de.bytes.consume("()");
+
         Ok(())
     }
 
@@ -513,9 +1378,19 @@ impl<'de, 'a> de::VariantAccess<'de> for Enum<'a, 'de> {
     where T: DeserializeSeed<'de> {
         self.de.bytes.skip_ws()?;
 
+        // `Variant{ field: .. }`, a struct without the parentheses around it
+        let unwrap = self.de.extension_enabled("unwrap_variant_newtypes");
+        if unwrap && self.de.bytes.peek() == Some(b'{') {
+            return self.de.value(seed, |seed, de| seed.deserialize(de));
+        }
+
+        let bytes = self.de.bytes;
         if self.de.bytes.consume("(") {
             self.de.bytes.skip_ws()?;
-            let val = seed.deserialize(&mut *self.de)?;
+            self.de.unwrapped = unwrap && fields_ahead(bytes);
+            let val = self.de.value(seed, |seed, de| seed.deserialize(de));
+            self.de.unwrapped = false;
+            let val = val?;
             self.de.bytes.comma()?;
 
             if self.de.bytes.consume(")") {
@@ -0,0 +1,152 @@
+// Reads one value of a document by its path, such as `server.tls` for the
+// `tls` entry of the `server` entry or `nodes.2` for the third element of
+// `nodes`. The values in front of it are skipped without being built and
+// nothing after it is read, so a quick lookup in a large scene does not pay
+// for the whole scene.
+
+use std::borrow::Cow;
+use std::marker::PhantomData;
+use std::str;
+
+use serde::de::{ DeserializeSeed, IgnoredAny };
+
+use super::{ Deserializer, Error, Result };
+use crate::parse::ParsedStr;
+
+impl<'de> Deserializer<'de> {
+    // Moves to the start of the value at `path`, whose segments are separated
+    // by `.`.
+    pub(crate) fn seek(&mut self, path: &str) -> Result<()> {
+        for segment in path.split('.').filter(|segment| !segment.is_empty()) {
+            self.bytes.skip_ws()?;
+            if !self.seek_segment(segment)? {
+                return Err(Error::NoSuchPath(String::from(path)));
+            }
+        }
+
+        self.bytes.skip_ws()
+    }
+
+    // Moves into the current value, to its entry named `segment` or to its
+    // element at index `segment`.
+    fn seek_segment(&mut self, segment: &str) -> Result<bool> {
+        while self.bytes.peek() == Some(b'$') && self.extension_enabled("variables") {
+            let name = self.bytes.variable()?;
+            self.bytes = self.definition(name)?;
+        }
+
+        // a struct name or variant in front of the content
+        let mut bytes = self.bytes;
+        if bytes.identifier().is_ok() && bytes.skip_ws().is_ok() && matches!(bytes.peek(), Some(b'{' | b'(')) {
+            self.bytes = bytes;
+        }
+
+        if self.bytes.consume("{") {
+            return self.seek_entry("}", segment);
+        }
+        if self.bytes.consume("(") {
+            if self.ron() && self.field_key_ahead() {
+                return self.seek_entry(")", segment);
+            }
+            return self.seek_element(")", segment);
+        }
+        if self.bytes.consume("[") {
+            return self.seek_element("]", segment);
+        }
+
+        Ok(false)
+    }
+
+    fn seek_entry(&mut self, closer: &str, segment: &str) -> Result<bool> {
+        loop {
+            self.bytes.skip_ws()?;
+            if self.bytes.consume(closer) {
+                return Ok(false);
+            }
+            if self.skip_definition()? {
+                self.bytes.comma()?;
+                continue;
+            }
+
+            let found = self.entry_key()? == segment;
+            self.bytes.skip_ws()?;
+            if !self.bytes.consume(":") && !self.bytes.consume(">") {
+                return Err(Error::ExpectedMapSeparator);
+            }
+            self.bytes.skip_ws()?;
+            if found {
+                return Ok(true);
+            }
+
+            self.skip_value()?;
+            if !self.bytes.comma()? {
+                self.bytes.skip_ws()?;
+                return match self.bytes.consume(closer) {
+                    true => Ok(false),
+                    false => Err(Error::ExpectedMapEnd),
+                };
+            }
+        }
+    }
+
+    fn seek_element(&mut self, closer: &str, segment: &str) -> Result<bool> {
+        let Ok(index) = segment.parse::<usize>() else {
+            return Ok(false);
+        };
+
+        for _ in 0..index {
+            self.bytes.skip_ws()?;
+            if self.bytes.consume(closer) {
+                return Ok(false);
+            }
+
+            self.skip_value()?;
+            if !self.bytes.comma()? {
+                return Ok(false);
+            }
+        }
+
+        self.bytes.skip_ws()?;
+        Ok(!self.bytes.bytes().starts_with(closer.as_bytes()))
+    }
+
+    // A bare or quoted key by its name, any other key as it is written.
+    fn entry_key(&mut self) -> Result<Cow<'de, str>> {
+        if self.bytes.consume("<") {
+            self.bytes.skip_ws()?;
+        }
+
+        let mut bytes = self.bytes;
+        if let Ok(ident) = bytes.identifier() {
+            let after = bytes;
+            bytes.skip_ws()?;
+            if matches!(bytes.peek(), Some(b':' | b'>')) {
+                self.bytes = after;
+                return Ok(Cow::Borrowed(str::from_utf8(ident)?));
+            }
+        }
+
+        if self.bytes.peek() == Some(b'"') {
+            return Ok(match self.bytes.string()? {
+                ParsedStr::Allocated(s) => Cow::Owned(s),
+                ParsedStr::Slice(s) => Cow::Borrowed(s),
+            });
+        }
+
+        let start = self.bytes.bytes();
+        self.skip_value()?;
+
+        Ok(Cow::Borrowed(str::from_utf8(&start[..start.len() - self.bytes.bytes().len()])?))
+    }
+
+    fn field_key_ahead(&self) -> bool {
+        let mut bytes = self.bytes;
+        bytes.skip_ws().is_ok() && bytes.identifier().is_ok() && bytes.skip_ws().is_ok() && bytes.peek() == Some(b':')
+    }
+
+    fn skip_value(&mut self) -> Result<()> {
+        self.value(PhantomData::<IgnoredAny>, |seed, de| seed.deserialize(de))?;
+
+        Ok(())
+    }
+}
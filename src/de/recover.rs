@@ -0,0 +1,280 @@
+use super::Deserializer;
+use crate::options::Options;
+
+use crate::error::{ Error, Position, SpannedError };
+use crate::parse::{ is_reserved_char, is_whitespace_char, Bytes };
+use serde::de::{ Deserialize, IgnoredAny };
+
+type Result<T = ()> = std::result::Result<T, SpannedError>;
+
+// Checks the syntax of a whole document, carrying on after errors. After an
+// error the rest of the element is skipped up to the next `,`, `;`, line break
+// or closing delimiter at the same depth, so a badly broken file still yields
+// one error per problem rather than the first one only.
+pub fn syntax_errors(s: &str) -> Vec<SpannedError> {
+    check(s, usize::MAX)
+}
+
+// Deserializes like `from_str`, but a document that fails with syntax errors
+// reports all of them instead of the first one. Errors only the type can tell
+// apart, such as a missing field, are reported once the syntax is sound. What
+// `from_str` accepts, `check_str` does too.
+pub fn check_str<'a, T>(s: &'a str) -> std::result::Result<T, Vec<SpannedError>> where T: Deserialize<'a> {
+    Options::default().check_str(s)
+}
+
+pub(crate) fn check(s: &str, max_errors: usize) -> Vec<SpannedError> {
+    let bytes = match Bytes::new(s.as_bytes()) {
+        Ok(bytes) => bytes,
+        Err(e) => return vec![e],
+    };
+
+    let mut checker = Checker { bytes, errors: Vec::new(), max_errors };
+    // The names are checked when deserializing, only the header syntax here.
+    if let Err(e) = checker.bytes.extensions() {
+        return vec![checker.bytes.span_error(e)];
+    }
+    if let Err(e) = checker.value().and_then(|_| checker.skip_ws()) {
+        checker.errors.push(e);
+    } else if !checker.bytes.bytes().is_empty() {
+        checker.errors.push(checker.bytes.span_error(Error::TrailingCharacters));
+    }
+
+    checker.errors.truncate(max_errors);
+    checker.errors
+}
+
+struct Checker<'a> {
+    bytes: Bytes<'a>,
+    errors: Vec<SpannedError>,
+    max_errors: usize,
+}
+
+impl Checker<'_> {
+    fn skip_ws(&mut self) -> Result {
+        self.bytes.skip_ws().map_err(|e| self.bytes.span_error(e))
+    }
+
+    fn value(&mut self) -> Result {
+        // `Name(..)` and `Name { .. }` are checked like their unnamed forms.
+        skip_anchor(&mut self.bytes).map_err(|e| self.bytes.span_error(e))?;
+        let mut bytes = self.bytes;
+        if bytes.identifier().is_ok() && bytes.skip_ws().is_ok() && matches!(bytes.peek(), Some(b'(' | b'{')) {
+            self.bytes = bytes;
+        }
+
+        match self.bytes.peek() {
+            Some(b'[') => self.collection(b']'),
+            Some(b'(') => self.collection(b')'),
+            Some(b'{') => self.collection(b'}'),
+            _ => skip_scalar(&mut self.bytes),
+        }
+    }
+
+    fn collection(&mut self, closer: u8) -> Result {
+        let open = self.bytes.position();
+        let _ = self.bytes.advance_single();
+
+        self.elements(closer, open)
+    }
+
+    fn elements(&mut self, closer: u8, open: Position) -> Result {
+        loop {
+            if self.errors.len() >= self.max_errors {
+                return Ok(());
+            }
+            self.skip_ws()?;
+
+            match self.bytes.peek() {
+                Some(b) if b == closer => {
+                    let _ = self.bytes.advance_single();
+                    return Ok(());
+                }
+                // Any closing delimiter ends the innermost collection, the
+                // wrong one is taken for a typo.
+                Some(b')' | b']' | b'}') => {
+                    let error = self.bytes.span_error(end_error(closer));
+                    let _ = self.bytes.advance_single();
+                    return Err(error);
+                }
+                // An unclosed collection is reported where it was opened.
+                None => return Err(SpannedError { code: end_error(closer), position: open }),
+                Some(_) => {}
+            }
+
+            match self.element(closer).and_then(|_| self.separator()) {
+                Ok(()) => {}
+                // A missing comma is assumed to be there.
+                Err(e) if e.code == Error::ExpectedComma => self.errors.push(e),
+                Err(e) => {
+                    self.errors.push(e);
+                    self.synchronize();
+                }
+            }
+        }
+    }
+
+    // A value, a `key: value` entry or a `<key> value` cavetta entry. Only
+    // maps require the key, tuples may hold named fields.
+    fn element(&mut self, closer: u8) -> Result {
+        if closer == b'}' && self.bytes.consume("<") {
+            self.skip_ws()?;
+            self.value()?;
+            self.skip_ws()?;
+            if !self.bytes.consume(">") {
+                return Err(self.bytes.span_error(Error::ExpectedMapSeparator));
+            }
+        } else {
+            self.value()?;
+            self.skip_ws()?;
+            if closer == b']' || (closer == b')' && self.bytes.peek() != Some(b':')) {
+                return Ok(());
+            }
+            if !self.bytes.consume(":") {
+                return Err(self.bytes.span_error(Error::ExpectedMapSeparator));
+            }
+        }
+
+        self.skip_ws()?;
+        self.value()
+    }
+
+    fn separator(&mut self) -> Result {
+        self.skip_ws()?;
+
+        if self.bytes.consume(",") || matches!(self.bytes.peek(), Some(b')' | b']' | b'}')) {
+            Ok(())
+        } else {
+            Err(self.bytes.span_error(Error::ExpectedComma))
+        }
+    }
+
+    // Skips to just after the next `,`, `;` or line break at the current
+    // depth, or to the closing delimiter of the current collection.
+    fn synchronize(&mut self) {
+        let bytes = self.bytes.bytes();
+        let mut depth = 0usize;
+        let mut i = 0;
+
+        while let Some(&b) = bytes.get(i) {
+            match b {
+                b'"' => {
+                    i += 1;
+                    while let Some(&c) = bytes.get(i) {
+                        i += if c == b'\\' { 2 } else { 1 };
+                        if c == b'"' {
+                            break;
+                        }
+                    }
+                    continue;
+                }
+                b'\'' if char_len(&bytes[i..]).is_some() => {
+                    i += char_len(&bytes[i..]).unwrap_or(1);
+                    continue;
+                }
+                b'#' => {
+                    i += bytes[i..].iter().take_while(|&&c| c != b'\n').count();
+                    continue;
+                }
+                b'(' | b'[' | b'{' => depth += 1,
+                b')' | b']' | b'}' if depth == 0 => break,
+                b')' | b']' | b'}' => depth -= 1,
+                b',' | b';' | b'\n' if depth == 0 => {
+                    i += 1;
+                    break;
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+
+        let _ = self.bytes.advance(i.min(bytes.len()));
+    }
+}
+
+// Moves past a value other than a collection, which is a single token checked
+// by the deserializer. `@file(..)` is checked without loading the file, and
+// `$name`, `$name + ".."` or `*name` without looking the names up.
+pub(crate) fn skip_scalar(bytes: &mut Bytes) -> Result {
+    if bytes.peek() == Some(b'@') {
+        return bytes.file_reference().map(|_| ()).map_err(|e| bytes.span_error(e));
+    }
+
+    if bytes.peek() == Some(b'$') {
+        return skip_variables(bytes).map_err(|e| bytes.span_error(e));
+    }
+
+    if let Some((_, after)) = anchor_name(*bytes, "*") {
+        *bytes = after;
+        return Ok(());
+    }
+
+    let mut de = Deserializer::ahead(*bytes);
+    let result = IgnoredAny::deserialize(&mut de);
+    *bytes = de.bytes;
+
+    result.map(|_| ()).map_err(|e| bytes.span_error(e))
+}
+
+// The `&name` label in front of a value, for the `anchors` extension.
+pub(crate) fn skip_anchor(bytes: &mut Bytes) -> std::result::Result<(), Error> {
+    if let Some((_, after)) = anchor_name(*bytes, "&") {
+        *bytes = after;
+        bytes.skip_ws()?;
+    }
+
+    Ok(())
+}
+
+// Past `&name` or `*name`, unless that only starts a bare word like `*.txt`.
+pub(crate) fn anchor_name<'a>(mut bytes: Bytes<'a>, sigil: &str) -> Option<(&'a str, Bytes<'a>)> {
+    if !bytes.consume(sigil) {
+        return None;
+    }
+
+    let name = bytes.identifier().ok().and_then(|name| std::str::from_utf8(name).ok())?;
+    let ended = bytes.peek().is_none_or(|b| is_reserved_char(b) || is_whitespace_char(b));
+
+    ended.then_some((name, bytes))
+}
+
+fn skip_variables(bytes: &mut Bytes) -> std::result::Result<(), Error> {
+    bytes.variable()?;
+
+    loop {
+        let mut ahead = *bytes;
+        ahead.skip_ws()?;
+        if !ahead.consume("+") {
+            return Ok(());
+        }
+
+        *bytes = ahead;
+        bytes.skip_ws()?;
+        match bytes.peek() {
+            Some(b'$') => bytes.variable().map(|_| ())?,
+            _ => bytes.string().map(|_| ())?,
+        }
+    }
+}
+
+pub(crate) fn end_error(closer: u8) -> Error {
+    match closer {
+        b']' => Error::ExpectedArrayEnd,
+        b')' => Error::ExpectedStructEnd,
+        _ => Error::ExpectedMapEnd,
+    }
+}
+
+// Length of the char literal at the start of `bytes`, if there is one. A
+// stray `'`, say in the middle of a broken literal, is not skipped over.
+fn char_len(bytes: &[u8]) -> Option<usize> {
+    let content = match bytes.get(1)? {
+        b'\\' => bytes[2..].iter().take(10).position(|&b| b == b'\'')? + 1,
+        &first => match first.leading_ones() {
+            0 => 1,
+            n => n as usize,
+        },
+    };
+
+    (bytes.get(1 + content) == Some(&b'\'')).then_some(content + 2)
+}
@@ -12,7 +12,7 @@ impl<'a, 'b: 'a> TagDeserializer<'a, 'b> {
     }
 }
 
-impl<'a, 'b: 'a, 'c> de::Deserializer<'b> for &'c mut TagDeserializer<'a, 'b> {
+impl<'a, 'b> de::Deserializer<'b> for &mut TagDeserializer<'a, 'b> {
     type Error = Error;
 
     fn deserialize_str<V>(self, visitor: V) -> Result<V::Value>
@@ -155,8 +155,13 @@ impl<'a, 'b: 'a, 'c> de::Deserializer<'b> for &'c mut TagDeserializer<'a, 'b> {
         self.d.deserialize_enum(name, variants, visitor)
     }
 
+    // The value of an entry the `Deserialize` impl skips.
     fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value>
     where V: Visitor<'b> {
-        self.d.deserialize_ignored_any(visitor)
+        let started = self.d.start_ignored();
+        let value = self.d.deserialize_ignored_any(visitor);
+        self.d.end_ignored(started);
+
+        value
     }
 }
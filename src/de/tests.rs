@@ -86,6 +86,18 @@ fn test_struct() {
     assert_eq!(Ok(my_struct), from_str("MyStruct { <x> 4, <y> 7 }"));
 }
 
+#[test]
+fn test_quoted_keys() {
+    let my_struct = MyStruct { x: 4.0, y: 7.0 };
+    assert_eq!(Ok(my_struct), from_str("MyStruct { \"x\": 4, \"y\": 7 }"));
+
+    let map = HashMap::from([(1u32, true), (2, false)]);
+    assert_eq!(Ok(map), from_str("{ \"1\": true, \"2\": false }"));
+
+    let map = HashMap::from([(String::from("hello world"), 'c')]);
+    assert_eq!(Ok(map), from_str("{ \"hello world\": 'c' }"));
+}
+
 #[test]
 fn test_vec_in_map() {
     #[derive(Clone, Debug, PartialEq, Deserialize)]
@@ -174,7 +186,7 @@ fn test_nested_map() {
         x: HashMap<String, HashMap<u16, u8>> 
     }
 
-    let nested_map = NestedMap { x: HashMap::from([("first".to_string(), HashMap::from([(4, 5), (6, 9)]))]) };
+    let _nested_map = NestedMap { x: HashMap::from([("first".to_string(), HashMap::from([(4, 5), (6, 9)]))]) };
     
     // Spaga Construction
     // assert_eq!(Ok(&nested_map),
@@ -235,6 +247,64 @@ fn test_array() {
     assert_eq!(Ok([String::from("zme"), String::from("rald")].to_vec()), from_str("[\"zme\",rald]"));
 }
 
+#[test]
+fn test_array_length() {
+    use serde::de::{ SeqAccess, Visitor };
+
+    let length = |expected, found, col| SpannedError { code: Error::ExpectedArrayLength { expected, found }, position: Position { line: 1, col } };
+    assert_eq!(from_str::<[i32; 5]>("(1,2,3)").unwrap_err(), length(5, 3, 7));
+    assert_eq!(from_str::<(u8, u8)>("(1, 2, 3, 4)").unwrap_err(), length(2, 4, 12));
+    assert_eq!(from_str::<[u8; 2]>("(1, 2, 3, )").unwrap_err(), length(2, 3, 11));
+    assert_eq!(from_str::<[u8; 2]>("(1, 2, )"), Ok([1, 2]));
+    assert_eq!(length(5, 1, 1).code.to_string(), "Expected 5 elements but found one element instead");
+
+    // plain values are counted up front
+    struct Hint(Option<usize>);
+
+    impl<'de> Deserialize<'de> for Hint {
+        fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            struct HintVisitor;
+
+            impl<'de> Visitor<'de> for HintVisitor {
+                type Value = Hint;
+
+                fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                    f.write_str("a sequence")
+                }
+
+                fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Hint, A::Error> {
+                    let hint = seq.size_hint();
+                    while seq.next_element::<serde::de::IgnoredAny>()?.is_some() {}
+                    Ok(Hint(hint))
+                }
+            }
+
+            deserializer.deserialize_seq(HintVisitor)
+        }
+    }
+
+    assert_eq!(from_str::<Hint>("[1, 2.5, true, -4,]").unwrap().0, Some(4));
+    assert_eq!(from_str::<Hint>("[]").unwrap().0, Some(0));
+    assert_eq!(from_str::<Hint>("[1, \"a,b\"]").unwrap().0, None);
+    assert_eq!(from_str::<Hint>("[[1], [2]]").unwrap().0, None);
+}
+
+#[test]
+fn test_brackets() {
+    use crate::options::{ Brackets, Options };
+
+    let mismatched = |expected, found| Error::MismatchedBrackets { expected, found };
+    assert_eq!(from_str::<Vec<u8>>("(1, 2)").unwrap_err().code, mismatched('[', '('));
+    assert_eq!(from_str::<(u8, u8)>("[1, 2]").unwrap_err().code, mismatched('(', '['));
+    assert_eq!(from_str::<[u8; 2]>("[1, 2]").unwrap_err().code, mismatched('(', '['));
+
+    let either = Options::new().brackets(Brackets::Interchangeable);
+    assert_eq!(either.from_str::<Vec<u8>>("(1, 2,)"), Ok(vec![1, 2]));
+    assert_eq!(either.from_str::<(u8, bool)>("[1, true]"), Ok((1, true)));
+    assert_eq!(either.from_str::<[u8; 2]>("[1, 2]"), Ok([1, 2]));
+    assert_eq!(either.from_str::<Vec<u8>>("(1, 2]").unwrap_err().code, Error::ExpectedComma);
+}
+
 #[test]
 fn test_string() {
     let s: String = from_str("\"わ\"").unwrap();
@@ -251,6 +321,185 @@ fn test_char() {
     assert_eq!(Ok('\''), from_str::<char>("'\\''"));
 }
 
+#[test]
+fn test_string_concatenation() {
+    use crate::value::Value;
+
+    assert_eq!(Ok(String::from("long string parts")), from_str("\"long \" \"string \"\n    \"parts\""));
+    assert_eq!(Ok(String::from("a\nb")), from_str::<String>("\"a\\n\" # the first line\n\"b\""));
+    assert_eq!(Ok(vec![String::from("ab"), String::from("c")]), from_str("[\"a\" \"b\", \"c\"]"));
+    assert_eq!(
+        "[\"/usr/\" \"share\"]".parse::<Value>().map_err(|e| e.code),
+        Ok(Value::Seq(vec![Value::from("/usr/share")]))
+    );
+
+    // only quoted strings are joined
+    assert!(from_str::<Vec<String>>("[\"a\" b]").is_err());
+}
+
+#[test]
+fn test_env_interpolation() {
+    use crate::Environment;
+
+    let environment = Environment::new(|name| (name == "HOME").then(|| String::from("/home/me")));
+    let options = Options::default().environment(environment);
+    let source = "#![enable(env)]\n[\"${HOME}/.config\", \"${PORT:-8080}\", \"$${HOME}\", plain]";
+    assert_eq!(
+        options.from_str(source),
+        Ok(vec![String::from("/home/me/.config"), String::from("8080"), String::from("${HOME}"), String::from("plain")])
+    );
+
+    // only documents that enable it are expanded
+    assert_eq!(options.from_str("\"${HOME}\""), Ok(String::from("${HOME}")));
+
+    assert_eq!(
+        options.from_str::<String>("#![enable(env)] \"${PORT}\"").map_err(|e| e.code),
+        Err(Error::Interpolation { name: String::from("PORT"), message: String::from("variable is not set") })
+    );
+    assert!(matches!(
+        Options::default().from_str::<String>("#![enable(env)] \"${HOME}\"").map_err(|e| e.code),
+        Err(Error::Interpolation { .. })
+    ));
+    assert!(options.from_str::<String>("#![enable(env)] \"${HOME\"").is_err());
+}
+
+#[test]
+fn test_variables() {
+    use crate::value::Value;
+    use crate::Options;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Paths {
+        fonts: String,
+        icons: Vec<String>,
+        size: Option<u32>,
+    }
+
+    let source = "#![enable(variables)]
+    {
+        $base: \"/usr/share\",
+        $size: 12,
+        fonts: $base + \"/fonts\", # joined
+        icons: [$icons, $base],
+        size: $size,
+        $icons: $base + \"/\" + $name,
+        $name: icons,
+    }";
+    let paths = Paths {
+        fonts: String::from("/usr/share/fonts"),
+        icons: vec![String::from("/usr/share/icons"), String::from("/usr/share")],
+        size: Some(12),
+    };
+    let allow = Options::new().extension("variables");
+    assert_eq!(allow.from_str(source), Ok(paths));
+    assert!(crate::de::syntax_errors(source).is_empty());
+
+    let value: Value = allow.from_str(source).unwrap();
+    assert_eq!(value.as_map().map(|map| map.len()), Some(3));
+
+    let variable = |name: &str, message: &str| Error::Variable { name: String::from(name), message: String::from(message) };
+    assert_eq!(
+        allow.from_str::<Vec<u8>>("#![enable(variables)] [$missing]").map_err(|e| e.code),
+        Err(variable("missing", "is not defined"))
+    );
+    assert_eq!(
+        allow.from_str::<HashMap<String, u8>>("#![enable(variables)] { $a: $b, $b: $a, x: $a }").map_err(|e| e.code),
+        Err(variable("b", "refers to itself through $b -> $a -> $b"))
+    );
+    assert_eq!(
+        allow.from_str::<HashMap<String, u8>>("#![enable(variables)] { $a: $a, x: 1 }").map_err(|e| e.code),
+        Err(variable("a", "refers to itself through $a -> $a"))
+    );
+    assert_eq!(
+        allow.from_str::<HashMap<String, u8>>("#![enable(variables)] { $a: 1, $a: 2 }").map_err(|e| e.code),
+        Err(variable("a", "is defined twice"))
+    );
+    assert_eq!(
+        allow.from_str::<HashMap<String, String>>("#![enable(variables)] { $a: [1], x: $a + \"x\" }").map_err(|e| e.code),
+        Err(variable("a", "is not a string and cannot be joined"))
+    );
+
+    // without the extension `$` is not part of the grammar
+    assert!(from_str::<Vec<u8>>("[$a]").is_err());
+
+    // documents cannot enable it on their own
+    assert_eq!(
+        from_str::<Paths>(source).map_err(|e| e.code),
+        Err(Error::ExtensionNotAllowed(String::from("variables")))
+    );
+
+    // each level reads the one below ten times, 10^7 values from 200 bytes
+    let mut bomb = String::from("#![enable(variables)] { $a0: 1");
+    for level in 1..8 {
+        bomb.push_str(&format!(", $a{}: [{}]", level, vec![format!("$a{}", level - 1); 10].join(", ")));
+    }
+    bomb.push_str(", x: $a7 }");
+    assert_eq!(
+        allow.from_str::<Value>(&bomb).map_err(|e| e.code),
+        Err(Error::ExpansionTooLarge(1 << 20))
+    );
+    let limits = crate::options::Limits::new().expansion(100);
+    assert_eq!(
+        allow.clone().limits(limits).from_str::<HashMap<String, u8>>("#![enable(variables)] { $a: 1, x: $a }"),
+        Ok(HashMap::from([(String::from("x"), 1)]))
+    );
+}
+
+#[test]
+fn test_tokenizer() {
+    use crate::de::{ Event, Tokenizer };
+    use crate::error::Span;
+    use std::borrow::Cow;
+
+    let source = "#![enable(hex_strings)]
+Scene {
+    name: \"main\", # a comment
+    nodes: [Light(1.5, On), None],
+    \"tags\": { <x\"00ff\"> 'c', () : -0x1F },
+    path: usr/share,
+}";
+    let events: Vec<Event> = Tokenizer::new(source).unwrap().map(|event| event.map(|(event, _)| event)).collect::<Result<_, _>>().unwrap();
+    assert_eq!(events, vec![
+        Event::Extension("hex_strings"),
+        Event::StructStart(Some("Scene")),
+        Event::FieldKey("name"),
+        Event::Str(Cow::Borrowed("main")),
+        Event::FieldKey("nodes"),
+        Event::SeqStart,
+        Event::TupleStart(Some("Light")),
+        Event::Number("1.5"),
+        Event::Ident("On"),
+        Event::TupleEnd,
+        Event::None,
+        Event::SeqEnd,
+        Event::Key,
+        Event::Str(Cow::Borrowed("tags")),
+        Event::StructStart(None),
+        Event::Key,
+        Event::Bytes(Cow::Owned(vec![0, 255])),
+        Event::Char('c'),
+        Event::Key,
+        Event::Unit,
+        Event::Number("-0x1F"),
+        Event::StructEnd,
+        Event::FieldKey("path"),
+        Event::Str(Cow::Borrowed("usr/share")),
+        Event::StructEnd,
+    ]);
+
+    let mut tokenizer = Tokenizer::new("[true,\n  \"two\"]").unwrap();
+    let spans: Vec<Span> = tokenizer.by_ref().map(|event| event.unwrap().1).collect();
+    let span = |line, col, end_line, end_col| Span { start: Position { line, col }, end: Position { line: end_line, col: end_col } };
+    assert_eq!(spans, vec![span(1, 1, 1, 2), span(1, 2, 1, 6), span(2, 3, 2, 8), span(2, 8, 2, 9)]);
+    assert!(tokenizer.next().is_none());
+
+    let mut tokenizer = Tokenizer::new("[1 2]").unwrap();
+    assert!(matches!(tokenizer.next(), Some(Ok((Event::SeqStart, _)))));
+    assert!(matches!(tokenizer.next(), Some(Ok((Event::Number("1"), _)))));
+    assert_eq!(tokenizer.next(), Some(err(Error::ExpectedComma, 1, 4)));
+    assert_eq!(tokenizer.next(), None);
+}
+
 #[test]
 fn test_bool() {
     assert_eq!(Ok(true), from_str("true"));
@@ -260,6 +509,20 @@ fn test_bool() {
 #[test]
 fn test_escape() {
     assert_eq!("\"Marked\"", from_str::<String>(r#""\"Marked\"""#).unwrap());
+    assert_eq!(
+        Ok(String::from("a\n\t\r\0\\'b\u{65e5}\u{1f600}A")),
+        from_str::<String>(r#""a\n\t\r\0\\\'b\u{65e5}\u{1F600}\x41""#)
+    );
+
+    for (source, c) in [(r"'\n'", '\n'), (r"'\0'", '\0'), (r"'\x7f'", '\x7f'), (r"'\u{e9}'", 'é'), (r#"'\"'"#, '"')] {
+        assert_eq!(Ok(c), from_str::<char>(source), "{}", source);
+    }
+
+    // `\x` stands for a byte, which in strings must be ASCII
+    assert_eq!(from_str::<String>(r#""\xff""#), err(Error::InvalidEscape("Byte escape out of ASCII range"), 1, 6));
+    assert!(from_str::<char>(r"'\u{d800}'").is_err());
+    assert!(from_str::<char>(r"'\u{1234567}'").is_err());
+    assert!(from_str::<String>(r#""\q""#).is_err());
 }
 
 #[test]
@@ -365,13 +628,13 @@ fn rename() {
 fn forgot_apostrophes() {
     let de: SpannedResult<(i32, String)> = from_str("(4, \"Hello)");
 
-    assert!(match de {
+    assert!(matches!(
+        de,
         Err(SpannedError {
             code: Error::ExpectedStringEnd,
             position: _,
-        }) => true,
-        _ => false,
-    });
+        })
+    ));
 }
 
 #[test]
@@ -397,12 +660,48 @@ fn test_byte_stream() {
     );
 }
 
+#[test]
+fn test_byte_string_literals() {
+    use crate::value::Value;
+
+    assert_eq!(Ok(serde_bytes::ByteBuf::from(b"a\"b\n\xff".to_vec())), from_str(r#"b"a\"b\n\xff""#));
+    assert_eq!(Ok(serde_bytes::ByteBuf::from(vec![])), from_str(r#"b"""#));
+    assert_eq!(Ok(&b"borrowed"[..]), from_str::<&[u8]>(r#"b"borrowed""#));
+    assert_eq!(Ok(Value::Bytes(b"raw".to_vec())), r#"b"raw""#.parse::<Value>().map_err(|e| e.code));
+    assert_eq!(
+        Ok(Value::Seq(vec![Value::Bytes(vec![0]), Value::from("b")])),
+        r#"[b"\0", "b"]"#.parse::<Value>().map_err(|e| e.code)
+    );
+
+    assert_eq!(
+        err(Error::InvalidEscape("Unicode escapes are not allowed in byte strings"), 1, 4),
+        from_str::<serde_bytes::ByteBuf>(r#"b"\u{e9}""#)
+    );
+    assert_eq!(err(Error::ExpectedStringEnd, 1, 3), from_str::<serde_bytes::ByteBuf>(r#"b"open"#));
+}
+
 #[test]
 fn test_numbers() {
     assert_eq!(
         Ok(vec![1234, 12345, 123456, 1234567, 555_555]),
         from_str("[1_234, 12_345, 1_2_3_4_5_6, 1_234_567, 5_55_55_5]"),
     );
+    assert_eq!(Ok(vec![1000.5, -1000.25, 1e10]), from_str("[1_000.5, -1_000.2_5, 1e1_0]"));
+    assert_eq!(err(Error::FloatUnderscore, 1, 1), from_str::<f64>("-_1.5"));
+    assert_eq!(err(Error::FloatUnderscore, 1, 1), from_str::<f64>("1._5"));
+}
+
+#[test]
+fn test_number_suffixes() {
+    let header = "#![enable(number_suffixes)]\n";
+    assert_eq!(Ok((1u8, 2.5f32, -3i64, 4.0f64)), from_str(&format!("{header}(1u8, 2.5f32, -3i64, 4f64)")));
+    assert_eq!(Ok(vec![1_000u32]), from_str(&format!("{header}[1_000usize]")));
+    assert_eq!(Ok(Value::Number(2.5.into())), from_str(&format!("{header}2.5f64")));
+    assert_eq!(Ok(Value::Number(7.into())), from_str(&format!("{header}0x7u8")));
+    assert_eq!(err(Error::ExpectedFloat, 2, 4), from_str::<f32>(&format!("{header}2.5u8")));
+
+    // not without the extension
+    assert_eq!(err(Error::TrailingCharacters, 1, 2), from_str::<u8>("1u8"));
 }
 
 #[test]
@@ -419,6 +718,17 @@ fn test_any_number_precision() {
     assert_eq!(de_any_number("1."), AnyNum::F32(1.));
     assert_eq!(de_any_number("-1."), AnyNum::F32(-1.));
     assert_eq!(de_any_number("0.3"), AnyNum::F64(0.3));
+
+    assert_eq!(AnyNum::parse("+1"), Ok(AnyNum::I8(1)));
+    assert_eq!(AnyNum::parse("300"), Ok(AnyNum::U16(300)));
+    assert_eq!(AnyNum::parse("1 2"), Err(Error::TrailingCharacters));
+    assert!(AnyNum::parse("+1").unwrap().is_signed());
+    assert!(!AnyNum::parse("1").unwrap().is_signed());
+    assert!(AnyNum::parse("0.5").unwrap().is_float());
+    assert_eq!(AnyNum::parse("+1").unwrap().widen(), AnyNum::I64(1));
+    assert_eq!(AnyNum::parse("1").unwrap().widen(), AnyNum::U64(1));
+    assert_eq!(AnyNum::parse("0.5").unwrap().widen(), AnyNum::F64(0.5));
+    assert_eq!(crate::value::Number::from(AnyNum::parse("-7").unwrap()), crate::value::Number::new(-7));
 }
 
 #[test]
@@ -456,4 +766,1183 @@ fn test_complex() {
     },
 }
 "))
-}   
\ No newline at end of file
+}
+
+#[test]
+fn test_reserved_keywords() {
+    assert_eq!(err(Error::ReservedKeyword(String::from("true")), 1, 1), from_str::<String>("true"));
+    assert_eq!(err(Error::ReservedKeyword(String::from("None")), 1, 5), from_str::<Vec<String>>("[a, None]"));
+    assert_eq!(Ok(String::from("true")), from_str("\"true\""));
+    assert_eq!(Ok(String::from("trueish")), from_str("trueish"));
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Keywords {
+        r#true: bool,
+    }
+
+    assert_eq!(Ok(Keywords { r#true: false }), from_str("Keywords { r#true: false }"));
+}
+
+#[test]
+fn test_duplicate_key_shape_mismatch() {
+    use crate::value::Value;
+    use crate::Options;
+    use self::Error::*;
+
+    // a lint, the last value still wins
+    let (value, warnings) = from_str_with_warnings::<Value>("{\n  \"x\": [4],\n  x: 5\n}").unwrap();
+    assert_eq!(value["x"], Value::from(5));
+    assert_eq!(
+        warnings,
+        vec![SpannedError {
+            code: MismatchedDuplicateKey {
+                key: String::from("x"),
+                first: Position { line: 2, col: 3 },
+                second: Position { line: 3, col: 3 },
+                expected: "a sequence",
+                found: "a single value",
+            },
+            position: Position { line: 3, col: 3 },
+        }]
+    );
+
+    assert_eq!(
+        Options::new().warnings_as_errors(true).from_str::<Value>("{ <x> {a: 1}, x: [2] }").map_err(|e| e.code),
+        Err(MismatchedDuplicateKey {
+            key: String::from("x"),
+            first: Position { line: 1, col: 4 },
+            second: Position { line: 1, col: 15 },
+            expected: "a map",
+            found: "a sequence",
+        })
+    );
+
+    // `Some(..)` is a variant rather than a tuple
+    let (value, warnings) = from_str_with_warnings::<Value>("{ x: Some(1), x: 2 }").unwrap();
+    assert_eq!(value["x"], Value::from(2));
+    assert!(matches!(&warnings[..], [SpannedError { code: MismatchedDuplicateKey { expected: "a variant", .. }, .. }]));
+    let (_, warnings) = from_str_with_warnings::<Value>("{ x: Some(1), x: None }").unwrap();
+    assert!(matches!(&warnings[..], [SpannedError { code: MismatchedDuplicateKey { found: "a single value", .. }, .. }]));
+    let (_, warnings) = from_str_with_warnings::<Value>("{ x: A(1), x: Some(2) }").unwrap();
+    assert!(matches!(&warnings[..], [SpannedError { code: DuplicateKey { .. }, .. }]));
+}
+
+#[test]
+fn test_supported_extensions() {
+    use crate::value::Value;
+
+    assert!(crate::supports_extension("byte_strings"));
+    assert_eq!("b\"a\"".parse(), Ok(Value::Bytes(b"a".to_vec())));
+    assert!(crate::supports_extension("hex_strings"));
+    assert_eq!("x\"61\"".parse(), Ok(Value::Bytes(b"a".to_vec())));
+    assert!(crate::supports_extension("raw_identifiers"));
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Keyword {
+        r#true: bool,
+    }
+    assert_eq!(Ok(Keyword { r#true: true }), from_str("{r#true: true}"));
+
+    assert!(!crate::supports_extension("implicit_some"));
+}
+
+#[test]
+fn test_extensions_header() {
+    use crate::{ Options, UnknownExtensions };
+    use std::sync::{ Arc, Mutex };
+
+    let source = "# settings\n#![enable(hex_strings, tabs)]\n#![ enable( byte_strings ) ]\n[1, 2]";
+    assert_eq!(
+        from_str::<Vec<u8>>(source).map_err(|e| (e.code, e.position.line, e.position.col)),
+        Err((Error::UnknownExtension(String::from("tabs")), 2, 24))
+    );
+
+    let options = Options::new().extension("tabs");
+    assert_eq!(options.from_str::<Vec<u8>>(source), Ok(vec![1, 2]));
+    let de = Deserializer::from_bytes_with_options(source.as_bytes(), options.clone()).unwrap();
+    assert!(de.extension_enabled("tabs"));
+    // always read, enabling it only warns
+    assert!(!de.extension_enabled("byte_strings"));
+    let (_, warnings) = options.from_str_with_warnings::<Vec<u8>>(source).unwrap();
+    let warnings = warnings.into_iter().map(|w| w.code).collect::<Vec<_>>();
+    assert_eq!(warnings, vec![Error::UnusedExtension(String::from("hex_strings")), Error::UnusedExtension(String::from("byte_strings"))]);
+
+    let warned = Arc::new(Mutex::new(Vec::new()));
+    let sink = warned.clone();
+    let options = Options::new()
+        .unknown_extensions(UnknownExtensions::Warn(Arc::new(move |e| sink.lock().unwrap().push(e.code.clone()))));
+    let de = Deserializer::from_bytes_with_options(source.as_bytes(), options).unwrap();
+    assert!(!de.extension_enabled("tabs"));
+    assert_eq!(*warned.lock().unwrap(), vec![Error::UnknownExtension(String::from("tabs"))]);
+
+    let ignore = Options::new().unknown_extensions(UnknownExtensions::Ignore);
+    assert_eq!(ignore.from_str::<Vec<u8>>(source), Ok(vec![1, 2]));
+
+    assert_eq!(from_str::<u8>("#![enable(hex_strings 1").map_err(|e| e.code), Err(Error::ExpectedAttributeEnd));
+    assert_eq!(from_str::<u8>("#![allow(x)] 1").map_err(|e| e.code), Err(Error::ExpectedAttribute));
+    assert_eq!(syntax_errors(source), vec![]);
+    assert_eq!(from_str::<u8>("#!comment\n1"), Ok(1));
+    // only a comment after the value has begun
+    assert_eq!(from_str::<Vec<u8>>("#![enable(env)]\n[1,\n#![enable(env)]\n2]"), Ok(vec![1, 2]));
+    assert_eq!(syntax_errors("[1, #![x]\n2]"), vec![]);
+}
+
+#[test]
+fn test_syntax_error_recovery() {
+    let errors = |s: &str| {
+        syntax_errors(s).into_iter().map(|e| (e.code, e.position.line, e.position.col)).collect::<Vec<_>>()
+    };
+
+    assert_eq!(errors("{ a: [1, 2], b: Some(3), <c> \"d\" }"), vec![]);
+    assert_eq!(errors("Layout { keys: [1, 2], kind: Iso(a: 1, 2) }"), vec![]);
+
+    let document = "{
+    a: [1, @, 3],
+    b: 'xy',
+    c: { d: 1 e: 2 },
+    f: [1, 2 },
+    g: 1.2.3
+}";
+    assert_eq!(
+        errors(document),
+        vec![
+            (Error::ExpectedFileReference, 2, 12),
+            (Error::ExpectedChar, 3, 9),
+            (Error::ExpectedComma, 4, 15),
+            (Error::ExpectedArrayEnd, 5, 14),
+            // `1.2.3` is a bare string
+        ]
+    );
+
+    assert_eq!(
+        errors("[1, (2, {a: 3]"),
+        vec![(Error::ExpectedMapEnd, 1, 14), (Error::ExpectedStructEnd, 1, 5), (Error::ExpectedArrayEnd, 1, 1)]
+    );
+    assert_eq!(errors("[1, 2)]"), vec![(Error::ExpectedArrayEnd, 1, 6)]);
+    assert_eq!(errors("[\n    @ 1\n    2 3,\n]"), vec![(Error::ExpectedFileReference, 2, 5), (Error::ExpectedComma, 3, 7)]);
+}
+
+#[test]
+fn test_check_str() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    assert_eq!(check_str::<Point>("{ x: 1, y: 2 }"), Ok(Point { x: 1, y: 2 }));
+
+    let errors = check_str::<Vec<Point>>("[{ x: 1 y: 2 }, { x: 'ab', y: 3 }, { x: 1.2.3, y: 0 }]").unwrap_err();
+    assert_eq!(
+        errors.iter().map(|e| (&e.code, e.position.col)).collect::<Vec<_>>(),
+        vec![(&Error::ExpectedComma, 9), (&Error::ExpectedChar, 23)]
+    );
+
+    // sound syntax, but not a `Point`
+    let errors = check_str::<Point>("{ x: 1 }").unwrap_err();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].code, Error::MissingStructField { field: "y", outer: Some(String::from("Point")) });
+
+    assert_eq!(crate::Options::new().max_errors(1).check_str::<Vec<u8>>("[@, @]").unwrap_err().len(), 1);
+
+    // bare words and suffixes the typed parse reads are not syntax errors
+    let source = "{ k: ctrl-q, v: 1.0.2 }";
+    assert_eq!(check_str::<HashMap<String, String>>(source), from_str::<HashMap<String, String>>(source).map_err(|e| vec![e]));
+    assert!(check_str::<HashMap<String, String>>(source).is_ok());
+    assert_eq!(check_str::<std::net::SocketAddr>("127.0.0.1:8080"), Ok(std::net::SocketAddr::from(([127, 0, 0, 1], 8080))));
+    assert_eq!(check_str::<Vec<u8>>("#![enable(number_suffixes)] [1u8]"), Ok(vec![1]));
+}
+
+#[test]
+fn test_options() {
+    use crate::value::Value;
+    use crate::Options;
+
+    let source = "{ x: 1, \"x\": 2 }";
+    assert!(source.parse::<Value>().is_ok());
+    assert_eq!(
+        Options::new().warnings_as_errors(true).from_str::<Value>(source).map_err(|e| e.code),
+        Err(Error::DuplicateKey {
+            key: String::from("x"),
+            first: Position { line: 1, col: 3 },
+            second: Position { line: 1, col: 9 },
+        })
+    );
+
+    let broken = "[@, @, @, @]";
+    assert_eq!(syntax_errors(broken).len(), 4);
+    assert_eq!(Options::new().max_errors(2).syntax_errors(broken), syntax_errors(broken)[..2]);
+}
+
+#[test]
+fn test_encodings() {
+    let utf16 = |text: &str, bom: [u8; 2], unit: fn(u16) -> [u8; 2]| {
+        bom.into_iter().chain(text.encode_utf16().flat_map(unit)).collect::<Vec<u8>>()
+    };
+
+    assert_eq!(from_bytes::<Vec<u8>>(b"\xEF\xBB\xBF[1, 2]"), Ok(vec![1, 2]));
+    assert_eq!(from_str::<String>("\u{feff}\"a\""), Ok(String::from("a")));
+
+    let le = utf16("[\"é\", \"𝄞\"]", [0xFF, 0xFE], u16::to_le_bytes);
+    let be = utf16("[\"é\", \"𝄞\"]", [0xFE, 0xFF], u16::to_be_bytes);
+    let expected = vec![String::from("é"), String::from("𝄞")];
+    assert_eq!(from_bytes_decoded::<Vec<String>>(&le), (Encoding::Utf16Le, Ok(expected.clone())));
+    assert_eq!(from_bytes_decoded::<Vec<String>>(&be), (Encoding::Utf16Be, Ok(expected.clone())));
+    assert_eq!(from_reader::<_, Vec<String>>(le.as_slice()), Ok(expected));
+    assert_eq!(from_bytes_decoded::<u8>(b"3"), (Encoding::Utf8, Ok(3)));
+
+    // errors are positioned in the transcoded text
+    let (encoding, result) = from_bytes_decoded::<Vec<u8>>(&utf16("[1,\n x]", [0xFF, 0xFE], u16::to_le_bytes));
+    assert_eq!(encoding, Encoding::Utf16Le);
+    assert_eq!(result.unwrap_err().position, Position { line: 2, col: 2 });
+
+    assert_eq!(
+        from_bytes_decoded::<u8>(b"\xFF\xFE3"),
+        (Encoding::Utf16Le, Err(SpannedError { code: Error::InvalidUtf16, position: Position { line: 1, col: 1 } }))
+    );
+    let mut lone = utf16("[\"é\",\n \"", [0xFF, 0xFE], u16::to_le_bytes);
+    lone.extend([0xD800].into_iter().chain("\"]".encode_utf16()).flat_map(u16::to_le_bytes));
+    assert_eq!(
+        from_bytes_decoded::<Vec<String>>(&lone).1,
+        Err(SpannedError { code: Error::InvalidUtf16, position: Position { line: 2, col: 3 } })
+    );
+
+    let error = from_bytes::<Vec<String>>(&be).unwrap_err();
+    assert_eq!(error, SpannedError { code: Error::UnsupportedEncoding(Encoding::Utf16Be), position: Position { line: 1, col: 1 } });
+    assert!(error.to_string().contains("from_bytes_decoded"));
+
+    assert_eq!(from_bytes_lossy::<Vec<String>>(b"[\"caf\xe9\", b]"), Ok(vec![String::from("caf\u{fffd}"), String::from("b")]));
+    assert!(from_bytes::<String>(b"\"caf\xe9\"").is_err());
+}
+
+#[test]
+fn test_struct_name_policy() {
+    use crate::{ Options, StructNames };
+    use std::sync::{ Arc, Mutex };
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Unit;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Meters(f32);
+
+    let renamed = "OldStruct { x: 1, y: 2 }";
+    let expected = MyStruct { x: 1.0, y: 2.0 };
+    assert!(Options::new().from_str::<MyStruct>(renamed).is_err());
+    assert_eq!(Options::new().struct_names(StructNames::Ignore).from_str(renamed), Ok(expected));
+    assert_eq!(Options::new().struct_names(StructNames::Ignore).from_str("Old(2)"), Ok(Meters(2.0)));
+
+    let warnings = Arc::new(Mutex::new(Vec::new()));
+    let sink = warnings.clone();
+    let options = Options::new().struct_names(StructNames::Warn(Arc::new(move |e| sink.lock().unwrap().push(e.clone()))));
+    assert_eq!(options.from_str("OldStruct { x: 1, y: 2 }"), Ok(MyStruct { x: 1.0, y: 2.0 }));
+    assert_eq!(options.from_str("MyStruct { x: 1, y: 2 }"), Ok(MyStruct { x: 1.0, y: 2.0 }));
+    assert_eq!(*warnings.lock().unwrap(), vec![SpannedError {
+        code: Error::ExpectedDifferentStructName { expected: "MyStruct", found: String::from("OldStruct") },
+        position: Position { line: 1, col: 1 },
+    }]);
+
+    let require = Options::new().struct_names(StructNames::Require);
+    assert_eq!(require.from_str("MyStruct { x: 1, y: 2 }"), Ok(MyStruct { x: 1.0, y: 2.0 }));
+    assert_eq!(require.from_str("Unit"), Ok(Unit));
+    assert_eq!(
+        require.from_str::<MyStruct>("{ x: 1, y: 2 }").map_err(|e| e.code),
+        Err(Error::ExpectedNamedStruct("MyStruct"))
+    );
+    assert_eq!(require.from_str::<Unit>("{}").map_err(|e| e.code), Err(Error::ExpectedNamedStruct("Unit")));
+}
+
+#[test]
+fn test_file_reference() {
+    use crate::ser::Serializer;
+    use crate::{ Options, Resolver };
+    use serde::Serialize;
+    use std::sync::{ Arc, Mutex };
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Asset {
+        name: String,
+        #[serde(with = "serde_bytes")]
+        data: Vec<u8>,
+    }
+
+    let files = Arc::new(Mutex::new(HashMap::new()));
+    let written = files.clone();
+    let mut output = Vec::new();
+    let mut serializer = Serializer::new(&mut output, None).unwrap().spill_bytes(4, move |bytes: &[u8]| {
+        let mut files = written.lock().unwrap();
+        let path = format!("blob{}.bin", files.len());
+        files.insert(path.clone(), bytes.to_vec());
+        Ok(path)
+    });
+
+    let assets = vec![
+        Asset { name: String::from("big"), data: vec![0, 1, 2, 3, 4] },
+        Asset { name: String::from("small"), data: vec![5] },
+    ];
+    assets.serialize(&mut serializer).unwrap();
+    let text = String::from_utf8(output).unwrap();
+    assert!(text.contains("data:@file(\"blob0.bin\")"));
+    assert_eq!(files.lock().unwrap().len(), 1);
+
+    let read = files.clone();
+    let options = Options::new().resolver(Resolver::new(move |path| {
+        read.lock().unwrap().get(path).cloned().ok_or_else(|| std::io::ErrorKind::NotFound.into())
+    }));
+    assert_eq!(options.from_str::<Vec<Asset>>(&text), Ok(assets));
+
+    let source = "{ name: \"x\", data: @file ( \"missing.bin\" ) }";
+    assert!(matches!(
+        options.from_str::<Asset>(source).map_err(|e| e.code),
+        Err(Error::FileReference { path, .. }) if path == "missing.bin"
+    ));
+    assert!(matches!(from_str::<Asset>(source).map_err(|e| e.code), Err(Error::FileReference { .. })));
+    assert_eq!(from_str::<Asset>("{ name: \"x\", data: @file(\"a\" }").map_err(|e| e.code), Err(Error::ExpectedFileReference));
+    assert_eq!(crate::de::syntax_errors(source), vec![]);
+
+    // files are read from the directory, and from nowhere else
+    let dir = std::env::temp_dir().join(format!("zmerald-file-reference-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("assets")).unwrap();
+    std::fs::write(dir.join("assets/blob.bin"), [1, 2]).unwrap();
+    std::fs::write(dir.join("secret.bin"), [3]).unwrap();
+    let options = Options::new().resolver(Resolver::directory(dir.join("assets")));
+    let asset = |path: &str| options.from_str::<Asset>(&format!("{{ name: \"x\", data: @file({:?}) }}", path)).map_err(|e| e.code);
+    assert_eq!(asset("blob.bin").map(|asset| asset.data), Ok(vec![1, 2]));
+    assert_eq!(asset("./blob.bin").map(|asset| asset.data), Ok(vec![1, 2]));
+    for escape in ["../secret.bin", "sub/../../secret.bin", dir.join("secret.bin").to_str().unwrap()] {
+        assert!(matches!(asset(escape), Err(Error::FileReference { path, .. }) if path == escape));
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_flatten() {
+    use crate::value::Value;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Inner {
+        b: i32,
+        c: String,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Outer {
+        a: i32,
+        #[serde(flatten)]
+        inner: Inner,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct CatchAll {
+        name: String,
+        #[serde(flatten)]
+        rest: HashMap<String, Value>,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    enum Mode {
+        Fast,
+        Slow,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Settings {
+        mode: Mode,
+        limit: Option<u8>,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Nested {
+        #[serde(flatten)]
+        outer: Outer,
+        #[serde(flatten)]
+        settings: Settings,
+    }
+
+    let outer = Outer { a: 1, inner: Inner { b: 2, c: String::from("x") } };
+    assert_eq!(from_str("Outer { a: 1, b: 2, c: \"x\" }"), Ok(Outer { a: 1, inner: Inner { b: 2, c: String::from("x") } }));
+    assert_eq!(from_str("{ a: 1, \"b\": 2, c: x }"), Ok(Outer { a: 1, inner: Inner { b: 2, c: String::from("x") } }));
+    assert_eq!(
+        from_str("{ c: x, mode: Fast, b: 2, limit: Some(4), a: 1 }"),
+        Ok(Nested { outer, settings: Settings { mode: Mode::Fast, limit: Some(4) } })
+    );
+    assert_eq!(
+        from_str::<Settings>("{ mode: Fast, limit: None }"),
+        Ok(Settings { mode: Mode::Fast, limit: None })
+    );
+
+    let catch_all = from_str::<CatchAll>("{ name: \"n\", size: 3, tags: [a, b], nested: { x: 1 } }").unwrap();
+    assert_eq!(catch_all.name, "n");
+    assert_eq!(catch_all.rest.len(), 3);
+    assert_eq!(catch_all.rest["size"], Value::from(3));
+    assert_eq!(catch_all.rest["tags"], Value::Seq(vec![Value::from("a"), Value::from("b")]));
+}
+
+#[test]
+fn test_untyped_transcoding() {
+    use serde_json::{ json, Value as Json };
+
+    let source = "Config {
+    name: benten,
+    keys: { <ctrl-q> quit, <\"ctrl n\"> new },
+    layout: Tiled(2),
+    split: Split(0.5, Horizontal),
+    pane: Pane(Browser { url: a.b/c }),
+    empty: Empty(),
+    mode: Dark,
+    tag: 'x',
+    size: (800, 600),
+    title: Some(\"main\"),
+    parent: None,
+}";
+    assert_eq!(
+        from_str::<Json>(source),
+        Ok(json!({
+            "name": "benten",
+            "keys": { "ctrl-q": "quit", "ctrl n": "new" },
+            "layout": { "Tiled": 2 },
+            "split": { "Split": [0.5, "Horizontal"] },
+            "pane": { "Pane": { "url": "a.b/c" } },
+            "empty": { "Empty": [] },
+            "mode": "Dark",
+            "tag": "x",
+            "size": [800, 600],
+            "title": "main",
+            "parent": null,
+        }))
+    );
+
+    // variants keep their name when buffered
+    #[derive(Debug, PartialEq, Deserialize)]
+    enum Layout {
+        Tiled(u8),
+        Split(f32, String),
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Window {
+        #[serde(flatten)]
+        layout: HashMap<String, Layout>,
+    }
+
+    let window = from_str::<Window>("{ a: Tiled(2), b: Split(0.5, h) }").unwrap();
+    assert_eq!(window.layout["a"], Layout::Tiled(2));
+    assert_eq!(window.layout["b"], Layout::Split(0.5, String::from("h")));
+}
+
+#[test]
+fn test_untyped_nesting() {
+    use serde_json::{ json, Value as Json };
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Cfg {
+        a: u8,
+    }
+
+    // Each level is read once, reading them again to tell `A(x)` from
+    // `A(x, y)` would double the time with each of them.
+    let depth = 50;
+    let nested = format!("{}1{}", "A(".repeat(depth), ")".repeat(depth));
+    let source = format!("Cfg {{ a: 1, junk: {}, tail: A(B(1), 2) }}", nested);
+
+    assert_eq!(from_str::<Cfg>(&source), Ok(Cfg { a: 1 }));
+    assert_eq!(from_str_at::<u8>(&source, "a"), Ok(1));
+
+    let json = from_str::<Json>(&source).unwrap();
+    let mut junk = &json["junk"];
+    for _ in 0..depth {
+        junk = &junk["A"];
+    }
+    assert_eq!(junk, &json!(1));
+    assert_eq!(json["tail"], json!({ "A": [{ "B": 1 }, 2] }));
+}
+
+#[test]
+fn test_transcode_bare_words() {
+    let source = "{ version: 1.0.2, listen: 127.0.0.1:80, any: ::1, link: fe80::1, key: ctrl-q, port: 80, ratio: -1.5 }";
+
+    let mut json = Vec::new();
+    let mut de = Deserializer::from_str(source).unwrap();
+    serde_transcode::transcode(&mut de, &mut serde_json::Serializer::new(&mut json)).unwrap();
+    de.end().unwrap();
+    assert_eq!(
+        String::from_utf8(json).unwrap(),
+        r#"{"version":"1.0.2","listen":"127.0.0.1:80","any":"::1","link":"fe80::1","key":"ctrl-q","port":80,"ratio":-1.5}"#
+    );
+
+    let strict = Options::default().strict_strings(true);
+    assert!(strict.from_str::<serde_json::Value>("[1.0.2]").is_err());
+}
+
+#[test]
+fn test_enum_representations() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[serde(tag = "type")]
+    enum Internal {
+        Unit,
+        Struct { x: i32, name: String },
+        Newtype(Inner),
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Inner {
+        a: Option<u8>,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[serde(tag = "t", content = "c")]
+    enum Adjacent {
+        Unit,
+        Newtype(i32),
+        Tuple(i32, String),
+        Struct { x: i32 },
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[serde(untagged)]
+    enum Untagged {
+        Num(i32),
+        Pair(i32, i32),
+        Named { x: i32 },
+    }
+
+    assert_eq!(from_str("{ type: Unit }"), Ok(Internal::Unit));
+    assert_eq!(from_str("{ type: Struct, x: 1, name: n }"), Ok(Internal::Struct { x: 1, name: String::from("n") }));
+    assert_eq!(from_str("Struct { x: 1, type: Struct, name: \"n\" }"), Ok(Internal::Struct { x: 1, name: String::from("n") }));
+    assert_eq!(from_str("{ type: \"Newtype\", a: Some(3) }"), Ok(Internal::Newtype(Inner { a: Some(3) })));
+    assert!(from_str::<Internal>("{ x: 1 }").is_err());
+
+    assert_eq!(from_str("{ t: Unit }"), Ok(Adjacent::Unit));
+    assert_eq!(from_str("{ t: Newtype, c: 3 }"), Ok(Adjacent::Newtype(3)));
+    assert_eq!(from_str("{ c: (1, \"a\"), t: Tuple }"), Ok(Adjacent::Tuple(1, String::from("a"))));
+    assert_eq!(from_str("{ t: Struct, c: Struct { x: 1 } }"), Ok(Adjacent::Struct { x: 1 }));
+
+    assert_eq!(
+        from_str("[1, (1, 2), { x: 3 }]"),
+        Ok(vec![Untagged::Num(1), Untagged::Pair(1, 2), Untagged::Named { x: 3 }])
+    );
+}
+
+#[cfg(feature = "trace")]
+#[test]
+fn test_trace() {
+    use crate::{ Options, Tracer };
+    use std::sync::{ Arc, Mutex };
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Config {
+        name: String,
+        limit: Option<u8>,
+    }
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let sink = events.clone();
+    let options = Options::new().tracer(Tracer::new(move |position, message| {
+        sink.lock().unwrap().push(format!("{}: {}", position, message))
+    }));
+
+    let config = options.from_str::<Config>("Config { name: \"x\", limit: 3 }");
+    assert_eq!(config, Ok(Config { name: String::from("x"), limit: Some(3) }));
+    assert_eq!(*events.lock().unwrap(), [
+        "1:1: expecting struct `Config`",
+        "1:10: key `name`",
+        "1:16: expecting str",
+        "1:21: key `limit`",
+        "1:28: option: implicit Some",
+        "1:28: expecting u8",
+    ]);
+
+    events.lock().unwrap().clear();
+    let value = options.from_str::<HashMap<String, String>>("{ a: b }");
+    assert_eq!(value.map(|map| map.len()), Ok(1));
+    assert_eq!(*events.lock().unwrap(), ["1:1: expecting map", "1:3: expecting str", "1:3: key `a`", "1:6: expecting str"]);
+
+    events.lock().unwrap().clear();
+    let any = options.from_str::<serde::de::IgnoredAny>("[word, Name(1)]");
+    assert!(any.is_ok());
+    assert_eq!(*events.lock().unwrap(), [
+        "1:1: any: `[`",
+        "1:1: expecting seq",
+        "1:6: any: bare word `word` read as a string",
+        "1:12: any: variant `Name` read as a map",
+        "1:13: any: `1`",
+    ]);
+}
+
+#[test]
+fn test_display_with_source() {
+    let source = "MyStruct {\n\tx: 1,\n\ty: true,\n}";
+    let error = from_str::<MyStruct>(source).unwrap_err();
+    assert_eq!(
+        error.display_with_source(source).to_string(),
+        "error: Expected float\n  --> 3:5\n  |\n3 | \ty: true,\n  | \t   ^"
+    );
+
+    let source = "{ \"ü\": x }";
+    let error = from_str::<HashMap<String, u8>>(source).unwrap_err();
+    assert_eq!(error.position, Position { line: 1, col: 9 });
+    assert!(error.display_with_source(source).to_string().ends_with("1 | { \"ü\": x }\n  |        ^"));
+
+    let error = SpannedError { code: Error::Eof, position: Position { line: 0, col: 0 } };
+    assert_eq!(error.display_with_source("").to_string(), "error: Unexpected end of RON");
+}
+
+#[test]
+fn test_did_you_mean() {
+    #[derive(Debug, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    #[allow(dead_code)]
+    struct Window {
+        width: u32,
+        height: u32,
+        title: String,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    enum Mode {
+        Windowed,
+        Fullscreen,
+    }
+
+    let error = from_str::<Window>("Window {\n  width: 1,\n  hieght: 2,\n  title: t }").unwrap_err();
+    assert_eq!(error.position, Position { line: 3, col: 3 });
+    assert_eq!(error.code.suggestion(), Some("height"));
+    assert_eq!(
+        error.code.to_string(),
+        "Unexpected field named `hieght` in `Window`, expected one of `width`, `height`, `title` instead, did you mean `height`?"
+    );
+
+    let error = from_str::<Mode>("FullScreen").unwrap_err();
+    assert_eq!(error.position, Position { line: 1, col: 1 });
+    assert_eq!(error.code.suggestion(), Some("Fullscreen"));
+
+    let error = from_str::<Window>("{ colour: 1 }").unwrap_err();
+    assert_eq!(error.code.suggestion(), None);
+    assert!(!error.code.to_string().contains("did you mean"));
+}
+
+#[test]
+fn test_field_errors() {
+    #[derive(Debug, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    #[allow(dead_code)]
+    struct Inner {
+        a: u8,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Outer {
+        inner: Inner,
+        b: u8,
+    }
+
+    let error = from_str::<Outer>("Outer { inner: Inner { a: 1, \"c\": 2 }, b: 3 }").unwrap_err();
+    assert_eq!(error.position, Position { line: 1, col: 30 });
+    assert!(matches!(error.code, Error::NoSuchStructField { outer: Some(ref outer), .. } if outer == "Inner"));
+
+    let error = from_str::<Outer>("{ b: 1,\n  b: 2 }").unwrap_err();
+    assert_eq!(error.position, Position { line: 2, col: 3 });
+    assert_eq!(error.code, Error::DuplicateStructField { field: "b", outer: Some(String::from("Outer")) });
+    assert_eq!(error.code.to_string(), "Unexpected duplicate field `b` in `Outer`");
+
+    let error = from_str::<Outer>("{ inner: { }, b: 1 }").unwrap_err();
+    assert_eq!(error.code, Error::MissingStructField { field: "a", outer: Some(String::from("Inner")) });
+}
+
+#[test]
+fn test_renames() {
+    use crate::options::{ Options, Renames };
+    use std::sync::{ Arc, Mutex };
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct Window {
+        color: Mode,
+        width: u32,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    enum Mode {
+        Dark,
+        Light,
+    }
+
+    let warnings = Arc::new(Mutex::new(Vec::new()));
+    let sink = warnings.clone();
+    let renames = Renames::new()
+        .rename("Window", "colour", "color")
+        .rename("Mode", "Night", "Dark")
+        .warn(move |w| sink.lock().unwrap().push(w.clone()));
+    let options = Options::new().renames(renames);
+
+    assert_eq!(options.from_str("Window { colour: Night, width: 2 }"), Ok(Window { color: Mode::Dark, width: 2 }));
+    assert_eq!(options.from_str("{ color: Light, width: 2 }"), Ok(Window { color: Mode::Light, width: 2 }));
+
+    let warnings = warnings.lock().unwrap();
+    assert_eq!(warnings.len(), 2);
+    assert_eq!(warnings[0].position, Position { line: 1, col: 10 });
+    assert_eq!(warnings[0].code.to_string(), "The name `colour` in `Window` is deprecated, use `color` instead");
+    assert_eq!(warnings[1].position, Position { line: 1, col: 18 });
+
+    // other types keep the name
+    assert!(from_str::<Window>("{ colour: Dark, width: 2 }").is_err());
+}
+
+#[test]
+fn test_warnings() {
+    use crate::options::{ Compat, Options };
+    use crate::value::Value;
+
+    let (value, warnings) = super::from_str_with_warnings::<Value>("#![enable(env, env)]\n{ a: 1, b: 2, a: 3 }").unwrap();
+    assert_eq!(value, from_str::<Value>("{ a: 3, b: 2 }").unwrap());
+    assert_eq!(
+        warnings,
+        vec![
+            SpannedError { code: Error::UnusedExtension(String::from("env")), position: Position { line: 1, col: 16 } },
+            SpannedError {
+                code: Error::DuplicateKey { key: String::from("a"), first: Position { line: 2, col: 3 }, second: Position { line: 2, col: 15 } },
+                position: Position { line: 2, col: 15 },
+            },
+        ]
+    );
+
+    // typed maps too, where the last one wins as well
+    let (map, warnings) = super::from_str_with_warnings::<HashMap<String, i32>>("{ \"a\": 1, b: 2, a: 3 }").unwrap();
+    assert_eq!(map, HashMap::from([(String::from("a"), 3), (String::from("b"), 2)]));
+    assert_eq!(
+        warnings.into_iter().map(|w| w.code).collect::<Vec<_>>(),
+        vec![Error::DuplicateKey { key: String::from("a"), first: Position { line: 1, col: 3 }, second: Position { line: 1, col: 17 } }]
+    );
+    let (_, warnings) = super::from_str_with_warnings::<std::collections::BTreeMap<String, i32>>("{ a: 1, a: 3 }").unwrap();
+    assert_eq!(warnings.len(), 1);
+
+    let ron = Options::new().compat(Compat::Ron);
+    let (_, warnings) = ron.from_str_with_warnings::<Option<u8>>("#![enable(implicit_some)] 1").unwrap();
+    assert_eq!(warnings[0].code.to_string(), "Extension `implicit_some` is enabled to no effect");
+
+    // integers that floats cannot hold
+    let (number, warnings) = super::from_str_with_warnings::<f32>("16_777_217").unwrap();
+    assert_eq!(number, 16_777_216.0);
+    assert_eq!(warnings[0].code, Error::RoundedInteger(String::from("16_777_217")));
+    assert!(super::from_str_with_warnings::<f64>("16777217").unwrap().1.is_empty());
+    assert!(super::from_str_with_warnings::<f32>("0.1").unwrap().1.is_empty());
+
+    assert_eq!(Options::new().warnings_as_errors(true).from_str::<f32>("16777217").unwrap_err().code, Error::RoundedInteger(String::from("16777217")));
+}
+
+#[test]
+fn test_ignored() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Window {
+        width: u32,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Config {
+        windows: Vec<Window>,
+        #[serde(default)]
+        title: String,
+    }
+
+    let source = "{ windows: [{ width: 1 }, { width: 2, widht: { a: 1, b: [2] } }], \"colour\": red }";
+    let (config, ignored) = super::from_str_with_ignored::<Config>(source).unwrap();
+    assert_eq!(config.windows, vec![Window { width: 1 }, Window { width: 2 }]);
+    assert_eq!(ignored, vec![String::from("windows.1.widht"), String::from("colour")]);
+
+    assert_eq!(super::from_str_with_ignored::<Window>("{ width: 3 }"), Ok((Window { width: 3 }, Vec::new())));
+}
+
+#[test]
+fn test_limits() {
+    use crate::options::{ Limits, Options };
+    use crate::value::Value;
+    use std::collections::HashMap;
+
+    let options = Options::new().limits(Limits::new().input_size(32).collection_len(3).token_len(5));
+    let code = |result: SpannedResult<Value>| result.unwrap_err().code;
+
+    assert_eq!(options.from_str::<Vec<u8>>("[1, 2, 3]"), Ok(vec![1, 2, 3]));
+    assert_eq!(code(options.from_str("[1, 2, 3, 4]")), Error::CollectionTooLarge(3));
+    assert_eq!(code(options.from_str("{ a: 1, b: 2, c: 3, d: 4 }")), Error::CollectionTooLarge(3));
+    assert!(options.from_str::<HashMap<String, u8>>("{ a: 1, b: 2, c: 3, }").is_ok());
+
+    let error = options.from_str::<Value>("[\"abcdef\"]").unwrap_err();
+    assert_eq!(error, SpannedError { code: Error::TokenTooLong(5), position: Position { line: 1, col: 3 } });
+    assert_eq!(code(options.from_str("[\"ab\\ncdef\"]")), Error::TokenTooLong(5));
+    assert_eq!(code(options.from_str("\"abc\" \"def\"")), Error::TokenTooLong(5));
+    assert_eq!(code(options.from_str("[abcdef]")), Error::TokenTooLong(5));
+    assert_eq!(code(options.from_str("1234567")), Error::TokenTooLong(5));
+
+    assert_eq!(code(options.from_str(&format!("[{}]", " ".repeat(32)))), Error::InputTooLarge(32));
+    assert_eq!(code(options.from_reader(" ".repeat(40).as_bytes())), Error::InputTooLarge(32));
+}
+
+#[test]
+fn test_skipped_positions() {
+    use crate::options::{ Compat, Options };
+    use crate::value::Value;
+
+    let position = |input: &str| from_str::<Value>(input).unwrap_err().position;
+
+    // Whitespace, comments and strings are skipped in one step, lines are
+    // still counted as when stepping byte by byte.
+    let spaces = " ".repeat(40);
+    assert_eq!(position(&format!("[{spaces}\n{spaces}\r\n{spaces}?]")), Position { line: 3, col: 41 });
+    assert_eq!(position(&format!("[ # {spaces}\n  # {spaces}\r\n {spaces} ?]")), Position { line: 3, col: 43 });
+    let ron = Options::new().compat(Compat::Ron);
+    let error = ron.from_str::<Value>(&format!("[ // {spaces}\n  /* {spaces}\r\n {spaces} */ ?]")).unwrap_err();
+    assert_eq!(error.position, Position { line: 3, col: 46 });
+    assert_eq!(position(&format!("[\"{spaces}\", ?]")), Position { line: 1, col: 46 });
+    assert_eq!(position(&format!("[\"{spaces}\\n{spaces}\", ?]")), Position { line: 1, col: 88 });
+}
+
+#[test]
+fn test_scanned_lengths() {
+    // Around the 16 byte steps of the `simd` feature, and the tail after them.
+    for len in 0..40 {
+        let spaces = " \t\r\n".repeat(len).chars().take(len).collect::<String>();
+        assert_eq!(from_str::<Vec<u8>>(&format!("[{spaces}1{spaces}]")), Ok(vec![1]));
+
+        let ascii = "a".repeat(len);
+        assert_eq!(from_str::<String>(&format!("\"{ascii}\"")), Ok(ascii.clone()));
+        assert_eq!(from_str::<String>(&format!("\"{ascii}\\t{ascii}\"")), Ok(format!("{ascii}\t{ascii}")));
+        assert_eq!(from_str::<String>(&format!("\"{ascii}\\u{{e9}}\"")), Ok(format!("{ascii}\u{e9}")));
+
+        for at in 0..=len {
+            let mut text = ascii.clone();
+            text.insert(at, '\u{e9}');
+            assert_eq!(from_str::<String>(&format!("\"{text}\"")), Ok(text.clone()));
+            assert_eq!(from_str::<String>(&format!("\"\\n{text}\"")), Ok(format!("\n{text}")));
+
+            let mut invalid = format!("\"\\n{ascii}\"").into_bytes();
+            invalid.insert(at + 3, 0xFF);
+            assert!(from_bytes::<String>(&invalid).is_err());
+            invalid.drain(1..3);
+            assert!(from_bytes::<String>(&invalid).is_err());
+        }
+    }
+}
+
+#[test]
+fn test_no_panics() {
+    use crate::options::{ Compat, Options };
+    use crate::value::Value;
+
+    // Every prefix of the documents, and each byte swapped for one of the
+    // grammar's, is read without panicking.
+    let documents = [
+        "#![enable(anchors, variables, env, number_suffixes)]\n{ $x: [1, 2.5e3, -0x1F], a: &y Some(\"s\\u{1F600}\"), b: *y, 'c': 'd', <k> v, e: b\"\\x00\", f: x\"00ff\", g: 1u8, h: ${HOME} }",
+        "Point { x: 1_000, y: (true, None, 'x'), z: Variant(1, [2]), w: r#raw, v: 127.0.0.1:80, u: @file(\"a\") }",
+        "(a: r#\"raw\"#, /* block */ b: Unit(), c: [inf, -inf, NaN, .5, 1.], // line\n)",
+    ];
+    let swaps = b"\"'\\(){}[]<>,:;$&*@#/!rbx0_.-+e \n\xff";
+    let ron = Options::new().compat(Compat::Ron).extension("anchors").extension("variables");
+
+    for document in documents {
+        let mut inputs: Vec<Vec<u8>> = (0..document.len()).map(|i| document.as_bytes()[..i].to_vec()).collect();
+        for i in 0..document.len() {
+            for &swap in swaps {
+                let mut input = document.as_bytes().to_vec();
+                input[i] = swap;
+                inputs.push(input);
+            }
+        }
+
+        for input in inputs {
+            let _ = from_bytes::<Value>(&input);
+            let _ = ron.from_bytes::<Value>(&input);
+            let _ = from_bytes::<(char, String, Vec<u8>, f32)>(&input);
+            if let Ok(s) = str::from_utf8(&input) {
+                let _ = syntax_errors(s);
+                let _ = Tokenizer::new(s).map(|events| events.count());
+                let _ = from_str_with_ignored::<HashMap<String, Value>>(s);
+            }
+        }
+    }
+
+    assert_eq!(from_str::<char>("").unwrap_err().code, Error::ExpectedChar);
+    assert_eq!(from_str::<Value>("Some").unwrap_err().code, Error::ExpectedOption);
+
+    // nesting fails before the stack overflows
+    let deep = format!("{}{}", "[".repeat(100_000), "]".repeat(100_000));
+    assert_eq!(from_str::<Value>(&deep).unwrap_err().code, Error::DepthLimitExceeded(64));
+    assert_eq!(from_str::<Value>(&"Some(".repeat(100_000)).unwrap_err().code, Error::DepthLimitExceeded(64));
+    let shallow = format!("{}{}", "[".repeat(64), "]".repeat(64));
+    assert!(from_str::<Value>(&shallow).is_ok());
+}
+
+#[test]
+fn test_document() {
+    use crate::document::{ value, Document };
+
+    let source = "# settings\n#![enable(hex_strings)]\n{\n    window: Window {\n        width: 800, # px\n        title: \"main\",\n    },\n    keys: { <\"q\"> Quit, \"n\": New(1, 2) },\n    tags: [ 'a' , 'b' ],\n    empty: ( ),\n    data: x\"00ff\",\n}\n";
+    let mut doc: Document = source.parse().unwrap();
+    assert_eq!(doc.to_string(), source);
+
+    assert_eq!(doc["window"]["width"].deserialize::<u32>(), Ok(800));
+    assert_eq!(doc["keys"].keys().collect::<Vec<_>>(), vec!["q", "n"]);
+    assert_eq!(doc["keys"]["n"][1].to_string(), "2");
+    assert_eq!(doc["tags"].len(), 2);
+    assert!(doc["empty"].is_empty() && doc.get("missing").is_none());
+
+    doc["window"]["width"] = value(&1024).unwrap();
+    doc["tags"][1] = value(&'c').unwrap();
+    doc["keys"]["q"] = value(&vec![1, 2]).unwrap();
+    assert_eq!(doc.to_string(), source.replace("800", "1024").replace("'b'", "'c'").replace("Quit", "[1,2]"));
+
+    // missing keys read as nothing and are added by assigning
+    assert_eq!(doc["missing"]["deeper"].to_string(), "");
+    doc["window"]["height"] = value(&600).unwrap();
+    let keys = doc.insert("keys", value(&'k').unwrap()).unwrap().unwrap();
+    assert_eq!(keys.to_string(), "{ <\"q\"> [1,2], \"n\": New(1, 2) }");
+    doc["keys"] = keys;
+    doc["keys"]["q"] = value("Quit").unwrap();
+    doc["keys"].insert("a b", value(&1).unwrap()).unwrap();
+    doc["empty"].insert("x", value(&true).unwrap()).unwrap();
+    assert_eq!(doc["tags"].insert("x", value(&1).unwrap()), Err(Error::ExpectedMap));
+    assert_eq!(
+        doc.to_string(),
+        "# settings\n#![enable(hex_strings)]\n{\n    window: Window {\n        width: 1024, # px\n        title: \"main\",\n        height: 600,\n    },\n    keys: { <\"q\"> \"Quit\", \"n\": New(1, 2), \"a b\": 1 },\n    tags: [ 'a' , 'c' ],\n    empty: (x: true),\n    data: x\"00ff\",\n}\n"
+    );
+
+    // removing takes the entry's comments along
+    assert_eq!(doc["window"].remove("width").map(|item| item.to_string()), Some(String::from("1024")));
+    assert!(doc.remove("data").is_some() && doc.remove("data").is_none());
+    doc["empty"].remove("x");
+    doc["keys"].remove("n");
+    assert_eq!(
+        doc.to_string(),
+        "# settings\n#![enable(hex_strings)]\n{\n    window: Window {\n        title: \"main\",\n        height: 600,\n    },\n    keys: { <\"q\"> \"Quit\", \"a b\": 1 },\n    tags: [ 'a' , 'c' ],\n    empty: (),\n}\n"
+    );
+    let mut doc: Document = "{\n    a: 1, # one\n    # about b\n    b: 2 # two\n}".parse().unwrap();
+    doc.remove("b");
+    assert_eq!(doc.to_string(), "{\n    a: 1 # one\n}");
+    doc.insert("c", value(&3).unwrap()).unwrap();
+    assert_eq!(doc.to_string(), "{\n    a: 1, # one\n    c: 3\n}");
+    doc.remove("a");
+    doc.remove("c");
+    assert_eq!(doc.to_string(), "{}");
+    doc.insert("true", value(&1).unwrap()).unwrap();
+    assert_eq!(doc.to_string(), "{ \"true\": 1 }");
+
+    let errors = |s: &str| s.parse::<Document>().map_err(|e| (e.code, e.position.line, e.position.col));
+    assert_eq!(errors("{ a: 1 b: 2 }"), Err((Error::ExpectedComma, 1, 8)));
+    assert_eq!(errors("[1, 2"), Err((Error::ExpectedArrayEnd, 1, 6)));
+    assert_eq!(errors("{ a }"), Err((Error::ExpectedMapSeparator, 1, 4)));
+    assert_eq!(errors("[1] 2"), Err((Error::TrailingCharacters, 1, 5)));
+}
+
+#[test]
+fn test_ron_syntax() {
+    use crate::options::Compat;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Meters(f32);
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Gap {
+        inner: u8,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    enum Layout {
+        Tiled(Gap),
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Config {
+        title: String,
+        width: Meters,
+        layout: Layout,
+    }
+
+    let ron = Options::default().compat(Compat::Ron);
+    let source = "// old config\n#![enable(implicit_some, unwrap_newtypes, unwrap_variant_newtypes)]\n/* block /* nested */ */\nConfig(\n    title: r#\"say \"hi\"\"#, // trailing\n    width: 1.5,\n    layout: Tiled(inner: 2),\n)";
+    let expected = Config { title: String::from("say \"hi\""), width: Meters(1.5), layout: Layout::Tiled(Gap { inner: 2 }) };
+    assert_eq!(ron.from_str(source), Ok(expected));
+    assert_eq!(ron.from_str::<String>("r\"a\\b\""), Ok(String::from("a\\b")));
+    assert_eq!(ron.from_str::<Layout>("Tiled(Gap(inner: 2))"), Ok(Layout::Tiled(Gap { inner: 2 })));
+
+    assert_eq!(ron.from_str::<u8>("/* open").unwrap_err().code, Error::UnclosedBlockComment);
+    let header = "#![enable(unwrap_newtypes)]\n1.5";
+    assert_eq!(from_str::<Meters>(header), Ok(Meters(1.5)));
+    assert_eq!(ron.from_str::<Meters>(header), Ok(Meters(1.5)));
+    let header = "#![enable(implicit_some)]\n1.5";
+    assert_eq!(from_str::<f32>(header).unwrap_err().code, Error::UnknownExtension(String::from("implicit_some")));
+}
+
+#[test]
+fn test_from_path() {
+    use crate::ser::PrettyConfig;
+
+    let dir = std::env::temp_dir().join(format!("zmerald-from-path-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("config.zm");
+
+    let config = HashMap::from([(String::from("width"), 800u32)]);
+    crate::to_path_pretty(&path, &config, PrettyConfig::new()).unwrap();
+    assert_eq!(crate::from_path::<_, HashMap<String, u32>>(&path), Ok(config));
+
+    std::fs::write(&path, b"\xEF\xBB\xBF{ width: 800 }").unwrap();
+    assert_eq!(crate::from_path::<_, HashMap<String, u32>>(&path).unwrap()["width"], 800);
+
+    std::fs::write(&path, "{\n  width: x }").unwrap();
+    let error = crate::from_path::<_, HashMap<String, u32>>(&path).unwrap_err();
+    assert_eq!(error.to_string(), format!("{}:2:10: Expected integer", path.display()));
+
+    let missing = dir.join("missing.zm");
+    let error = crate::from_path::<_, u32>(&missing).unwrap_err();
+    assert!(matches!(error.code, Error::InFile { ref path, .. } if path.ends_with("missing.zm")));
+    assert!(crate::to_path(dir.join("no/such/dir.zm"), &1).unwrap_err().to_string().contains("dir.zm"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_crlf_line_endings() {
+    use crate::value::Value;
+
+    let unix = "{\n  keys: [quit,\n    open],\n  width: 1 2,\n}";
+    let windows = unix.replace('\n', "\r\n");
+
+    let keys = from_str::<HashMap<String, Vec<String>>>("{\r\n  keys: [ctrl-q,\r\n    alt-x],\r\n}").unwrap();
+    assert_eq!(keys["keys"], vec![String::from("ctrl-q"), String::from("alt-x")]);
+
+    let error = from_str::<Value>(unix).unwrap_err();
+    assert_eq!(error.position, Position { line: 4, col: 12 });
+    assert_eq!(from_str::<Value>(&windows).unwrap_err(), error);
+    assert_eq!(from_str::<Vec<u8>>("[1,\r\n2\r\n").unwrap_err().position, Position { line: 3, col: 1 });
+
+    // the `\r` of a line break takes no column
+    let mut bytes = crate::parse::Bytes::new(b"ab\r\ncd").unwrap();
+    bytes.advance(3).unwrap();
+    assert_eq!(bytes.position(), Position { line: 1, col: 3 });
+}
+
+#[test]
+fn test_strict_strings() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Config {
+        name: String,
+        key: char,
+        tag: Option<String>,
+    }
+
+    let strict = Options::new().strict_strings(true);
+    let config = Config { name: String::from("main"), key: 'q', tag: Some(String::from("None")) };
+    assert_eq!(strict.from_str("{ name: \"main\", key: 'q', tag: \"None\" }"), Ok(config));
+    assert_eq!(from_str::<Config>("{ name: main, key: q, tag: None }").unwrap().name, "main");
+
+    let error = strict.from_str::<Config>("{ name: main, key: 'q', tag: None }").unwrap_err();
+    assert_eq!(error, SpannedError { code: Error::UnquotedString, position: Position { line: 1, col: 9 } });
+    assert_eq!(strict.from_str::<Config>("{ name: \"a\", key: q }").unwrap_err().code, Error::UnquotedString);
+
+    // keys may stay bare
+    let map: HashMap<String, serde_json::Value> = strict.from_str("{ width: \"wide\", height: 2 }").unwrap();
+    assert_eq!(map["width"], "wide");
+    assert_eq!(strict.from_str::<serde_json::Value>("[wide]").unwrap_err().code, Error::UnquotedString);
+}
+
+#[test]
+fn test_from_str_at() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Tls {
+        cert: String,
+        port: u16,
+    }
+
+    let source = r#"Config {
+        name: "edge",
+        server: Server {
+            hosts: ["a", "b", { "c": (1, 2) }],
+            "tls": Tls { cert: "edge.pem", port: 443 },
+        },
+        rest: [ this is never read
+    "#;
+    let tls = Tls { cert: String::from("edge.pem"), port: 443 };
+    assert_eq!(crate::from_str_at(source, "server.tls"), Ok(tls));
+    assert_eq!(crate::from_str_at(source, "server.hosts.1"), Ok("b"));
+    assert_eq!(crate::from_str_at(source, "server.hosts.2.c.1"), Ok(2));
+    assert_eq!(crate::from_str_at(source, ""), Err::<(), _>(SpannedError {
+        code: Error::ExpectedUnit,
+        position: Position { line: 1, col: 1 },
+    }));
+
+    let error = crate::from_str_at::<u16>(source, "server.port").unwrap_err();
+    assert_eq!(error.code, Error::NoSuchPath(String::from("server.port")));
+    assert_eq!(error.position, Position { line: 6, col: 10 });
+    assert_eq!(crate::from_str_at::<u16>(source, "name.0").unwrap_err().code, Error::NoSuchPath(String::from("name.0")));
+    assert_eq!(crate::from_str_at::<u16>(source, "server.hosts.3").unwrap_err().code, Error::NoSuchPath(String::from("server.hosts.3")));
+
+    // no trailing comma after the last entry
+    let source = "{ a: 1, b: { c: 2 } }";
+    assert_eq!(crate::from_str_at::<u16>(source, "x").unwrap_err().code, Error::NoSuchPath(String::from("x")));
+    assert_eq!(crate::from_str_at::<u16>(source, "b.x").unwrap_err().code, Error::NoSuchPath(String::from("b.x")));
+}
+
+#[test]
+fn test_scalar_parser() {
+    use crate::options::{ Options, Scalar, ScalarParser };
+    use crate::value::{ Number, Value };
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Config {
+        timeout: u64,
+        cache: u32,
+        since: String,
+        retries: u8,
+    }
+
+    let units = ScalarParser::new(|token| {
+        if let Some(mib) = token.strip_suffix("MiB") {
+            return mib.parse::<u64>().ok().map(|mib| Scalar::Number(Number::from(mib << 20)));
+        }
+        if let Some(secs) = token.strip_suffix('s') {
+            return secs.parse::<u64>().ok().map(|secs| Scalar::Number(Number::from(secs)));
+        }
+        (token.len() == 10 && token.as_bytes()[4] == b'-').then(|| Scalar::String(String::from(token)))
+    });
+    let options = Options::new().scalar_parser(units);
+
+    let config = Config { timeout: 5, cache: 128 << 20, since: String::from("2023-01-01"), retries: 3 };
+    assert_eq!(options.from_str("{ timeout: 5s, cache: 128MiB, since: 2023-01-01, retries: 3 }"), Ok(config));
+    assert_eq!(options.from_str("[5s, 2]"), Ok(vec![Value::Number(Number::new(5)), Value::Number(Number::new(2))]));
+    assert_eq!(options.from_str::<u8>("300s").unwrap_err().code.to_string(), "Expected u8 but found the unsigned integer `300` instead");
+
+    // declined tokens are read as usual
+    assert!(Options::new().from_str::<u64>("5s").is_err());
+    assert_eq!(options.from_str::<String>("\"5s\""), Ok(String::from("5s")));
+}
+
+#[test]
+fn test_bare_addresses() {
+    use std::net::{ IpAddr, Ipv6Addr, SocketAddr };
+
+    #[derive(Debug, PartialEq, Deserialize, serde::Serialize)]
+    struct Net {
+        bind: SocketAddr,
+        peer: IpAddr,
+        v4: IpAddr,
+        v6: SocketAddr,
+        local: Ipv6Addr,
+    }
+
+    let net = Net {
+        bind: "127.0.0.1:8080".parse().unwrap(),
+        peer: "::1".parse().unwrap(),
+        v4: "10.0.0.1".parse().unwrap(),
+        v6: "[::1]:443".parse().unwrap(),
+        local: "fe80::1".parse().unwrap(),
+    };
+    let text = "{ bind: 127.0.0.1:8080, peer: ::1, v4: 10.0.0.1, v6: [::1]:443, local: fe80::1 }";
+    assert_eq!(from_str::<Net>(text), Ok(net));
+
+    let net = from_str::<Net>(text).unwrap();
+    assert_eq!(from_str::<Net>(&crate::ser::to_string(&net).unwrap()), Ok(net));
+
+    // keys still end at their `:`
+    let hosts: HashMap<IpAddr, String> = from_str("{ 10.0.0.1: gateway, \"::1\": local }").unwrap();
+    assert_eq!(hosts[&"10.0.0.1".parse::<IpAddr>().unwrap()], "gateway");
+    assert_eq!(from_str::<SocketAddr>("127.0.0.1:port").unwrap_err().code, Error::Message(String::from("invalid socket address syntax")));
+}
@@ -0,0 +1,293 @@
+// A document as a stream of events, without serde or a target type, for tools
+// such as syntax highlighters, linters or extracting a few values:
+//
+//     for event in Tokenizer::new(source)? {
+//         let (event, span) = event?;
+//         ...
+//     }
+//
+// `{ .. }` holds either struct fields or map entries, which only the type
+// tells apart: a bare key is a `FieldKey`, any other key is announced by
+// `Key` and followed by its events. Comments are skipped.
+
+use std::borrow::Cow;
+use std::str;
+
+use crate::error::{ Error, Position, Result, Span, SpannedResult };
+use crate::parse::{ is_keyword, is_reserved_char, is_whitespace_char, Bytes, ParsedBytes, ParsedStr };
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event<'a> {
+    // A name in the `#![enable(..)]` header.
+    Extension(&'a str),
+    // `{` or `Name {`
+    StructStart(Option<&'a str>),
+    StructEnd,
+    // A bare key and its `:`.
+    FieldKey(&'a str),
+    // The events up to the next `:` or `>` are a key.
+    Key,
+    SeqStart,
+    SeqEnd,
+    // `(` or `Name(`, which includes `Some(`.
+    TupleStart(Option<&'a str>),
+    TupleEnd,
+    // A bare identifier, such as a unit variant.
+    Ident(&'a str),
+    Str(Cow<'a, str>),
+    Char(char),
+    Bytes(Cow<'a, [u8]>),
+    // The number as written, `0x1F` or `-1.5e3`.
+    Number(&'a str),
+    Bool(bool),
+    None,
+    Unit,
+    FileReference(String),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Seq,
+    Tuple,
+    Struct,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum State {
+    // At the start or after a comma, an element or the closer may follow.
+    Element,
+    // After an element, a comma or the closer.
+    Separator,
+    // Within the key announced by `Key`, `<` for cavetta keys.
+    Key { cavetta: bool },
+    // After a key, at its `:` or `>`.
+    KeyEnd { cavetta: bool },
+    // After the `:` of an entry.
+    Value,
+}
+
+pub struct Tokenizer<'a> {
+    bytes: Bytes<'a>,
+    extensions: Vec<(Position, &'a str)>,
+    frames: Vec<(Kind, State)>,
+    done: bool,
+    failed: bool,
+}
+
+impl<'a> Tokenizer<'a> {
+    pub fn new(s: &'a str) -> SpannedResult<Self> {
+        let mut bytes = Bytes::new(s.as_bytes())?;
+        let mut extensions = bytes.extensions().map_err(|e| bytes.span_error(e))?;
+        extensions.reverse();
+
+        Ok(Tokenizer { bytes, extensions, frames: Vec::new(), done: false, failed: false })
+    }
+
+    fn event(&mut self) -> Result<Option<(Event<'a>, Span)>> {
+        if let Some((start, name)) = self.extensions.pop() {
+            let end = Position { line: start.line, col: start.col + name.len() };
+            return Ok(Some((Event::Extension(name), Span { start, end })));
+        }
+
+        loop {
+            self.bytes.skip_ws()?;
+            let start = self.bytes.position();
+
+            let Some(&(kind, state)) = self.frames.last() else {
+                if !self.done {
+                    return self.value(start);
+                }
+                if !self.bytes.bytes().is_empty() {
+                    return Err(Error::TrailingCharacters);
+                }
+                return Ok(None);
+            };
+
+            let closer = match kind {
+                Kind::Seq => "]",
+                Kind::Tuple => ")",
+                Kind::Struct => "}",
+            };
+
+            match state {
+                State::Element | State::Separator if self.bytes.consume(closer) => {
+                    self.frames.pop();
+                    self.complete();
+                    let event = match kind {
+                        Kind::Seq => Event::SeqEnd,
+                        Kind::Tuple => Event::TupleEnd,
+                        Kind::Struct => Event::StructEnd,
+                    };
+                    return Ok(Some((event, self.span(start))));
+                }
+                State::Separator if self.bytes.consume(",") => self.set_state(State::Element),
+                State::Separator => return Err(Error::ExpectedComma),
+                State::Element if kind == Kind::Struct => {
+                    if let Some(key) = self.field_key()? {
+                        self.set_state(State::Value);
+                        return Ok(Some((Event::FieldKey(key), self.span(start))));
+                    }
+
+                    let cavetta = self.bytes.consume("<");
+                    self.set_state(State::Key { cavetta });
+                    return Ok(Some((Event::Key, self.span(start))));
+                }
+                State::KeyEnd { cavetta } => {
+                    if !self.bytes.consume(if cavetta { ">" } else { ":" }) {
+                        return Err(Error::ExpectedMapSeparator);
+                    }
+                    self.set_state(State::Value);
+                }
+                State::Element | State::Key { .. } | State::Value => return self.value(start),
+            }
+        }
+    }
+
+    // A bare key followed by `:`, which is consumed with it.
+    fn field_key(&mut self) -> Result<Option<&'a str>> {
+        let mut bytes = self.bytes;
+        let Ok(key) = bytes.identifier() else {
+            return Ok(None);
+        };
+
+        bytes.skip_ws()?;
+        if !bytes.consume(":") {
+            return Ok(None);
+        }
+
+        self.bytes = bytes;
+        Ok(Some(str::from_utf8(key)?))
+    }
+
+    fn value(&mut self, start: Position) -> Result<Option<(Event<'a>, Span)>> {
+        let event = match self.bytes.peek().ok_or(Error::Eof)? {
+            b'[' => self.open(Kind::Seq, Event::SeqStart),
+            b'{' => self.open(Kind::Struct, Event::StructStart(None)),
+            b'(' if self.bytes.consume("()") => Event::Unit,
+            b'(' => self.open(Kind::Tuple, Event::TupleStart(None)),
+            b'"' => Event::Str(self.str()?),
+            b'\'' => Event::Char(self.bytes.char()?),
+            b'@' => Event::FileReference(self.bytes.file_reference()?),
+            b'b' | b'x' if self.bytes.bytes().get(1) == Some(&b'"') => Event::Bytes(self.byte_string()?),
+            b'0'..=b'9' | b'+' | b'-' | b'.' if self.starts_number() || self.bytes.next_bytes_is_float() => {
+                Event::Number(self.number()?)
+            }
+            _ => self.word()?,
+        };
+
+        if !matches!(event, Event::SeqStart | Event::StructStart(_) | Event::TupleStart(_)) {
+            self.complete();
+        }
+
+        Ok(Some((event, self.span(start))))
+    }
+
+    // A keyword, a name in front of a collection, an identifier or a bare
+    // string.
+    fn word(&mut self) -> Result<Event<'a>> {
+        for (keyword, event) in [("true", Event::Bool(true)), ("false", Event::Bool(false)), ("None", Event::None)] {
+            if self.bytes.consume_ident(keyword) {
+                return Ok(event);
+            }
+        }
+        for number in ["inf", "-inf", "NaN"] {
+            if self.bytes.consume_ident(number) {
+                return Ok(Event::Number(number));
+            }
+        }
+
+        let start = self.bytes;
+        if let Ok(ident) = self.bytes.identifier() {
+            let name = str::from_utf8(ident)?;
+            if self.bytes.peek().is_none_or(|b| is_reserved_char(b) || is_whitespace_char(b)) {
+                let mut bytes = self.bytes;
+                bytes.skip_ws()?;
+                match bytes.peek() {
+                    Some(b'{') => {
+                        self.bytes = bytes;
+                        return Ok(self.open(Kind::Struct, Event::StructStart(Some(name))));
+                    }
+                    Some(b'(') => {
+                        self.bytes = bytes;
+                        return Ok(self.open(Kind::Tuple, Event::TupleStart(Some(name))));
+                    }
+                    _ if !is_keyword(name) => return Ok(Event::Ident(name)),
+                    _ => {}
+                }
+            }
+            self.bytes = start;
+        }
+
+        self.str().map(Event::Str)
+    }
+
+    fn str(&mut self) -> Result<Cow<'a, str>> {
+        Ok(match self.bytes.string()? {
+            ParsedStr::Allocated(s) => Cow::Owned(s),
+            ParsedStr::Slice(s) => Cow::Borrowed(s),
+        })
+    }
+
+    fn byte_string(&mut self) -> Result<Cow<'a, [u8]>> {
+        if self.bytes.peek() == Some(b'x') {
+            return self.bytes.hex_string().map(Cow::Owned);
+        }
+
+        Ok(match self.bytes.byte_string()? {
+            ParsedBytes::Allocated(b) => Cow::Owned(b),
+            ParsedBytes::Slice(b) => Cow::Borrowed(b),
+        })
+    }
+
+    fn starts_number(&self) -> bool {
+        let bytes = self.bytes.bytes();
+        let digit = usize::from(matches!(bytes.first(), Some(b'+' | b'-')));
+        bytes.get(digit).is_some_and(u8::is_ascii_digit)
+    }
+
+    fn number(&mut self) -> Result<&'a str> {
+        let start = self.bytes.bytes();
+        self.bytes.any_num()?;
+
+        Ok(str::from_utf8(&start[..start.len() - self.bytes.bytes().len()])?)
+    }
+
+    fn open(&mut self, kind: Kind, event: Event<'a>) -> Event<'a> {
+        let _ = self.bytes.advance_single();
+        self.frames.push((kind, State::Element));
+
+        event
+    }
+
+    // A value, or the last event of one, was read.
+    fn complete(&mut self) {
+        match self.frames.last().map(|&(_, state)| state) {
+            Some(State::Key { cavetta }) => self.set_state(State::KeyEnd { cavetta }),
+            Some(_) => self.set_state(State::Separator),
+            None => self.done = true,
+        }
+    }
+
+    fn set_state(&mut self, state: State) {
+        if let Some(frame) = self.frames.last_mut() {
+            frame.1 = state;
+        }
+    }
+
+    fn span(&self, start: Position) -> Span {
+        Span { start, end: self.bytes.position() }
+    }
+}
+
+impl<'a> Iterator for Tokenizer<'a> {
+    type Item = SpannedResult<(Event<'a>, Span)>;
+
+    // Stops after the first error.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        self.event().map_err(|e| self.bytes.span_error(e)).inspect_err(|_| self.failed = true).transpose()
+    }
+}
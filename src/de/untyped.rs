@@ -0,0 +1,284 @@
+use serde::de::value::{ BorrowedStrDeserializer, MapDeserializer, SeqDeserializer };
+use serde::de::{ self, DeserializeSeed, IntoDeserializer, MapAccess, SeqAccess, Visitor };
+use serde::forward_to_deserialize_any;
+
+use std::fmt;
+use std::marker::PhantomData;
+
+use super::{ CommaSeparated, Deserializer, Error, Result };
+
+// `Name(..)` read without a type, as the single-entry map `{ Name: .. }` that
+// serde expects of an externally tagged enum. This keeps the variant when the
+// value is buffered, as for `#[serde(flatten)]`, or transcoded.
+pub struct VariantMap<'a, 'de: 'a> {
+    de: &'a mut Deserializer<'de>,
+    name: Option<&'de str>,
+}
+
+impl<'a, 'de> VariantMap<'a, 'de> {
+    pub fn new(de: &'a mut Deserializer<'de>, name: &'de str) -> Self {
+        VariantMap { de, name: Some(name) }
+    }
+}
+
+impl<'de> MapAccess<'de> for VariantMap<'_, 'de> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>> where K: DeserializeSeed<'de> {
+        match self.name.take() {
+            Some(name) => seed.deserialize(BorrowedStrDeserializer::new(name)).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value> where V: DeserializeSeed<'de> {
+        seed.deserialize(VariantContent { de: &mut *self.de })
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(1)
+    }
+}
+
+// What is between the parentheses: `Name(x)` holds `x`, `Name(a, b)` and
+// `Name()` a sequence. The first element is read before the `,` or `)` after
+// it tells which, so it is kept as `Content` until then.
+struct VariantContent<'a, 'de: 'a> {
+    de: &'a mut Deserializer<'de>,
+}
+
+impl<'de> de::Deserializer<'de> for VariantContent<'_, 'de> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
+        let de = self.de;
+        let mut bytes = de.bytes;
+        if !bytes.consume("(") || bytes.skip_ws().is_err() || bytes.peek() == Some(b')') {
+            return de.deserialize_tuple(0, visitor);
+        }
+
+        de.bytes = bytes;
+        de.enter_index(0);
+        let first = de.value(PhantomData::<Content>, |seed, de| seed.deserialize(de));
+        de.leave();
+        let first = first?;
+        let had_comma = de.bytes.comma()?;
+
+        if de.bytes.consume(")") {
+            return first.deserialize_any(visitor);
+        }
+
+        let mut rest = CommaSeparated::new(b')', de);
+        rest.had_comma = had_comma;
+        rest.index = 1;
+        let value = visitor.visit_seq(Elements { first: Some(first), rest: &mut rest })?;
+        de.bytes.comma()?;
+
+        if de.bytes.consume(")") {
+            Ok(value)
+        } else {
+            Err(Error::ExpectedArrayEnd)
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+// The elements of `Name(a, b, ..)`, the first of them already read.
+struct Elements<'s, 'a, 'de: 'a> {
+    first: Option<Content<'de>>,
+    rest: &'s mut CommaSeparated<'a, 'de>,
+}
+
+impl<'de> SeqAccess<'de> for Elements<'_, '_, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>> where T: DeserializeSeed<'de> {
+        match self.first.take() {
+            Some(first) => seed.deserialize(first).map(Some),
+            None => self.rest.next_element_seed(seed),
+        }
+    }
+}
+
+// A value as `deserialize_any` visited it, to be visited again later.
+enum Content<'de> {
+    Bool(bool),
+    I64(i64),
+    U64(u64),
+    #[cfg(feature = "integer128")]
+    I128(i128),
+    #[cfg(feature = "integer128")]
+    U128(u128),
+    F64(f64),
+    Char(char),
+    Str(&'de str),
+    String(String),
+    Bytes(&'de [u8]),
+    ByteBuf(Vec<u8>),
+    None,
+    Some(Box<Content<'de>>),
+    Unit,
+    Newtype(Box<Content<'de>>),
+    Seq(Vec<Content<'de>>),
+    Map(Vec<(Content<'de>, Content<'de>)>),
+}
+
+impl<'de> de::Deserialize<'de> for Content<'de> {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error> where D: de::Deserializer<'de> {
+        deserializer.deserialize_any(ContentVisitor)
+    }
+}
+
+struct ContentVisitor;
+
+impl<'de> Visitor<'de> for ContentVisitor {
+    type Value = Content<'de>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("any value")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> std::result::Result<Self::Value, E> {
+        Ok(Content::Bool(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> std::result::Result<Self::Value, E> {
+        Ok(Content::I64(v))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> std::result::Result<Self::Value, E> {
+        Ok(Content::U64(v))
+    }
+
+    #[cfg(feature = "integer128")]
+    fn visit_i128<E>(self, v: i128) -> std::result::Result<Self::Value, E> {
+        Ok(Content::I128(v))
+    }
+
+    #[cfg(feature = "integer128")]
+    fn visit_u128<E>(self, v: u128) -> std::result::Result<Self::Value, E> {
+        Ok(Content::U128(v))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> std::result::Result<Self::Value, E> {
+        Ok(Content::F64(v))
+    }
+
+    fn visit_char<E>(self, v: char) -> std::result::Result<Self::Value, E> {
+        Ok(Content::Char(v))
+    }
+
+    fn visit_borrowed_str<E>(self, v: &'de str) -> std::result::Result<Self::Value, E> {
+        Ok(Content::Str(v))
+    }
+
+    fn visit_str<E>(self, v: &str) -> std::result::Result<Self::Value, E> {
+        Ok(Content::String(v.to_owned()))
+    }
+
+    fn visit_string<E>(self, v: String) -> std::result::Result<Self::Value, E> {
+        Ok(Content::String(v))
+    }
+
+    fn visit_borrowed_bytes<E>(self, v: &'de [u8]) -> std::result::Result<Self::Value, E> {
+        Ok(Content::Bytes(v))
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> std::result::Result<Self::Value, E> {
+        Ok(Content::ByteBuf(v.to_vec()))
+    }
+
+    fn visit_byte_buf<E>(self, v: Vec<u8>) -> std::result::Result<Self::Value, E> {
+        Ok(Content::ByteBuf(v))
+    }
+
+    fn visit_none<E>(self) -> std::result::Result<Self::Value, E> {
+        Ok(Content::None)
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> std::result::Result<Self::Value, D::Error> where D: de::Deserializer<'de> {
+        de::Deserialize::deserialize(deserializer).map(|v| Content::Some(Box::new(v)))
+    }
+
+    fn visit_unit<E>(self) -> std::result::Result<Self::Value, E> {
+        Ok(Content::Unit)
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> std::result::Result<Self::Value, D::Error> where D: de::Deserializer<'de> {
+        de::Deserialize::deserialize(deserializer).map(|v| Content::Newtype(Box::new(v)))
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> std::result::Result<Self::Value, A::Error> where A: SeqAccess<'de> {
+        let mut elements = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(element) = seq.next_element()? {
+            elements.push(element);
+        }
+
+        Ok(Content::Seq(elements))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> std::result::Result<Self::Value, A::Error> where A: MapAccess<'de> {
+        let mut entries = Vec::with_capacity(map.size_hint().unwrap_or(0));
+        while let Some(entry) = map.next_entry()? {
+            entries.push(entry);
+        }
+
+        Ok(Content::Map(entries))
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Content<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
+        match self {
+            Content::Bool(v) => visitor.visit_bool(v),
+            Content::I64(v) => visitor.visit_i64(v),
+            Content::U64(v) => visitor.visit_u64(v),
+            #[cfg(feature = "integer128")]
+            Content::I128(v) => visitor.visit_i128(v),
+            #[cfg(feature = "integer128")]
+            Content::U128(v) => visitor.visit_u128(v),
+            Content::F64(v) => visitor.visit_f64(v),
+            Content::Char(v) => visitor.visit_char(v),
+            Content::Str(v) => visitor.visit_borrowed_str(v),
+            Content::String(v) => visitor.visit_string(v),
+            Content::Bytes(v) => visitor.visit_borrowed_bytes(v),
+            Content::ByteBuf(v) => visitor.visit_byte_buf(v),
+            Content::None => visitor.visit_none(),
+            Content::Some(v) => visitor.visit_some(*v),
+            Content::Unit => visitor.visit_unit(),
+            Content::Newtype(v) => visitor.visit_newtype_struct(*v),
+            Content::Seq(v) => visitor.visit_seq(SeqDeserializer::new(v.into_iter())),
+            Content::Map(v) => visitor.visit_map(MapDeserializer::new(v.into_iter())),
+        }
+    }
+
+    // Like the text, a value that is not an option is taken to be a `Some`.
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
+        match self {
+            Content::None => visitor.visit_none(),
+            Content::Some(v) => visitor.visit_some(*v),
+            v => visitor.visit_some(v),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Content<'de> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
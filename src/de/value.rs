@@ -1,10 +1,19 @@
-use crate::error::SpannedResult;
-use crate::value::{ Map, Number, Value };
+use crate::error::{ self, SpannedResult };
+use crate::parse::is_keyword;
+use crate::value::{ Map, Number, Value, STRUCT_TOKEN, VALUE_TOKEN };
 
-use std::fmt;
-use serde::de::{ Error, MapAccess, SeqAccess, Visitor };
+use std::{ fmt, str };
+use serde::de::value::BorrowedStrDeserializer;
+#[cfg(feature = "arbitrary_precision")]
+use serde::de::value::MapDeserializer;
+#[cfg(feature = "arbitrary_precision")]
+use crate::value::NUMBER_TOKEN;
+use serde::de::{ DeserializeSeed, EnumAccess, Error, MapAccess, SeqAccess, VariantAccess, Visitor };
 use serde::de::{ Deserialize, Deserializer };
 
+use super::recover::anchor_name;
+use super::CommaSeparated;
+
 impl std::str::FromStr for Value {
     type Err = crate::error::SpannedError;
 
@@ -18,9 +27,244 @@ impl std::str::FromStr for Value {
     }
 }
 
+// `Value` asks for a private newtype struct, which the zmerald deserializer
+// answers with struct names and variants that plain serde visitors would not
+// expect from `deserialize_any`.
 impl<'de> Deserialize<'de> for Value {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: Deserializer<'de> {
-        deserializer.deserialize_any(ValueVisitor)
+        deserializer.deserialize_newtype_struct(VALUE_TOKEN, ValueVisitor)
+    }
+}
+
+impl<'de> super::Deserializer<'de> {
+    pub(super) fn deserialize_value<V>(&mut self, visitor: V) -> error::Result<V::Value>
+    where V: Visitor<'de> {
+        if let Some(value) = self.anchored_value()? {
+            return value.deserialize_newtype_struct(VALUE_TOKEN, visitor);
+        }
+
+        let mut bytes = self.bytes;
+        let raw = bytes.bytes().starts_with(b"r#");
+
+        if let Ok(ident) = bytes.identifier() {
+            let name = str::from_utf8(ident)?;
+
+            if raw || !is_keyword(name) {
+                self.bytes = bytes;
+                self.bytes.skip_ws()?;
+
+                return if self.bytes.consume("{") {
+                    self.deserialize_struct_value(Some(name), "}", visitor)
+                } else if self.ron_fields_ahead(self.bytes) {
+                    self.bytes.consume("(");
+                    self.deserialize_struct_value(Some(name), ")", visitor)
+                } else {
+                    visitor.visit_enum(VariantValue { de: self, name })
+                };
+            }
+        }
+
+        if self.starts_with_field()? {
+            self.bytes.consume("{");
+            return self.deserialize_struct_value(None, "}", visitor);
+        }
+
+        if self.ron_fields_ahead(self.bytes) {
+            self.bytes.consume("(");
+            return self.deserialize_struct_value(None, ")", visitor);
+        }
+
+        #[cfg(feature = "arbitrary_precision")]
+        if let Some(literal) = self.number_literal()? {
+            let entry = std::iter::once((NUMBER_TOKEN, literal));
+            return visitor.visit_map(MapDeserializer::<_, error::Error>::new(entry));
+        }
+
+        self.deserialize_any(visitor)
+    }
+
+    // The text of the number ahead, handed out under `NUMBER_TOKEN` when an
+    // `i64` or `f64` wouldn't write it back the same.
+    #[cfg(feature = "arbitrary_precision")]
+    fn number_literal(&mut self) -> error::Result<Option<&'de str>> {
+        let mut bytes = self.bytes;
+        let start = bytes.bytes();
+        let digit = match start.first() {
+            Some(b'+' | b'-') => 1,
+            _ => 0,
+        };
+
+        if !start.get(digit).is_some_and(u8::is_ascii_digit) {
+            return Ok(None);
+        }
+
+        bytes.any_num()?;
+        let literal = str::from_utf8(&start[..start.len() - bytes.bytes().len()])?;
+        let number = Number::parse_literal(literal);
+        if number.as_i64().is_some() || crate::ser::to_string(&number).ok().as_deref() == Some(literal) {
+            return Ok(None);
+        }
+
+        self.bytes = bytes;
+        Ok(Some(literal))
+    }
+
+    // With the `anchors` extension, `&name value` labels a value for `*name`
+    // to repeat later in the document, each use is a copy of its own. A copy
+    // counts as its text and the copies within it read again.
+    fn anchored_value(&mut self) -> error::Result<Option<Value>> {
+        if !self.extension_enabled("anchors") {
+            return Ok(None);
+        }
+
+        if let Some((name, bytes)) = anchor_name(self.bytes, "&") {
+            self.bytes = bytes;
+            self.bytes.skip_ws()?;
+            let (start, expanded) = (self.bytes, self.expanded);
+            let value = self.nested(|de| Value::deserialize(de))?;
+            let len = start.bytes().len() - self.bytes.bytes().len() + self.expanded - expanded;
+            self.anchors.insert(name, (value.clone(), len));
+
+            Ok(Some(value))
+        } else if let Some((name, bytes)) = anchor_name(self.bytes, "*") {
+            self.bytes = bytes;
+            let len = self.anchors.get(name).map(|&(_, len)| len).ok_or_else(|| error::Error::UndefinedAnchor(String::from(name)))?;
+            self.expand(len)?;
+
+            Ok(Some(self.anchors[name].0.clone()))
+        } else {
+            Ok(None)
+        }
+    }
+
+    // `{` followed by a bare identifier and a colon.
+    fn starts_with_field(&self) -> error::Result<bool> {
+        let mut bytes = self.bytes;
+        if !bytes.consume("{") {
+            return Ok(false);
+        }
+
+        bytes.skip_ws()?;
+        let raw = bytes.bytes().starts_with(b"r#");
+        match bytes.identifier() {
+            Ok(ident) if raw || !is_keyword(str::from_utf8(ident)?) => {
+                bytes.skip_ws()?;
+                Ok(bytes.consume(":"))
+            }
+            _ => Ok(false),
+        }
+    }
+
+    // The fields up to `closer`, which is `)` for RON structs.
+    fn deserialize_struct_value<V>(&mut self, name: Option<&'de str>, closer: &str, visitor: V) -> error::Result<V::Value>
+    where V: Visitor<'de> {
+        let mut fields = CommaSeparated::new(closer.as_bytes()[0], self);
+        fields.untyped = true;
+        fields.keys = Some(std::collections::HashMap::new());
+
+        let value = visitor.visit_map(StructValue { name: Some(name), fields })?;
+        self.bytes.comma()?;
+
+        if self.bytes.consume(closer) {
+            Ok(value)
+        } else {
+            Err(error::Error::ExpectedStructEnd)
+        }
+    }
+}
+
+// Hands out the struct name under `STRUCT_TOKEN` before the fields.
+struct StructValue<'a, 'de: 'a> {
+    name: Option<Option<&'de str>>,
+    fields: CommaSeparated<'a, 'de>,
+}
+
+impl<'de, 'a> MapAccess<'de> for StructValue<'a, 'de> {
+    type Error = error::Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> error::Result<Option<K::Value>>
+    where K: DeserializeSeed<'de> {
+        if self.name.is_some() {
+            seed.deserialize(BorrowedStrDeserializer::<error::Error>::new(STRUCT_TOKEN)).map(Some)
+        } else {
+            self.fields.next_key_seed(seed)
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> error::Result<V::Value>
+    where V: DeserializeSeed<'de> {
+        match self.name.take() {
+            Some(name) => seed.deserialize(BorrowedStrDeserializer::<error::Error>::new(name.unwrap_or(""))),
+            None => self.fields.next_value_seed(seed),
+        }
+    }
+}
+
+// A variant named `name`, optionally followed by its contents in parentheses.
+struct VariantValue<'a, 'de: 'a> {
+    de: &'a mut super::Deserializer<'de>,
+    name: &'de str,
+}
+
+impl<'de, 'a> EnumAccess<'de> for VariantValue<'a, 'de> {
+    type Error = error::Error;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> error::Result<(V::Value, Self)>
+    where V: DeserializeSeed<'de> {
+        let name = seed.deserialize(BorrowedStrDeserializer::<error::Error>::new(self.name))?;
+
+        Ok((name, self))
+    }
+}
+
+impl<'de, 'a> VariantAccess<'de> for VariantValue<'a, 'de> {
+    type Error = error::Error;
+
+    fn unit_variant(self) -> error::Result<()> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> error::Result<T::Value>
+    where T: DeserializeSeed<'de> {
+        if !self.de.bytes.consume("(") {
+            return seed.deserialize(Value::Unit);
+        }
+
+        let mut elements = Vec::new();
+        loop {
+            self.de.bytes.skip_ws()?;
+            if self.de.bytes.consume(")") {
+                break;
+            }
+
+            elements.push(Value::deserialize(&mut *self.de)?);
+
+            if !self.de.bytes.comma()? {
+                if self.de.bytes.consume(")") {
+                    break;
+                }
+
+                return Err(error::Error::ExpectedStructEnd);
+            }
+        }
+
+        let content = match elements.len() {
+            1 => elements.pop().unwrap_or(Value::Unit),
+            _ => Value::Seq(elements),
+        };
+
+        seed.deserialize(content)
+    }
+
+    fn tuple_variant<V>(self, len: usize, visitor: V) -> error::Result<V::Value>
+    where V: Visitor<'de> {
+        serde::Deserializer::deserialize_tuple(&mut *self.de, len, visitor)
+    }
+
+    fn struct_variant<V>(self, fields: &'static [&'static str], visitor: V) -> error::Result<V::Value>
+    where V: Visitor<'de> {
+        serde::Deserializer::deserialize_struct(&mut *self.de, "", fields, visitor)
     }
 }
 
@@ -45,6 +289,16 @@ impl<'de> Visitor<'de> for ValueVisitor {
         Ok(Value::Number(Number::new(v)))
     }
 
+    #[cfg(feature = "integer128")]
+    fn visit_i128<E>(self, v: i128) -> Result<Self::Value, E> where E: Error {
+        Ok(Value::Number(Number::new(v)))
+    }
+
+    #[cfg(feature = "integer128")]
+    fn visit_u128<E>(self, v: u128) -> Result<Self::Value, E> where E: Error {
+        Ok(Value::Number(Number::new(v)))
+    }
+
     fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E> where E: Error {
         Ok(Value::Number(Number::new(v)))
     }
@@ -66,7 +320,7 @@ impl<'de> Visitor<'de> for ValueVisitor {
     }
 
     fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Self::Value, E> where E: Error {
-        self.visit_string(String::from_utf8(v).map_err(|e| Error::custom(format!("{}", e)))?)
+        Ok(Value::Bytes(v))
     }
 
     fn visit_none<E>(self) -> Result<Self::Value, E> where E: Error {
@@ -74,9 +328,7 @@ impl<'de> Visitor<'de> for ValueVisitor {
     }
 
     fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error> where D: Deserializer<'de> {
-        Ok(Value::Option(Some(Box::new(
-            deserializer.deserialize_any(ValueVisitor)?,
-        ))))
+        Ok(Value::Option(Some(Box::new(Value::deserialize(deserializer)?))))
     }
 
     fn visit_unit<E>(self) -> Result<Self::Value, E> where E: Error {
@@ -100,13 +352,40 @@ impl<'de> Visitor<'de> for ValueVisitor {
         Ok(Value::Seq(vec))
     }
 
+    fn visit_enum<A>(self, data: A) -> Result<Self::Value, A::Error> where A: EnumAccess<'de> {
+        let (name, variant) = data.variant::<String>()?;
+        let content = variant.newtype_variant::<Value>()?;
+
+        Ok(Value::Variant { name, content: Box::new(content) })
+    }
+
     fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error> where A: MapAccess<'de> {
-        // put in spaga logic
         let mut res: Map = Map::new();
-        while let Some(entry) = map.next_entry()? {
-            res.insert(entry.0, entry.1);
+        let mut name = None;
+
+        match map.next_key::<Value>()? {
+            #[cfg(feature = "arbitrary_precision")]
+            Some(Value::String(ref key)) if key == NUMBER_TOKEN => {
+                return Ok(Value::Number(Number::Literal(map.next_value()?)));
+            }
+            Some(Value::String(ref key)) if key == STRUCT_TOKEN => {
+                name = Some(map.next_value::<String>()?);
+            }
+            Some(key) => {
+                let value = map.next_value()?;
+                res.insert(key, value);
+            }
+            None => return Ok(Value::Map(res)),
         }
 
-        Ok(Value::Map(res))
+        while let Some((key, value)) = map.next_entry()? {
+            res.insert(key, value);
+        }
+
+        Ok(match name {
+            Some(name) if name.is_empty() => Value::Struct { name: None, fields: res },
+            Some(name) => Value::Struct { name: Some(name), fields: res },
+            None => Value::Map(res),
+        })
     }
-}
\ No newline at end of file
+}
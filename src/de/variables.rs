@@ -0,0 +1,176 @@
+// The `variables` extension: a `$name: value` entry of a map or struct defines
+// a variable for the whole document rather than an entry, `$name` stands for
+// its value anywhere a value goes and `$base + "/fonts"` joins strings.
+//
+// A document can only enable it when `Options::extension("variables")` allows
+// it, and the bytes read again count against `Limits::expansion`.
+//
+//     #![enable(variables)]
+//     {
+//         $base: "/usr/share",
+//         fonts: $base + "/fonts",
+//         icons: $base + "/icons",
+//     }
+
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+use serde::de::value::StringDeserializer;
+use serde::de::DeserializeSeed;
+
+use super::{ Deserializer, Error, Result };
+use crate::error::SpannedResult;
+use crate::parse::Bytes;
+
+#[derive(Default)]
+pub struct Variables<'de> {
+    // Where the value of each variable starts.
+    definitions: HashMap<&'de str, Bytes<'de>>,
+    // The variables being read, innermost last, to catch cycles.
+    resolving: Vec<&'de str>,
+}
+
+impl<'de> Variables<'de> {
+    // Finds the definitions of the document before it is read, so that a
+    // variable can be used before or inside the collection defining it.
+    pub fn collect(mut bytes: Bytes<'de>) -> SpannedResult<Self> {
+        let mut variables = Variables::default();
+
+        while let Some(b) = bytes.peek() {
+            let skipped = match b {
+                b'"' => bytes.string().is_ok(),
+                b'\'' => bytes.char().is_ok(),
+                b'#' => bytes.skip_ws().is_ok(),
+                b'$' => match bytes.variable() {
+                    Ok(name) => {
+                        let mut value = bytes;
+                        if value.skip_ws().is_ok()
+                            && value.consume(":")
+                            && value.skip_ws().is_ok()
+                            && variables.definitions.insert(name, value).is_some()
+                        {
+                            return Err(bytes.span_error(error(name, "is defined twice")));
+                        }
+                        true
+                    }
+                    Err(_) => false,
+                },
+                _ => false,
+            };
+
+            if !skipped {
+                let _ = bytes.advance_single();
+            }
+        }
+
+        Ok(variables)
+    }
+}
+
+impl<'de> Deserializer<'de> {
+    // Reads a value with `read`, or the value of a variable from where it is
+    // defined when there is a `$name` instead.
+    pub(crate) fn value<S, F>(&mut self, seed: S, read: F) -> Result<S::Value>
+    where S: DeserializeSeed<'de>, F: FnOnce(S, &mut Self) -> Result<S::Value> {
+        if self.bytes.peek() != Some(b'$') || !self.extension_enabled("variables") {
+            return self.nested(|de| read(seed, de));
+        }
+
+        let name = self.bytes.variable()?;
+        let mut ahead = self.bytes;
+        ahead.skip_ws()?;
+        if ahead.peek() == Some(b'+') {
+            let joined = self.joined(name)?;
+            return seed.deserialize(StringDeserializer::new(joined));
+        }
+
+        let after = self.bytes;
+        let start = self.definition(name)?;
+        self.bytes = start;
+        let value = self.value(seed, read);
+        self.variables.resolving.pop();
+
+        let value = value?;
+        // the variables it refers to were counted when read
+        self.expand(start.bytes().len() - self.bytes.bytes().len())?;
+        self.bytes = after;
+
+        Ok(value)
+    }
+
+    // `$first + "..." + $other`, each part a string.
+    fn joined(&mut self, first: &'de str) -> Result<String> {
+        let mut joined = self.string_variable(first)?;
+
+        loop {
+            let mut ahead = self.bytes;
+            ahead.skip_ws()?;
+            if !ahead.consume("+") {
+                return Ok(joined);
+            }
+
+            self.bytes = ahead;
+            self.bytes.skip_ws()?;
+            if self.bytes.peek() == Some(b'$') {
+                let name = self.bytes.variable()?;
+                joined.push_str(&self.string_variable(name)?);
+            } else {
+                joined.push_str(self.bytes.string()?.as_str());
+            }
+        }
+    }
+
+    fn string_variable(&mut self, name: &'de str) -> Result<String> {
+        let after = self.bytes;
+        let start = self.definition(name)?;
+        self.bytes = start;
+        let value = self.value(PhantomData::<String>, |seed, de| seed.deserialize(de));
+        self.variables.resolving.pop();
+
+        let value = value.map_err(|e| match e {
+            Error::ExpectedString | Error::InvalidValueForType { .. } => error(name, "is not a string and cannot be joined"),
+            e => e,
+        })?;
+        // the variables it refers to were counted when read
+        self.expand(start.bytes().len() - self.bytes.bytes().len())?;
+        self.bytes = after;
+
+        Ok(value)
+    }
+
+    // Where the value of `name` starts, `name` is then being resolved until
+    // the caller pops it.
+    pub(super) fn definition(&mut self, name: &'de str) -> Result<Bytes<'de>> {
+        let bytes = *self.variables.definitions.get(name).ok_or_else(|| error(name, "is not defined"))?;
+        if let Some(first) = self.variables.resolving.iter().position(|&n| n == name) {
+            let cycle: Vec<_> = self.variables.resolving[first..].iter().chain([&name]).map(|n| format!("${}", n)).collect();
+            return Err(error(name, &format!("refers to itself through {}", cycle.join(" -> "))));
+        }
+
+        self.variables.resolving.push(name);
+
+        Ok(bytes)
+    }
+
+    // Skips a `$name: value` definition, if there is one.
+    pub(super) fn skip_definition(&mut self) -> Result<bool> {
+        if self.bytes.peek() != Some(b'$') || !self.extension_enabled("variables") {
+            return Ok(false);
+        }
+
+        self.bytes.variable()?;
+        self.bytes.skip_ws()?;
+        if !self.bytes.consume(":") {
+            return Err(Error::ExpectedMapSeparator);
+        }
+
+        self.bytes.skip_ws()?;
+        self.value(PhantomData::<serde::de::IgnoredAny>, |seed, de| seed.deserialize(de))?;
+
+        Ok(true)
+    }
+}
+
+fn error(name: &str, message: &str) -> Error {
+    Error::Variable { name: String::from(name), message: String::from(message) }
+}
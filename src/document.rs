@@ -0,0 +1,603 @@
+// A document that keeps its comments and layout through edits. Every item owns
+// its text, including the whitespace and comments around its children, so
+// printing an unchanged document gives back the input byte for byte, and
+// replacing an item only changes that item's text:
+//
+//     let mut doc: Document = source.parse()?;
+//     doc["window"]["width"] = value(&1024)?;
+//     doc.remove("legacy");
+//     fs::write(path, doc.to_string())?;
+
+use crate::comments::{ Comments, NodeComments, Segment };
+use crate::de::recover::{ end_error, skip_anchor, skip_scalar };
+use crate::error::{ Error, Result, SpannedError, SpannedResult };
+use crate::parse::Bytes;
+use crate::syntax::{ escape_string, needs_quoting };
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt;
+use std::ops::{ Index, IndexMut };
+use std::str::FromStr;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Document {
+    // Comments and extension headers in front of the value.
+    head: String,
+    root: Item,
+    tail: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Item {
+    kind: Kind,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Kind {
+    // A value without children, such as a number or a string.
+    Scalar(String),
+    // `head` runs from the name or opening bracket up to the first child, the
+    // last child's tail includes the closing bracket.
+    Collection { head: String, children: Vec<Child> },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Child {
+    key: Option<String>,
+    // The key and separator, such as `width: ` or `<"q"> `.
+    lead: String,
+    item: Item,
+    // The comma, whitespace and comments up to the next child.
+    tail: String,
+}
+
+// What indexing gives for a key that isn't there, printed as nothing.
+static MISSING: Item = Item { kind: Kind::Scalar(String::new()) };
+
+// An item holding `value`, written compactly.
+pub fn value<T>(value: &T) -> Result<Item> where T: ?Sized + Serialize {
+    Ok(Item { kind: Kind::Scalar(crate::ser::to_string(value)?) })
+}
+
+impl Document {
+    pub fn root(&self) -> &Item {
+        &self.root
+    }
+
+    pub fn root_mut(&mut self) -> &mut Item {
+        &mut self.root
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Item> {
+        self.root.get(key)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Item> {
+        self.root.get_mut(key)
+    }
+
+    pub fn insert(&mut self, key: &str, item: Item) -> Result<Option<Item>> {
+        self.root.insert(key, item)
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<Item> {
+        self.root.remove(key)
+    }
+}
+
+impl Item {
+    // The value of a map entry or struct field.
+    pub fn get(&self, key: &str) -> Option<&Item> {
+        self.children().iter().find(|c| c.key.as_deref() == Some(key)).map(|c| &c.item)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Item> {
+        self.children_mut().iter_mut().find(|c| c.key.as_deref() == Some(key)).map(|c| &mut c.item)
+    }
+
+    // Sets `key` in a map or struct. An existing entry keeps its place, a new
+    // one goes last, written like the entry before it.
+    pub fn insert(&mut self, key: &str, item: Item) -> Result<Option<Item>> {
+        if let Some(old) = self.get_mut(key) {
+            return Ok(Some(std::mem::replace(old, item)));
+        }
+
+        let (head, children) = match self.kind {
+            Kind::Collection { ref head, ref mut children } if children.last().is_some_and(|c| c.key.is_some()) => (head, children),
+            Kind::Scalar(ref text) => {
+                let (head, closer) = empty_collection(text).ok_or(Error::ExpectedMap)?;
+                let pad = if closer == '}' { " " } else { "" };
+                let child = Child { key: Some(String::from(key)), lead: lead_like("", key), item, tail: format!("{}{}", pad, closer) };
+                self.kind = Kind::Collection { head: format!("{}{}", head, pad), children: vec![child] };
+
+                return Ok(None);
+            }
+            Kind::Collection { .. } => return Err(Error::ExpectedMap),
+        };
+
+        // What goes between two entries, from the last two or the head.
+        let between = match children.len() {
+            1 => head.rfind('\n').map_or_else(|| String::from(", "), |newline| format!(",{}", &head[newline..])),
+            n => {
+                let tail = &children[n - 2].tail;
+                tail.rfind('\n').map_or_else(|| tail.clone(), |newline| format!(",{}", &tail[newline..]))
+            }
+        };
+
+        let last = children.last_mut().expect("checked above");
+        let lead = lead_like(&last.lead, key);
+        let closing = std::mem::take(&mut last.tail);
+        let tail = match closing.rfind('\n') {
+            // The last entry keeps its comment, the new one gets the trailing
+            // comma if there was one.
+            Some(newline) => {
+                let (own, closing) = closing.split_at(newline);
+                let comma = own.trim_start().starts_with(',');
+                let indent = between.find('\n').map_or(" ", |newline| &between[newline..]);
+                last.tail = format!("{}{}{}", if comma { "" } else { "," }, own, indent);
+                format!("{}{}", if comma { "," } else { "" }, closing)
+            }
+            None => {
+                last.tail = between;
+                closing
+            }
+        };
+        children.push(Child { key: Some(String::from(key)), lead, item, tail });
+
+        Ok(None)
+    }
+
+    // Takes `key` out of a map or struct, with its comments.
+    pub fn remove(&mut self, key: &str) -> Option<Item> {
+        let Kind::Collection { ref mut head, ref mut children } = self.kind else {
+            return None;
+        };
+        let index = children.iter().position(|c| c.key.as_deref() == Some(key))?;
+        let removed = children.remove(index);
+
+        if children.is_empty() {
+            let head = head.trim_end();
+            let closer = &removed.tail[removed.tail.rfind('\n').map_or(removed.tail.len() - 1, |newline| newline + 1)..];
+            // A comment in the head would take the closing bracket with it.
+            let text = match head.lines().last().is_some_and(|line| line.contains('#')) {
+                true => format!("{}\n{}", head, closer),
+                false => format!("{}{}", head, closer.trim_start()),
+            };
+            self.kind = Kind::Scalar(text);
+        } else if index == children.len() {
+            let before = &mut children[index - 1].tail;
+            *before = closing(before, &removed.tail);
+        } else {
+            // The text before the entry keeps its first line, the comments of
+            // the entry before, the rest are the removed entry's.
+            let before = match index {
+                0 => head,
+                _ => &mut children[index - 1].tail,
+            };
+            if let (Some(end), Some(start)) = (before.find('\n'), removed.tail.find('\n')) {
+                before.truncate(end);
+                before.push_str(&removed.tail[start..]);
+            }
+        }
+
+        Some(removed.item)
+    }
+
+    // The keys of a map or struct, in order.
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.children().iter().filter_map(|c| c.key.as_deref())
+    }
+
+    // The number of elements, entries or fields.
+    pub fn len(&self) -> usize {
+        self.children().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.children().is_empty()
+    }
+
+    // Reads the item as a `T`, like `from_str` would.
+    pub fn deserialize<T>(&self) -> SpannedResult<T> where T: DeserializeOwned {
+        crate::from_str(&self.to_string())
+    }
+
+    fn children(&self) -> &[Child] {
+        match self.kind {
+            Kind::Collection { ref children, .. } => children,
+            Kind::Scalar(_) => &[],
+        }
+    }
+
+    fn children_mut(&mut self) -> &mut [Child] {
+        match self.kind {
+            Kind::Collection { ref mut children, .. } => children,
+            Kind::Scalar(_) => &mut [],
+        }
+    }
+}
+
+impl Index<&str> for Document {
+    type Output = Item;
+
+    fn index(&self, key: &str) -> &Item {
+        &self.root[key]
+    }
+}
+
+impl IndexMut<&str> for Document {
+    fn index_mut(&mut self, key: &str) -> &mut Item {
+        &mut self.root[key]
+    }
+}
+
+impl Index<usize> for Document {
+    type Output = Item;
+
+    fn index(&self, index: usize) -> &Item {
+        &self.root[index]
+    }
+}
+
+impl IndexMut<usize> for Document {
+    fn index_mut(&mut self, index: usize) -> &mut Item {
+        &mut self.root[index]
+    }
+}
+
+// A missing key gives an empty item, see `get` to tell.
+impl Index<&str> for Item {
+    type Output = Item;
+
+    fn index(&self, key: &str) -> &Item {
+        self.get(key).unwrap_or(&MISSING)
+    }
+}
+
+// A missing key is inserted holding `()`, for the value to be assigned.
+impl IndexMut<&str> for Item {
+    fn index_mut(&mut self, key: &str) -> &mut Item {
+        if self.get(key).is_none() {
+            let unit = Item { kind: Kind::Scalar(String::from("()")) };
+            if self.insert(key, unit).is_err() {
+                panic!("no entry `{}` in something other than a map or struct", key);
+            }
+        }
+
+        self.get_mut(key).expect("inserted above")
+    }
+}
+
+impl Index<usize> for Item {
+    type Output = Item;
+
+    fn index(&self, index: usize) -> &Item {
+        &self.children()[index].item
+    }
+}
+
+impl IndexMut<usize> for Item {
+    fn index_mut(&mut self, index: usize) -> &mut Item {
+        &mut self.children_mut()[index].item
+    }
+}
+
+impl fmt::Display for Document {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}{}", self.head, self.root, self.tail)
+    }
+}
+
+impl fmt::Display for Item {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            Kind::Scalar(ref text) => f.write_str(text),
+            Kind::Collection { ref head, ref children } => {
+                f.write_str(head)?;
+                for child in children {
+                    write!(f, "{}{}{}", child.lead, child.item, child.tail)?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl FromStr for Document {
+    type Err = SpannedError;
+
+    fn from_str(s: &str) -> SpannedResult<Self> {
+        let mut parser = Parser { source: s, bytes: Bytes::new(s.as_bytes())? };
+        parser.bytes.extensions().map_err(|e| parser.bytes.span_error(e))?;
+        let head = String::from(&s[..parser.offset()]);
+
+        let root = parser.item()?;
+        let start = parser.offset();
+        parser.skip_ws()?;
+        if !parser.bytes.bytes().is_empty() {
+            return Err(parser.bytes.span_error(Error::TrailingCharacters));
+        }
+
+        Ok(Document { head, root, tail: String::from(&s[start..]) })
+    }
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    bytes: Bytes<'a>,
+}
+
+impl Parser<'_> {
+    fn offset(&self) -> usize {
+        self.source.len() - self.bytes.bytes().len()
+    }
+
+    fn skip_ws(&mut self) -> SpannedResult<()> {
+        self.bytes.skip_ws().map_err(|e| self.bytes.span_error(e))
+    }
+
+    fn item(&mut self) -> SpannedResult<Item> {
+        let start = self.offset();
+
+        // `Name(..)` and `Name { .. }` keep the name in the head, as does an
+        // `&name` label.
+        skip_anchor(&mut self.bytes).map_err(|e| self.bytes.span_error(e))?;
+        let mut bytes = self.bytes;
+        if bytes.identifier().is_ok() && bytes.skip_ws().is_ok() && matches!(bytes.peek(), Some(b'(' | b'{')) {
+            self.bytes = bytes;
+        }
+
+        let closer = match self.bytes.peek() {
+            Some(b'[') => b']',
+            Some(b'(') => b')',
+            Some(b'{') => b'}',
+            _ => {
+                skip_scalar(&mut self.bytes)?;
+                return Ok(Item { kind: Kind::Scalar(String::from(&self.source[start..self.offset()])) });
+            }
+        };
+        let _ = self.bytes.advance_single();
+        self.skip_ws()?;
+
+        let head = String::from(&self.source[start..self.offset()]);
+        let mut children = Vec::new();
+        let mut closed = self.close(closer);
+        while !closed {
+            if self.bytes.bytes().is_empty() {
+                return Err(self.bytes.span_error(end_error(closer)));
+            }
+            let mut child = self.child(closer)?;
+
+            let tail_start = self.offset();
+            self.skip_ws()?;
+            let comma = self.bytes.consume(",");
+            self.skip_ws()?;
+            closed = self.close(closer);
+            if !closed && !comma {
+                let code = match self.bytes.peek() {
+                    None | Some(b')' | b']' | b'}') => end_error(closer),
+                    _ => Error::ExpectedComma,
+                };
+                return Err(self.bytes.span_error(code));
+            }
+
+            child.tail = String::from(&self.source[tail_start..self.offset()]);
+            children.push(child);
+        }
+
+        // An empty collection is a single token.
+        let kind = if children.is_empty() {
+            Kind::Scalar(String::from(&self.source[start..self.offset()]))
+        } else {
+            Kind::Collection { head, children }
+        };
+
+        Ok(Item { kind })
+    }
+
+    fn close(&mut self, closer: u8) -> bool {
+        let closed = self.bytes.peek() == Some(closer);
+        if closed {
+            let _ = self.bytes.advance_single();
+        }
+
+        closed
+    }
+
+    // An element, a `key: value` entry or a `<key> value` cavetta entry.
+    fn child(&mut self, closer: u8) -> SpannedResult<Child> {
+        let start = self.offset();
+
+        let key = if closer == b'}' && self.bytes.consume("<") {
+            self.skip_ws()?;
+            let key = self.item()?;
+            self.skip_ws()?;
+            if !self.bytes.consume(">") {
+                return Err(self.bytes.span_error(Error::ExpectedMapSeparator));
+            }
+            Some(key)
+        } else {
+            let item = self.item()?;
+            let mut bytes = self.bytes;
+            let _ = bytes.skip_ws();
+            if closer != b']' && bytes.consume(":") {
+                self.bytes = bytes;
+                Some(item)
+            } else if closer == b'}' {
+                return Err(self.bytes.span_error(Error::ExpectedMapSeparator));
+            } else {
+                return Ok(Child { key: None, lead: String::new(), item, tail: String::new() });
+            }
+        };
+        self.skip_ws()?;
+
+        let lead = String::from(&self.source[start..self.offset()]);
+        let item = self.item()?;
+
+        Ok(Child { key: key.map(|key| key_name(&key.to_string())), lead, item, tail: String::new() })
+    }
+}
+
+// `{}` or `Name ()` as a head to put entries after, and the closing bracket.
+fn empty_collection(text: &str) -> Option<(&str, char)> {
+    let closer = match text.chars().last()? {
+        '}' => '}',
+        ')' => ')',
+        _ => return None,
+    };
+    let head = text[..text.len() - 1].trim_end();
+
+    head.ends_with(['{', '(']).then_some((head, closer))
+}
+
+// The key and separator of a new entry, in the style of `sibling`, or as
+// `key: ` without one.
+fn lead_like(sibling: &str, key: &str) -> String {
+    let quoted = format!("\"{}\"", escape_string(key));
+    if sibling.starts_with('<') {
+        return format!("<{}>{}", quoted, sibling.rfind('>').map_or("", |end| &sibling[end + 1..]));
+    }
+
+    let separator = match sibling.rfind(':') {
+        Some(colon) => &sibling[sibling[..colon].trim_end().len()..],
+        None => ": ",
+    };
+    match sibling.starts_with('"') || needs_quoting(key) {
+        true => format!("{}{}", quoted, separator),
+        false => format!("{}{}", key, separator),
+    }
+}
+
+// The tail of the entry before a removed last one, which now closes the
+// collection: its own comment, then the removed one's closing bracket.
+fn closing(before: &str, removed: &str) -> String {
+    let Some(end) = before.find('\n') else {
+        return String::from(removed);
+    };
+    let mut own = String::from(&before[..end]);
+    if !removed.trim_start().starts_with(',') {
+        own = own.replacen(',', "", 1);
+    }
+
+    match removed.find('\n') {
+        Some(start) => own + &removed[start..],
+        None if own.contains('#') => format!("{}\n{}", own, removed.trim_start()),
+        None => own + removed,
+    }
+}
+
+// Quoted keys are looked up by their contents, others by their text.
+fn key_name(text: &str) -> String {
+    if text.starts_with('"') {
+        if let Ok(key) = crate::from_str::<String>(text) {
+            return key;
+        }
+    }
+
+    String::from(text.strip_prefix("r#").unwrap_or(text))
+}
+
+impl Document {
+    // The comments around each item, see `comments::from_str`.
+    pub(crate) fn comments(&self) -> Comments {
+        let mut comments = Comments::default();
+        let mut path = Vec::new();
+
+        let root = NodeComments { leading: comment_lines(&self.head), trailing: trailing_comment(&self.tail) };
+        comments.insert_node(path.clone(), root);
+        self.root.collect_comments(&mut path, &mut comments);
+
+        comments
+    }
+
+    // Writes `comments` back next to their items. Only items on lines of their
+    // own get them, as a comment would end a line that goes on.
+    pub(crate) fn attach_comments(&mut self, comments: &Comments) {
+        let mut path = Vec::new();
+        if let Some(root) = comments.get(&path) {
+            for line in &root.leading {
+                self.head.push_str(&format!("#{}\n", line));
+            }
+            if let Some(ref trailing) = root.trailing {
+                self.tail.insert_str(0, &format!(" #{}", trailing));
+            }
+        }
+
+        self.root.attach_comments(&mut path, comments);
+    }
+}
+
+impl Item {
+    fn collect_comments(&self, path: &mut Vec<Segment>, comments: &mut Comments) {
+        let Kind::Collection { ref head, ref children } = self.kind else {
+            return;
+        };
+
+        let mut before = head.as_str();
+        for (i, child) in children.iter().enumerate() {
+            path.push(child.segment(i));
+            let (trailing, rest) = child.tail.split_once('\n').unwrap_or((&child.tail, ""));
+            let node = NodeComments { leading: comment_lines(before), trailing: trailing_comment(trailing) };
+            comments.insert_node(path.clone(), node);
+
+            child.item.collect_comments(path, comments);
+            path.pop();
+            before = rest;
+        }
+    }
+
+    fn attach_comments(&mut self, path: &mut Vec<Segment>, comments: &Comments) {
+        let Kind::Collection { ref mut head, ref mut children } = self.kind else {
+            return;
+        };
+
+        for i in 0..children.len() {
+            path.push(children[i].segment(i));
+            if let Some(node) = comments.get(path) {
+                let before = match i {
+                    0 => &mut *head,
+                    _ => &mut children[i - 1].tail,
+                };
+                if let Some(newline) = before.rfind('\n') {
+                    let indent = String::from(&before[newline + 1..]);
+                    for line in &node.leading {
+                        before.push_str(&format!("#{}\n{}", line, indent));
+                    }
+                }
+
+                let tail = &mut children[i].tail;
+                if let (Some(trailing), Some(newline)) = (&node.trailing, tail.find('\n')) {
+                    tail.insert_str(newline, &format!(" #{}", trailing));
+                }
+            }
+
+            children[i].item.attach_comments(path, comments);
+            path.pop();
+        }
+    }
+}
+
+impl Child {
+    fn segment(&self, index: usize) -> Segment {
+        match self.key {
+            Some(ref key) => Segment::Key(key.clone()),
+            None => Segment::Index(index),
+        }
+    }
+}
+
+// The text after `#` of each comment in `trivia`, leaving out extension
+// headers.
+fn comment_lines(trivia: &str) -> Vec<String> {
+    trivia
+        .lines()
+        .filter_map(|line| line.split_once('#').map(|(_, comment)| comment))
+        .filter(|comment| !comment.starts_with("!["))
+        .map(|comment| String::from(comment.trim_end()))
+        .collect()
+}
+
+fn trailing_comment(trivia: &str) -> Option<String> {
+    comment_lines(trivia.lines().next().unwrap_or_default()).pop()
+}
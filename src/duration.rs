@@ -0,0 +1,66 @@
+// Writes a `Duration` as a string of seconds, for use with
+// `#[serde(with = "zmerald::duration")]`:
+//
+//     timeout: "1.5s"
+//
+// rather than serde's `{ secs: 1, nanos: 500000000 }`. Reading also accepts
+// the other units of `parse`, such as `"250ms"` or `"2h"`.
+
+use serde::de::{ self, Deserialize, Deserializer, Unexpected };
+use serde::ser::Serializer;
+use std::time::Duration;
+
+const NANOS_PER_SEC: u128 = 1_000_000_000;
+
+pub fn serialize<S>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+    serializer.serialize_str(&format(*duration))
+}
+
+pub fn deserialize<'de, D>(deserializer: D) -> Result<Duration, D::Error> where D: Deserializer<'de> {
+    let s = String::deserialize(deserializer)?;
+
+    parse(&s).ok_or_else(|| de::Error::invalid_value(Unexpected::Str(&s), &"a duration such as `1.5s`"))
+}
+
+// `1.5s`, exact to the nanosecond.
+pub fn format(duration: Duration) -> String {
+    match duration.subsec_nanos() {
+        0 => format!("{}s", duration.as_secs()),
+        nanos => format!("{}.{}s", duration.as_secs(), format!("{:09}", nanos).trim_end_matches('0')),
+    }
+}
+
+// A number followed by `ns`, `us`, `ms`, `s`, `m`, `h` or `d`, such as `1.5s`
+// or `250ms`. Digits past the nanosecond are dropped.
+pub fn parse(s: &str) -> Option<Duration> {
+    let (number, unit) = s.split_at(s.find(|c: char| !c.is_ascii_digit() && c != '.')?);
+    let nanos_per_unit = match unit {
+        "ns" => 1,
+        "us" | "µs" => 1_000,
+        "ms" => 1_000_000,
+        "s" => NANOS_PER_SEC,
+        "m" => 60 * NANOS_PER_SEC,
+        "h" => 60 * 60 * NANOS_PER_SEC,
+        "d" => 24 * 60 * 60 * NANOS_PER_SEC,
+        _ => return None,
+    };
+
+    let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+    if whole.is_empty() && fraction.is_empty() || fraction.contains('.') {
+        return None;
+    }
+
+    let whole = match whole {
+        "" => 0,
+        whole => whole.parse::<u128>().ok()?,
+    };
+    let mut nanos = whole.checked_mul(nanos_per_unit)?;
+    let mut scale = nanos_per_unit;
+    for digit in fraction.bytes() {
+        scale /= 10;
+        nanos += u128::from(digit - b'0') * scale;
+    }
+
+    let secs = u64::try_from(nanos / NANOS_PER_SEC).ok()?;
+    Some(Duration::new(secs, (nanos % NANOS_PER_SEC) as u32))
+}
@@ -1,6 +1,7 @@
 use serde::{de, ser};
 use std::{error::Error as StdError, fmt, io, str::Utf8Error, string::FromUtf8Error};
-use crate::parse::{is_ident_first_char, is_ident_other_char, is_ident_raw_char};
+use crate::de::Encoding;
+use crate::parse::{is_ident_first_char, is_ident_other_char, is_ident_raw_char, is_keyword};
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct SpannedError {
@@ -20,8 +21,19 @@ pub enum Error {
     Eof,
     ExpectedArray,
     ExpectedArrayEnd,
+    // A sequence in `(..)` or a tuple in `[..]`, see `Options::brackets`.
+    MismatchedBrackets {
+        expected: char,
+        found: char,
+    },
     ExpectedAttribute,
     ExpectedAttributeEnd,
+    UnknownExtension(String),
+    // An extension enabled twice, or one that is always on.
+    UnusedExtension(String),
+    // An extension that expands the document, which the application did not
+    // allow, see `Options::extension`.
+    ExtensionNotAllowed(String),
     ExpectedBoolean,
     ExpectedComma,
     ExpectedChar,
@@ -34,7 +46,33 @@ pub enum Error {
     ExpectedMapSeparator,
     ExpectedMapEnd,
     ExpectedString,
+    ExpectedByteString,
+    InvalidHexString,
+    ExpectedFileReference,
+    FileReference {
+        path: String,
+        message: String,
+    },
     ExpectedStringEnd,
+    UnquotedString,
+    Interpolation {
+        name: String,
+        message: String,
+    },
+    Variable {
+        name: String,
+        message: String,
+    },
+    UndefinedAnchor(String),
+    UnexpectedEvent(&'static str),
+    InvalidConfig(String),
+    NoSuchPath(String),
+    RenamedOption {
+        found: String,
+        replacement: &'static str,
+    },
+    DepthLimitExceeded(usize),
+    CycleDetected,
     ExpectedIdentifier,
     ExpectedDifferentStructName { // ExpectedStructName {
         expected: &'static str,
@@ -49,12 +87,17 @@ pub enum Error {
     InvalidEscape(&'static str),
 
     IntegerOutOfBounds,
+    // An integer read as a float that cannot hold it exactly.
+    RoundedInteger(String),
 
     UnclosedBlockComment,
     UnderscoreAtBeginning,
     UnexpectedByte(char),
 
     Utf8Error(Utf8Error),
+    InvalidUtf16,
+    // The input starts with the byte order mark of another encoding.
+    UnsupportedEncoding(Encoding),
     TrailingCharacters,
 
     InvalidValueForType {
@@ -67,6 +110,13 @@ pub enum Error {
         found: usize,
     },
 
+    // A tuple or fixed-size array with too few or too many elements, at its
+    // closing bracket.
+    ExpectedArrayLength {
+        expected: usize,
+        found: usize,
+    },
+
     NoSuchEnumVariant {
         expected: &'static [&'static str],
         found: String,
@@ -89,13 +139,62 @@ pub enum Error {
         outer: Option<String>,
     },
 
+    // An old field or variant name read as `new`, see `Options::renames`.
+    DeprecatedName {
+        old: String,
+        new: String,
+        outer: Option<String>,
+    },
+
+    NoMatchingVariant {
+        name: &'static str,
+        // Each variant tried, with the reason it did not match.
+        errors: Vec<(&'static str, Error)>,
+    },
+
+    DuplicateKey {
+        key: String,
+        first: Position,
+        second: Position,
+    },
+
+    MismatchedDuplicateKey {
+        key: String,
+        first: Position,
+        second: Position,
+        expected: &'static str,
+        found: &'static str,
+    },
+
+    SizeLimitExceeded(usize),
+
+    // Exceeded bounds of `Options::limits`, each with the bound.
+    InputTooLarge(usize),
+    CollectionTooLarge(usize),
+    TokenTooLong(usize),
+    ExpansionTooLarge(usize),
+
     InvalidIdentifier(String),
     SuggestRawIdentifier(String),
+    ReservedKeyword(String),
     ExpectedRawValue,
+
+    // An error reading or writing the file at `path`.
+    InFile {
+        path: String,
+        error: Box<Error>,
+    },
 }
 
 impl fmt::Display for SpannedError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // `path:line:col: error`, as compilers write it.
+        if let Error::InFile { ref path, ref error } = self.code {
+            if (self.position != Position { line: 0, col: 0 }) {
+                return write!(f, "{}:{}: {}", path, self.position, error);
+            }
+        }
+
         if (self.position == Position { line: 0, col: 0 }) {
             write!(f, "{}", self.code)
         } else {
@@ -104,19 +203,128 @@ impl fmt::Display for SpannedError {
     }
 }
 
+impl Error {
+    // For an unknown field or variant, the expected name closest to the one
+    // found, if any is close enough to likely be what was meant.
+    pub fn suggestion(&self) -> Option<&'static str> {
+        let (expected, found) = match self {
+            Error::NoSuchEnumVariant { expected, found, .. } | Error::NoSuchStructField { expected, found, .. } => {
+                (*expected, found)
+            }
+            _ => return None,
+        };
+
+        // Allow about one typo per three characters.
+        let max_distance = found.chars().count() / 3 + 1;
+
+        expected
+            .iter()
+            .map(|name| (edit_distance(name, found), *name))
+            .filter(|&(distance, _)| distance <= max_distance)
+            .min_by_key(|&(distance, _)| distance)
+            .map(|(_, name)| name)
+    }
+}
+
+// Levenshtein distance, ignoring case so that `Width` suggests `width`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().flat_map(char::to_lowercase).collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().flat_map(char::to_lowercase).enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+
+    row[b.len()]
+}
+
+impl SpannedError {
+    // Renders the error with the line it occurred on and a caret under its
+    // position, `src` being the document that was parsed:
+    //
+    //     error: Expected float
+    //      --> 2:5
+    //       |
+    //     2 |  x: true
+    //       |     ^
+    pub fn display_with_source<'a>(&'a self, src: &'a str) -> WithSource<'a> {
+        WithSource { error: self, src }
+    }
+}
+
+pub struct WithSource<'a> {
+    error: &'a SpannedError,
+    src: &'a str,
+}
+
+impl fmt::Display for WithSource<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Position { line, col } = self.error.position;
+        write!(f, "error: {}", self.error.code)?;
+
+        let text = match line.checked_sub(1).and_then(|i| self.src.lines().nth(i)) {
+            Some(text) => text,
+            // No position, or one past the last line.
+            None => return Ok(()),
+        };
+
+        // Columns count bytes, the caret is indented by as many characters,
+        // tabs kept so that it lines up.
+        let indent: String = text
+            .char_indices()
+            .take_while(|&(i, _)| i + 1 < col)
+            .map(|(_, c)| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let gutter = " ".repeat(line.to_string().len());
+
+        write!(f, "\n{} --> {}", gutter, self.error.position)?;
+        write!(f, "\n{} |", gutter)?;
+        write!(f, "\n{} | {}", line, text)?;
+        write!(f, "\n{} | {}^", gutter, indent)
+    }
+}
+
+struct Elements(usize);
+
+impl fmt::Display for Elements {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            0 => f.write_str("zero elements"),
+            1 => f.write_str("one element"),
+            n => write!(f, "{} elements", n),
+        }
+    }
+}
+
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match *self {
             Error::Io(ref s) => f.write_str(s),
             Error::Message(ref s) => f.write_str(s),
+            Error::InFile { ref path, ref error } => write!(f, "{}: {}", path, error),
             Error::Base64Error(ref e) => fmt::Display::fmt(e, f),
             Error::Eof => f.write_str("Unexpected end of RON"),
             Error::ExpectedArray => f.write_str("Expected opening `[`"),
             Error::ExpectedArrayEnd => f.write_str("Expected closing `]`"),
+            Error::MismatchedBrackets { expected, found } => {
+                write!(f, "Expected opening `{}` but found `{}`, sequences are written in `[..]` and tuples in `(..)`", expected, found)
+            }
             Error::ExpectedAttribute => f.write_str("Expected an `#![enable(...)]` attribute"),
             Error::ExpectedAttributeEnd => {
                 f.write_str("Expected closing `)]` after the enable attribute")
             }
+            Error::UnknownExtension(ref name) => write!(f, "Unknown extension `{}`", name),
+            Error::UnusedExtension(ref name) => write!(f, "Extension `{}` is enabled to no effect", name),
+            Error::ExtensionNotAllowed(ref name) => {
+                write!(f, "Extension `{}` expands the document and must be allowed with `Options::extension`", name)
+            }
             Error::ExpectedBoolean => f.write_str("Expected boolean"),
             Error::ExpectedComma => f.write_str("Expected comma"),
             Error::ExpectedChar => f.write_str("Expected char"),
@@ -138,11 +346,30 @@ impl fmt::Display for Error {
             Error::ExpectedStructEnd => f.write_str("Expected closing `)`"),
             Error::ExpectedUnit => f.write_str("Expected unit"),
             Error::ExpectedString => f.write_str("Expected string"),
+            Error::ExpectedByteString => f.write_str("Expected byte string"),
+            Error::InvalidHexString => f.write_str("Expected pairs of hex digits in hex string"),
             Error::ExpectedStringEnd => f.write_str("Expected end of string"),
             Error::ExpectedIdentifier => f.write_str("Expected identifier"),
             Error::InvalidEscape(e) => write!(f, "Invalid escape sequence '{}'", e),
             Error::IntegerOutOfBounds => f.write_str("Integer is out of bounds"),
+            Error::RoundedInteger(ref integer) => write!(f, "Integer `{}` is rounded to the nearest float", integer),
             Error::Utf8Error(ref e) => fmt::Display::fmt(e, f),
+            Error::ExpectedFileReference => f.write_str("Expected a file reference like `@file(\"path\")`"),
+            Error::FileReference { ref path, ref message } => write!(f, "Could not load `{}`: {}", path, message),
+            Error::InvalidUtf16 => f.write_str("Invalid UTF-16 input"),
+            Error::Interpolation { ref name, ref message } => write!(f, "Could not expand `${{{}}}`: {}", name, message),
+            Error::Variable { ref name, ref message } => write!(f, "Variable `${}` {}", name, message),
+            Error::UndefinedAnchor(ref name) => write!(f, "No value is labelled `&{}` before `*{}`", name, name),
+            Error::UnexpectedEvent(event) => write!(f, "Cannot emit {}", event),
+            Error::InvalidConfig(ref message) => write!(f, "Invalid pretty config: {}", message),
+            Error::NoSuchPath(ref path) => write!(f, "Nothing is at `{}` in the document", path),
+            Error::RenamedOption { ref found, replacement } => write!(f, "The option `{}` is replaced by {}", found, replacement),
+            Error::DepthLimitExceeded(limit) => write!(f, "Value is nested deeper than the depth limit of {}", limit),
+            Error::CycleDetected => f.write_str("Value contains itself through a shared pointer"),
+            Error::UnquotedString => f.write_str("Expected a quoted string or char, see `Options::strict_strings`"),
+            Error::UnsupportedEncoding(encoding) => {
+                write!(f, "Unsupported encoding {:?}, only UTF-8 is read here, see `from_bytes_decoded`", encoding)
+            }
             Error::UnclosedBlockComment => f.write_str("Unclosed block comment"),
             Error::UnderscoreAtBeginning => f.write_str("Unexpected leading underscore in an integer"),
             Error::UnexpectedByte(ref byte) => write!(f, "Unexpected byte {:?}", byte),
@@ -153,15 +380,11 @@ impl fmt::Display for Error {
             }
 
             Error::ExpectedDifferentLength { ref expected, found } => {
-                write!(f, "Expected {} but found ", expected)?;
-
-                match found {
-                    0 => f.write_str("zero elements")?,
-                    1 => f.write_str("one element")?,
-                    n => write!(f, "{} elements", n)?,
-                }
+                write!(f, "Expected {} but found {} instead", expected, Elements(found))
+            }
 
-                f.write_str(" instead")
+            Error::ExpectedArrayLength { expected, found } => {
+                write!(f, "Expected {} but found {} instead", Elements(expected), Elements(found))
             }
 
             Error::NoSuchEnumVariant { expected, ref found, ref outer } => {
@@ -174,7 +397,7 @@ impl fmt::Display for Error {
                 write!(f, "variant named {}", Identifier(found))?;
 
                 if let Some(outer) = outer {
-                    write!(f, "in enum {}", Identifier(outer))?;
+                    write!(f, " in enum {}", Identifier(outer))?;
                 }
 
                 write!(
@@ -184,14 +407,19 @@ impl fmt::Display for Error {
                         alts: expected,
                         none: "variants"
                     }
-                )
+                )?;
+
+                match self.suggestion() {
+                    Some(suggestion) => write!(f, ", did you mean {}?", Identifier(suggestion)),
+                    None => Ok(()),
+                }
             }
 
             Error::NoSuchStructField { expected, ref found, ref outer } => {
                 write!(f, "Unexpected field named {}", Identifier(found))?;
 
                 if let Some(outer) = outer {
-                    write!(f, "in {}", Identifier(outer))?;
+                    write!(f, " in {}", Identifier(outer))?;
                 }
 
                 write!(
@@ -201,7 +429,12 @@ impl fmt::Display for Error {
                         alts: expected,
                         none: "fields"
                     }
-                )
+                )?;
+
+                match self.suggestion() {
+                    Some(suggestion) => write!(f, ", did you mean {}?", Identifier(suggestion)),
+                    None => Ok(()),
+                }
             }
 
             Error::MissingStructField { field, ref outer } => {
@@ -222,12 +455,53 @@ impl fmt::Display for Error {
                 }
             }
 
+            Error::DeprecatedName { ref old, ref new, ref outer } => {
+                write!(f, "The name {}", Identifier(old))?;
+
+                if let Some(outer) = outer {
+                    write!(f, " in {}", Identifier(outer))?;
+                }
+
+                write!(f, " is deprecated, use {} instead", Identifier(new))
+            }
+
+            Error::NoMatchingVariant { name, ref errors } => {
+                write!(f, "Data did not match any variant of untagged enum {}", Identifier(name))?;
+
+                for (variant, error) in errors {
+                    write!(f, "\n  {}: {}", Identifier(variant), error)?;
+                }
+
+                Ok(())
+            }
+
+            Error::DuplicateKey { ref key, first, second } => {
+                write!(f, "Duplicate key `{}` at {} was already given at {}", key, second, first)
+            }
+
+            Error::MismatchedDuplicateKey { ref key, first, second, expected, found } => write!(
+                f,
+                "Duplicate key `{}` at {} is {} but was {} at {}",
+                key, second, found, expected, first
+            ),
+
+            Error::SizeLimitExceeded(limit) => write!(f, "Value exceeds the size limit of {} bytes", limit),
+            Error::InputTooLarge(limit) => write!(f, "Document is larger than the limit of {} bytes", limit),
+            Error::CollectionTooLarge(limit) => write!(f, "Collection has more than the limit of {} elements", limit),
+            Error::TokenTooLong(limit) => write!(f, "Token is longer than the limit of {} bytes", limit),
+            Error::ExpansionTooLarge(limit) => write!(f, "Document expands to more than the limit of {} bytes", limit),
+
             Error::InvalidIdentifier(ref invalid) => write!(f, "Invalid identifier {:?}", invalid),
             Error::SuggestRawIdentifier(ref identifier) => write!(
                 f,
                 "Found invalid std identifier `{}`, try the raw identifier `r#{}` instead",
                 identifier, identifier
             ),
+            Error::ReservedKeyword(ref keyword) => write!(
+                f,
+                "Found reserved keyword `{}` where a string was expected, try the quoted string \"{}\" instead",
+                keyword, keyword
+            ),
             Error::ExpectedRawValue => f.write_str("Expected a `ron::value::RawValue`"),
         }
     }
@@ -239,6 +513,13 @@ pub struct Position {
     pub col: usize,
 }
 
+// From `start` up to, but not including, `end`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
 impl fmt::Display for Position {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}:{}", self.line, self.col)
@@ -336,7 +617,6 @@ impl de::Error for Error {
 
     #[cold]
     fn duplicate_field(field: &'static str) -> Self {
-        println!("field ĦAQQQQQ: {}", field);
         Error::DuplicateStructField { field, outer: None }
     }
 }
@@ -416,7 +696,7 @@ impl<'a> fmt::Display for Identifier<'a> {
 
         let mut bytes = self.0.as_bytes().iter().copied();
 
-        if !bytes.next().map_or(false, is_ident_first_char) || !bytes.all(is_ident_other_char) {
+        if !bytes.next().is_some_and(is_ident_first_char) || !bytes.all(is_ident_other_char) || is_keyword(self.0) {
             write!(f, "`r#{}`", self.0)
         } else {
             write!(f, "`{}`", self.0)
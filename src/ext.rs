@@ -0,0 +1,29 @@
+use crate::de;
+use crate::error::{ Result, SpannedResult };
+use crate::ser::{ self, PrettyConfig };
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::io;
+
+// Method forms of `ser::to_writer` and friends for code already holding a
+// writer, e.g. `file.write_zmerald_pretty(&config, &pretty)?`.
+pub trait ZmeraldWriteExt: io::Write {
+    fn write_zmerald<T>(&mut self, value: &T) -> Result<()> where T: ?Sized + Serialize {
+        ser::to_writer(self, value)
+    }
+
+    fn write_zmerald_pretty<T>(&mut self, value: &T, config: &PrettyConfig) -> Result<()>
+    where T: ?Sized + Serialize {
+        ser::to_writer_pretty(self, value, config.clone())
+    }
+}
+
+impl<W: io::Write + ?Sized> ZmeraldWriteExt for W {}
+
+pub trait ZmeraldReadExt: io::Read {
+    fn read_zmerald<T>(&mut self) -> SpannedResult<T> where T: DeserializeOwned {
+        de::from_reader(self)
+    }
+}
+
+impl<R: io::Read + ?Sized> ZmeraldReadExt for R {}
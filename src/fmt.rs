@@ -0,0 +1,23 @@
+use crate::error::{ Position, SpannedError, SpannedResult };
+use crate::ser::{ to_string_pretty, BytesFormat, PrettyConfig };
+use crate::value::Value;
+
+// Reads any document and writes it back in the style given by `config`, such
+// as for normalizing config files in a pre-commit hook. Struct and variant
+// names are kept, comments are not. Only the layout of `config` is used, the
+// settings that would change the data read back are overridden: floats keep
+// their decimal point and all their digits, bytes stay bytes and `Some` is
+// written out.
+pub fn format_str(input: &str, config: &PrettyConfig) -> SpannedResult<String> {
+    let value: Value = crate::from_str(input)?;
+
+    let mut config = config.clone();
+    config.decimal_floats = true;
+    config.float_precision = None;
+    config.bytes_format = BytesFormat::ByteString;
+    config.explicit_some = true;
+    config.skip_defaults = false;
+    config.json_compatible = false;
+
+    to_string_pretty(&value, config).map_err(|code| SpannedError { code, position: Position { line: 0, col: 0 } })
+}
@@ -1,6 +1,49 @@
 pub mod ser;
 pub mod de;
-pub use de::{ from_str, from_bytes, from_reader }; 
+pub use de::{ from_str, from_str_at, from_bytes, from_reader, from_path };
+pub use ser::{ to_path, to_path_pretty, SerializeOptions };
+pub mod comments;
+pub mod document;
+pub mod duration;
 pub mod error;
+pub mod ext;
+pub mod fmt;
+pub use ext::{ ZmeraldReadExt, ZmeraldWriteExt };
+pub mod options;
+pub use options::{ Brackets, Compat, Environment, Limits, Options, Renames, Resolver, Scalar, ScalarParser, StructNames, UnknownExtensions, Warnings };
+#[cfg(feature = "trace")]
+pub use options::Tracer;
 pub mod parse;
-pub mod value;
\ No newline at end of file
+pub mod schema;
+pub mod soa;
+pub mod syntax;
+pub mod value;
+#[cfg(feature = "simd")]
+mod simd;
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
+
+mod macros;
+
+// Version of the grammar described in grammar.md. It is bumped whenever a
+// document written by this crate may not be readable by an older version.
+pub const FORMAT_VERSION: u32 = 1;
+
+// Syntax on top of the base grammar that a document turns on in its
+// `#![enable(..)]` header.
+pub(crate) const EXTENSIONS: &[&str] = &["anchors", "env", "number_suffixes", "unwrap_newtypes", "unwrap_variant_newtypes",
+    "variables"];
+
+// Syntax on top of the base grammar that is always read. Enabling it in a
+// header has no effect.
+pub(crate) const BUILTIN_SYNTAX: &[&str] = &["byte_strings", "hex_strings", "raw_identifiers"];
+
+// Extensions under which a few bytes can stand for far more, `$name` reads a
+// definition again and `*name` copies a value. Documents only enable them when
+// the application allows it with `Options::extension`.
+pub(crate) const EXPANDING_EXTENSIONS: &[&str] = &["anchors", "variables"];
+
+// Writers targeting older readers can check for a name before relying on it.
+pub fn supports_extension(name: &str) -> bool {
+    EXTENSIONS.contains(&name) || BUILTIN_SYNTAX.contains(&name)
+}
@@ -0,0 +1,78 @@
+// Builds a `Value` with syntax close to the text format:
+//
+//     zmerald!{ name: "x", list: [1, 2, 3], inner: { on: true }, opt: Some(1) }
+//
+// Braces whose first key is an identifier build an anonymous struct, as they
+// do when parsed, `Name { .. }` a named one and literal keys a map. Any other
+// value is an expression converted with `Value::from`.
+#[macro_export]
+macro_rules! zmerald {
+    // Map entries: munch the key, then the value tokens up to the next comma.
+    (@map $map:ident ()) => {};
+    (@map $map:ident ($key:ident : $($rest:tt)*)) => {
+        $crate::zmerald!(@entry $map [$crate::value::Value::String(::std::string::String::from(stringify!($key)))] () ($($rest)*))
+    };
+    (@map $map:ident ($key:literal : $($rest:tt)*)) => {
+        $crate::zmerald!(@entry $map [$crate::value::Value::from($key)] () ($($rest)*))
+    };
+    (@entry $map:ident [$key:expr] ($($value:tt)+) ($(, $($rest:tt)*)?)) => {
+        $map.insert($key, $crate::zmerald!($($value)+));
+        $crate::zmerald!(@map $map ($($($rest)*)?));
+    };
+    (@entry $map:ident [$key:expr] ($($value:tt)*) ($next:tt $($rest:tt)*)) => {
+        $crate::zmerald!(@entry $map [$key] ($($value)* $next) ($($rest)*))
+    };
+
+    // Sequence elements: munch the element tokens up to the next comma and
+    // collect the finished elements into a single `vec!`.
+    (@seq [$($done:expr),*] () ()) => {
+        $crate::value::Value::Seq(::std::vec![$($done),*])
+    };
+    (@seq [$($done:expr),*] ($($elem:tt)+) ($(, $($rest:tt)*)?)) => {
+        $crate::zmerald!(@seq [$($done,)* $crate::zmerald!($($elem)+)] () ($($($rest)*)?))
+    };
+    (@seq [$($done:expr),*] ($($elem:tt)*) ($next:tt $($rest:tt)*)) => {
+        $crate::zmerald!(@seq [$($done),*] ($($elem)* $next) ($($rest)*))
+    };
+
+    (()) => {
+        $crate::value::Value::Unit
+    };
+    (None) => {
+        $crate::value::Value::Option(None)
+    };
+    (Some($($inner:tt)+)) => {
+        $crate::value::Value::Option(Some(::std::boxed::Box::new($crate::zmerald!($($inner)+))))
+    };
+    ([ $($elems:tt)* ]) => {
+        $crate::zmerald!(@seq [] () ($($elems)*))
+    };
+    ({ $key:ident : $($rest:tt)* }) => {{
+        let mut fields = $crate::value::Map::new();
+        $crate::zmerald!(@map fields ($key : $($rest)*));
+        $crate::value::Value::Struct { name: None, fields }
+    }};
+    ({ $($entries:tt)* }) => {{
+        #[allow(unused_mut)]
+        let mut map = $crate::value::Map::new();
+        $crate::zmerald!(@map map ($($entries)*));
+        $crate::value::Value::Map(map)
+    }};
+    ($name:ident { $($entries:tt)* }) => {{
+        #[allow(unused_mut)]
+        let mut fields = $crate::value::Map::new();
+        $crate::zmerald!(@map fields ($($entries)*));
+        $crate::value::Value::Struct { name: Some(::std::string::String::from(stringify!($name))), fields }
+    }};
+
+    // A top-level map may omit its braces.
+    ($key:ident : $($rest:tt)*) => {
+        $crate::zmerald!({ $key : $($rest)* })
+    };
+    ($key:literal : $($rest:tt)*) => {
+        $crate::zmerald!({ $key : $($rest)* })
+    };
+    ($other:expr) => {
+        $crate::value::Value::from($other)
+    };
+}
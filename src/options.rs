@@ -0,0 +1,651 @@
+use crate::de::{ self, Deserializer, Encoding };
+use crate::error::{ Error, SpannedError, SpannedResult };
+#[cfg(feature = "trace")]
+use crate::error::Position;
+use crate::value::Number;
+use serde::de::{ Deserialize, DeserializeOwned, DeserializeSeed };
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{ Component, Path, PathBuf };
+use std::sync::{ Arc, Mutex, PoisonError };
+
+// Settings shared by the deserializer and the checking passes, the free
+// functions in `de` use the defaults.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct Options {
+    // Checking stops once this many errors were found.
+    pub max_errors: usize,
+    // Lints, such as a key given twice, fail the document rather than being
+    // let through.
+    pub warnings_as_errors: bool,
+    // Told about the lints that are let through.
+    pub warnings: Option<Warnings>,
+    pub struct_names: StructNames,
+    // Loads the files referenced by `@file("...")`, without one such
+    // references are an error.
+    pub resolver: Option<Resolver>,
+    // Looks up the variables of `${NAME}` in documents that enable the `env`
+    // extension, without one such strings are an error.
+    pub environment: Option<Environment>,
+    // Told about each decision the deserializer makes, such as which type it
+    // expects next or how it read an untyped value.
+    #[cfg(feature = "trace")]
+    pub tracer: Option<Tracer>,
+    // Names that may be enabled in the `#![enable(...)]` header on top of
+    // the built-in ones, for extensions defined outside this crate.
+    pub extensions: Vec<String>,
+    pub unknown_extensions: UnknownExtensions,
+    pub compat: Compat,
+    // Strings and chars must be quoted, so that `None` or `true` cannot be
+    // mistaken for one.
+    pub strict_strings: bool,
+    // Reads unquoted tokens before the deserializer does.
+    pub scalar_parser: Option<ScalarParser>,
+    pub brackets: Brackets,
+    pub renames: Renames,
+    pub limits: Limits,
+}
+
+// The syntax read and written, for projects moving over from another format.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Compat {
+    #[default]
+    Zmerald,
+    // RON as well: `Name(field: ..)` structs, `//` and `/* */` comments, raw
+    // strings such as `r#"..."#` and RON's extensions. Written documents are
+    // valid RON.
+    Ron,
+}
+
+// RON's extensions that zmerald lacks, `implicit_some` is always on here.
+pub(crate) const RON_EXTENSIONS: &[&str] = &["implicit_some"];
+
+#[cfg(feature = "trace")]
+#[derive(Clone)]
+pub struct Tracer(Arc<Trace>);
+
+#[cfg(feature = "trace")]
+type Trace = dyn Fn(Position, &str) + Send + Sync;
+
+#[cfg(feature = "trace")]
+impl Tracer {
+    pub fn new<F>(trace: F) -> Self where F: Fn(Position, &str) + Send + Sync + 'static {
+        Tracer(Arc::new(trace))
+    }
+
+    // Prints each decision as `line:col: message`.
+    pub fn stderr() -> Self {
+        Tracer::new(|position, message| eprintln!("{}: {}", position, message))
+    }
+
+    pub fn trace(&self, position: Position, message: &str) {
+        (self.0)(position, message)
+    }
+}
+
+#[cfg(feature = "trace")]
+impl fmt::Debug for Tracer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Tracer(..)")
+    }
+}
+
+#[derive(Clone)]
+pub struct Resolver(Arc<Resolve>);
+
+type Resolve = dyn Fn(&str) -> io::Result<Vec<u8>> + Send + Sync;
+
+impl Resolver {
+    pub fn new<F>(resolve: F) -> Self where F: Fn(&str) -> io::Result<Vec<u8>> + Send + Sync + 'static {
+        Resolver(Arc::new(resolve))
+    }
+
+    // Reads referenced files relative to `dir`. Paths that could lead out of
+    // it, absolute ones or those with a `..`, are refused.
+    pub fn directory<P: Into<PathBuf>>(dir: P) -> Self {
+        let dir = dir.into();
+        Resolver::new(move |path| {
+            if !Path::new(path).components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir)) {
+                return Err(io::Error::new(io::ErrorKind::PermissionDenied, "path leads out of the directory"));
+            }
+
+            fs::read(dir.join(path))
+        })
+    }
+
+    pub fn resolve(&self, path: &str) -> io::Result<Vec<u8>> {
+        (self.0)(path)
+    }
+}
+
+impl fmt::Debug for Resolver {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Resolver(..)")
+    }
+}
+
+#[derive(Clone)]
+pub struct Environment(Arc<Lookup>);
+
+type Lookup = dyn Fn(&str) -> Option<String> + Send + Sync;
+
+impl Environment {
+    pub fn new<F>(lookup: F) -> Self where F: Fn(&str) -> Option<String> + Send + Sync + 'static {
+        Environment(Arc::new(lookup))
+    }
+
+    // The variables of this process.
+    pub fn process() -> Self {
+        Environment::new(|name| std::env::var(name).ok())
+    }
+
+    pub fn var(&self, name: &str) -> Option<String> {
+        (self.0)(name)
+    }
+}
+
+impl fmt::Debug for Environment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Environment(..)")
+    }
+}
+
+// Receives what is wrong with a document without failing it: keys given
+// twice, names deprecated by `Options::renames`, extensions enabled to no
+// effect and integers rounded when read as floats.
+#[derive(Clone)]
+pub struct Warnings(Arc<Warn>);
+
+impl Warnings {
+    pub fn new<F>(warn: F) -> Self where F: Fn(&SpannedError) + Send + Sync + 'static {
+        Warnings(Arc::new(warn))
+    }
+
+    pub fn warn(&self, warning: &SpannedError) {
+        (self.0)(warning)
+    }
+}
+
+impl fmt::Debug for Warnings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Warnings(..)")
+    }
+}
+
+// Gets first crack at the unquoted tokens of numbers, strings and untyped
+// values, such as `5s`, `128MiB` or `2023-01-01`, so that documents can use
+// units friendlier than plain numbers:
+//
+//     let parser = ScalarParser::new(|token| {
+//         let mib = token.strip_suffix("MiB")?.parse::<u64>().ok()?;
+//         Some(Scalar::Number(Number::from(mib << 20)))
+//     });
+//
+// A token runs up to whitespace, a comment or a reserved char such as `,`.
+// It is read as usual when the parser returns `None`.
+#[derive(Clone)]
+pub struct ScalarParser(Arc<ParseScalar>);
+
+type ParseScalar = dyn Fn(&str) -> Option<Scalar> + Send + Sync;
+
+impl ScalarParser {
+    pub fn new<F>(parse: F) -> Self where F: Fn(&str) -> Option<Scalar> + Send + Sync + 'static {
+        ScalarParser(Arc::new(parse))
+    }
+
+    pub fn parse(&self, token: &str) -> Option<Scalar> {
+        (self.0)(token)
+    }
+}
+
+impl fmt::Debug for ScalarParser {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("ScalarParser(..)")
+    }
+}
+
+// What a `ScalarParser` reads a token as, the field it is read into converts
+// it as it would a number or string of the document.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Scalar {
+    String(String),
+    Number(Number),
+}
+
+// How the name in front of a struct, such as `Point` in `Point(x: 1)`, is
+// checked against the type being deserialized.
+#[derive(Clone, Default)]
+pub enum StructNames {
+    // The name may be left out, but must match when given.
+    #[default]
+    Check,
+    // The name must be given and must match.
+    Require,
+    // Any name is accepted, which helps reading files written before a
+    // type was renamed.
+    Ignore,
+    // Like `Ignore`, but a mismatching name is passed to the callback first.
+    Warn(Arc<dyn Fn(&SpannedError) + Send + Sync>),
+}
+
+impl fmt::Debug for StructNames {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StructNames::Check => f.write_str("Check"),
+            StructNames::Require => f.write_str("Require"),
+            StructNames::Ignore => f.write_str("Ignore"),
+            StructNames::Warn(_) => f.write_str("Warn(..)"),
+        }
+    }
+}
+
+// Old names of fields and variants read as their new ones, so that documents
+// written before a type changed still load:
+//
+//     let renames = Renames::new()
+//         .rename("Window", "colour", "color")
+//         .warn(|warning| eprintln!("{}", warning));
+//
+// Types are named as serde names them, struct variants have the name `""`.
+// Only names written as identifiers are renamed, not quoted ones.
+#[derive(Clone, Default)]
+pub struct Renames {
+    // New names by type and old name.
+    names: HashMap<String, HashMap<String, String>>,
+    warn: Option<Arc<Warn>>,
+}
+
+type Warn = dyn Fn(&SpannedError) + Send + Sync;
+
+impl Renames {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn rename(mut self, ty: &str, old: &str, new: &str) -> Self {
+        self.names.entry(String::from(ty)).or_default().insert(String::from(old), String::from(new));
+
+        self
+    }
+
+    // Passes each old name read to `warn`, as an `Error::DeprecatedName`.
+    pub fn warn<F>(mut self, warn: F) -> Self where F: Fn(&SpannedError) + Send + Sync + 'static {
+        self.warn = Some(Arc::new(warn));
+
+        self
+    }
+
+    pub fn get(&self, ty: &str, old: &str) -> Option<&str> {
+        self.names.get(ty)?.get(old).map(String::as_str)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub(crate) fn deprecated(&self, warning: &SpannedError) {
+        if let Some(warn) = &self.warn {
+            warn(warning)
+        }
+    }
+}
+
+impl fmt::Debug for Renames {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Renames").field("names", &self.names).finish_non_exhaustive()
+    }
+}
+
+// Bounds on a document, for input that is not trusted such as uploaded files
+// or mods. Each is unbounded by default but `depth`, which is 64 so that
+// nesting cannot overflow the stack:
+//
+//     let limits = Limits::new().input_size(1 << 20).collection_len(10_000).token_len(4096);
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Limits {
+    // Bytes of the whole document.
+    pub input_size: usize,
+    // Elements of a sequence, or entries of a map or struct.
+    pub collection_len: usize,
+    // Bytes of a string, byte string, identifier or number.
+    pub token_len: usize,
+    // Values nested in one another, such as the elements of a sequence in
+    // the field of a struct.
+    pub depth: usize,
+    // Bytes read again for `$name`, or those `*name` copies the value of,
+    // summed over the document.
+    pub expansion: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            input_size: usize::MAX,
+            collection_len: usize::MAX,
+            token_len: usize::MAX,
+            depth: 64,
+            expansion: 1 << 20,
+        }
+    }
+}
+
+impl Limits {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn input_size(mut self, input_size: usize) -> Self {
+        self.input_size = input_size;
+
+        self
+    }
+
+    pub fn collection_len(mut self, collection_len: usize) -> Self {
+        self.collection_len = collection_len;
+
+        self
+    }
+
+    pub fn token_len(mut self, token_len: usize) -> Self {
+        self.token_len = token_len;
+
+        self
+    }
+
+    pub fn depth(mut self, depth: usize) -> Self {
+        self.depth = depth;
+
+        self
+    }
+
+    pub fn expansion(mut self, expansion: usize) -> Self {
+        self.expansion = expansion;
+
+        self
+    }
+}
+
+// Which brackets sequences and tuples are read from. Tuples include tuple
+// structs and fixed-size arrays, which serde reads as tuples.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Brackets {
+    // `[..]` for sequences and `(..)` for tuples, as they are written. The
+    // other one is an error, as it is usually a mistake.
+    #[default]
+    Strict,
+    // Either for both, `[1, 2]` is read as a tuple and `(1, 2)` as a `Vec`.
+    Interchangeable,
+}
+
+// What happens to a name in the `#![enable(...)]` header that is neither
+// built in nor registered with `Options::extension`.
+#[derive(Clone, Default)]
+pub enum UnknownExtensions {
+    #[default]
+    Error,
+    Ignore,
+    // Like `Ignore`, but the name is passed to the callback first.
+    Warn(Arc<dyn Fn(&SpannedError) + Send + Sync>),
+}
+
+impl fmt::Debug for UnknownExtensions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UnknownExtensions::Error => f.write_str("Error"),
+            UnknownExtensions::Ignore => f.write_str("Ignore"),
+            UnknownExtensions::Warn(_) => f.write_str("Warn(..)"),
+        }
+    }
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            max_errors: usize::MAX,
+            warnings_as_errors: false,
+            warnings: None,
+            struct_names: StructNames::Check,
+            resolver: None,
+            environment: None,
+            #[cfg(feature = "trace")]
+            tracer: None,
+            extensions: Vec::new(),
+            unknown_extensions: UnknownExtensions::Error,
+            compat: Compat::Zmerald,
+            strict_strings: false,
+            scalar_parser: None,
+            brackets: Brackets::Strict,
+            renames: Renames::default(),
+            limits: Limits::default(),
+        }
+    }
+}
+
+impl Options {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn max_errors(mut self, max_errors: usize) -> Self {
+        self.max_errors = max_errors;
+
+        self
+    }
+
+    pub fn warnings_as_errors(mut self, warnings_as_errors: bool) -> Self {
+        self.warnings_as_errors = warnings_as_errors;
+
+        self
+    }
+
+    pub fn warnings(mut self, warnings: Warnings) -> Self {
+        self.warnings = Some(warnings);
+
+        self
+    }
+
+    pub fn struct_names(mut self, struct_names: StructNames) -> Self {
+        self.struct_names = struct_names;
+
+        self
+    }
+
+    pub fn resolver(mut self, resolver: Resolver) -> Self {
+        self.resolver = Some(resolver);
+
+        self
+    }
+
+    pub fn environment(mut self, environment: Environment) -> Self {
+        self.environment = Some(environment);
+
+        self
+    }
+
+    #[cfg(feature = "trace")]
+    pub fn tracer(mut self, tracer: Tracer) -> Self {
+        self.tracer = Some(tracer);
+
+        self
+    }
+
+    // Registers an extension, documents may then enable it by name. Also
+    // allows the built-in ones that expand the document, such as `variables`.
+    pub fn extension(mut self, name: &str) -> Self {
+        self.extensions.push(String::from(name));
+
+        self
+    }
+
+    pub fn unknown_extensions(mut self, unknown_extensions: UnknownExtensions) -> Self {
+        self.unknown_extensions = unknown_extensions;
+
+        self
+    }
+
+    pub fn compat(mut self, compat: Compat) -> Self {
+        self.compat = compat;
+
+        self
+    }
+
+    pub fn strict_strings(mut self, strict_strings: bool) -> Self {
+        self.strict_strings = strict_strings;
+
+        self
+    }
+
+    pub fn scalar_parser(mut self, scalar_parser: ScalarParser) -> Self {
+        self.scalar_parser = Some(scalar_parser);
+
+        self
+    }
+
+    pub fn brackets(mut self, brackets: Brackets) -> Self {
+        self.brackets = brackets;
+
+        self
+    }
+
+    pub fn renames(mut self, renames: Renames) -> Self {
+        self.renames = renames;
+
+        self
+    }
+
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+
+        self
+    }
+
+    // Whether `name` is built in or registered.
+    pub fn supports_extension(&self, name: &str) -> bool {
+        crate::supports_extension(name)
+            || self.extensions.iter().any(|e| e == name)
+            || (self.compat == Compat::Ron && RON_EXTENSIONS.contains(&name))
+    }
+
+    // Reads no more than `Limits::input_size` allows.
+    pub fn from_reader<R, T>(&self, rdr: R) -> SpannedResult<T> where R: io::Read, T: DeserializeOwned {
+        let mut bytes = Vec::new();
+        let max = u64::try_from(self.limits.input_size).unwrap_or(u64::MAX);
+        io::Read::read_to_end(&mut rdr.take(max.saturating_add(1)), &mut bytes)?;
+
+        self.from_bytes_decoded(&bytes).1
+    }
+
+    // Reads the file at `path` as `from_bytes_decoded` does, errors name the
+    // file, see `Error::InFile`.
+    pub fn from_path<P, T>(&self, path: P) -> SpannedResult<T> where P: AsRef<Path>, T: DeserializeOwned {
+        let path = path.as_ref();
+        let in_file = |e: SpannedError| SpannedError {
+            code: Error::InFile { path: path.display().to_string(), error: Box::new(e.code) },
+            position: e.position,
+        };
+
+        let bytes = fs::read(path).map_err(|e| in_file(e.into()))?;
+        self.from_bytes_decoded(&bytes).1.map_err(in_file)
+    }
+
+    pub fn from_str<'a, T>(&self, s: &'a str) -> SpannedResult<T> where T: Deserialize<'a> {
+        self.from_bytes(s.as_bytes())
+    }
+
+    pub fn from_bytes<'a, T>(&self, s: &'a [u8]) -> SpannedResult<T> where T: Deserialize<'a> {
+        self.from_bytes_seed(s, std::marker::PhantomData)
+    }
+
+    // Also returns the warnings of the document, in the order they were found.
+    // The ones given to `Options::warnings` as well.
+    pub fn from_str_with_warnings<'a, T>(&self, s: &'a str) -> SpannedResult<(T, Vec<SpannedError>)>
+    where T: Deserialize<'a> {
+        let found = Arc::new(Mutex::new(Vec::new()));
+        let sink = found.clone();
+        let previous = self.warnings.clone();
+        let options = self.clone().warnings(Warnings::new(move |warning| {
+            if let Some(previous) = &previous {
+                previous.warn(warning);
+            }
+            sink.lock().unwrap_or_else(PoisonError::into_inner).push(warning.clone());
+        }));
+
+        let value = options.from_str(s)?;
+        let warnings = std::mem::take(&mut *found.lock().unwrap_or_else(PoisonError::into_inner));
+        Ok((value, warnings))
+    }
+
+    // Also returns the paths of the entries that were skipped, such as
+    // `window.widht` for an unknown field of a struct that allows them, in
+    // the order they were found.
+    pub fn from_str_with_ignored<'a, T>(&self, s: &'a str) -> SpannedResult<(T, Vec<String>)> where T: Deserialize<'a> {
+        let mut deserializer = Deserializer::from_bytes_with_options(s.as_bytes(), self.clone())?;
+        deserializer.track_ignored();
+        let value = deserializer.value(std::marker::PhantomData, |seed, de| seed.deserialize(de));
+        let value = value.map_err(|e| deserializer.span_error(e))?;
+        deserializer.end().map_err(|e| deserializer.span_error(e))?;
+
+        Ok((value, deserializer.take_ignored()))
+    }
+
+    // Reads only the value at `path`, see `de::from_str_at`. Anything after
+    // that value is not read, nor checked.
+    pub fn from_str_at<'a, T>(&self, s: &'a str, path: &str) -> SpannedResult<T> where T: Deserialize<'a> {
+        let mut deserializer = Deserializer::from_bytes_with_options(s.as_bytes(), self.clone())?;
+        deserializer.seek(path).map_err(|e| deserializer.span_error(e))?;
+        let value = deserializer.value(std::marker::PhantomData, |seed, de| seed.deserialize(de));
+
+        value.map_err(|e| deserializer.span_error(e))
+    }
+
+    // Accepts UTF-16 with a byte order mark as well as UTF-8, positions in
+    // errors then count characters of the transcoded text.
+    pub fn from_bytes_decoded<T>(&self, s: &[u8]) -> (Encoding, SpannedResult<T>) where T: DeserializeOwned {
+        let encoding = Encoding::detect(s);
+        let result = encoding.decode(s).and_then(|bytes| self.from_bytes(&bytes));
+
+        (encoding, result)
+    }
+
+    // Invalid UTF-8, such as Latin-1 text in a string, is read as U+FFFD
+    // rather than failing the document.
+    pub fn from_bytes_lossy<T>(&self, s: &[u8]) -> SpannedResult<T> where T: DeserializeOwned {
+        self.from_str(&String::from_utf8_lossy(s))
+    }
+
+    // A UTF-8 byte order mark is skipped.
+    pub fn from_bytes_seed<'a, S, T>(&self, s: &'a [u8], seed: S) -> SpannedResult<T>
+    where S: DeserializeSeed<'a, Value = T> {
+        let mut deserializer = Deserializer::from_bytes_with_options(s, self.clone())?;
+        let value = deserializer.value(seed, |seed, de| seed.deserialize(de)).map_err(|e| deserializer.span_error(e))?;
+        deserializer.end().map_err(|e| deserializer.span_error(e))?;
+        Ok(value)
+    }
+
+    // See `de::from_str_in`.
+    #[cfg(feature = "arena")]
+    pub fn from_str_in<'a>(&self, s: &'a str, arena: &'a bumpalo::Bump) -> SpannedResult<crate::value::ValueRef<'a>> {
+        self.from_bytes_seed(s.as_bytes(), crate::value::ArenaSeed::new(arena))
+    }
+
+    // See `de::syntax_errors`, at most `max_errors` are returned.
+    pub fn syntax_errors(&self, s: &str) -> Vec<SpannedError> {
+        de::recover::check(s, self.max_errors)
+    }
+
+    // See `de::check_str`, at most `max_errors` are returned.
+    pub fn check_str<'a, T>(&self, s: &'a str) -> Result<T, Vec<SpannedError>> where T: Deserialize<'a> {
+        self.from_str(s).map_err(|e| {
+            let errors = self.syntax_errors(s);
+            match errors.is_empty() {
+                true => vec![e],
+                false => errors,
+            }
+        })
+    }
+}
@@ -1,15 +1,17 @@
 #![allow(clippy::identity_op)]
 
 use crate::error::{ Error, SpannedError, Position, Result, SpannedResult };
+use crate::options::Compat;
 
 use std::{
     char::from_u32 as char_from_u32,
+    net::{ IpAddr, SocketAddr },
     str::{ from_utf8, from_utf8_unchecked, FromStr },
 };
 
 // We have the following char categories.
 const INT_CHAR: u8 = 1 << 0; // [0-9A-Fa-f_]
-const FLOAT_CHAR: u8 = 1 << 1; // [0-9\.Ee+-]
+const FLOAT_CHAR: u8 = 1 << 1; // [0-9\.Ee+-_]
 const IDENT_FIRST_CHAR: u8 = 1 << 2; // [A-Za-z_]
 const IDENT_OTHER_CHAR: u8 = 1 << 3; // [A-Za-z_0-9]
 const IDENT_RAW_CHAR: u8 = 1 << 4; // [A-Za-z_0-9\.+-]
@@ -19,7 +21,7 @@ const RESERVED_CHAR: u8 = 1 << 6; // [{}<>[]()$:;',]
 // We encode each char as belonging to some number of these categories.
 const DIGIT: u8 = INT_CHAR | FLOAT_CHAR | IDENT_OTHER_CHAR | IDENT_RAW_CHAR; // [0-9]
 const ABCDF: u8 = INT_CHAR | IDENT_FIRST_CHAR | IDENT_OTHER_CHAR | IDENT_RAW_CHAR; // [ABCDFabcdf]
-const UNDER: u8 = INT_CHAR | IDENT_FIRST_CHAR | IDENT_OTHER_CHAR | IDENT_RAW_CHAR; // [_]
+const UNDER: u8 = INT_CHAR | FLOAT_CHAR | IDENT_FIRST_CHAR | IDENT_OTHER_CHAR | IDENT_RAW_CHAR; // [_]
 const E____: u8 = INT_CHAR | FLOAT_CHAR | IDENT_FIRST_CHAR | IDENT_OTHER_CHAR | IDENT_RAW_CHAR; // [Ee]
 const G2Z__: u8 = IDENT_FIRST_CHAR | IDENT_OTHER_CHAR | IDENT_RAW_CHAR; // [G-Zg-z]
 const PUNCT: u8 = FLOAT_CHAR | IDENT_RAW_CHAR; // [\.+-]
@@ -80,14 +82,32 @@ pub const fn is_ident_raw_char(c: u8) -> bool {
     ENCODINGS[c as usize] & IDENT_RAW_CHAR != 0
 }
 
-const fn is_whitespace_char(c: u8) -> bool {
+pub(crate) const fn is_whitespace_char(c: u8) -> bool {
     ENCODINGS[c as usize] & WHITESPACE_CHAR != 0
 }
 
-const fn is_reserved_char(c: u8) -> bool {
+pub(crate) const fn is_reserved_char(c: u8) -> bool {
     ENCODINGS[c as usize] & RESERVED_CHAR != 0
 }
 
+// Words with a fixed meaning in the grammar. They can neither be used as
+// unquoted strings nor as plain identifiers, the latter must be written as
+// raw identifiers (`r#true`) instead.
+pub const KEYWORDS: &[&str] = &["true", "false", "None", "Some", "inf", "NaN"];
+
+pub fn is_keyword(s: &str) -> bool {
+    KEYWORDS.contains(&s)
+}
+
+// A number read without knowing its type, as `deserialize_any` hands it to
+// visitors:
+//
+// - an integer is the smallest type that holds it, signed if it is written
+//   with a sign and unsigned if not: `1` is `U8(1)`, `+1` is `I8(1)` and
+//   `300` is `U16(300)`
+// - an integer neither an `i64` nor a `u64` holds is read as a float
+// - a float is `F32` if an `f32` holds it exactly and `F64` if not: `0.5` is
+//   `F32(0.5)` and `0.3` is `F64(0.3)`
 #[derive(Clone, Debug, PartialEq)]
 pub enum AnyNum {
     F32(f32),
@@ -102,10 +122,53 @@ pub enum AnyNum {
     U64(u64),
 }
 
+impl AnyNum {
+    // Reads `s`, a whole number such as `+1`, `0x1f` or `-2.5e3`.
+    pub fn parse(s: &str) -> Result<AnyNum> {
+        let mut bytes = Bytes::new(s.as_bytes())?;
+        let num = bytes.any_num()?;
+
+        match bytes.bytes().is_empty() {
+            true => Ok(num),
+            false => Err(Error::TrailingCharacters),
+        }
+    }
+
+    // Whether the integer was written with a sign, floats don't keep it.
+    pub fn is_signed(&self) -> bool {
+        matches!(self, AnyNum::I8(_) | AnyNum::I16(_) | AnyNum::I32(_) | AnyNum::I64(_))
+    }
+
+    pub fn is_float(&self) -> bool {
+        matches!(self, AnyNum::F32(_) | AnyNum::F64(_))
+    }
+
+    // The widest type of the same kind, which a `Value` holds the number as:
+    // `I64` for signed integers, `U64` for unsigned ones and `F64` for floats.
+    pub fn widen(self) -> AnyNum {
+        match self {
+            AnyNum::F32(x) => AnyNum::F64(f64::from(x)),
+            AnyNum::I8(x) => AnyNum::I64(i64::from(x)),
+            AnyNum::I16(x) => AnyNum::I64(i64::from(x)),
+            AnyNum::I32(x) => AnyNum::I64(i64::from(x)),
+            AnyNum::U8(x) => AnyNum::U64(u64::from(x)),
+            AnyNum::U16(x) => AnyNum::U64(u64::from(x)),
+            AnyNum::U32(x) => AnyNum::U64(u64::from(x)),
+            wide @ (AnyNum::F64(_) | AnyNum::I64(_) | AnyNum::U64(_)) => wide,
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct Bytes<'a> {
     bytes: &'a [u8],
     cursor: Position,
+    compat: Compat,
+    // Longest string, identifier or number read, see `Limits::token_len`.
+    max_token: usize,
+    // Whether `#![` still starts an extensions header rather than a comment,
+    // until `extensions` has read them.
+    header: bool,
 }
 
 pub(crate) type LargeUInt = u64;
@@ -115,13 +178,35 @@ impl<'a> Bytes<'a> {
     pub fn new(bytes: &'a [u8]) -> SpannedResult<Self> {
         let mut b = Bytes {
             bytes,
-            cursor: Position { line: 1, col: 1 }
+            cursor: Position { line: 1, col: 1 },
+            compat: Compat::Zmerald,
+            max_token: usize::MAX,
+            header: true,
         };
 
         b.skip_ws().map_err(|e| b.span_error(e))?;
         Ok(b)
     }
 
+    // Reads the syntax of `compat` from here on, skipping what is then
+    // whitespace.
+    pub fn set_compat(&mut self, compat: Compat) -> Result<()> {
+        self.compat = compat;
+        self.skip_ws()
+    }
+
+    pub fn set_max_token(&mut self, max_token: usize) {
+        self.max_token = max_token;
+    }
+
+    fn check_token(&self, len: usize) -> Result<()> {
+        if len > self.max_token {
+            return Err(Error::TokenTooLong(self.max_token));
+        }
+
+        Ok(())
+    }
+
     pub fn append(&mut self, bytes: &'a [u8]) {
         [self.bytes, bytes].concat();
     }
@@ -133,20 +218,46 @@ impl<'a> Bytes<'a> {
         }
     }
 
+    // Moves over `bytes`, long runs at once with only their line breaks
+    // looked at.
     pub fn advance(&mut self, bytes: usize) -> Result<()> {
-        for _ in 0..bytes {
-            self.advance_single()?;
+        if bytes <= 16 {
+            for _ in 0..bytes {
+                self.advance_single()?;
+            }
+
+            return Ok(());
         }
 
+        let Some((skipped, rest)) = self.bytes.split_at_checked(bytes) else {
+            self.advance(self.bytes.len())?;
+            return Err(Error::Eof);
+        };
+
+        // `\r\n` is one line break, see `advance_single`.
+        let columns = |line: &[u8]| line.len() - usize::from(line.last() == Some(&b'\r') && rest.first() == Some(&b'\n'));
+        match memchr::memrchr(b'\n', skipped) {
+            Some(last) => {
+                self.cursor.line += memchr::memchr_iter(b'\n', skipped).count();
+                self.cursor.col = 1 + columns(&skipped[last + 1..]);
+            }
+            None => self.cursor.col += columns(skipped),
+        }
+        self.bytes = rest;
+
         Ok(())
     }
 
     pub fn advance_single(&mut self) -> Result<()> {
-        if self.peek_or_eof()? == b'\n' {
-            self.cursor.line += 1;
-            self.cursor.col = 1;
-        } else {
-            self.cursor.col += 1;
+        match self.peek_or_eof()? {
+            b'\n' => {
+                self.cursor.line += 1;
+                self.cursor.col = 1;
+            }
+            // `\r\n` is one line break, so that positions in Windows files
+            // are the same as in Unix ones.
+            b'\r' if self.bytes.get(1) == Some(&b'\n') => {}
+            _ => self.cursor.col += 1,
         }
 
         self.bytes = &self.bytes[1..];
@@ -173,11 +284,14 @@ impl<'a> Bytes<'a> {
         }
 
         let num_bytes = self.next_bytes_contained_in(is_int_char);
+        self.check_token(num_bytes)?;
 
         if num_bytes == 0 {
             return Err(Error::ExpectedInteger);
         }
 
+        // Integer chars are ASCII.
+        debug_assert!(self.bytes[..num_bytes].is_ascii());
         let s = unsafe { from_utf8_unchecked(&self.bytes[0..num_bytes]) };
 
         if s.as_bytes()[0] == b'_' {
@@ -245,16 +359,16 @@ impl<'a> Bytes<'a> {
 
             any_float(f)
         } else {
-            let max_u8 = LargeUInt::from(std::u8::MAX);
-            let max_u16 = LargeUInt::from(std::u16::MAX);
-            let max_u32 = LargeUInt::from(std::u32::MAX);
+            let max_u8 = LargeUInt::from(u8::MAX);
+            let max_u16 = LargeUInt::from(u16::MAX);
+            let max_u32 = LargeUInt::from(u32::MAX);
 
-            let min_i8 = LargeSInt::from(std::i8::MIN);
-            let max_i8 = LargeSInt::from(std::i8::MAX);
-            let min_i16 = LargeSInt::from(std::i16::MIN);
-            let max_i16 = LargeSInt::from(std::i16::MAX);
-            let min_i32 = LargeSInt::from(std::i32::MIN);
-            let max_i32 = LargeSInt::from(std::i32::MAX);
+            let min_i8 = LargeSInt::from(i8::MIN);
+            let max_i8 = LargeSInt::from(i8::MAX);
+            let min_i16 = LargeSInt::from(i16::MIN);
+            let max_i16 = LargeSInt::from(i16::MAX);
+            let min_i32 = LargeSInt::from(i32::MIN);
+            let max_i32 = LargeSInt::from(i32::MAX);
 
             if is_signed {
                 match self.signed_integer::<LargeSInt>() {
@@ -266,7 +380,7 @@ impl<'a> Bytes<'a> {
                         } else if x >= min_i32 && x <= max_i32 {
                             Ok(AnyNum::I32(x as i32))
                         } else {
-                            Ok(AnyNum::I64(x as i64))
+                            Ok(AnyNum::I64(x))
                         }
                     }
                     Err(_) => {
@@ -284,7 +398,7 @@ impl<'a> Bytes<'a> {
                         } else if x <= max_u32 {
                             Ok(AnyNum::U32(x as u32))
                         } else {
-                            Ok(AnyNum::U64(x as u64))
+                            Ok(AnyNum::U64(x))
                         }
                     }
                     Err(_) => {
@@ -306,19 +420,25 @@ impl<'a> Bytes<'a> {
         }
     }
 
-    pub fn bytes(&self) -> &[u8] {
+    pub fn bytes(&self) -> &'a [u8] {
         self.bytes
     }
 
+    pub fn position(&self) -> Position {
+        self.cursor
+    }
+
     // fix this for failed char test in error
     pub fn char(&mut self) -> Result<char> {
         if self.consume("'") {
             self.escaped_char()
         } else {
             let i = self.bytes.iter().take_while(|&&b | !is_reserved_char(b) && !is_whitespace_char(b)).count();
+            self.check_token(i)?;
             let s = from_utf8(&self.bytes[..i]).map_err(Error::from)?;
+            let c = s.chars().next().ok_or(Error::ExpectedChar)?;
             self.consume(s);
-            Ok(s.chars().next().unwrap())
+            Ok(c)
         }  
     }
 
@@ -334,10 +454,7 @@ impl<'a> Bytes<'a> {
             // interpret the rest as UTF-8
 
             let max = self.bytes.len().min(5);
-            let pos: usize = self.bytes[..max]
-                .iter()
-                .position(|&x| x == b'\'')
-                .ok_or(Error::ExpectedChar)?;
+            let pos = memchr::memchr(b'\'', &self.bytes[..max]).ok_or(Error::ExpectedChar)?;
             let s = from_utf8(&self.bytes[0..pos]).map_err(Error::from)?;
             let mut chars = s.chars();
 
@@ -379,7 +496,7 @@ impl<'a> Bytes<'a> {
     fn check_ident_other_char(&self, index: usize) -> bool {
         self.bytes
             .get(index)
-            .map_or(false, |&b| is_ident_other_char(b))
+            .is_some_and(|&b| is_ident_other_char(b))
     }
 
     pub fn check_tuple_struct(mut self) -> Result<bool> {
@@ -437,29 +554,6 @@ impl<'a> Bytes<'a> {
         }
     }
 
-    pub fn dollar(&mut self) -> Result<Option<(String, String)>> {
-        todo!()
-        // if self.consume("$") {
-        //     self.skip_ws()?;
-
-        //     let i = self.bytes.iter().take_while(|&&b | b!='=' as u8).count();
-        //     let name = from_utf8(&self.bytes[..i]).map_err(Error::from)?;
-        //     if !name.is_empty() {
-        //         self.consume(name);
-        //     }
-
-        //     let i = self.bytes.iter().take_while(|&&b | b!=';' as u8).count();
-        //     let value = from_utf8(&self.bytes[..i]).map_err(Error::from)?;
-        //     if !value.is_empty() {
-        //         self.consume(value);
-        //     }
-
-        //     return Ok(Some((String::from(name), String::from(value))));
-        // }
-
-        // Ok(None)
-    }
-
     pub fn eat_byte(&mut self) -> Result<u8> {
         let peek = self.peek_or_eof()?;
         let _ = self.advance_single();
@@ -475,27 +569,47 @@ impl<'a> Bytes<'a> {
     pub fn float<T>(&mut self) -> Result<T> where T: FromStr {
         for literal in &["inf", "+inf", "-inf", "NaN", "+NaN", "-NaN"] {
             if self.consume_ident(literal) {
-                return FromStr::from_str(literal).map_err(|_| unreachable!()); // must not fail
+                return FromStr::from_str(literal).map_err(|_| Error::ExpectedFloat);
             }
         }
 
         let num_bytes = self.next_bytes_contained_in(is_float_char);
+        self.check_token(num_bytes)?;
 
-        // Since `rustc` allows `1_0.0_1`, lint against underscores in floats
-        // if let Some(err_bytes) = self.bytes[0..num_bytes].iter().position(|b| *b == b'_') {
-        //     let _ = self.advance(err_bytes);
-
-        //     return Err(Error::FloatUnderscore);
-        // }
-
+        // Float chars are ASCII.
+        debug_assert!(self.bytes[..num_bytes].is_ascii());
         let s = unsafe { from_utf8_unchecked(&self.bytes[0..num_bytes]) };
-        let res = FromStr::from_str(s).map_err(|_| Error::ExpectedFloat);
+
+        // `1_000.5` is separated as integers are, as `rustc` allows, but not
+        // `_1.5`, `1._5` nor `1e_5`
+        let res = if s.contains('_') {
+            let digits = s.trim_start_matches(['+', '-']);
+            if digits.starts_with('_') || s.contains("._") || s.contains("e_") || s.contains("E_") {
+                return Err(Error::FloatUnderscore);
+            }
+            FromStr::from_str(&s.replace('_', ""))
+        } else {
+            FromStr::from_str(s)
+        }.map_err(|_| Error::ExpectedFloat);
 
         let _ = self.advance(num_bytes);
 
         res
     }
 
+    // `$name`, a variable of the `variables` extension.
+    pub fn variable(&mut self) -> Result<&'a str> {
+        let mut bytes = *self;
+        if !bytes.consume("$") {
+            return Err(Error::ExpectedIdentifier);
+        }
+
+        let name = from_utf8(bytes.identifier()?).map_err(Error::from)?;
+        *self = bytes;
+
+        Ok(name)
+    }
+
     pub fn identifier(&mut self) -> Result<&'a [u8]> {
         let next = self.peek_or_eof()?;
         if !is_ident_first_char(next) {
@@ -516,6 +630,11 @@ impl<'a> Bytes<'a> {
 
         // If the next two bytes signify the start of a raw string literal,
         // return an error.
+        // Same for byte string and hex string literals.
+        if matches!(next, b'b' | b'x') && self.bytes.get(1) == Some(&b'"') {
+            return Err(Error::ExpectedIdentifier);
+        }
+
         let length = if next == b'r' {
             match self.bytes.get(1).ok_or(Error::Eof)? {
                 b'"' => return Err(Error::ExpectedIdentifier),
@@ -559,6 +678,7 @@ impl<'a> Bytes<'a> {
             // std_ident_length
         };
 
+        self.check_token(length)?;
         let ident = &self.bytes[..length];
         let _ = self.advance(length);
 
@@ -581,10 +701,83 @@ impl<'a> Bytes<'a> {
         Ok(None)
     } 
 
+    // The `#![enable(a, b)]` headers in front of a document, with where each
+    // name was given.
+    pub fn extensions(&mut self) -> Result<Vec<(Position, &'a str)>> {
+        let mut names = Vec::new();
+        while self.consume("#![") {
+            self.skip_ws()?;
+            if !self.consume("enable") {
+                return Err(Error::ExpectedAttribute);
+            }
+            self.skip_ws()?;
+            if !self.consume("(") {
+                return Err(Error::ExpectedAttribute);
+            }
+            self.skip_ws()?;
+
+            while !self.consume(")") {
+                let position = self.position();
+                let name = from_utf8(self.identifier()?).map_err(Error::from)?;
+                names.push((position, name));
+                if !self.comma()? {
+                    self.skip_ws()?;
+                    if !self.consume(")") {
+                        return Err(Error::ExpectedAttributeEnd);
+                    }
+                    break;
+                }
+            }
+
+            self.skip_ws()?;
+            if !self.consume("]") {
+                return Err(Error::ExpectedAttributeEnd);
+            }
+            self.skip_ws()?;
+        }
+        self.header = false;
+
+        Ok(names)
+    }
+
+    // `@file("path")`, a reference to a file holding the bytes of a value.
+    pub fn file_reference(&mut self) -> Result<String> {
+        if !self.consume("@file") {
+            return Err(Error::ExpectedFileReference);
+        }
+        self.skip_ws()?;
+        if !self.consume("(") {
+            return Err(Error::ExpectedFileReference);
+        }
+        self.skip_ws()?;
+
+        let path = match self.string()? {
+            ParsedStr::Allocated(s) => s,
+            ParsedStr::Slice(s) => String::from(s),
+        };
+
+        self.skip_ws()?;
+        if !self.consume(")") {
+            return Err(Error::ExpectedFileReference);
+        }
+
+        Ok(path)
+    }
+
     pub fn next_bytes_contained_in(&self, allowed: fn(u8) -> bool) -> usize {
         self.bytes.iter().take_while(|&&b| allowed(b)).count()
     }
 
+    #[cfg(not(feature = "simd"))]
+    fn whitespace_len(&self) -> usize {
+        self.next_bytes_contained_in(is_whitespace_char)
+    }
+
+    #[cfg(feature = "simd")]
+    fn whitespace_len(&self) -> usize {
+        crate::simd::whitespace_len(self.bytes)
+    }
+
     pub fn next_bytes_is_float(&self) -> bool {
         if let Some(byte) = self.peek() {
             let skip = match byte {
@@ -606,9 +799,7 @@ impl<'a> Bytes<'a> {
 
     pub fn skip_ws(&mut self) -> Result<()> {
         loop {
-            while self.peek().map_or(false, is_whitespace_char) {
-                let _ = self.advance_single();
-            }
+            let _ = self.advance(self.whitespace_len());
 
             if !self.skip_comment()? {
                 return Ok(());
@@ -652,10 +843,22 @@ impl<'a> Bytes<'a> {
 
     pub fn string(&mut self) -> Result<ParsedStr<'a>> {
         if self.consume("\"") {
-            return self.escaped_string();
+            let first = self.escaped_string()?;
+            return self.concatenated(first);
+        } else if self.check_raw_string() {
+            let _ = self.advance_single();
+            return self.raw_string();
+        } else if let Some(s) = self.address() {
+            self.advance(s.len())?;
+            return Ok(ParsedStr::Slice(s));
         } else {
             let i = self.bytes.iter().take_while(|&&b | !is_reserved_char(b) && !is_whitespace_char(b)).count();
+            self.check_token(i)?;
             let s = from_utf8(&self.bytes[..i]).map_err(Error::from)?;
+            if is_keyword(s) {
+                return Err(Error::ReservedKeyword(String::from(s)));
+            }
+
             if !s.is_empty() {
                 self.consume(s);
                 return Ok(ParsedStr::Slice(s));
@@ -665,15 +868,67 @@ impl<'a> Bytes<'a> {
         Err(Error::ExpectedString)
     }
 
-    fn escaped_string(&mut self) -> Result<ParsedStr<'a>> {
-        use std::iter::repeat;
+    pub fn check_address(&self) -> bool {
+        self.address().is_some()
+    }
 
-        let (i, end_or_escape) = self.bytes.iter().enumerate()
-            .find(|&(_, &b)| b == b'\\' || b == b'"')
-            .ok_or(Error::ExpectedStringEnd)?;
+    // A bare `127.0.0.1:8080`, `::1` or `[::1]:8080`, whose `:` and brackets
+    // would end any other bare string.
+    fn address(&self) -> Option<&'a str> {
+        let i = self.bytes.iter().take_while(|&&b| b.is_ascii_hexdigit() || matches!(b, b'.' | b':' | b'[' | b']')).count();
+        let s = from_utf8(&self.bytes[..i]).ok()?;
 
-        if *end_or_escape == b'"' {
-            let s = from_utf8(&self.bytes[..i]).map_err(Error::from)?;
+        (s.contains(':') && (s.parse::<IpAddr>().is_ok() || s.parse::<SocketAddr>().is_ok())).then_some(s)
+    }
+
+    // Quoted strings separated only by whitespace and comments are one string,
+    // `"long " "value"`, so that long values can be wrapped over lines.
+    fn concatenated(&mut self, first: ParsedStr<'a>) -> Result<ParsedStr<'a>> {
+        let mut joined: Option<String> = None;
+
+        loop {
+            let mut ahead = *self;
+            if ahead.skip_ws().is_err() || !ahead.consume("\"") {
+                break;
+            }
+
+            *self = ahead;
+            let next = self.escaped_string()?;
+            let s = joined.get_or_insert_with(|| String::from(first.as_str()));
+            self.check_token(s.len() + next.as_str().len())?;
+            s.push_str(next.as_str());
+        }
+
+        Ok(joined.map_or(first, ParsedStr::Allocated))
+    }
+
+    // The index of the next `"` or `\`, which of the two it is, and whether
+    // the bytes before it are known to be ASCII.
+    #[cfg(not(feature = "simd"))]
+    fn end_or_escape(&self) -> Result<(usize, u8, bool)> {
+        let i = memchr::memchr2(b'"', b'\\', self.bytes).ok_or(Error::ExpectedStringEnd)?;
+
+        Ok((i, self.bytes[i], false))
+    }
+
+    #[cfg(feature = "simd")]
+    fn end_or_escape(&self) -> Result<(usize, u8, bool)> {
+        let (i, ascii) = crate::simd::string_end(self.bytes).ok_or(Error::ExpectedStringEnd)?;
+
+        Ok((i, self.bytes[i], ascii))
+    }
+
+    fn escaped_string(&mut self) -> Result<ParsedStr<'a>> {
+        let (i, end_or_escape, mut ascii) = self.end_or_escape()?;
+
+        self.check_token(i)?;
+        if end_or_escape == b'"' {
+            let s = if ascii {
+                debug_assert!(self.bytes[..i].is_ascii());
+                unsafe { from_utf8_unchecked(&self.bytes[..i]) }
+            } else {
+                from_utf8(&self.bytes[..i]).map_err(Error::from)?
+            };
 
             // Advance by the number of bytes of the string + 1 for the `"`.
             let _ = self.advance(i + 1);
@@ -690,55 +945,125 @@ impl<'a> Bytes<'a> {
                     1 => s.push(character as u8),
                     len => {
                         let start = s.len();
-                        s.extend(repeat(0).take(len));
+                        s.extend(std::iter::repeat_n(0, len));
                         character.encode_utf8(&mut s[start..]);
                     }
                 }
 
-                let (new_i, end_or_escape) = self.bytes.iter().enumerate()
-                    .find(|&(_, &b)| b == b'\\' || b == b'"')
-                    .ok_or(Error::ExpectedStringEnd)?;
+                let (new_i, end_or_escape, rest_ascii) = self.end_or_escape()?;
 
                 i = new_i;
+                ascii &= rest_ascii;
+                self.check_token(s.len() + i)?;
                 s.extend_from_slice(&self.bytes[..i]);
 
-                if *end_or_escape == b'"' {
+                if end_or_escape == b'"' {
                     let _ = self.advance(i + 1);
 
-                    let s = String::from_utf8(s).map_err(Error::from)?;
+                    // Escapes are written out as UTF-8, only the input between
+                    // them can be invalid.
+                    let s = if ascii {
+                        debug_assert!(from_utf8(&s).is_ok());
+                        unsafe { String::from_utf8_unchecked(s) }
+                    } else {
+                        String::from_utf8(s).map_err(Error::from)?
+                    };
                     break Ok(ParsedStr::Allocated(s));
                 }
             }
         }
     }
 
-    // fn raw_string(&mut self) -> Result<ParsedStr<'a>> {
-    //     let num_hashes = self.bytes.iter().take_while(|&&b| b == b'#').count();
-    //     let hashes = &self.bytes[..num_hashes];
-    //     let _ = self.advance(num_hashes);
+    pub fn byte_string(&mut self) -> Result<ParsedBytes<'a>> {
+        if !self.consume("b\"") {
+            return Err(Error::ExpectedByteString);
+        }
+
+        let mut s = Vec::new();
+        loop {
+            let (i, end_or_escape, _) = self.end_or_escape()?;
+            self.check_token(s.len() + i)?;
+
+            if end_or_escape == b'"' && s.is_empty() {
+                let bytes = &self.bytes[..i];
+                let _ = self.advance(i + 1);
+
+                return Ok(ParsedBytes::Slice(bytes));
+            }
+
+            s.extend_from_slice(&self.bytes[..i]);
+            let _ = self.advance(i + 1);
+
+            if end_or_escape == b'"' {
+                return Ok(ParsedBytes::Allocated(s));
+            }
+
+            // Every escape but `\u{..}` stands for a single byte.
+            if self.peek() == Some(b'u') {
+                return Err(Error::InvalidEscape("Unicode escapes are not allowed in byte strings"));
+            }
+
+            if self.consume("x") {
+                s.push(self.decode_ascii_escape()?);
+            } else {
+                s.push(self.parse_escape()? as u8);
+            }
+        }
+    }
+
+    pub fn hex_string(&mut self) -> Result<Vec<u8>> {
+        if !self.consume("x\"") {
+            return Err(Error::ExpectedByteString);
+        }
 
-    //     if !self.consume("\"") {
-    //         return Err(Error::ExpectedString);
-    //     }
+        let digits = memchr::memchr(b'"', self.bytes).ok_or(Error::ExpectedStringEnd)?;
 
-    //     let ending = [&[b'"'], hashes].concat();
-    //     let i = self
-    //         .bytes
-    //         .windows(num_hashes + 1)
-    //         .position(|window| window == ending.as_slice())
-    //         .ok_or(Error::ExpectedStringEnd))?;
+        if digits % 2 != 0 {
+            return Err(Error::InvalidHexString);
+        }
+        self.check_token(digits / 2)?;
 
-    //     let s = from_utf8(&self.bytes[..i]).map_err(Error::from)?;
+        let bytes = self.bytes[..digits]
+            .chunks(2)
+            .map(|pair| Ok(self.decode_hex(pair[0])? << 4 | self.decode_hex(pair[1])?))
+            .collect::<Result<Vec<u8>>>()
+            .map_err(|_| Error::InvalidHexString)?;
+        let _ = self.advance(digits + 1);
 
-    //     // Advance by the number of bytes of the string
-    //     // + `num_hashes` + 1 for the `"`.
-    //     let _ = self.advance(i + num_hashes + 1);
+        Ok(bytes)
+    }
 
-    //     Ok(ParsedStr::Slice(s))
-    // }
+    // RON's `r"..."`, with as many `#` around the quotes as needed.
+    fn raw_string(&mut self) -> Result<ParsedStr<'a>> {
+        let num_hashes = self.bytes.iter().take_while(|&&b| b == b'#').count();
+        let hashes = &self.bytes[..num_hashes];
+        let _ = self.advance(num_hashes);
+
+        if !self.consume("\"") {
+            return Err(Error::ExpectedString);
+        }
+
+        let ending = [b"\"".as_slice(), hashes].concat();
+        let i = memchr::memmem::find(self.bytes, &ending).ok_or(Error::ExpectedStringEnd)?;
+        self.check_token(i)?;
+
+        let s = from_utf8(&self.bytes[..i]).map_err(Error::from)?;
+
+        // Advance by the number of bytes of the string
+        // + `num_hashes` + 1 for the `"`.
+        let _ = self.advance(i + num_hashes + 1);
+
+        Ok(ParsedStr::Slice(s))
+    }
+
+    // RON's raw strings, `r"..."` or `r#"..."#`, when reading RON.
+    pub fn check_raw_string(&self) -> bool {
+        let hashes = self.bytes.iter().skip(1).take_while(|&&b| b == b'#').count();
+        self.compat == Compat::Ron && self.peek() == Some(b'r') && self.bytes.get(hashes + 1) == Some(&b'"')
+    }
 
     fn test_for(&self, s: &str) -> bool {
-        s.bytes().enumerate().all(|(i, b)| self.bytes.get(i).map_or(false, |t| *t == b))
+        self.bytes.starts_with(s.as_bytes())
     }
 
     pub fn unsigned_integer<T: Num>(&mut self) -> Result<T> {
@@ -776,7 +1101,10 @@ impl<'a> Bytes<'a> {
             b'r' => '\r',
             b't' => '\t',
             b'0' => '\0',
-            b'x' => self.decode_ascii_escape()? as char,
+            b'x' => match self.decode_ascii_escape()? {
+                byte if byte.is_ascii() => byte as char,
+                _ => return Err(Error::InvalidEscape("Byte escape out of ASCII range")),
+            },
             b'u' => {
                 self.expect_byte(b'{', Error::InvalidEscape("Missing {"))?;
 
@@ -821,17 +1149,44 @@ impl<'a> Bytes<'a> {
         self.bytes = &self.bytes[0..len];
     }
 
+    // `#![` in front of the value starts an extensions header rather than a
+    // comment.
     fn skip_comment(&mut self) -> Result<bool> {
-        if self.consume("#") {
-            let bytes = self.bytes.iter().take_while(|&&b| b != b'\n').count();
-            let _ = self.advance(bytes);
-            // todo: take into account <EOF>
+        if self.compat == Compat::Ron && self.consume("//") {
+            let _ = self.advance(memchr::memchr(b'\n', self.bytes).unwrap_or(self.bytes.len()));
+
+            Ok(true)
+        } else if self.compat == Compat::Ron && self.consume("/*") {
+            self.skip_block_comment()?;
+
+            Ok(true)
+        } else if !(self.header && self.bytes.starts_with(b"#![")) && self.consume("#") {
+            let _ = self.advance(memchr::memchr(b'\n', self.bytes).unwrap_or(self.bytes.len()));
 
             Ok(true)
         } else {
             Ok(false)
         }
     }
+
+    // The rest of a `/* */` comment, which may nest.
+    fn skip_block_comment(&mut self) -> Result<()> {
+        let mut level = 1;
+        while level > 0 {
+            if self.consume("/*") {
+                level += 1;
+            } else if self.consume("*/") {
+                level -= 1;
+            } else if self.advance_single().is_err() {
+                return Err(Error::UnclosedBlockComment);
+            } else {
+                // on to the next `/` or `*`
+                let _ = self.advance(memchr::memchr2(b'/', b'*', self.bytes).unwrap_or(self.bytes.len()));
+            }
+        }
+
+        Ok(())
+    }
 }
 
 pub trait Num {
@@ -890,4 +1245,19 @@ impl_num!(u8 u16 u32 u64 i8 i16 i32 i64);
 pub enum ParsedStr<'a> {
     Allocated(String),
     Slice(&'a str),
+}
+
+impl ParsedStr<'_> {
+    pub fn as_str(&self) -> &str {
+        match *self {
+            ParsedStr::Allocated(ref s) => s,
+            ParsedStr::Slice(s) => s,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum ParsedBytes<'a> {
+    Allocated(Vec<u8>),
+    Slice(&'a [u8]),
 }
\ No newline at end of file
@@ -0,0 +1,271 @@
+// Documents a config type by example: `template::<Config>(..)` writes the
+// default config with the type of each item in a comment next to it, as for a
+// `--dump-default-config` flag.
+//
+//     {
+//         width: 800, # u32
+//         title: "main", # string
+//         theme: None, # optional
+//     }
+
+use crate::comments::{ self, Comments, NodeComments, Segment };
+use crate::error::{ Error, Result };
+use crate::ser::PrettyConfig;
+use serde::ser::{ self, Serialize };
+
+// The default `T`, with the type of each item after it.
+pub fn template<T>(config: PrettyConfig) -> Result<String> where T: Serialize + Default {
+    let value = T::default();
+    let annotations = annotations(&value)?;
+
+    comments::to_string_pretty(&value, &annotations, config)
+}
+
+// The type of each item of `value` as trailing comments, to which more can be
+// added before writing them with `comments::to_string_pretty`.
+pub fn annotations<T>(value: &T) -> Result<Comments> where T: ?Sized + Serialize {
+    let mut path = Vec::new();
+    let mut comments = Comments::default();
+    value.serialize(Annotator { path: &mut path, comments: &mut comments })?;
+
+    Ok(comments)
+}
+
+// Serializes into the name of the type, noting the types of nested items on
+// the way.
+struct Annotator<'a> {
+    path: &'a mut Vec<Segment>,
+    comments: &'a mut Comments,
+}
+
+impl<'a> Annotator<'a> {
+    fn child<T>(&mut self, segment: Segment, value: &T) -> Result<()> where T: ?Sized + Serialize {
+        self.path.push(segment);
+        let name = value.serialize(Annotator { path: self.path, comments: self.comments });
+        if let Ok(ref name) = name {
+            let comments = NodeComments { leading: Vec::new(), trailing: Some(format!(" {}", name)) };
+            self.comments.insert(self.path.clone(), comments);
+        }
+        self.path.pop();
+
+        name.map(|_| ())
+    }
+
+    fn compound(self, name: &str) -> Result<Compound<'a>> {
+        Ok(Compound { annotator: self, name: String::from(name), index: 0, key: None })
+    }
+}
+
+macro_rules! named {
+    ($($method:ident($ty:ty) => $name:expr,)*) => {
+        $(
+            fn $method(self, _: $ty) -> Result<String> {
+                Ok(String::from($name))
+            }
+        )*
+    };
+}
+
+impl<'a> ser::Serializer for Annotator<'a> {
+    type Ok = String;
+    type Error = Error;
+    type SerializeSeq = Compound<'a>;
+    type SerializeTuple = Compound<'a>;
+    type SerializeTupleStruct = Compound<'a>;
+    type SerializeTupleVariant = Compound<'a>;
+    type SerializeMap = Compound<'a>;
+    type SerializeStruct = Compound<'a>;
+    type SerializeStructVariant = Compound<'a>;
+
+    named! {
+        serialize_bool(bool) => "bool",
+        serialize_i8(i8) => "i8",
+        serialize_i16(i16) => "i16",
+        serialize_i32(i32) => "i32",
+        serialize_i64(i64) => "i64",
+        serialize_i128(i128) => "i128",
+        serialize_u8(u8) => "u8",
+        serialize_u16(u16) => "u16",
+        serialize_u32(u32) => "u32",
+        serialize_u64(u64) => "u64",
+        serialize_u128(u128) => "u128",
+        serialize_f32(f32) => "f32",
+        serialize_f64(f64) => "f64",
+        serialize_char(char) => "char",
+        serialize_str(&str) => "string",
+        serialize_bytes(&[u8]) => "bytes",
+    }
+
+    fn serialize_unit(self) -> Result<String> {
+        Ok(String::from("()"))
+    }
+
+    fn serialize_none(self) -> Result<String> {
+        Ok(String::from("optional"))
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<String> where T: ?Sized + Serialize {
+        value.serialize(self).map(|name| format!("optional {}", name))
+    }
+
+    fn serialize_unit_struct(self, name: &'static str) -> Result<String> {
+        Ok(String::from(name))
+    }
+
+    fn serialize_unit_variant(self, name: &'static str, _: u32, _: &'static str) -> Result<String> {
+        Ok(String::from(name))
+    }
+
+    fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<String>
+    where T: ?Sized + Serialize {
+        value.serialize(self)?;
+        Ok(String::from(name))
+    }
+
+    fn serialize_newtype_variant<T>(mut self, name: &'static str, _: u32, _: &'static str, value: &T) -> Result<String>
+    where T: ?Sized + Serialize {
+        self.child(Segment::Index(0), value)?;
+        Ok(String::from(name))
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Compound<'a>> {
+        self.compound("list")
+    }
+
+    fn serialize_tuple(self, _: usize) -> Result<Compound<'a>> {
+        self.compound("tuple")
+    }
+
+    fn serialize_tuple_struct(self, name: &'static str, _: usize) -> Result<Compound<'a>> {
+        self.compound(name)
+    }
+
+    fn serialize_tuple_variant(self, name: &'static str, _: u32, _: &'static str, _: usize) -> Result<Compound<'a>> {
+        self.compound(name)
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<Compound<'a>> {
+        self.compound("map")
+    }
+
+    fn serialize_struct(self, name: &'static str, _: usize) -> Result<Compound<'a>> {
+        self.compound(name)
+    }
+
+    fn serialize_struct_variant(self, name: &'static str, _: u32, _: &'static str, _: usize) -> Result<Compound<'a>> {
+        self.compound(name)
+    }
+}
+
+struct Compound<'a> {
+    annotator: Annotator<'a>,
+    name: String,
+    index: usize,
+    // The last map key, as the document would index it.
+    key: Option<String>,
+}
+
+impl Compound<'_> {
+    fn element<T>(&mut self, value: &T) -> Result<()> where T: ?Sized + Serialize {
+        self.index += 1;
+        self.annotator.child(Segment::Index(self.index - 1), value)
+    }
+}
+
+impl ser::SerializeSeq for Compound<'_> {
+    type Ok = String;
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()> where T: ?Sized + Serialize {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<String> {
+        Ok(self.name)
+    }
+}
+
+impl ser::SerializeTuple for Compound<'_> {
+    type Ok = String;
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()> where T: ?Sized + Serialize {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<String> {
+        Ok(self.name)
+    }
+}
+
+impl ser::SerializeTupleStruct for Compound<'_> {
+    type Ok = String;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()> where T: ?Sized + Serialize {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<String> {
+        Ok(self.name)
+    }
+}
+
+impl ser::SerializeTupleVariant for Compound<'_> {
+    type Ok = String;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()> where T: ?Sized + Serialize {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<String> {
+        Ok(self.name)
+    }
+}
+
+impl ser::SerializeMap for Compound<'_> {
+    type Ok = String;
+    type Error = Error;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<()> where T: ?Sized + Serialize {
+        let text = crate::ser::to_string(key)?;
+        self.key = Some(crate::from_str::<String>(&text).unwrap_or(text));
+        Ok(())
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<()> where T: ?Sized + Serialize {
+        let key = self.key.take().unwrap_or_default();
+        self.annotator.child(Segment::Key(key), value)
+    }
+
+    fn end(self) -> Result<String> {
+        Ok(self.name)
+    }
+}
+
+impl ser::SerializeStruct for Compound<'_> {
+    type Ok = String;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()> where T: ?Sized + Serialize {
+        self.annotator.child(Segment::Key(String::from(key)), value)
+    }
+
+    fn end(self) -> Result<String> {
+        Ok(self.name)
+    }
+}
+
+impl ser::SerializeStructVariant for Compound<'_> {
+    type Ok = String;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()> where T: ?Sized + Serialize {
+        self.annotator.child(Segment::Key(String::from(key)), value)
+    }
+
+    fn end(self) -> Result<String> {
+        Ok(self.name)
+    }
+}
@@ -0,0 +1,97 @@
+// Reads a `PrettyConfig` from a zmerald document, so the style of a project's
+// files can be committed next to them, such as in `.zmerald-fmt`:
+//
+//     {
+//         indentor: "  ",
+//         struct_names: true,
+//     }
+//
+// Options left out keep their defaults. Unknown and renamed options are
+// given back as warnings, with the option likely meant.
+
+use serde::de::{ self, Deserialize, Visitor };
+
+use super::PrettyConfig;
+use crate::de::Deserializer;
+use crate::error::{ Error, Position, SpannedError, SpannedResult };
+use crate::value::Value;
+
+// Options of earlier versions and what replaced them.
+const RENAMED: &[(&str, &str)] = &[
+    ("separator", "`space_after_colon` and `space_after_comma`"),
+    ("escape_strings", "`escape`"),
+];
+
+impl PrettyConfig {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> SpannedResult<(PrettyConfig, Vec<SpannedError>)> {
+        let config: PrettyConfig = crate::from_str(s)?;
+
+        let fields = field_names::<PrettyConfig>();
+        let keys = match crate::from_str(s)? {
+            Value::Map(map) | Value::Struct { fields: map, .. } => map,
+            _ => Default::default(),
+        };
+
+        let mut warnings = Vec::new();
+        for key in keys.keys().filter_map(Value::as_str) {
+            let warning = match RENAMED.iter().find(|(old, _)| *old == key) {
+                Some(&(_, replacement)) => Error::RenamedOption { found: String::from(key), replacement },
+                None if !fields.contains(&key) => Error::NoSuchStructField {
+                    expected: fields,
+                    found: String::from(key),
+                    outer: Some(String::from("PrettyConfig")),
+                },
+                None => continue,
+            };
+
+            warnings.push(at_key(s, key, warning));
+        }
+
+        Ok((config, warnings))
+    }
+}
+
+// The warning at the value of `key`.
+fn at_key(s: &str, key: &str, warning: Error) -> SpannedError {
+    let Ok(mut de) = Deserializer::from_str(s) else {
+        return SpannedError { code: warning, position: Position { line: 0, col: 0 } };
+    };
+
+    match de.seek(key) {
+        Ok(()) => de.span_error(warning),
+        Err(_) => SpannedError { code: warning, position: Position { line: 0, col: 0 } },
+    }
+}
+
+// The fields `T` reads, as its `Deserialize` impl lists them.
+fn field_names<'de, T>() -> &'static [&'static str] where T: Deserialize<'de> {
+    let mut fields: &'static [&'static str] = &[];
+    let _ = T::deserialize(FieldNames { fields: &mut fields });
+
+    fields
+}
+
+struct FieldNames<'a> {
+    fields: &'a mut &'static [&'static str],
+}
+
+impl<'de> de::Deserializer<'de> for FieldNames<'_> {
+    type Error = de::value::Error;
+
+    fn deserialize_any<V>(self, _: V) -> Result<V::Value, Self::Error> where V: Visitor<'de> {
+        Err(de::Error::custom("not a struct"))
+    }
+
+    fn deserialize_struct<V>(self, _: &'static str, fields: &'static [&'static str], _: V) -> Result<V::Value, Self::Error>
+    where V: Visitor<'de> {
+        *self.fields = fields;
+
+        Err(de::Error::custom("only the fields are read"))
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf option unit
+        unit_struct newtype_struct seq tuple tuple_struct map enum identifier ignored_any
+    }
+}
@@ -0,0 +1,189 @@
+// Writes only what differs from a reference value, such as the settings a
+// user changed from the defaults, to keep the config files a program writes
+// short:
+//
+//     to_string_pretty(&SerializeDiff::new(&config, &Config::default()), PrettyConfig::new())
+//
+// Fields of structs equal to those of the reference are left out, nested
+// structs are compared field by field and anything else is written whole when
+// it differs. Values are compared as `Value`s, buffered from their compact
+// text.
+
+use serde::ser::{ self, Error as _, Serialize, Serializer };
+
+use crate::error::Result;
+use crate::value::{ Map, Value };
+
+pub struct SerializeDiff<'a, T: ?Sized> {
+    value: &'a T,
+    reference: &'a T,
+}
+
+impl<'a, T: ?Sized + Serialize> SerializeDiff<'a, T> {
+    pub fn new(value: &'a T, reference: &'a T) -> Self {
+        SerializeDiff { value, reference }
+    }
+}
+
+impl<T: ?Sized + Serialize> Serialize for SerializeDiff<'_, T> {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> where S: Serializer {
+        let reference = buffered(self.reference).map_err(S::Error::custom)?;
+
+        self.value.serialize(Diff { inner: serializer, reference: &reference })
+    }
+}
+
+fn buffered<T>(value: &T) -> Result<Value> where T: ?Sized + Serialize {
+    Ok(crate::from_str(&super::to_string(value)?)?)
+}
+
+// A value to be written against what it is compared with.
+struct Against<'r, T: ?Sized> {
+    value: &'r T,
+    reference: &'r Value,
+}
+
+impl<T: ?Sized + Serialize> Serialize for Against<'_, T> {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> where S: Serializer {
+        self.value.serialize(Diff { inner: serializer, reference: self.reference })
+    }
+}
+
+// Passes everything on to `inner`, but the fields of structs that are the
+// same in `reference`.
+struct Diff<'r, S> {
+    inner: S,
+    reference: &'r Value,
+}
+
+macro_rules! forward {
+    ($($method:ident($($arg:ident: $ty:ty),*) -> $ok:ty;)*) => {
+        $(
+            fn $method(self, $($arg: $ty),*) -> std::result::Result<$ok, S::Error> {
+                self.inner.$method($($arg),*)
+            }
+        )*
+    };
+}
+
+impl<'r, S: Serializer> Serializer for Diff<'r, S> {
+    type Ok = S::Ok;
+    type Error = S::Error;
+    type SerializeSeq = S::SerializeSeq;
+    type SerializeTuple = S::SerializeTuple;
+    type SerializeTupleStruct = S::SerializeTupleStruct;
+    type SerializeTupleVariant = S::SerializeTupleVariant;
+    type SerializeMap = S::SerializeMap;
+    type SerializeStruct = DiffStruct<'r, S::SerializeStruct>;
+    type SerializeStructVariant = DiffStruct<'r, S::SerializeStructVariant>;
+
+    forward! {
+        serialize_bool(v: bool) -> S::Ok;
+        serialize_i8(v: i8) -> S::Ok;
+        serialize_i16(v: i16) -> S::Ok;
+        serialize_i32(v: i32) -> S::Ok;
+        serialize_i64(v: i64) -> S::Ok;
+        serialize_i128(v: i128) -> S::Ok;
+        serialize_u8(v: u8) -> S::Ok;
+        serialize_u16(v: u16) -> S::Ok;
+        serialize_u32(v: u32) -> S::Ok;
+        serialize_u64(v: u64) -> S::Ok;
+        serialize_u128(v: u128) -> S::Ok;
+        serialize_f32(v: f32) -> S::Ok;
+        serialize_f64(v: f64) -> S::Ok;
+        serialize_char(v: char) -> S::Ok;
+        serialize_str(v: &str) -> S::Ok;
+        serialize_bytes(v: &[u8]) -> S::Ok;
+        serialize_none() -> S::Ok;
+        serialize_unit() -> S::Ok;
+        serialize_unit_struct(name: &'static str) -> S::Ok;
+        serialize_unit_variant(name: &'static str, index: u32, variant: &'static str) -> S::Ok;
+        serialize_seq(len: Option<usize>) -> S::SerializeSeq;
+        serialize_tuple(len: usize) -> S::SerializeTuple;
+        serialize_tuple_struct(name: &'static str, len: usize) -> S::SerializeTupleStruct;
+        serialize_tuple_variant(name: &'static str, index: u32, variant: &'static str, len: usize) -> S::SerializeTupleVariant;
+        serialize_map(len: Option<usize>) -> S::SerializeMap;
+    }
+
+    // `Some` is left out of the text the reference is read from.
+    fn serialize_some<T>(self, value: &T) -> std::result::Result<S::Ok, S::Error> where T: ?Sized + Serialize {
+        self.inner.serialize_some(&Against { value, reference: self.reference })
+    }
+
+    fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> std::result::Result<S::Ok, S::Error>
+    where T: ?Sized + Serialize {
+        self.inner.serialize_newtype_struct(name, value)
+    }
+
+    fn serialize_newtype_variant<T>(self, name: &'static str, index: u32, variant: &'static str, value: &T) -> std::result::Result<S::Ok, S::Error>
+    where T: ?Sized + Serialize {
+        self.inner.serialize_newtype_variant(name, index, variant, value)
+    }
+
+    fn serialize_struct(self, name: &'static str, len: usize) -> std::result::Result<Self::SerializeStruct, S::Error> {
+        let fields = match *self.reference {
+            Value::Struct { ref fields, .. } | Value::Map(ref fields) => Some(fields),
+            _ => None,
+        };
+
+        Ok(DiffStruct { inner: self.inner.serialize_struct(name, len)?, fields })
+    }
+
+    // Only compared with the same variant.
+    fn serialize_struct_variant(self, name: &'static str, index: u32, variant: &'static str, len: usize)
+    -> std::result::Result<Self::SerializeStructVariant, S::Error> {
+        let fields = match *self.reference {
+            Value::Struct { name: Some(ref name), ref fields } if name == variant => Some(fields),
+            _ => None,
+        };
+
+        Ok(DiffStruct { inner: self.inner.serialize_struct_variant(name, index, variant, len)?, fields })
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.inner.is_human_readable()
+    }
+}
+
+struct DiffStruct<'r, C> {
+    inner: C,
+    fields: Option<&'r Map>,
+}
+
+// What the field `key` is compared with, unless it is the same and left out.
+fn reference<'r, T, E>(fields: Option<&'r Map>, key: &'static str, value: &T) -> std::result::Result<Option<Option<&'r Value>>, E>
+where T: ?Sized + Serialize, E: ser::Error {
+    let Some(reference) = fields.and_then(|fields| fields.get(&Value::from(key))) else {
+        return Ok(Some(None));
+    };
+
+    match buffered(value).map_err(E::custom)? == *reference {
+        true => Ok(None),
+        false => Ok(Some(Some(reference))),
+    }
+}
+
+macro_rules! diff_struct {
+    ($trait:ident) => {
+        impl<C: ser::$trait> ser::$trait for DiffStruct<'_, C> {
+            type Ok = C::Ok;
+            type Error = C::Error;
+
+            fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> std::result::Result<(), C::Error>
+            where T: ?Sized + Serialize {
+                match reference(self.fields, key, value)? {
+                    None => self.inner.skip_field(key),
+                    Some(Some(reference)) => self.inner.serialize_field(key, &Against { value, reference }),
+                    Some(None) => self.inner.serialize_field(key, value),
+                }
+            }
+
+            fn end(self) -> std::result::Result<C::Ok, C::Error> {
+                self.inner.end()
+            }
+        }
+    };
+}
+
+diff_struct!(SerializeStruct);
+diff_struct!(SerializeStructVariant);
@@ -0,0 +1,249 @@
+// Writes a document from calls rather than from a value in memory, for
+// documents too big to build first. The output is what `Serializer` writes
+// for the same data with the same config:
+//
+//     let mut emitter = Emitter::new(writer, Some(PrettyConfig::new()))?;
+//     emitter.begin_struct("Scene")?;
+//     emitter.field("nodes")?;
+//     emitter.begin_seq()?;
+//     for node in nodes {
+//         emitter.value(&node)?;
+//     }
+//     emitter.end()?;
+//     emitter.end()?;
+//     let writer = emitter.finish()?;
+
+use std::io::{ self, Write };
+
+use serde::Serialize;
+
+use super::{ PrettyConfig, Serializer };
+use crate::error::{ Error, Result };
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Struct,
+    Map,
+    Seq,
+    Tuple,
+}
+
+struct Frame {
+    kind: Kind,
+    closer: &'static [u8],
+    empty: bool,
+    // Whether the items are indented on lines of their own, if the depth
+    // limit allows.
+    indents: bool,
+}
+
+pub struct Emitter<W: io::Write> {
+    ser: Serializer<W>,
+    frames: Vec<Frame>,
+    // A field or key was written, its value is next.
+    after_key: bool,
+    done: bool,
+}
+
+impl<W: io::Write> Emitter<W> {
+    pub fn new(writer: W, config: Option<PrettyConfig>) -> Result<Self> {
+        Ok(Emitter { ser: Serializer::new(writer, config)?, frames: Vec::new(), after_key: false, done: false })
+    }
+
+    // `Name{ .. }`, the name is written as `PrettyConfig::struct_names` says.
+    pub fn begin_struct(&mut self, name: &str) -> Result<()> {
+        self.before_value()?;
+        if self.ser.struct_names(name) {
+            self.ser.write_identifier(name)?;
+        }
+
+        let (open, close) = self.ser.struct_brackets();
+        self.open(Kind::Struct, open, close)
+    }
+
+    // `Variant{ .. }`
+    pub fn begin_struct_variant(&mut self, variant: &str) -> Result<()> {
+        self.before_value()?;
+        self.ser.write_identifier(variant)?;
+
+        let (open, close) = self.ser.struct_brackets();
+        self.open(Kind::Struct, open, close)
+    }
+
+    pub fn begin_map(&mut self) -> Result<()> {
+        self.before_value()?;
+        self.open(Kind::Map, b"{", b"}")
+    }
+
+    pub fn begin_seq(&mut self) -> Result<()> {
+        self.before_value()?;
+        self.open(Kind::Seq, b"[", b"]")
+    }
+
+    pub fn begin_tuple(&mut self) -> Result<()> {
+        self.before_value()?;
+        self.open(Kind::Tuple, b"(", b")")
+    }
+
+    // `Variant(..)`
+    pub fn begin_tuple_variant(&mut self, variant: &str) -> Result<()> {
+        self.before_value()?;
+        self.ser.write_identifier(variant)?;
+        self.open(Kind::Tuple, b"(", b")")
+    }
+
+    // The name of the next field of the innermost struct.
+    pub fn field(&mut self, name: &str) -> Result<()> {
+        self.expect_key(Kind::Struct, "a field outside of a struct")?;
+        self.item()?;
+
+        if self.ser.quote_fields() {
+            self.ser.serialize_escaped_str(name)?;
+        } else {
+            self.ser.write_identifier(name)?;
+        }
+        self.ser.write_colon()?;
+        self.after_key = true;
+
+        Ok(())
+    }
+
+    // The key of the next entry of the innermost map.
+    pub fn key<T>(&mut self, key: &T) -> Result<()> where T: ?Sized + Serialize {
+        self.expect_key(Kind::Map, "a key outside of a map")?;
+        self.item()?;
+
+        let cavetta = self.ser.cavetta_keys();
+        if cavetta {
+            self.ser.open_cavetta()?;
+        }
+        if self.ser.quote_keys() {
+            self.ser.serialize_quoted_key(key)?;
+        } else {
+            key.serialize(&mut self.ser)?;
+        }
+        if cavetta {
+            self.ser.close_cavetta()?;
+        } else {
+            self.ser.write_colon()?;
+        }
+        self.after_key = true;
+
+        Ok(())
+    }
+
+    // A whole value at once, such as a number or an element small enough to
+    // build in memory.
+    pub fn value<T>(&mut self, value: &T) -> Result<()> where T: ?Sized + Serialize {
+        self.before_value()?;
+        self.ser.serialize_item(value)?;
+        self.done = self.frames.is_empty();
+
+        Ok(())
+    }
+
+    // Closes the innermost struct, map, sequence or tuple.
+    pub fn end(&mut self) -> Result<()> {
+        if self.after_key {
+            return Err(Error::UnexpectedEvent("the end of a collection after a key"));
+        }
+        let frame = self.frames.pop().ok_or(Error::UnexpectedEvent("the end of a collection that was not begun"))?;
+
+        let multiline = self.multiline(&frame);
+        if let Some((ref config, ref mut pretty)) = self.ser.pretty {
+            if multiline && !frame.empty {
+                self.ser.output.write_all(b",")?;
+                self.ser.output.write_all(config.new_line.as_bytes())?;
+                for level in 1..pretty.indent {
+                    self.ser.output.write_all(config.indentor_at(level)?.as_bytes())?;
+                }
+            }
+            if frame.indents {
+                pretty.indent -= 1;
+            }
+        }
+        self.ser.output.write_all(frame.closer)?;
+        self.ser.leave();
+        self.done = self.frames.is_empty();
+
+        Ok(())
+    }
+
+    // The writer, once the document is complete.
+    pub fn finish(self) -> Result<W> {
+        if !self.done {
+            return Err(Error::UnexpectedEvent("the end of an unfinished document"));
+        }
+
+        Ok(self.ser.output.writer)
+    }
+
+    fn open(&mut self, kind: Kind, open: &[u8], closer: &'static [u8]) -> Result<()> {
+        self.ser.enter()?;
+        self.ser.output.write_all(open)?;
+
+        let indents = match kind {
+            Kind::Struct => !self.ser.compact_structs(),
+            Kind::Map => !self.ser.compact_maps(),
+            Kind::Seq => !self.ser.compact_arrays(),
+            Kind::Tuple => self.ser.separate_tuple_members(),
+        };
+        if let (true, Some((_, ref mut pretty))) = (indents, &mut self.ser.pretty) {
+            pretty.indent += 1;
+        }
+        self.frames.push(Frame { kind, closer, empty: true, indents });
+
+        Ok(())
+    }
+
+    fn before_value(&mut self) -> Result<()> {
+        match self.frames.last() {
+            None if self.done => Err(Error::UnexpectedEvent("a second value after the document")),
+            None => Ok(()),
+            Some(frame) if matches!(frame.kind, Kind::Struct | Kind::Map) => {
+                if !std::mem::take(&mut self.after_key) {
+                    return Err(Error::UnexpectedEvent("a value without a field or key"));
+                }
+                Ok(())
+            }
+            Some(_) => self.item(),
+        }
+    }
+
+    fn expect_key(&self, kind: Kind, message: &'static str) -> Result<()> {
+        match self.frames.last() {
+            Some(frame) if frame.kind == kind && !self.after_key => Ok(()),
+            _ => Err(Error::UnexpectedEvent(message)),
+        }
+    }
+
+    // The separator and indentation in front of an element or entry.
+    fn item(&mut self) -> Result<()> {
+        let Some(frame) = self.frames.last() else {
+            return Ok(());
+        };
+
+        let multiline = self.multiline(frame);
+        let first = self.frames.last_mut().is_some_and(|frame| std::mem::replace(&mut frame.empty, false));
+
+        if !first {
+            self.ser.output.write_all(b",")?;
+        }
+        if let Some((ref config, _)) = self.ser.pretty {
+            if multiline {
+                self.ser.output.write_all(config.new_line.as_bytes())?;
+            } else if !first {
+                self.ser.output.write_all(config.after_comma().as_bytes())?;
+            }
+        }
+        if multiline {
+            self.ser.indent()?;
+        }
+
+        Ok(())
+    }
+
+    fn multiline(&self, frame: &Frame) -> bool {
+        self.ser.pretty.as_ref().is_some_and(|(config, pretty)| frame.indents && pretty.indent <= config.depth_limit)
+    }
+}
@@ -0,0 +1,65 @@
+// Lets an application change how numbers, strings and bytes are written
+// without forking the serializer, such as colours in hex or fewer digits for
+// floats:
+//
+//     struct Colours;
+//
+//     impl ValueFormatter for Colours {
+//         fn write_uint(&mut self, writer: &mut dyn io::Write, value: u128, literal: &str) -> io::Result<()> {
+//             match value > 0xffff {
+//                 true => write!(writer, "0x{:06x}", value),
+//                 false => writer.write_all(literal.as_bytes()),
+//             }
+//         }
+//     }
+//
+//     let mut serializer = Serializer::new(writer, None)?.formatter(Colours);
+//
+// Each method is given the value and `literal`, what the serializer writes
+// for it with its config. The defaults write `literal`, the output is then
+// what it is without a formatter. Whatever is written has to be read back as
+// the same type.
+
+use std::io;
+
+pub trait ValueFormatter {
+    // Integers of every width.
+    fn write_int(&mut self, writer: &mut dyn io::Write, value: i128, literal: &str) -> io::Result<()> {
+        let _ = value;
+        writer.write_all(literal.as_bytes())
+    }
+
+    fn write_uint(&mut self, writer: &mut dyn io::Write, value: u128, literal: &str) -> io::Result<()> {
+        let _ = value;
+        writer.write_all(literal.as_bytes())
+    }
+
+    fn write_f32(&mut self, writer: &mut dyn io::Write, value: f32, literal: &str) -> io::Result<()> {
+        let _ = value;
+        writer.write_all(literal.as_bytes())
+    }
+
+    fn write_f64(&mut self, writer: &mut dyn io::Write, value: f64, literal: &str) -> io::Result<()> {
+        let _ = value;
+        writer.write_all(literal.as_bytes())
+    }
+
+    // `literal` is quoted and escaped, or bare, see `PrettyConfig::quote_strings`.
+    fn write_str(&mut self, writer: &mut dyn io::Write, value: &str, literal: &str) -> io::Result<()> {
+        let _ = value;
+        writer.write_all(literal.as_bytes())
+    }
+
+    // Not called for `BytesFormat::IntArray`, whose elements are integers, nor
+    // for spilled bytes.
+    fn write_bytes(&mut self, writer: &mut dyn io::Write, value: &[u8], literal: &str) -> io::Result<()> {
+        let _ = value;
+        writer.write_all(literal.as_bytes())
+    }
+}
+
+// Writes every value as the serializer would without a formatter.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DefaultFormatter;
+
+impl ValueFormatter for DefaultFormatter {}
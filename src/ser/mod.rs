@@ -1,13 +1,36 @@
 use serde::{ ser, Deserialize, Serialize };
-use std::io;
+use std::{ borrow::Cow, fmt, fs, io::{ self, Write }, path::Path, sync::Arc };
 
 use crate::{
     error::{ Error, Result },
-    parse::{ is_ident_first_char, is_ident_other_char, LargeSInt, LargeUInt },
+    options::Compat,
+    parse::{ is_keyword, is_whitespace_char, LargeSInt, LargeUInt },
+    syntax::{ escape_char, escape_string, escape_string_ascii, is_bare_word, is_identifier, is_safe_token },
+    value::{ FIELD_TOKEN, RAW_VALUE_TOKEN, STRUCT_TOKEN, VARIANT_TOKEN },
 };
 
 mod value;
 
+mod config;
+
+mod emitter;
+pub use emitter::Emitter;
+
+mod formatter;
+pub use formatter::{ DefaultFormatter, ValueFormatter };
+
+mod diff;
+pub use diff::SerializeDiff;
+
+mod probe;
+
+mod shared;
+pub use shared::SerializeShared;
+use shared::SHARED_TOKEN;
+
+#[cfg(test)]
+mod tests;
+
 pub fn to_writer<W, T>(writer: W, value: &T) -> Result<()>
 where W: io::Write, T: ?Sized + ser::Serialize {
     let mut s = Serializer::with_options(writer, None)?;
@@ -16,8 +39,8 @@ where W: io::Write, T: ?Sized + ser::Serialize {
 
 pub fn to_writer_pretty<W, T>(writer: W, value: &T, config: PrettyConfig) -> Result<()>
 where W: io::Write, T: ?Sized + ser::Serialize {
-    let mut s = Serializer::with_options(writer, Some(config), )?;
-    value.serialize(&mut s)
+    let mut s = Serializer::new(writer, Some(config))?;
+    s.serialize_item(value)
 }
 
 pub fn to_string<T>(value: &T) -> Result<String>
@@ -25,15 +48,129 @@ where T: ?Sized + ser::Serialize {
     let mut output = Vec::new();
     let mut s = Serializer::with_options(&mut output, None, )?;
     value.serialize(&mut s)?;
-    Ok(String::from_utf8(output).expect("Ron should be utf-8"))
+    Ok(String::from_utf8(output)?)
 }
 
 pub fn to_string_pretty<T>(value: &T, config: PrettyConfig) -> Result<String>
 where T: ?Sized + ser::Serialize {
     let mut output = Vec::new();
-    let mut s = Serializer::with_options(&mut output, Some(config), )?;
-    value.serialize(&mut s)?;
-    Ok(String::from_utf8(output).expect("Ron should be utf-8"))
+    let mut s = Serializer::new(&mut output, Some(config))?;
+    s.serialize_item(value)?;
+    Ok(String::from_utf8(output)?)
+}
+
+pub fn to_path<P, T>(path: P, value: &T) -> Result<()>
+where P: AsRef<Path>, T: ?Sized + ser::Serialize {
+    write_path(path.as_ref(), |writer| to_writer(writer, value))
+}
+
+pub fn to_path_pretty<P, T>(path: P, value: &T, config: PrettyConfig) -> Result<()>
+where P: AsRef<Path>, T: ?Sized + ser::Serialize {
+    write_path(path.as_ref(), |writer| to_writer_pretty(writer, value, config))
+}
+
+// Creates or truncates the file at `path` and writes it through a buffer,
+// errors name the file, see `Error::InFile`.
+fn write_path<F>(path: &Path, write: F) -> Result<()>
+where F: FnOnce(&mut io::BufWriter<fs::File>) -> Result<()> {
+    let result = fs::File::create(path).map_err(Error::from).and_then(|file| {
+        let mut writer = io::BufWriter::new(file);
+        write(&mut writer)?;
+        writer.flush().map_err(Error::from)
+    });
+
+    result.map_err(|e| Error::InFile { path: path.display().to_string(), error: Box::new(e) })
+}
+
+// How `serialize_bytes` writes its input.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum BytesFormat {
+    // `"aGVsbG8="`
+    #[default]
+    Base64,
+    // `x"68656c6c6f"`
+    Hex,
+    // `[104, 101, 108, 108, 111]`
+    IntArray,
+    // `b"hello"`
+    ByteString,
+}
+
+// When `serialize_str` writes its input without quotes. Bare strings are
+// read back by typed string fields, while an untyped `Value` reads a bare
+// identifier as a unit variant.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum QuoteStrings {
+    // `"main"`
+    #[default]
+    Always,
+    // `main` and `ctrl-q`, but `"two words"`, see `syntax::is_safe_token`
+    WhenRequired,
+    // Unless the string could not be read back, see `syntax::is_bare_word`
+    Never,
+}
+
+// Which characters of strings and chars are written as escapes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum Escape {
+    // Only quotes, backslashes and control characters, `"日本語\n"`
+    #[default]
+    Minimal,
+    // Also every non-ASCII character, `"\u{65e5}\u{672c}\u{8a9e}\n"`
+    Ascii,
+}
+
+// How floats are laid out, see `PrettyConfig::float_precision` for the
+// number of digits.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum FloatStyle {
+    // `0.25`, but `1e-9` and `1.5e20` for very small and very large magnitudes
+    #[default]
+    Auto,
+    // `0.25` and `0.000000001`
+    Fixed,
+    // `2.5e-1` and `1e-9`
+    Scientific,
+}
+
+// Whitespace written on either side of a delimiter.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Spacing {
+    pub before: String,
+    pub after: String,
+}
+
+impl Spacing {
+    pub fn new<B: Into<String>, A: Into<String>>(before: B, after: A) -> Self {
+        Spacing { before: before.into(), after: after.into() }
+    }
+}
+
+// Gives the indentation of a level, counted from 1, see
+// `PrettyConfig::indent_with`.
+#[derive(Clone)]
+pub struct IndentWith(Arc<dyn Fn(usize) -> String + Send + Sync>);
+
+impl fmt::Debug for IndentWith {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("IndentWith(..)")
+    }
+}
+
+// Decides by its name whether a struct's name is written, see
+// `PrettyConfig::struct_names_filter`.
+#[derive(Clone)]
+pub struct StructNamesFilter(Arc<dyn Fn(&str) -> bool + Send + Sync>);
+
+impl fmt::Debug for StructNamesFilter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("StructNamesFilter(..)")
+    }
 }
 
 struct Pretty {
@@ -46,15 +183,87 @@ struct Pretty {
 #[non_exhaustive]
 pub struct PrettyConfig {
     pub depth_limit: usize,
+    // Whether collections nested deeper than `depth_limit` are an error
+    // rather than written on one line, to catch absurdly deep data
+    pub depth_limit_error: bool,
     pub new_line: String,
     pub indentor: String,
+    // The indentation of the first levels, such as two spaces for the first
+    // and `indentor` after
+    pub level_indentors: Vec<String>,
+    // Replaces `indentor` and `level_indentors` when set
+    #[serde(skip)]
+    pub indent_with: Option<IndentWith>,
+    // The space after both `:` and `,`, for `space_after_colon` and
+    // `space_after_comma` left unset
+    #[deprecated(note = "use `space_after_colon` and `space_after_comma`")]
     pub separator: String,
+    pub space_before_colon: String,
+    pub space_after_colon: Option<String>,
+    // Between members on one line
+    pub space_after_comma: Option<String>,
     // Whether to emit struct names
     pub struct_names: bool,
+    // Replaces `struct_names` when set, the names it accepts are written
+    #[serde(skip)]
+    pub struct_names_filter: Option<StructNamesFilter>,
+    // Whether unit variants and named unit structs are followed by `()`
+    pub unit_parens: bool,
+    // Whether option contents are written as `Some(x)` rather than bare, as
+    // RON tooling expects
+    pub explicit_some: bool,
+    // Whether newtype structs are written as their content, `5` rather than
+    // `Meters(5)`, under the `unwrap_newtypes` extension
+    pub unwrap_newtypes: bool,
+    // Whether the struct of a newtype variant is written as if the variant
+    // were a struct variant, `Circle{radius: 1.0}`, under the
+    // `unwrap_variant_newtypes` extension
+    pub unwrap_variant_newtypes: bool,
     pub separate_tuple_members: bool,
     pub enumerate_arrays: bool,
+    // The comment of `enumerate_arrays`, `{index}` is replaced by the index
+    pub enumerate_format: String,
+    // Whether the comment follows the comma after the element rather than
+    // preceding it, line comments such as `# {index}` can only follow
+    pub enumerate_trailing: bool,
     pub decimal_floats: bool,
+    // Digits after the decimal point, of the mantissa in scientific notation.
+    // `FloatStyle::Auto` drops trailing zeros. When unset, floats are written
+    // with as few digits as read back to the same value.
+    pub float_precision: Option<usize>,
+    pub float_style: FloatStyle,
     pub compact_arrays: bool,
+    // Whether structs and struct variants are written on one line
+    pub compact_structs: bool,
+    pub compact_maps: bool,
+    // Collections written in fewer columns than this are put on one line,
+    // `0` turns this off
+    pub compact_below_length: usize,
+    // Collections are put on one line when they fit before this column, and
+    // broken into lines otherwise
+    pub max_width: Option<usize>,
+    // Whether to leave out struct fields holding `None`, which serde reads
+    // back in unless the field has a `default` of its own. Other values are
+    // written, see `SerializeDiff` to leave out those of a reference
+    pub skip_defaults: bool,
+    // Whether a value of `SerializeShared` reached again from inside itself
+    // is an error
+    pub detect_cycles: bool,
+    // Whether to emit every map key as a quoted string
+    pub quote_keys: bool,
+    // Whether to emit struct field names as quoted strings
+    pub quote_fields: bool,
+    pub bytes_format: BytesFormat,
+    pub quote_strings: QuoteStrings,
+    pub escape: Escape,
+    // Whether to write map entries as `<key> value`
+    pub cavetta_keys: bool,
+    // `before` pads the key inside the brackets, `after` follows the `>`
+    pub cavetta_spacing: Spacing,
+    // Whether to write documents JSON can read: quoted keys, arrays for
+    // tuples, `null` for `None` and `()`, `{"Name": ..}` for variants and no
+    // trailing commas
+    pub json_compatible: bool,
 }
 
 impl PrettyConfig {
@@ -62,12 +271,34 @@ impl PrettyConfig {
         Default::default()
     }
 
+    // Everything on one line, with a space after each `:` and `,`.
+    pub fn compact() -> Self {
+        PrettyConfig::new().depth_limit(0)
+    }
+
+    // Every collection broken into lines, tuples too.
+    pub fn expanded() -> Self {
+        PrettyConfig::new().separate_tuple_members(true)
+    }
+
+    // The style of RON documents, with named structs and floats that read as
+    // floats. Their syntax is `Compat::Ron`, see `Serializer::compat`.
+    pub fn ron_like() -> Self {
+        PrettyConfig::new().struct_names(true).decimal_floats(true)
+    }
+
     pub fn depth_limit(mut self, depth_limit: usize) -> Self {
         self.depth_limit = depth_limit;
 
         self
     }
 
+    pub fn depth_limit_error(mut self, depth_limit_error: bool) -> Self {
+        self.depth_limit_error = depth_limit_error;
+
+        self
+    }
+
     pub fn new_line(mut self, new_line: String) -> Self {
         self.new_line = new_line;
 
@@ -80,18 +311,84 @@ impl PrettyConfig {
         self
     }
 
+    pub fn level_indentors(mut self, level_indentors: Vec<String>) -> Self {
+        self.level_indentors = level_indentors;
+
+        self
+    }
+
+    pub fn indent_with<F>(mut self, indent_with: F) -> Self
+    where F: Fn(usize) -> String + Send + Sync + 'static {
+        self.indent_with = Some(IndentWith(Arc::new(indent_with)));
+
+        self
+    }
+
+    #[allow(deprecated)]
+    #[deprecated(note = "use `space_after_colon` and `space_after_comma`")]
     pub fn separator(mut self, separator: String) -> Self {
         self.separator = separator;
 
         self
     }
 
+    pub fn space_before_colon(mut self, space_before_colon: String) -> Self {
+        self.space_before_colon = space_before_colon;
+
+        self
+    }
+
+    pub fn space_after_colon(mut self, space_after_colon: String) -> Self {
+        self.space_after_colon = Some(space_after_colon);
+
+        self
+    }
+
+    pub fn space_after_comma(mut self, space_after_comma: String) -> Self {
+        self.space_after_comma = Some(space_after_comma);
+
+        self
+    }
+
     pub fn struct_names(mut self, struct_names: bool) -> Self {
         self.struct_names = struct_names;
 
         self
     }
 
+    // Writes the names of only some structs, such as `Scene` and `Prefab`,
+    // leaving the small ones inside them anonymous.
+    pub fn struct_names_filter<F>(mut self, struct_names_filter: F) -> Self
+    where F: Fn(&str) -> bool + Send + Sync + 'static {
+        self.struct_names_filter = Some(StructNamesFilter(Arc::new(struct_names_filter)));
+
+        self
+    }
+
+    pub fn unit_parens(mut self, unit_parens: bool) -> Self {
+        self.unit_parens = unit_parens;
+
+        self
+    }
+
+    pub fn explicit_some(mut self, explicit_some: bool) -> Self {
+        self.explicit_some = explicit_some;
+
+        self
+    }
+
+    pub fn unwrap_newtypes(mut self, unwrap_newtypes: bool) -> Self {
+        self.unwrap_newtypes = unwrap_newtypes;
+
+        self
+    }
+
+    pub fn unwrap_variant_newtypes(mut self, unwrap_variant_newtypes: bool) -> Self {
+        self.unwrap_variant_newtypes = unwrap_variant_newtypes;
+
+        self
+    }
+
     pub fn separate_tuple_members(mut self, separate_tuple_members: bool) -> Self {
         self.separate_tuple_members = separate_tuple_members;
 
@@ -104,78 +401,622 @@ impl PrettyConfig {
         self
     }
 
+    pub fn enumerate_format(mut self, enumerate_format: String) -> Self {
+        self.enumerate_format = enumerate_format;
+
+        self
+    }
+
+    pub fn enumerate_trailing(mut self, enumerate_trailing: bool) -> Self {
+        self.enumerate_trailing = enumerate_trailing;
+
+        self
+    }
+
     pub fn decimal_floats(mut self, decimal_floats: bool) -> Self {
         self.decimal_floats = decimal_floats;
 
         self
     }
 
+    pub fn float_precision(mut self, float_precision: usize) -> Self {
+        self.float_precision = Some(float_precision);
+
+        self
+    }
+
+    pub fn float_style(mut self, float_style: FloatStyle) -> Self {
+        self.float_style = float_style;
+
+        self
+    }
+
     pub fn compact_arrays(mut self, compact_arrays: bool) -> Self {
         self.compact_arrays = compact_arrays;
 
         self
     }
+
+    pub fn compact_structs(mut self, compact_structs: bool) -> Self {
+        self.compact_structs = compact_structs;
+
+        self
+    }
+
+    pub fn compact_maps(mut self, compact_maps: bool) -> Self {
+        self.compact_maps = compact_maps;
+
+        self
+    }
+
+    pub fn compact_below_length(mut self, compact_below_length: usize) -> Self {
+        self.compact_below_length = compact_below_length;
+
+        self
+    }
+
+    pub fn max_width(mut self, max_width: usize) -> Self {
+        self.max_width = Some(max_width);
+
+        self
+    }
+
+    pub fn skip_defaults(mut self, skip_defaults: bool) -> Self {
+        self.skip_defaults = skip_defaults;
+
+        self
+    }
+
+    pub fn detect_cycles(mut self, detect_cycles: bool) -> Self {
+        self.detect_cycles = detect_cycles;
+
+        self
+    }
+
+    pub fn quote_keys(mut self, quote_keys: bool) -> Self {
+        self.quote_keys = quote_keys;
+
+        self
+    }
+
+    pub fn quote_fields(mut self, quote_fields: bool) -> Self {
+        self.quote_fields = quote_fields;
+
+        self
+    }
+
+    pub fn bytes_format(mut self, bytes_format: BytesFormat) -> Self {
+        self.bytes_format = bytes_format;
+
+        self
+    }
+
+    pub fn quote_strings(mut self, quote_strings: QuoteStrings) -> Self {
+        self.quote_strings = quote_strings;
+
+        self
+    }
+
+    pub fn escape(mut self, escape: Escape) -> Self {
+        self.escape = escape;
+
+        self
+    }
+
+    // Shorthand for `escape`: on, the default, keeps UTF-8 text as is and
+    // escapes only quotes, backslashes and control characters, off writes
+    // `Escape::Ascii`.
+    pub fn escape_strings(self, escape_strings: bool) -> Self {
+        self.escape(if escape_strings { Escape::Minimal } else { Escape::Ascii })
+    }
+
+    pub fn cavetta_keys(mut self, cavetta_keys: bool) -> Self {
+        self.cavetta_keys = cavetta_keys;
+
+        self
+    }
+
+    pub fn json_compatible(mut self, json_compatible: bool) -> Self {
+        self.json_compatible = json_compatible;
+
+        self
+    }
+
+    pub fn cavetta_spacing(mut self, cavetta_spacing: Spacing) -> Self {
+        self.cavetta_spacing = cavetta_spacing;
+
+        self
+    }
+
+    // The indentation of `level`, counted from 1.
+    fn indentor_at(&self, level: usize) -> Result<Cow<'_, str>> {
+        match self.indent_with {
+            Some(IndentWith(ref indent_with)) => {
+                let indentor = indent_with(level);
+                whitespace("the indentation from `indent_with`", &indentor)?;
+
+                Ok(Cow::Owned(indentor))
+            }
+            None => Ok(Cow::Borrowed(self.level_indentors.get(level - 1).unwrap_or(&self.indentor))),
+        }
+    }
+
+    // Fails for options that don't go together, and for anything but
+    // whitespace in the indentation, line breaks or spacing, which would not
+    // read back. Serializers check their config when they are made.
+    pub fn validate(&self) -> Result<()> {
+        whitespace("`new_line`", &self.new_line)?;
+        whitespace("`indentor`", &self.indentor)?;
+        for indentor in &self.level_indentors {
+            whitespace("`level_indentors`", indentor)?;
+        }
+        whitespace("the space before `:`", &self.space_before_colon)?;
+        whitespace("the space after `:`", self.after_colon())?;
+        whitespace("the space after `,`", self.after_comma())?;
+
+        if self.enumerate_arrays && self.compact_arrays {
+            return Err(Error::InvalidConfig(String::from("`enumerate_arrays` needs the lines `compact_arrays` leaves out")));
+        }
+
+        Ok(())
+    }
+
+    #[allow(deprecated)]
+    fn after_colon(&self) -> &str {
+        self.space_after_colon.as_deref().unwrap_or(&self.separator)
+    }
+
+    #[allow(deprecated)]
+    fn after_comma(&self) -> &str {
+        self.space_after_comma.as_deref().unwrap_or(&self.separator)
+    }
+}
+
+fn whitespace(name: &str, s: &str) -> Result<()> {
+    match s.bytes().all(is_whitespace_char) {
+        true => Ok(()),
+        false => Err(Error::InvalidConfig(format!("{} must be whitespace, not {:?}", name, s))),
+    }
 }
 
 impl Default for PrettyConfig {
+    #[allow(deprecated)]
     fn default() -> Self {
         PrettyConfig {
             depth_limit: !0,
+            depth_limit_error: false,
             new_line: String::from("\n"),
             indentor: String::from("    "),
+            level_indentors: Vec::new(),
+            indent_with: None,
             separator: String::from(" "),
+            space_before_colon: String::new(),
+            space_after_colon: None,
+            space_after_comma: None,
             struct_names: false,
+            struct_names_filter: None,
+            unit_parens: false,
+            explicit_some: false,
+            unwrap_newtypes: false,
+            unwrap_variant_newtypes: false,
             separate_tuple_members: false,
             enumerate_arrays: false,
+            enumerate_format: String::from("/*[{index}]*/ "),
+            enumerate_trailing: false,
             decimal_floats: false,
+            float_precision: None,
+            float_style: FloatStyle::Auto,
             compact_arrays: false,
+            compact_structs: false,
+            compact_maps: false,
+            compact_below_length: 0,
+            max_width: None,
+            skip_defaults: false,
+            detect_cycles: false,
+            quote_keys: false,
+            quote_fields: false,
+            bytes_format: BytesFormat::Base64,
+            quote_strings: QuoteStrings::Always,
+            escape: Escape::Minimal,
+            cavetta_keys: false,
+            cavetta_spacing: Spacing::new("", " "),
+            json_compatible: false,
+        }
+    }
+}
+
+// Settings of the serializer that apply with and without a `PrettyConfig`,
+// such as struct names for self-describing files written compactly:
+//
+//     SerializeOptions::new().struct_names(true).to_string(&scene)?
+#[derive(Clone, Debug, Default)]
+pub struct SerializeOptions {
+    // Whether to emit struct names, as `PrettyConfig::struct_names`
+    pub struct_names: bool,
+    pub compat: Compat,
+}
+
+impl SerializeOptions {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn struct_names(mut self, struct_names: bool) -> Self {
+        self.struct_names = struct_names;
+
+        self
+    }
+
+    pub fn compat(mut self, compat: Compat) -> Self {
+        self.compat = compat;
+
+        self
+    }
+
+    pub fn to_writer<W, T>(&self, writer: W, value: &T) -> Result<()>
+    where W: io::Write, T: ?Sized + ser::Serialize {
+        let mut s = Serializer::with_options(writer, None)?.options(self.clone());
+        value.serialize(&mut s)
+    }
+
+    pub fn to_writer_pretty<W, T>(&self, writer: W, value: &T, config: PrettyConfig) -> Result<()>
+    where W: io::Write, T: ?Sized + ser::Serialize {
+        let mut s = Serializer::new(writer, Some(config))?.options(self.clone());
+        s.serialize_item(value)
+    }
+
+    pub fn to_string<T>(&self, value: &T) -> Result<String>
+    where T: ?Sized + ser::Serialize {
+        let mut output = Vec::new();
+        self.to_writer(&mut output, value)?;
+        Ok(String::from_utf8(output)?)
+    }
+
+    pub fn to_string_pretty<T>(&self, value: &T, config: PrettyConfig) -> Result<String>
+    where T: ?Sized + ser::Serialize {
+        let mut output = Vec::new();
+        self.to_writer_pretty(&mut output, value, config)?;
+        Ok(String::from_utf8(output)?)
+    }
+}
+
+pub struct Serializer<W: io::Write> {
+    output: Output<W>,
+    pretty: Option<(PrettyConfig, Pretty)>,
+    is_empty: Option<bool>,
+    newtype_variant: bool,
+    named: bool,
+    spill: Option<(usize, Box<Spill>)>,
+    compat: Compat,
+    struct_names: bool,
+    // A `Value` struct in RON mode, its fields go between parentheses.
+    struct_map: bool,
+    // The `Value` being written by a named tuple is a variant.
+    named_variant: bool,
+    // The struct of an unwrapped newtype variant, written without its name.
+    unwrapped_variant: bool,
+    // The contents of a `Value` variant in JSON mode, a single one is
+    // written without brackets.
+    json_content: bool,
+    formatter: Box<dyn ValueFormatter>,
+    // The collections being written and how many may be, see
+    // `PrettyConfig::depth_limit_error`.
+    depth: usize,
+    max_depth: Option<usize>,
+    // The addresses of the `SerializeShared` values being written.
+    shared: Vec<usize>,
+}
+
+type Spill = dyn FnMut(&[u8]) -> io::Result<String>;
+
+impl<W: io::Write> Serializer<W> {
+    // Starts a document, with the extensions its config needs enabled.
+    // `with_options` leaves them out, for writing part of a document.
+    pub fn new(writer: W, config: Option<PrettyConfig>) -> Result<Self> {
+        let mut ser = Self::with_options(writer, config)?;
+        if let Some((ref config, _)) = ser.pretty {
+            let extensions: Vec<&str> = [
+                (config.unwrap_newtypes, "unwrap_newtypes"),
+                (config.unwrap_variant_newtypes, "unwrap_variant_newtypes"),
+            ]
+            .into_iter()
+            .filter_map(|(enabled, name)| enabled.then_some(name))
+            .collect();
+
+            if !extensions.is_empty() && !config.json_compatible {
+                write!(ser.output, "#![enable({})]", extensions.join(", "))?;
+                ser.output.write_all(config.new_line.as_bytes())?;
+            }
+        }
+
+        Ok(ser)
+    }
+
+    pub fn with_options(writer: W, config: Option<PrettyConfig>) -> Result<Self> {
+        if let Some(ref config) = config {
+            config.validate()?;
+        }
+
+        let max_depth = config.as_ref().filter(|config| config.depth_limit_error).map(|config| config.depth_limit);
+
+        Ok(Serializer {
+            output: Output { writer, column: 0 },
+            pretty: config.map(|conf| {(
+                conf,
+                Pretty {
+                    indent: 0,
+                    sequence_index: Vec::new(),
+                })
+            }),
+            is_empty: None,
+            newtype_variant: false,
+            named: false,
+            spill: None,
+            compat: Compat::Zmerald,
+            struct_names: false,
+            struct_map: false,
+            named_variant: false,
+            unwrapped_variant: false,
+            json_content: false,
+            formatter: Box::new(DefaultFormatter),
+            depth: 0,
+            max_depth,
+            shared: Vec::new(),
+        })
+    }
+
+    // Writes the syntax of `compat`. In `Compat::Ron`, structs go between
+    // parentheses and options are written as `Some(..)`; cavetta keys, hex
+    // strings and spilled bytes, which RON lacks, are not written.
+    pub fn compat(mut self, compat: Compat) -> Self {
+        self.compat = compat;
+
+        self
+    }
+
+    // Struct names are written with and without a `PrettyConfig` when
+    // `options` asks for them.
+    pub fn options(mut self, options: SerializeOptions) -> Self {
+        self.struct_names = options.struct_names;
+        self.compat = options.compat;
+
+        self
+    }
+
+    // Byte buffers of at least `min_len` bytes are handed to `spill`, which
+    // stores them and returns the path to write as `@file("path")`.
+    pub fn spill_bytes<F>(mut self, min_len: usize, spill: F) -> Self
+    where F: FnMut(&[u8]) -> io::Result<String> + 'static {
+        self.spill = Some((min_len, Box::new(spill)));
+
+        self
+    }
+
+    // Numbers, strings and bytes are written by `formatter`, see
+    // `ValueFormatter`.
+    pub fn formatter<F>(mut self, formatter: F) -> Self
+    where F: ValueFormatter + 'static {
+        self.formatter = Box::new(formatter);
+
+        self
+    }
+
+    fn separate_tuple_members(&self) -> bool {
+        self.pretty
+            .as_ref()
+            .is_some_and(|(config, _)| config.separate_tuple_members)
+    }
+
+    fn decimal_floats(&self) -> bool {
+        self.pretty
+            .as_ref()
+            .is_some_and(|(config, _)| config.decimal_floats)
+    }
+
+    // The text of a finite float, `.0` is added for `decimal_floats` when
+    // there is neither a fraction nor an exponent.
+    fn float_literal<F>(&self, v: F, magnitude: f64) -> String
+    where F: ryu::Float + std::fmt::Display + std::fmt::LowerExp + std::str::FromStr + Into<f64> {
+        let (precision, style) = self.pretty.as_ref().map_or((None, FloatStyle::Auto), |(config, _)| {
+            (config.float_precision, config.float_style)
+        });
+
+        let scientific = match style {
+            FloatStyle::Auto => magnitude != 0.0 && !(1e-5..1e16).contains(&magnitude),
+            FloatStyle::Fixed => false,
+            FloatStyle::Scientific => true,
+        };
+        let mut literal = format_float(v, style, scientific, precision);
+
+        // Rounding up near `MAX` would read back as infinity, keep the
+        // shortest text instead.
+        if precision.is_some() && literal.parse::<F>().is_ok_and(|f| f.into().is_infinite()) {
+            literal = format_float(v, style, scientific, None);
+        }
+        if self.decimal_floats() && !literal.contains(['.', 'e']) {
+            literal.push_str(".0");
+        }
+
+        literal
+    }
+
+    fn compact_arrays(&self) -> bool {
+        self.pretty
+            .as_ref()
+            .is_some_and(|(config, _)| config.compact_arrays)
+    }
+
+    fn compact_structs(&self) -> bool {
+        self.pretty
+            .as_ref()
+            .is_some_and(|(config, _)| config.compact_structs)
+    }
+
+    fn compact_maps(&self) -> bool {
+        self.pretty
+            .as_ref()
+            .is_some_and(|(config, _)| config.compact_maps)
+    }
+
+    // Serializes an element, field or entry value, or a whole document, on
+    // one line if it fits in `compact_below_length` or `max_width`.
+    fn serialize_item<T>(&mut self, value: &T) -> Result<()>
+    where T: ?Sized + Serialize {
+        let inline = match self.pretty {
+            Some((ref config, ref pretty)) if pretty.indent < config.depth_limit => {
+                let room = config.max_width.map_or(0, |width| width.saturating_sub(self.output.column));
+                let limit = config.compact_below_length.max(room);
+                limit > 0 && self.fits(value, config, limit)
+            }
+            _ => false,
+        };
+        if !inline {
+            return value.serialize(&mut *self);
+        }
+
+        // Nothing deeper than the current indentation is broken into lines.
+        let Some((config, pretty)) = self.pretty.as_mut() else {
+            return value.serialize(&mut *self);
+        };
+        let depth_limit = std::mem::replace(&mut config.depth_limit, pretty.indent);
+        let result = value.serialize(&mut *self);
+        if let Some((ref mut config, _)) = self.pretty {
+            config.depth_limit = depth_limit;
+        }
+
+        result
+    }
+
+    // Whether `value` on one line takes fewer than `limit` columns.
+    fn fits<T>(&self, value: &T, config: &PrettyConfig, limit: usize) -> bool
+    where T: ?Sized + Serialize {
+        let mut output = Limited { written: 0, limit };
+        let inline = config.clone().depth_limit(0).depth_limit_error(false).enumerate_arrays(false);
+        let options = SerializeOptions { struct_names: self.struct_names, compat: self.compat };
+        Serializer::with_options(&mut output, Some(inline)).is_ok_and(|ser| value.serialize(&mut ser.options(options)).is_ok())
+    }
+
+    // Whether `value` is left out by `skip_defaults`, which it is when it is
+    // `None`, what serde reads a missing `Option` field as.
+    fn skipped<T>(&self, value: &T) -> bool
+    where T: ?Sized + Serialize {
+        self.pretty.as_ref().is_some_and(|(config, _)| config.skip_defaults) && probe::is_none(value)
+    }
+
+    fn explicit_some(&self) -> bool {
+        self.pretty.as_ref().is_some_and(|(config, _)| config.explicit_some && !config.json_compatible)
+    }
+
+    fn write_unit_parens(&mut self) -> Result<()> {
+        if self.pretty.as_ref().is_some_and(|(config, _)| config.unit_parens) {
+            self.output.write_all(b"()")?;
         }
+        Ok(())
     }
-}
 
-pub struct Serializer<W: io::Write> {
-    output: W,
-    pretty: Option<(PrettyConfig, Pretty)>,
-    is_empty: Option<bool>,
-    newtype_variant: bool,
-}
+    fn detect_cycles(&self) -> bool {
+        self.pretty.as_ref().is_some_and(|(config, _)| config.detect_cycles)
+    }
 
-impl<W: io::Write> Serializer<W> {
-    pub fn new(writer: W, config: Option<PrettyConfig>) -> Result<Self> {
-        Self::with_options(writer, config)
+    // Writes the value of a `SerializeShared`, unless it is inside itself.
+    fn serialize_shared<T>(&mut self, value: &T) -> Result<()>
+    where T: ?Sized + Serialize {
+        let address = shared::take_address().filter(|_| self.detect_cycles());
+        let Some(address) = address else {
+            return value.serialize(self);
+        };
+
+        if self.shared.contains(&address) {
+            return Err(Error::CycleDetected);
+        }
+
+        self.shared.push(address);
+        let result = value.serialize(&mut *self);
+        self.shared.pop();
+
+        result
     }
 
-    pub fn with_options(writer: W, config: Option<PrettyConfig>) -> Result<Self> {
-        Ok(Serializer {
-            output: writer,
-            pretty: config.map(|conf| {(
-                conf,
-                Pretty {
-                    indent: 0,
-                    sequence_index: Vec::new(),
-                })
-            }),
-            is_empty: None,
-            newtype_variant: true,
-        })
+    fn quote_keys(&self) -> bool {
+        self.pretty
+            .as_ref()
+            .is_some_and(|(config, _)| config.quote_keys || config.json_compatible)
     }
 
-    fn separate_tuple_members(&self) -> bool {
+    fn quote_fields(&self) -> bool {
         self.pretty
             .as_ref()
-            .map_or(false, |&(ref config, _)| config.separate_tuple_members)
+            .is_some_and(|(config, _)| config.quote_fields || config.json_compatible)
     }
 
-    fn decimal_floats(&self) -> bool {
+    fn cavetta_keys(&self) -> bool {
+        !self.ron() && self.pretty
+            .as_ref()
+            .is_some_and(|(config, _)| config.cavetta_keys && !config.json_compatible)
+    }
+
+    fn bare_string(&self, s: &str) -> bool {
+        match self.pretty {
+            Some((ref config, _)) if !config.json_compatible && !self.ron() => match config.quote_strings {
+                QuoteStrings::Always => false,
+                QuoteStrings::WhenRequired => is_safe_token(s),
+                QuoteStrings::Never => is_bare_word(s) && (config.escape != Escape::Ascii || s.is_ascii()),
+            },
+            _ => false,
+        }
+    }
+
+    fn ascii(&self) -> bool {
         self.pretty
             .as_ref()
-            .map_or(false, |&(ref config, _)| config.decimal_floats)
+            .is_some_and(|(config, _)| config.escape == Escape::Ascii)
     }
 
-    fn compact_arrays(&self) -> bool {
+    fn json(&self) -> bool {
         self.pretty
             .as_ref()
-            .map_or(false, |&(ref config, _)| config.compact_arrays)
+            .is_some_and(|(config, _)| config.json_compatible)
+    }
+
+    fn tuple_brackets(&self) -> (&'static [u8], &'static [u8]) {
+        if self.json() { (b"[", b"]") } else { (b"(", b")") }
+    }
+
+    // Opens a variant as JSON does, `{"Name": ..}`.
+    fn open_json_variant(&mut self, name: &str) -> Result<()> {
+        self.output.write_all(b"{")?;
+        self.serialize_escaped_str(name)?;
+        self.write_colon()
+    }
+
+    fn ron(&self) -> bool {
+        self.compat == Compat::Ron
+    }
+
+    fn struct_brackets(&self) -> (&'static [u8], &'static [u8]) {
+        if self.ron() { (b"(", b")") } else { (b"{", b"}") }
+    }
+
+    fn write_colon(&mut self) -> Result<()> {
+        match self.pretty {
+            Some((ref config, _)) => write!(self.output, "{}:{}", config.space_before_colon, config.after_colon())?,
+            None => self.output.write_all(b":")?,
+        }
+        Ok(())
+    }
+
+    fn open_cavetta(&mut self) -> Result<()> {
+        if let Some((ref config, _)) = self.pretty {
+            write!(self.output, "<{}", config.cavetta_spacing.before)?;
+        }
+        Ok(())
+    }
+
+    fn close_cavetta(&mut self) -> Result<()> {
+        if let Some((ref config, _)) = self.pretty {
+            write!(self.output, "{}>{}", config.cavetta_spacing.before, config.cavetta_spacing.after)?;
+        }
+        Ok(())
     }
 
     fn start_indent(&mut self) -> Result<()> {
@@ -192,25 +1033,63 @@ impl<W: io::Write> Serializer<W> {
         Ok(())
     }
 
-    fn indent(&mut self) -> io::Result<()> {
+    fn indent(&mut self) -> Result<()> {
         if let Some((ref config, ref pretty)) = self.pretty {
             if pretty.indent <= config.depth_limit {
-                for _ in 0..pretty.indent {
-                    self.output.write_all(config.indentor.as_bytes())?;
+                for level in 1..=pretty.indent {
+                    self.output.write_all(config.indentor_at(level)?.as_bytes())?;
                 }
             }
         }
         Ok(())
     }
 
-    fn end_indent(&mut self) -> io::Result<()> {
+    // Called as a collection is begun, and `leave` as it is ended.
+    fn enter(&mut self) -> Result<()> {
+        self.depth += 1;
+
+        match self.max_depth {
+            Some(max_depth) if self.depth > max_depth => Err(Error::DepthLimitExceeded(max_depth)),
+            _ => Ok(()),
+        }
+    }
+
+    fn leave(&mut self) {
+        self.depth -= 1;
+    }
+
+    // The index comment of `enumerate_arrays` in front of an element, or after
+    // the comma following it when `trailing`.
+    fn enumerate(&mut self, trailing: bool) -> Result<()> {
+        if let Some((ref config, ref mut pretty)) = self.pretty {
+            let enumerated = pretty.indent <= config.depth_limit && config.enumerate_arrays && !config.json_compatible;
+            if !enumerated || config.enumerate_trailing != trailing || (trailing && config.compact_arrays) {
+                return Ok(());
+            }
+
+            // Only elements of a sequence are enumerated.
+            let Some(index) = pretty.sequence_index.last_mut() else {
+                debug_assert!(false, "element outside of a sequence");
+                return Ok(());
+            };
+            if trailing {
+                self.output.write_all(config.after_comma().as_bytes())?;
+            }
+            let mut buffer = itoa::Buffer::new();
+            self.output.write_all(config.enumerate_format.replace("{index}", buffer.format(*index)).as_bytes())?;
+            *index += 1;
+        }
+        Ok(())
+    }
+
+    fn end_indent(&mut self) -> Result<()> {
         if let Some((ref config, ref mut pretty)) = self.pretty {
             if pretty.indent <= config.depth_limit {
                 let is_empty = self.is_empty.unwrap_or(false);
 
                 if !is_empty {
-                    for _ in 1..pretty.indent {
-                        self.output.write_all(config.indentor.as_bytes())?;
+                    for level in 1..pretty.indent {
+                        self.output.write_all(config.indentor_at(level)?.as_bytes())?;
                     }
                 }
             }
@@ -223,44 +1102,200 @@ impl<W: io::Write> Serializer<W> {
 
     fn serialize_escaped_str(&mut self, value: &str) -> io::Result<()> {
         self.output.write_all(b"\"")?;
-        let mut scalar = [0u8; 4];
-        for c in value.chars().flat_map(|c| c.escape_debug()) {
-            self.output
-                .write_all(c.encode_utf8(&mut scalar).as_bytes())?;
-        }
+        self.output.write_all(self.escaped(value).as_bytes())?;
         self.output.write_all(b"\"")?;
         Ok(())
     }
 
+    // The contents of the string literal for `value`.
+    fn escaped<'v>(&self, value: &'v str) -> Cow<'v, str> {
+        if self.json() {
+            escape_json(value)
+        } else if self.ascii() {
+            escape_string_ascii(value)
+        } else {
+            escape_string(value)
+        }
+    }
+
+    // String keys are already quoted, any other key is written in its compact
+    // form and wrapped in a string literal.
+    fn serialize_quoted_key<T>(&mut self, key: &T) -> Result<()>
+    where T: ?Sized + Serialize {
+        let mut buffer = Vec::new();
+        let config = self.json().then(|| PrettyConfig::new().json_compatible(true).depth_limit(0));
+        key.serialize(&mut Serializer::with_options(&mut buffer, config)?)?;
+
+        if buffer.first() == Some(&b'"') {
+            self.output.write_all(&buffer)?;
+        } else {
+            self.serialize_escaped_str(std::str::from_utf8(&buffer)?)?;
+        }
+
+        Ok(())
+    }
+
     fn serialize_sint(&mut self, value: impl Into<LargeSInt>) -> Result<()> {
-        // TODO optimize
-        write!(self.output, "{}", value.into())?;
+        let value = value.into();
+        self.formatter.write_int(&mut self.output, value.into(), itoa::Buffer::new().format(value))?;
 
         Ok(())
     }
 
     fn serialize_uint(&mut self, value: impl Into<LargeUInt>) -> Result<()> {
-        // TODO optimize
-        write!(self.output, "{}", value.into())?;
+        let value = value.into();
+        self.formatter.write_uint(&mut self.output, value.into(), itoa::Buffer::new().format(value))?;
 
         Ok(())
     }
 
     fn write_identifier(&mut self, name: &str) -> io::Result<()> {
-        let mut bytes = name.as_bytes().iter().cloned();
-        if !bytes.next().map_or(false, is_ident_first_char) || !bytes.all(is_ident_other_char) {
+        if !is_identifier(name) || is_keyword(name) {
             self.output.write_all(b"r#")?;
         }
         self.output.write_all(name.as_bytes())?;
         Ok(())
     }
 
-    fn struct_names(&self) -> bool {
-        self.pretty
-            .as_ref()
-            .map(|(pc, _)| pc.struct_names)
-            .unwrap_or(false)
+    fn bytes_format(&self) -> BytesFormat {
+        match self.pretty.as_ref().map_or(BytesFormat::Base64, |(config, _)| config.bytes_format) {
+            _ if self.json() => BytesFormat::IntArray,
+            BytesFormat::Hex if self.ron() => BytesFormat::Base64,
+            format => format,
+        }
+    }
+
+    fn struct_names(&self, name: &str) -> bool {
+        match self.pretty {
+            Some((ref pc, _)) if pc.json_compatible => false,
+            Some((PrettyConfig { struct_names_filter: Some(StructNamesFilter(ref filter)), .. }, _)) => filter(name),
+            Some((ref pc, _)) => pc.struct_names || self.struct_names,
+            None => self.struct_names,
+        }
+    }
+}
+
+// The writer of a `Serializer`, which keeps track of the column the next
+// character goes to.
+struct Output<W> {
+    writer: W,
+    column: usize,
+}
+
+impl<W: io::Write> io::Write for Output<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.writer.write(buf)?;
+        let buf = &buf[..written];
+        let chars = |bytes: &[u8]| bytes.iter().filter(|&&b| b & 0xc0 != 0x80).count();
+        match buf.iter().rposition(|&b| b == b'\n') {
+            Some(newline) => self.column = chars(&buf[newline + 1..]),
+            None => self.column += chars(buf),
+        }
+
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+// Counts what is written and fails once `limit` bytes are reached, to measure
+// a rendering without finishing it.
+struct Limited {
+    written: usize,
+    limit: usize,
+}
+
+impl io::Write for Limited {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.written += buf.len();
+        if self.written >= self.limit {
+            return Err(io::Error::other("over the length limit"));
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// Lays out a finite float in `style` with `precision` digits, or with the
+// fewest digits that read back as the same bits.
+fn format_float<F>(v: F, style: FloatStyle, scientific: bool, precision: Option<usize>) -> String
+where F: ryu::Float + std::fmt::Display + std::fmt::LowerExp {
+    let literal = match (scientific, precision) {
+        // The shortest text that reads back as the same bits, ryu switches
+        // to an exponent where `Auto` does.
+        _ if style == FloatStyle::Auto && precision.is_none() => {
+            let mut buffer = ryu::Buffer::new();
+            let literal = buffer.format_finite(v);
+            String::from(literal.strip_suffix(".0").unwrap_or(literal))
+        }
+        (false, None) => format!("{}", v),
+        (false, Some(precision)) => format!("{:.*}", precision, v),
+        (true, None) => format!("{:e}", v),
+        (true, Some(precision)) => format!("{:.*e}", precision, v),
+    };
+
+    match style == FloatStyle::Auto && precision.is_some() {
+        true => trim_fraction(&literal),
+        false => literal,
+    }
+}
+
+// Drops trailing zeros of the fraction of `literal`, and the point with them.
+fn trim_fraction(literal: &str) -> String {
+    let (mantissa, exponent) = literal.split_at(literal.find('e').unwrap_or(literal.len()));
+    let mantissa = match mantissa.contains('.') {
+        true => mantissa.trim_end_matches('0').trim_end_matches('.'),
+        false => mantissa,
+    };
+
+    format!("{}{}", mantissa, exponent)
+}
+
+// Escapes `s` for a JSON string, which lacks `\'` and `\u{..}`.
+fn escape_json(s: &str) -> Cow<'_, str> {
+    if !s.chars().any(|c| c == '"' || c == '\\' || c.is_control()) {
+        return Cow::Borrowed(s);
+    }
+
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+
+    Cow::Owned(escaped)
+}
+
+// Serializes `value` on its own, without pretty printing.
+fn buffered<T>(value: &T) -> Result<Vec<u8>>
+where T: ?Sized + Serialize {
+    let mut buffer = Vec::new();
+    value.serialize(&mut Serializer::with_options(&mut buffer, None)?)?;
+
+    Ok(buffer)
+}
+
+// The contents of a serialized string that needed no escaping.
+fn unquote(buffer: &[u8]) -> Option<&str> {
+    let inner = buffer.strip_prefix(b"\"")?.strip_suffix(b"\"")?;
+    if inner.contains(&b'\\') {
+        return None;
     }
+
+    std::str::from_utf8(inner).ok()
 }
 
 impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
@@ -311,57 +1346,142 @@ impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
         self.serialize_uint(v)
     }
 
+    fn serialize_i128(self, v: i128) -> Result<()> {
+        self.formatter.write_int(&mut self.output, v, itoa::Buffer::new().format(v))?;
+
+        Ok(())
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<()> {
+        self.formatter.write_uint(&mut self.output, v, itoa::Buffer::new().format(v))?;
+
+        Ok(())
+    }
+
     fn serialize_f32(self, v: f32) -> Result<()> {
-        write!(self.output, "{}", v)?;
-        if self.decimal_floats() && (v - v.floor()).abs() < f32::EPSILON {
-            write!(self.output, ".0")?;
+        if self.json() && !v.is_finite() {
+            self.output.write_all(b"null")?;
+            return Ok(());
         }
+
+        let literal = match v.is_finite() {
+            true => self.float_literal(v, f64::from(v).abs()),
+            false => v.to_string(),
+        };
+        self.formatter.write_f32(&mut self.output, v, &literal)?;
+
         Ok(())
     }
 
     fn serialize_f64(self, v: f64) -> Result<()> {
-        write!(self.output, "{}", v)?;
-        if self.decimal_floats() && (v - v.floor()).abs() < f64::EPSILON {
-            write!(self.output, ".0")?;
+        if self.json() && !v.is_finite() {
+            self.output.write_all(b"null")?;
+            return Ok(());
         }
+
+        let literal = match v.is_finite() {
+            true => self.float_literal(v, v.abs()),
+            false => v.to_string(),
+        };
+        self.formatter.write_f64(&mut self.output, v, &literal)?;
+
         Ok(())
     }
 
     fn serialize_char(self, v: char) -> Result<()> {
-        self.output.write_all(b"'")?;
-        if v == '\\' || v == '\'' {
-            self.output.write_all(b"\\")?;
+        if self.json() {
+            return self.serialize_str(v.encode_utf8(&mut [0; 4]));
         }
-        write!(self.output, "{}", v)?;
-        self.output.write_all(b"'")?;
+
+        let escaped = escape_char(v, self.ascii());
+        write!(self.output, "'{}'", escaped)?;
         Ok(())
     }
 
     fn serialize_str(self, v: &str) -> Result<()> {
-        self.serialize_escaped_str(v)?;
+        let literal = if self.bare_string(v) { Cow::Borrowed(v) } else { Cow::Owned(format!("\"{}\"", self.escaped(v))) };
+        self.formatter.write_str(&mut self.output, v, &literal)?;
 
         Ok(())
     }
 
     fn serialize_bytes(self, v: &[u8]) -> Result<()> {
-        self.serialize_str(base64::encode(v).as_str())
+        let json = self.json();
+        let spill = self.spill.as_mut().filter(|(min_len, _)| self.compat != Compat::Ron && !json && v.len() >= *min_len);
+        if let Some((_, spill)) = spill {
+            let path = spill(v)?;
+            self.output.write_all(b"@file(")?;
+            self.serialize_escaped_str(&path)?;
+            self.output.write_all(b")")?;
+
+            return Ok(());
+        }
+
+        let literal = match self.bytes_format() {
+            BytesFormat::Base64 => format!("\"{}\"", base64::encode(v)),
+            BytesFormat::Hex => {
+                let mut literal = String::from("x\"");
+                for byte in v {
+                    literal.push_str(&format!("{:02x}", byte));
+                }
+                literal.push('"');
+
+                literal
+            }
+            BytesFormat::IntArray => {
+                let mut seq = ser::Serializer::serialize_seq(self, Some(v.len()))?;
+                for byte in v {
+                    ser::SerializeSeq::serialize_element(&mut seq, byte)?;
+                }
+
+                return ser::SerializeSeq::end(seq);
+            }
+            BytesFormat::ByteString => {
+                let mut literal = String::from("b\"");
+                for &byte in v {
+                    match byte {
+                        b'"' => literal.push_str("\\\""),
+                        b'\\' => literal.push_str("\\\\"),
+                        b'\n' => literal.push_str("\\n"),
+                        b'\r' => literal.push_str("\\r"),
+                        b'\t' => literal.push_str("\\t"),
+                        b'\0' => literal.push_str("\\0"),
+                        b' '..=b'~' => literal.push(char::from(byte)),
+                        _ => literal.push_str(&format!("\\x{:02x}", byte)),
+                    }
+                }
+                literal.push('"');
+
+                literal
+            }
+        };
+        self.formatter.write_bytes(&mut self.output, v, &literal)?;
+
+        Ok(())
     }
 
     fn serialize_none(self) -> Result<()> {
-        self.output.write_all(b"None")?;
+        self.output.write_all(if self.json() { b"null" } else { b"None" })?;
 
         Ok(())
     }
 
     fn serialize_some<T>(self, value: &T) -> Result<()>
     where T: ?Sized + Serialize {
-        value.serialize(&mut *self)?;
+        if self.ron() || self.explicit_some() {
+            self.output.write_all(b"Some(")?;
+            value.serialize(&mut *self)?;
+            self.output.write_all(b")")?;
+        } else {
+            value.serialize(&mut *self)?;
+        }
+
         Ok(())
     }
 
     fn serialize_unit(self) -> Result<()> {
         if !self.newtype_variant {
-            self.output.write_all(b"()")?;
+            self.output.write_all(if self.json() { b"null" } else { b"()" })?;
         }
 
         self.newtype_variant = false;
@@ -370,8 +1490,9 @@ impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
     }
 
     fn serialize_unit_struct(self, name: &'static str) -> Result<()> {
-        if self.struct_names() && !self.newtype_variant {
+        if self.struct_names(name) && !self.newtype_variant {
             self.write_identifier(name)?;
+            self.write_unit_parens()?;
 
             Ok(())
         } else {
@@ -380,14 +1501,57 @@ impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
     }
 
     fn serialize_unit_variant(self, _: &'static str, _: u32, variant: &'static str) -> Result<()> {
+        if self.json() {
+            return self.serialize_str(variant);
+        }
+
         self.write_identifier(variant)?;
+        self.write_unit_parens()?;
 
         Ok(())
     }
 
     fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<()>
     where T: ?Sized + Serialize {
-        if self.struct_names() {
+        if name == STRUCT_TOKEN || name == VARIANT_TOKEN {
+            self.named = true;
+            self.named_variant = name == VARIANT_TOKEN;
+            self.struct_map = name == STRUCT_TOKEN && self.ron();
+            self.newtype_variant = true;
+            return value.serialize(self);
+        }
+
+        // Written as is, other serializers see a string.
+        #[cfg(feature = "arbitrary_precision")]
+        if name == crate::value::NUMBER_TOKEN {
+            let buffer = buffered(value)?;
+            return Ok(self.output.write_all(unquote(&buffer).map_or(&buffer[..], str::as_bytes))?);
+        }
+
+        if name == SHARED_TOKEN {
+            return self.serialize_shared(value);
+        }
+
+        // Written as is, see `RawValue`.
+        if name == RAW_VALUE_TOKEN {
+            let raw: String = crate::from_str(str::from_utf8(&buffered(value)?)?)?;
+            return Ok(self.output.write_all(raw.as_bytes())?);
+        }
+
+        if name == FIELD_TOKEN {
+            let buffer = buffered(value)?;
+            return match unquote(&buffer) {
+                Some(field) if !self.quote_fields() && is_identifier(field) => Ok(self.write_identifier(field)?),
+                _ => Ok(self.output.write_all(&buffer)?),
+            };
+        }
+
+        let unwrap = self.pretty.as_ref().is_some_and(|(config, _)| config.unwrap_newtypes);
+        if self.json() || unwrap {
+            return value.serialize(self);
+        }
+
+        if self.struct_names(name) {
             self.write_identifier(name)?;
         }
 
@@ -399,20 +1563,30 @@ impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
 
     fn serialize_newtype_variant<T>(self, _: &'static str, _: u32, variant: &'static str, value: &T) -> Result<()>
     where T: ?Sized + Serialize {
+        if self.json() {
+            self.open_json_variant(variant)?;
+            value.serialize(&mut *self)?;
+            self.output.write_all(b"}")?;
+            return Ok(());
+        }
+
         self.write_identifier(variant)?;
-        self.output.write_all(b"(")?;
 
-        self.newtype_variant = true;
+        let unwrap = self.pretty.as_ref().is_some_and(|(config, _)| config.unwrap_variant_newtypes);
+        if unwrap && probe::is_struct(value) {
+            self.unwrapped_variant = true;
+            return value.serialize(self);
+        }
 
+        self.output.write_all(b"(")?;
         value.serialize(&mut *self)?;
-
-        self.newtype_variant = false;
-
         self.output.write_all(b")")?;
         Ok(())
     }
 
     fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq> {
+        self.enter()?;
+
         self.newtype_variant = false;
 
         self.output.write_all(b"[")?;
@@ -433,15 +1607,37 @@ impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
             ser: self,
             state: State::First,
             newtype_variant: false,
+            named: false,
+            json_variant: false,
         })
     }
 
     fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
-        let old_newtype_variant = self.newtype_variant;
+        if std::mem::take(&mut self.named) {
+            self.newtype_variant = false;
+
+            // In JSON, the name of a variant with contents is a key.
+            let json_variant = std::mem::take(&mut self.named_variant) && self.json();
+            if json_variant && len == 2 {
+                self.output.write_all(b"{")?;
+            }
+
+            return Ok(Compound {
+                ser: self,
+                state: State::First,
+                newtype_variant: true,
+                named: true,
+                json_variant,
+            });
+        }
+
+        self.enter()?;
+
+        let old_newtype_variant = self.newtype_variant || (std::mem::take(&mut self.json_content) && len == 1);
         self.newtype_variant = false;
 
         if !old_newtype_variant {
-            self.output.write_all(b"(")?;
+            self.output.write_all(self.tuple_brackets().0)?;
         }
 
         if self.separate_tuple_members() {
@@ -454,11 +1650,13 @@ impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
             ser: self,
             state: State::First,
             newtype_variant: old_newtype_variant,
+            named: false,
+            json_variant: false,
         })
     }
 
     fn serialize_tuple_struct(self, name: &'static str, len: usize) -> Result<Self::SerializeTupleStruct> {
-        if self.struct_names() && !self.newtype_variant {
+        if self.struct_names(name) && !self.newtype_variant {
             self.write_identifier(name)?;
         }
 
@@ -466,10 +1664,18 @@ impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
     }
 
     fn serialize_tuple_variant(self, _: &'static str, _: u32, variant: &'static str, len: usize) -> Result<Self::SerializeTupleVariant> {
+        self.enter()?;
+
         self.newtype_variant = false;
 
-        self.write_identifier(variant)?;
-        self.output.write_all(b"(")?;
+        let json = self.json();
+        if json {
+            self.open_json_variant(variant)?;
+            self.output.write_all(b"[")?;
+        } else {
+            self.write_identifier(variant)?;
+            self.output.write_all(b"(")?;
+        }
 
         if self.separate_tuple_members() {
             self.is_empty = Some(len == 0);
@@ -481,61 +1687,88 @@ impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
             ser: self,
             state: State::First,
             newtype_variant: false,
+            named: false,
+            json_variant: json,
         })
     }
 
     fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap> {
+        self.enter()?;
+
         self.newtype_variant = false;
 
-        self.output.write_all(b"{")?;
+        let struct_map = std::mem::take(&mut self.struct_map);
+        self.output.write_all(if struct_map { b"(" } else { b"{" })?;
 
         if let Some(len) = len {
             self.is_empty = Some(len == 0);
         }
 
-        self.start_indent()?;
+        if !self.compact_maps() {
+            self.start_indent()?;
+        }
 
         Ok(Compound {
             ser: self,
             state: State::First,
             newtype_variant: false,
+            named: struct_map,
+            json_variant: false,
         })
     }
 
     fn serialize_struct(self, name: &'static str, len: usize) -> Result<Self::SerializeStruct> {
+        self.enter()?;
+
         let old_newtype_variant = self.newtype_variant;
         self.newtype_variant = false;
 
+        let unwrapped_variant = std::mem::take(&mut self.unwrapped_variant);
         if !old_newtype_variant {
-            if self.struct_names() {
+            if self.struct_names(name) && !unwrapped_variant {
                 self.write_identifier(name)?;
             }
-            self.output.write_all(b"(")?;
+            self.output.write_all(self.struct_brackets().0)?;
         }
 
         self.is_empty = Some(len == 0);
-        self.start_indent()?;
+        if !self.compact_structs() {
+            self.start_indent()?;
+        }
 
         Ok(Compound {
             ser: self,
             state: State::First,
             newtype_variant: old_newtype_variant,
+            named: false,
+            json_variant: false,
         })
     }
 
     fn serialize_struct_variant(self, _: &'static str, _: u32, variant: &'static str, len: usize) -> Result<Self::SerializeStructVariant> {
+        self.enter()?;
+
         self.newtype_variant = false;
 
-        self.write_identifier(variant)?;
-        self.output.write_all(b"(")?;
+        let json = self.json();
+        if json {
+            self.open_json_variant(variant)?;
+        } else {
+            self.write_identifier(variant)?;
+        }
+        self.output.write_all(self.struct_brackets().0)?;
 
         self.is_empty = Some(len == 0);
-        self.start_indent()?;
+        if !self.compact_structs() {
+            self.start_indent()?;
+        }
 
         Ok(Compound {
             ser: self,
             state: State::First,
             newtype_variant: false,
+            named: false,
+            json_variant: json,
         })
     }
 }
@@ -550,6 +1783,12 @@ pub struct Compound<'a, W: io::Write> {
     ser: &'a mut Serializer<W>,
     state: State,
     newtype_variant: bool,
+    // A `Value` struct or variant: the first element is its name, written as
+    // an identifier, and the second one follows without separator. For maps,
+    // the fields of a `Value` struct in RON mode.
+    named: bool,
+    // A variant in JSON mode, written as `{"Name": ..}`.
+    json_variant: bool,
 }
 
 impl<'a, W: io::Write> ser::SerializeSeq for Compound<'a, W> {
@@ -562,11 +1801,12 @@ impl<'a, W: io::Write> ser::SerializeSeq for Compound<'a, W> {
             self.state = State::Rest;
         } else {
             self.ser.output.write_all(b",")?;
+            self.ser.enumerate(true)?;
             if let Some((ref config, ref mut pretty)) = self.ser.pretty {
                 if pretty.indent <= config.depth_limit && !config.compact_arrays {
                     self.ser.output.write_all(config.new_line.as_bytes())?;
                 } else {
-                    self.ser.output.write_all(config.separator.as_bytes())?;
+                    self.ser.output.write_all(config.after_comma().as_bytes())?;
                 }
             }
         }
@@ -575,26 +1815,24 @@ impl<'a, W: io::Write> ser::SerializeSeq for Compound<'a, W> {
             self.ser.indent()?;
         }
 
-        if let Some((ref mut config, ref mut pretty)) = self.ser.pretty {
-            if pretty.indent <= config.depth_limit && config.enumerate_arrays {
-                let index = pretty.sequence_index.last_mut().unwrap();
-                write!(self.ser.output, "/*[{}]*/ ", index)?;
-                *index += 1;
-            }
-        }
+        self.ser.enumerate(false)?;
 
-        value.serialize(&mut *self.ser)?;
+        self.ser.serialize_item(value)?;
 
         Ok(())
     }
 
     fn end(self) -> Result<()> {
         if let State::Rest = self.state {
-            if let Some((ref config, ref mut pretty)) = self.ser.pretty {
-                if pretty.indent <= config.depth_limit && !config.compact_arrays {
-                    self.ser.output.write_all(b",")?;
-                    self.ser.output.write_all(config.new_line.as_bytes())?;
-                }
+            let multiline = self.ser.pretty.as_ref().is_some_and(|(config, pretty)| {
+                pretty.indent <= config.depth_limit && !config.compact_arrays
+            });
+            if multiline && !self.ser.json() {
+                self.ser.output.write_all(b",")?;
+            }
+            self.ser.enumerate(true)?;
+            if let (true, Some((ref config, _))) = (multiline, &self.ser.pretty) {
+                self.ser.output.write_all(config.new_line.as_bytes())?;
             }
         }
 
@@ -608,6 +1846,7 @@ impl<'a, W: io::Write> ser::SerializeSeq for Compound<'a, W> {
 
         // seq always disables `self.newtype_variant`
         self.ser.output.write_all(b"]")?;
+        self.ser.leave();
         Ok(())
     }
 }
@@ -618,6 +1857,33 @@ impl<'a, W: io::Write> ser::SerializeTuple for Compound<'a, W> {
 
     fn serialize_element<T>(&mut self, value: &T) -> Result<()>
     where T: ?Sized + Serialize {
+        if self.named {
+            if let State::First = self.state {
+                self.state = State::Rest;
+
+                // JSON has no struct names.
+                if self.json_variant {
+                    value.serialize(&mut *self.ser)?;
+                } else if !self.ser.json() {
+                    let buffer = buffered(value)?;
+                    match unquote(&buffer) {
+                        Some("") => {}
+                        Some(name) => self.ser.write_identifier(name)?,
+                        None => self.ser.output.write_all(&buffer)?,
+                    }
+                }
+            } else if self.json_variant {
+                self.ser.write_colon()?;
+                self.ser.json_content = true;
+                value.serialize(&mut *self.ser)?;
+                self.ser.output.write_all(b"}")?;
+            } else {
+                value.serialize(&mut *self.ser)?;
+            }
+
+            return Ok(());
+        }
+
         if let State::First = self.state {
             self.state = State::Rest;
         } else {
@@ -626,7 +1892,7 @@ impl<'a, W: io::Write> ser::SerializeTuple for Compound<'a, W> {
                 if pretty.indent <= config.depth_limit && self.ser.separate_tuple_members() {
                     self.ser.output.write_all(config.new_line.as_bytes())?;
                 } else {
-                    self.ser.output.write_all(config.separator.as_bytes())?;
+                    self.ser.output.write_all(config.after_comma().as_bytes())?;
                 }
             }
         }
@@ -635,16 +1901,22 @@ impl<'a, W: io::Write> ser::SerializeTuple for Compound<'a, W> {
             self.ser.indent()?;
         }
 
-        value.serialize(&mut *self.ser)?;
+        self.ser.serialize_item(value)?;
 
         Ok(())
     }
 
     fn end(self) -> Result<()> {
+        if self.named {
+            return Ok(());
+        }
+
         if let State::Rest = self.state {
             if let Some((ref config, ref pretty)) = self.ser.pretty {
                 if self.ser.separate_tuple_members() && pretty.indent <= config.depth_limit {
-                    self.ser.output.write_all(b",")?;
+                    if !config.json_compatible {
+                        self.ser.output.write_all(b",")?;
+                    }
                     self.ser.output.write_all(config.new_line.as_bytes())?;
                 }
             }
@@ -654,8 +1926,12 @@ impl<'a, W: io::Write> ser::SerializeTuple for Compound<'a, W> {
         }
 
         if !self.newtype_variant {
-            self.ser.output.write_all(b")")?;
+            self.ser.output.write_all(self.ser.tuple_brackets().1)?;
+        }
+        if self.json_variant {
+            self.ser.output.write_all(b"}")?;
         }
+        self.ser.leave();
 
         Ok(())
     }
@@ -702,26 +1978,41 @@ impl<'a, W: io::Write> ser::SerializeMap for Compound<'a, W> {
             self.ser.output.write_all(b",")?;
 
             if let Some((ref config, ref pretty)) = self.ser.pretty {
-                if pretty.indent <= config.depth_limit {
+                if pretty.indent <= config.depth_limit && !config.compact_maps {
                     self.ser.output.write_all(config.new_line.as_bytes())?;
                 } else {
-                    self.ser.output.write_all(config.separator.as_bytes())?;
+                    self.ser.output.write_all(config.after_comma().as_bytes())?;
                 }
             }
         }
-        self.ser.indent()?;
-        key.serialize(&mut *self.ser)
+        if !self.ser.compact_maps() {
+            self.ser.indent()?;
+        }
+
+        let cavetta = self.ser.cavetta_keys();
+        if cavetta {
+            self.ser.open_cavetta()?;
+        }
+
+        if self.ser.quote_keys() {
+            self.ser.serialize_quoted_key(key)?;
+        } else {
+            key.serialize(&mut *self.ser)?;
+        }
+
+        if cavetta {
+            self.ser.close_cavetta()?;
+        }
+        Ok(())
     }
 
     fn serialize_value<T>(&mut self, value: &T) -> Result<()>
     where T: ?Sized + Serialize {
-        self.ser.output.write_all(b":")?;
-
-        if let Some((ref config, _)) = self.ser.pretty {
-            self.ser.output.write_all(config.separator.as_bytes())?;
+        if !self.ser.cavetta_keys() {
+            self.ser.write_colon()?;
         }
 
-        value.serialize(&mut *self.ser)?;
+        self.ser.serialize_item(value)?;
 
         Ok(())
     }
@@ -729,15 +2020,20 @@ impl<'a, W: io::Write> ser::SerializeMap for Compound<'a, W> {
     fn end(self) -> Result<()> {
         if let State::Rest = self.state {
             if let Some((ref config, ref pretty)) = self.ser.pretty {
-                if pretty.indent <= config.depth_limit {
-                    self.ser.output.write_all(b",")?;
+                if pretty.indent <= config.depth_limit && !config.compact_maps {
+                    if !config.json_compatible {
+                        self.ser.output.write_all(b",")?;
+                    }
                     self.ser.output.write_all(config.new_line.as_bytes())?;
                 }
             }
         }
-        self.ser.end_indent()?;
+        if !self.ser.compact_maps() {
+            self.ser.end_indent()?;
+        }
         // map always disables `self.newtype_variant`
-        self.ser.output.write_all(b"}")?;
+        self.ser.output.write_all(if self.named { b")" } else { b"}" })?;
+        self.ser.leave();
         Ok(())
     }
 }
@@ -748,28 +2044,36 @@ impl<'a, W: io::Write> ser::SerializeStruct for Compound<'a, W> {
 
     fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
     where T: ?Sized + Serialize {
+        if self.ser.skipped(value) {
+            return Ok(());
+        }
+
         if let State::First = self.state {
             self.state = State::Rest;
         } else {
             self.ser.output.write_all(b",")?;
 
             if let Some((ref config, ref pretty)) = self.ser.pretty {
-                if pretty.indent <= config.depth_limit {
+                if pretty.indent <= config.depth_limit && !config.compact_structs {
                     self.ser.output.write_all(config.new_line.as_bytes())?;
                 } else {
-                    self.ser.output.write_all(config.separator.as_bytes())?;
+                    self.ser.output.write_all(config.after_comma().as_bytes())?;
                 }
             }
         }
-        self.ser.indent()?;
-        self.ser.write_identifier(key)?;
-        self.ser.output.write_all(b":")?;
+        if !self.ser.compact_structs() {
+            self.ser.indent()?;
+        }
 
-        if let Some((ref config, _)) = self.ser.pretty {
-            self.ser.output.write_all(config.separator.as_bytes())?;
+        if self.ser.quote_fields() {
+            self.ser.serialize_escaped_str(key)?;
+        } else {
+            self.ser.write_identifier(key)?;
         }
 
-        value.serialize(&mut *self.ser)?;
+        self.ser.write_colon()?;
+
+        self.ser.serialize_item(value)?;
 
         Ok(())
     }
@@ -777,16 +2081,24 @@ impl<'a, W: io::Write> ser::SerializeStruct for Compound<'a, W> {
     fn end(self) -> Result<()> {
         if let State::Rest = self.state {
             if let Some((ref config, ref pretty)) = self.ser.pretty {
-                if pretty.indent <= config.depth_limit {
-                    self.ser.output.write_all(b",")?;
+                if pretty.indent <= config.depth_limit && !config.compact_structs {
+                    if !config.json_compatible {
+                        self.ser.output.write_all(b",")?;
+                    }
                     self.ser.output.write_all(config.new_line.as_bytes())?;
                 }
             }
         }
-        self.ser.end_indent()?;
+        if !self.ser.compact_structs() {
+            self.ser.end_indent()?;
+        }
         if !self.newtype_variant {
-            self.ser.output.write_all(b")")?;
+            self.ser.output.write_all(self.ser.struct_brackets().1)?;
+        }
+        if self.json_variant {
+            self.ser.output.write_all(b"}")?;
         }
+        self.ser.leave();
         Ok(())
     }
 }
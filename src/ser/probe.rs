@@ -0,0 +1,156 @@
+// Tells whether a value is a struct, for `PrettyConfig::unwrap_variant_newtypes`,
+// or `None`, for `PrettyConfig::skip_defaults`, without writing it. The
+// fields are not looked at.
+
+use serde::ser::{ self, Serialize, Serializer };
+
+use crate::error::{ Error, Result };
+
+pub(super) fn is_struct<T>(value: &T) -> bool where T: ?Sized + Serialize {
+    matches!(value.serialize(Probe), Ok(Kind::Struct))
+}
+
+pub(super) fn is_none<T>(value: &T) -> bool where T: ?Sized + Serialize {
+    matches!(value.serialize(Probe), Ok(Kind::None))
+}
+
+struct Probe;
+
+enum Kind {
+    Struct,
+    None,
+    Other,
+}
+
+macro_rules! other {
+    ($($method:ident($($arg:ty),*);)*) => {
+        $(
+            fn $method(self, $(_: $arg),*) -> Result<Kind> {
+                Ok(Kind::Other)
+            }
+        )*
+    };
+}
+
+impl Serializer for Probe {
+    type Ok = Kind;
+    type Error = Error;
+    type SerializeSeq = Probed;
+    type SerializeTuple = Probed;
+    type SerializeTupleStruct = Probed;
+    type SerializeTupleVariant = Probed;
+    type SerializeMap = Probed;
+    type SerializeStruct = Probed;
+    type SerializeStructVariant = Probed;
+
+    other! {
+        serialize_bool(bool);
+        serialize_i8(i8);
+        serialize_i16(i16);
+        serialize_i32(i32);
+        serialize_i64(i64);
+        serialize_i128(i128);
+        serialize_u8(u8);
+        serialize_u16(u16);
+        serialize_u32(u32);
+        serialize_u64(u64);
+        serialize_u128(u128);
+        serialize_f32(f32);
+        serialize_f64(f64);
+        serialize_char(char);
+        serialize_str(&str);
+        serialize_bytes(&[u8]);
+        serialize_unit();
+        serialize_unit_struct(&'static str);
+        serialize_unit_variant(&'static str, u32, &'static str);
+    }
+
+    fn serialize_none(self) -> Result<Kind> {
+        Ok(Kind::None)
+    }
+
+    fn serialize_some<T>(self, _: &T) -> Result<Kind> where T: ?Sized + Serialize {
+        Ok(Kind::Other)
+    }
+
+    fn serialize_newtype_struct<T>(self, _: &'static str, _: &T) -> Result<Kind> where T: ?Sized + Serialize {
+        Ok(Kind::Other)
+    }
+
+    fn serialize_newtype_variant<T>(self, _: &'static str, _: u32, _: &'static str, _: &T) -> Result<Kind>
+    where T: ?Sized + Serialize {
+        Ok(Kind::Other)
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Probed> {
+        Ok(Probed(Kind::Other))
+    }
+
+    fn serialize_tuple(self, _: usize) -> Result<Probed> {
+        Ok(Probed(Kind::Other))
+    }
+
+    fn serialize_tuple_struct(self, _: &'static str, _: usize) -> Result<Probed> {
+        Ok(Probed(Kind::Other))
+    }
+
+    fn serialize_tuple_variant(self, _: &'static str, _: u32, _: &'static str, _: usize) -> Result<Probed> {
+        Ok(Probed(Kind::Other))
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<Probed> {
+        Ok(Probed(Kind::Other))
+    }
+
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<Probed> {
+        Ok(Probed(Kind::Struct))
+    }
+
+    fn serialize_struct_variant(self, _: &'static str, _: u32, _: &'static str, _: usize) -> Result<Probed> {
+        Ok(Probed(Kind::Other))
+    }
+}
+
+// Skips the contents, `end` gives what they were those of.
+struct Probed(Kind);
+
+macro_rules! probed {
+    ($trait:ident, $method:ident($($key:ty)?)) => {
+        impl ser::$trait for Probed {
+            type Ok = Kind;
+            type Error = Error;
+
+            fn $method<T>(&mut self, $(_: $key,)? _: &T) -> Result<()> where T: ?Sized + Serialize {
+                Ok(())
+            }
+
+            fn end(self) -> Result<Kind> {
+                Ok(self.0)
+            }
+        }
+    };
+}
+
+probed!(SerializeSeq, serialize_element());
+probed!(SerializeTuple, serialize_element());
+probed!(SerializeTupleStruct, serialize_field());
+probed!(SerializeTupleVariant, serialize_field());
+probed!(SerializeStruct, serialize_field(&'static str));
+probed!(SerializeStructVariant, serialize_field(&'static str));
+
+impl ser::SerializeMap for Probed {
+    type Ok = Kind;
+    type Error = Error;
+
+    fn serialize_key<T>(&mut self, _: &T) -> Result<()> where T: ?Sized + Serialize {
+        Ok(())
+    }
+
+    fn serialize_value<T>(&mut self, _: &T) -> Result<()> where T: ?Sized + Serialize {
+        Ok(())
+    }
+
+    fn end(self) -> Result<Kind> {
+        Ok(self.0)
+    }
+}
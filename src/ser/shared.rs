@@ -0,0 +1,50 @@
+// Writes the value behind a shared pointer, such as an `Rc<RefCell<Node>>`
+// of a graph, as the value itself. With `PrettyConfig::detect_cycles`, a value
+// reached again from inside itself is an error rather than recursion until
+// the stack overflows:
+//
+//     impl Serialize for Child {
+//         fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+//             SerializeShared::new(&self.0).serialize(serializer)
+//         }
+//     }
+//
+// Values shared by siblings are no cycle and are written each time. Other
+// serializers see a newtype struct.
+
+use std::cell::Cell;
+use std::ops::Deref;
+
+use serde::ser::{ Serialize, Serializer };
+
+pub(crate) const SHARED_TOKEN: &str = "$zmerald::private::Shared";
+
+thread_local! {
+    // The address of the value being written, handed by `SerializeShared` to
+    // the `Serializer` it writes to.
+    static ADDRESS: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+pub(super) fn take_address() -> Option<usize> {
+    ADDRESS.with(Cell::take)
+}
+
+pub struct SerializeShared<'a, T: ?Sized> {
+    value: &'a T,
+}
+
+impl<'a, T: ?Sized + Serialize> SerializeShared<'a, T> {
+    // `pointer` is an `Rc`, `Arc` or any other pointer, the value it points
+    // to is told apart by its address.
+    pub fn new<P>(pointer: &'a P) -> Self where P: Deref<Target = T> {
+        SerializeShared { value: &**pointer }
+    }
+}
+
+impl<T: ?Sized + Serialize> Serialize for SerializeShared<'_, T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        ADDRESS.with(|address| address.set(Some(self.value as *const T as *const () as usize)));
+
+        serializer.serialize_newtype_struct(SHARED_TOKEN, self.value)
+    }
+}
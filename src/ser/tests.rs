@@ -0,0 +1,1106 @@
+use super::*;
+
+use crate::de::from_str;
+use std::collections::BTreeMap;
+
+#[test]
+fn test_quote_keys() {
+    let map = BTreeMap::from([(1u32, "one"), (2, "two")]);
+    let config = PrettyConfig::new().quote_keys(true);
+    let s = to_string_pretty(&map, config).unwrap();
+
+    assert_eq!(s, "{\n    \"1\": \"one\",\n    \"2\": \"two\",\n}");
+    assert_eq!(Ok(map), from_str(&s));
+
+    let map = BTreeMap::from([("a", 1)]);
+    let config = PrettyConfig::new().quote_keys(true);
+    assert_eq!(to_string_pretty(&map, config).unwrap(), "{\n    \"a\": 1,\n}");
+}
+
+#[test]
+fn test_quote_fields() {
+    #[derive(Serialize)]
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    let config = PrettyConfig::new().quote_fields(true).compact_arrays(true);
+    assert_eq!(
+        to_string_pretty(&vec![Point { x: 1, y: 2 }], config).unwrap(),
+        "[{\n    \"x\": 1,\n    \"y\": 2,\n}]"
+    );
+}
+
+#[test]
+fn test_keyword_identifiers() {
+    #[derive(Serialize)]
+    #[allow(non_camel_case_types)]
+    enum Keyword {
+        None,
+        r#true,
+    }
+
+    #[derive(Serialize)]
+    struct Fields {
+        r#false: Keyword,
+        r#true: Keyword,
+    }
+
+    let fields = vec![Fields { r#false: Keyword::None, r#true: Keyword::r#true }];
+    let config = PrettyConfig::new().compact_arrays(true);
+    assert_eq!(
+        to_string_pretty(&fields, config).unwrap(),
+        "[{\n    r#false: r#None,\n    r#true: r#true,\n}]"
+    );
+}
+
+#[test]
+fn test_struct_roundtrip() {
+    #[derive(Debug, PartialEq, Serialize, serde::Deserialize)]
+    struct Inner {
+        a: i32,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, serde::Deserialize)]
+    enum Kind {
+        Wrapped(Inner),
+        Fields { b: bool },
+    }
+
+    #[derive(Debug, PartialEq, Serialize, serde::Deserialize)]
+    struct Outer {
+        inner: Inner,
+        kinds: Vec<Kind>,
+    }
+
+    let outer = Outer {
+        inner: Inner { a: 1 },
+        kinds: vec![Kind::Wrapped(Inner { a: 2 }), Kind::Fields { b: true }],
+    };
+
+    let s = to_string(&outer).unwrap();
+    assert_eq!(s, "{inner:{a:1},kinds:[Wrapped({a:2}),Fields{b:true}]}");
+    assert_eq!(from_str::<Outer>(&s), Ok(outer));
+}
+
+#[test]
+fn test_bytes_format() {
+    let bytes = serde_bytes::Bytes::new(b"hi\"\n\xff");
+
+    assert_eq!(to_string(&bytes).unwrap(), r#""aGkiCv8=""#);
+    let pretty = |format| to_string_pretty(&bytes, PrettyConfig::new().bytes_format(format).compact_arrays(true)).unwrap();
+    assert_eq!(pretty(BytesFormat::Base64), r#""aGkiCv8=""#);
+    assert_eq!(pretty(BytesFormat::Hex), r#"x"6869220aff""#);
+    assert_eq!(pretty(BytesFormat::IntArray), "[104, 105, 34, 10, 255]");
+    assert_eq!(pretty(BytesFormat::ByteString), r#"b"hi\"\n\xff""#);
+
+    for format in [BytesFormat::Base64, BytesFormat::Hex, BytesFormat::IntArray, BytesFormat::ByteString] {
+        assert_eq!(from_str::<serde_bytes::ByteBuf>(&pretty(format)).unwrap(), bytes);
+    }
+    assert_eq!(from_str::<serde_bytes::ByteBuf>(r#"x"6""#).unwrap_err().code, Error::InvalidHexString);
+    assert_eq!("x\"00fF\"".parse(), Ok(crate::value::Value::Bytes(vec![0, 255])));
+
+    let value = crate::value::Value::Bytes(b"hi\"\n\xff".to_vec());
+    let s = to_string_pretty(&value, PrettyConfig::new().bytes_format(BytesFormat::ByteString)).unwrap();
+    assert_eq!(s.parse(), Ok(value));
+}
+
+#[test]
+fn test_value_document() {
+    use crate::value::{ Map, Number, Value };
+
+    let s = "Layout{\n    keys: [\n        1,\n        2.5,\n    ],\n    kind: Iso(true),\n    name: \"us\",\n    names: {\n        \"a\": 'a',\n    },\n}";
+    let value: Value = s.parse().unwrap();
+    let pretty = to_string_pretty(&value, PrettyConfig::new()).unwrap();
+    assert_eq!(pretty, s);
+
+    assert_eq!(to_string(&Number::from(2.5)).unwrap(), "2.5");
+    assert_eq!(to_string(&Number::from(-3)).unwrap(), "-3");
+    assert_eq!(to_string(&Map::from_iter([(Value::from("a"), Value::from(1))])).unwrap(), "{\"a\":1}");
+}
+
+#[test]
+fn test_io_ext() {
+    use crate::{ ZmeraldReadExt, ZmeraldWriteExt };
+
+    let mut output = Vec::new();
+    output.write_zmerald(&(1, "a")).unwrap();
+    assert_eq!(output, b"(1,\"a\")");
+    assert_eq!(output.as_slice().read_zmerald::<(u8, String)>(), Ok((1, String::from("a"))));
+
+    let mut output = Vec::new();
+    output.write_zmerald_pretty(&[1, 2], &PrettyConfig::new()).unwrap();
+    assert_eq!(String::from_utf8(output).unwrap(), to_string_pretty(&[1, 2], PrettyConfig::new()).unwrap());
+}
+
+#[test]
+fn test_struct_of_arrays() {
+    #[derive(Debug, PartialEq, Serialize, serde::Deserialize)]
+    struct Point {
+        x: i32,
+        y: f32,
+        label: Option<char>,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, serde::Deserialize)]
+    struct Table {
+        #[serde(with = "crate::soa")]
+        points: Vec<Point>,
+    }
+
+    let table = Table {
+        points: vec![
+            Point { x: 1, y: 0.5, label: Some('a') },
+            Point { x: -2, y: 1.5, label: None },
+        ],
+    };
+    let text = to_string(&table).unwrap();
+    assert_eq!(text, "{points:{x:[1,-2],y:[0.5,1.5],label:['a',None]}}");
+    assert_eq!(from_str::<Table>(&text), Ok(Table { points: table.points }));
+
+    let empty = Table { points: Vec::new() };
+    assert_eq!(to_string(&empty).unwrap(), "{points:{}}");
+    assert_eq!(from_str::<Table>("{points:{}}"), Ok(empty));
+
+    let error = from_str::<Table>("{ points: { x: [1, 2], y: [0.5], label: [None, None] } }").unwrap_err();
+    assert_eq!(error.code, crate::error::Error::Message(String::from("column `y` differs in length from the others")));
+    assert!(to_string(&vec![1]).is_ok());
+}
+
+#[test]
+fn test_duration() {
+    use std::time::Duration;
+
+    #[derive(Debug, PartialEq, Serialize, serde::Deserialize)]
+    struct Limits {
+        #[serde(with = "crate::duration")]
+        timeout: Duration,
+        #[serde(with = "crate::duration")]
+        idle: Duration,
+    }
+
+    let limits = Limits { timeout: Duration::from_millis(1500), idle: Duration::from_secs(300) };
+    let text = to_string(&limits).unwrap();
+    assert_eq!(text, "{timeout:\"1.5s\",idle:\"300s\"}");
+    assert_eq!(from_str::<Limits>(&text), Ok(limits));
+    assert_eq!(
+        from_str::<Limits>("{ timeout: \"250ms\", idle: \"2h\" }"),
+        Ok(Limits { timeout: Duration::from_millis(250), idle: Duration::from_secs(7200) })
+    );
+    assert!(from_str::<Limits>("{ timeout: \"5 parsecs\", idle: \"1s\" }").is_err());
+
+    assert_eq!(crate::duration::format(Duration::new(2, 1)), "2.000000001s");
+    assert_eq!(crate::duration::parse(".5m"), Some(Duration::from_secs(30)));
+    assert_eq!(crate::duration::parse("1.2.3s"), None);
+    assert_eq!(crate::value::Value::from(Duration::from_millis(20)).as_duration(), Some(Duration::from_millis(20)));
+}
+
+#[test]
+fn test_syntax_helpers() {
+    use crate::syntax::{ escape_string, needs_quoting };
+    use std::borrow::Cow;
+
+    assert!(!needs_quoting("name"));
+    assert!(!needs_quoting("snake_case_2"));
+    for s in ["", "true", "None", "two words", "1st", "a:b", "é"] {
+        assert!(needs_quoting(s), "{}", s);
+    }
+
+    assert!(matches!(escape_string("plain"), Cow::Borrowed("plain")));
+    assert_eq!(escape_string("a \"b\"\n\\"), "a \\\"b\\\"\\n\\\\");
+
+    // the serializer writes exactly that
+    let s = "tab\tquote\" é";
+    assert_eq!(to_string(&s).unwrap(), format!("\"{}\"", escape_string(s)));
+    assert_eq!(from_str::<String>(&format!("\"{}\"", escape_string(s))), Ok(String::from(s)));
+}
+
+#[test]
+fn test_escape() {
+    let value = (String::from("日本語 \"q\" it's\t\0\u{7f}"), 'é', '\'', '\n');
+    assert_eq!(to_string(&value).unwrap(), "(\"日本語 \\\"q\\\" it's\\t\\0\\u{7f}\",'é','\\'','\\n')");
+
+    let ascii = to_string_pretty(&value, PrettyConfig::new().escape(Escape::Ascii)).unwrap();
+    assert_eq!(ascii, "(\"\\u{65e5}\\u{672c}\\u{8a9e} \\\"q\\\" it's\\t\\0\\u{7f}\", '\\u{e9}', '\\'', '\\n')");
+    assert!(ascii.is_ascii());
+
+    for s in [to_string(&value).unwrap(), ascii] {
+        assert_eq!(from_str::<(String, char, char, char)>(&s), Ok(value.clone()));
+    }
+}
+
+#[test]
+fn test_escape_strings() {
+    let config = PrettyConfig::new();
+    assert!(config.escape == Escape::Minimal);
+    assert_eq!(to_string_pretty(&"日本語", config.clone().escape_strings(true)).unwrap(), "\"日本語\"");
+    assert_eq!(
+        to_string_pretty(&"日本語\n", config.escape_strings(false)).unwrap(),
+        "\"\\u{65e5}\\u{672c}\\u{8a9e}\\n\""
+    );
+}
+
+#[test]
+fn test_emitter() {
+    #[derive(Serialize)]
+    struct Node(u8, String);
+
+    #[derive(Serialize)]
+    struct Scene {
+        name: String,
+        nodes: Vec<Node>,
+        empty: Vec<u8>,
+        tags: BTreeMap<String, (u8, u8)>,
+    }
+
+    let scene = Scene {
+        name: String::from("main"),
+        nodes: vec![Node(1, String::from("a")), Node(2, String::from("b"))],
+        empty: vec![],
+        tags: [(String::from("x"), (1, 2))].into_iter().collect(),
+    };
+
+    let emit = |config: Option<PrettyConfig>| -> Result<String> {
+        let mut emitter = Emitter::new(Vec::new(), config)?;
+        emitter.begin_struct("Scene")?;
+        emitter.field("name")?;
+        emitter.value("main")?;
+        emitter.field("nodes")?;
+        emitter.begin_seq()?;
+        for (i, name) in [(1u8, "a"), (2, "b")] {
+            emitter.begin_tuple()?;
+            emitter.value(&i)?;
+            emitter.value(name)?;
+            emitter.end()?;
+        }
+        emitter.end()?;
+        emitter.field("empty")?;
+        emitter.begin_seq()?;
+        emitter.end()?;
+        emitter.field("tags")?;
+        emitter.begin_map()?;
+        emitter.key("x")?;
+        emitter.value(&(1, 2))?;
+        emitter.end()?;
+        emitter.end()?;
+
+        Ok(String::from_utf8(emitter.finish()?).unwrap())
+    };
+
+    // the same output as serializing, named tuple structs aside
+    let configs = [
+        None,
+        Some(PrettyConfig::new()),
+        Some(PrettyConfig::new().depth_limit(1).compact_arrays(true)),
+        Some(PrettyConfig::new().compact_structs(true).compact_maps(true)),
+        Some(PrettyConfig::new().new_line(String::from("\r\n")).indentor(String::from("\t"))),
+    ];
+    for config in configs {
+        let expected = match config {
+            Some(ref config) => to_string_pretty(&scene, config.clone()).unwrap(),
+            None => to_string(&scene).unwrap(),
+        };
+        assert_eq!(emit(config).unwrap(), expected);
+    }
+
+    let mut emitter = Emitter::new(Vec::new(), None).unwrap();
+    emitter.begin_struct("Scene").unwrap();
+    assert!(matches!(emitter.value(&1), Err(Error::UnexpectedEvent(_))));
+    assert!(matches!(emitter.key(&1), Err(Error::UnexpectedEvent(_))));
+    emitter.field("a").unwrap();
+    assert!(matches!(emitter.end(), Err(Error::UnexpectedEvent(_))));
+    emitter.value(&1).unwrap();
+    emitter.end().unwrap();
+    assert!(matches!(emitter.end(), Err(Error::UnexpectedEvent(_))));
+    assert!(matches!(emitter.value(&1), Err(Error::UnexpectedEvent(_))));
+    assert_eq!(emitter.finish().map(String::from_utf8), Ok(Ok(String::from("{a:1}"))));
+}
+
+#[test]
+fn test_spacing() {
+    #[derive(Debug, PartialEq, Serialize, serde::Deserialize)]
+    struct Entry {
+        name: String,
+        tags: Vec<u8>,
+        counts: BTreeMap<String, u8>,
+    }
+
+    let entry = Entry {
+        name: String::from("a"),
+        tags: vec![1, 2],
+        counts: BTreeMap::from([(String::from("x"), 1), (String::from("y"), 2)]),
+    };
+    let base = PrettyConfig::new().depth_limit(0);
+    assert_eq!(
+        to_string_pretty(&entry, base.clone()).unwrap(),
+        "{name: \"a\", tags: [1, 2], counts: {\"x\": 1, \"y\": 2}}",
+    );
+
+    let styles = [
+        ("", "", "", "{name:\"a\",tags:[1,2],counts:{\"x\":1,\"y\":2}}"),
+        (" ", " ", " ", "{name : \"a\", tags : [1, 2], counts : {\"x\" : 1, \"y\" : 2}}"),
+    ];
+    for (before, after, comma, expected) in styles {
+        let config = base
+            .clone()
+            .space_before_colon(String::from(before))
+            .space_after_colon(String::from(after))
+            .space_after_comma(String::from(comma));
+        let s = to_string_pretty(&entry, config).unwrap();
+        assert_eq!(s, expected);
+        assert_eq!(from_str::<Entry>(&s).as_ref(), Ok(&entry));
+    }
+
+    let config = base.clone().cavetta_keys(true);
+    let s = to_string_pretty(&entry.counts, config).unwrap();
+    assert_eq!(s, "{<\"x\"> 1, <\"y\"> 2}");
+    assert_eq!(from_str::<BTreeMap<String, u8>>(&s), Ok(entry.counts.clone()));
+
+    let config = base.cavetta_keys(true).cavetta_spacing(Spacing::new(" ", "  "));
+    let s = to_string_pretty(&entry.counts, config).unwrap();
+    assert_eq!(s, "{< \"x\" >  1, < \"y\" >  2}");
+    assert_eq!(from_str::<BTreeMap<String, u8>>(&s), Ok(entry.counts.clone()));
+}
+
+#[test]
+fn test_format_str() {
+    use crate::fmt::format_str;
+
+    let source = "Window{title:\"main\",size:(800,600),kind:Tiled(2),keys:{<ctrl_q>\"quit\"}}";
+    let config = PrettyConfig::new().indentor(String::from("  "));
+    let formatted = format_str(source, &config).unwrap();
+    assert_eq!(
+        formatted,
+        "Window{\n  title: \"main\",\n  size: [\n    800,\n    600,\n  ],\n  kind: Tiled(2),\n  keys: {\n    \"ctrl_q\": \"quit\",\n  },\n}"
+    );
+    assert_eq!(format_str(&formatted, &config).unwrap(), formatted);
+    assert_eq!(
+        format_str(source, &PrettyConfig::new().depth_limit(0)).unwrap(),
+        "Window{title: \"main\", size: [800, 600], kind: Tiled(2), keys: {\"ctrl_q\": \"quit\"}}"
+    );
+
+    let error = format_str("{ a: [1, }", &config).unwrap_err();
+    assert_eq!(error.position.col, 10);
+
+    // the data reads back the same whatever the config says
+    let lossy = PrettyConfig::new().decimal_floats(false).float_precision(0).bytes_format(BytesFormat::Base64).explicit_some(false);
+    for source in ["1.0", "-0.0", "b\"ab\"", "Some(1)", "{ a: [1.5, Some(None)], b: S { c: None } }"] {
+        let formatted = format_str(source, &lossy).unwrap();
+        assert_eq!(from_str::<crate::value::Value>(&formatted), from_str::<crate::value::Value>(source), "{}", formatted);
+    }
+    assert_eq!(format_str("-0.0", &lossy).unwrap(), "-0.0");
+}
+
+#[test]
+fn test_schema_template() {
+    use crate::schema::{ annotations, template };
+
+    #[derive(Debug, Default, PartialEq, Serialize, serde::Deserialize)]
+    struct Config {
+        name: String,
+        window: Window,
+        theme: Option<String>,
+        plugins: Vec<String>,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, serde::Deserialize)]
+    struct Window {
+        size: (u32, u32),
+        fullscreen: bool,
+    }
+
+    impl Default for Window {
+        fn default() -> Self {
+            Window { size: (800, 600), fullscreen: false }
+        }
+    }
+
+    let s = template::<Config>(PrettyConfig::new()).unwrap();
+    assert_eq!(
+        s,
+        "{
+    name: \"\", # string
+    window: {
+        size: (800, 600), # tuple
+        fullscreen: false, # bool
+    }, # Window
+    theme: None, # optional
+    plugins: [], # list
+}"
+    );
+    assert_eq!(from_str::<Config>(&s), Ok(Config::default()));
+
+    let comments = annotations(&Some(vec![1u8])).unwrap();
+    let types = comments.iter().map(|(_, c)| c.trailing.as_deref().unwrap_or_default()).collect::<Vec<_>>();
+    assert_eq!(types, vec![" u8"]);
+}
+
+#[test]
+fn test_ron_compat() {
+    use crate::options::{ Compat, Options };
+    use crate::value::Value;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Config {
+        name: Option<String>,
+        size: (u32, u32),
+        mode: Mode,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Mode {
+        Tiled { gap: u8 },
+    }
+
+    let config = Config { name: Some(String::from("main")), size: (1, 2), mode: Mode::Tiled { gap: 4 } };
+    let mut output = Vec::new();
+    let mut serializer = Serializer::new(&mut output, None).unwrap().compat(Compat::Ron);
+    config.serialize(&mut serializer).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert_eq!(output, "(name:Some(\"main\"),size:(1,2),mode:Tiled(gap:4))");
+
+    let ron = Options::default().compat(Compat::Ron);
+    assert_eq!(ron.from_str::<Config>(&output), Ok(config));
+
+    let value: Value = ron.from_str("Point(x: 1, y: 2)").unwrap();
+    let mut output = Vec::new();
+    let mut serializer = Serializer::new(&mut output, None).unwrap().compat(Compat::Ron);
+    value.serialize(&mut serializer).unwrap();
+    assert_eq!(String::from_utf8(output).unwrap(), "Point(x:1,y:2)");
+
+    // unit types, which RON writes as `()`, read back both ways
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Marker;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Units {
+        u: (),
+        us: Marker,
+        some: Option<()>,
+        none: Option<Marker>,
+        list: Vec<()>,
+    }
+
+    let units = Units { u: (), us: Marker, some: Some(()), none: None, list: vec![(), ()] };
+    let mut output = Vec::new();
+    let mut serializer = Serializer::new(&mut output, None).unwrap().compat(Compat::Ron);
+    units.serialize(&mut serializer).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert_eq!(ron.from_str::<Units>(&output), Ok(Units { u: (), us: Marker, some: Some(()), none: None, list: vec![(), ()] }));
+    assert_eq!(ron::from_str::<Units>(&output), Ok(Units { u: (), us: Marker, some: Some(()), none: None, list: vec![(), ()] }));
+    let written = ron::to_string(&units).unwrap();
+    assert_eq!(ron.from_str::<Units>(&written), Ok(units));
+}
+
+#[test]
+fn test_json_compatible() {
+    use crate::value::Value;
+
+    #[derive(Serialize)]
+    struct Window {
+        title: String,
+        size: (u32, u32),
+        icon: Option<char>,
+        ratio: f64,
+        keys: BTreeMap<u8, Action>,
+        layout: Layout,
+        data: Vec<u8>,
+    }
+
+    #[derive(Serialize)]
+    enum Action {
+        Quit,
+        Open(String),
+        Move(i32, i32),
+    }
+
+    #[derive(Serialize)]
+    enum Layout {
+        Tiled { gap: u8 },
+    }
+
+    let window = Window {
+        title: String::from("it's \"main\"\u{1b}"),
+        size: (800, 600),
+        icon: None,
+        ratio: f64::NAN,
+        keys: BTreeMap::from([(1, Action::Quit), (2, Action::Open(String::from("a"))), (3, Action::Move(-1, 1))]),
+        layout: Layout::Tiled { gap: 4 },
+        data: vec![1, 2],
+    };
+
+    let config = PrettyConfig::new().struct_names(true).enumerate_arrays(true).json_compatible(true);
+    let output = to_string_pretty(&window, config).unwrap();
+    assert!(!output.contains(",\n}") && !output.contains(",\n]"));
+    let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(json, serde_json::json!({
+        "title": "it's \"main\"\u{1b}",
+        "size": [800, 600],
+        "icon": null,
+        "ratio": null,
+        "keys": { "1": "Quit", "2": { "Open": "a" }, "3": { "Move": [-1, 1] } },
+        "layout": { "Tiled": { "gap": 4 } },
+        "data": [1, 2],
+    }));
+
+    let value: Value = from_str("Point { x: 'a', tags: [A, B(1), C(2, 3)], unit: () }").unwrap();
+    let output = to_string_pretty(&value, PrettyConfig::new().json_compatible(true)).unwrap();
+    let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(json, serde_json::json!({ "x": "a", "tags": ["A", { "B": 1 }, { "C": [2, 3] }], "unit": null }));
+}
+
+#[test]
+fn test_quote_strings() {
+    let words = vec!["main", "ctrl-q", "themes/dark", "1.0.2", "two words", "None", "", "#tag", "a\"b"];
+
+    let config = PrettyConfig::new().compact_arrays(true);
+    let always = to_string_pretty(&words, config.clone()).unwrap();
+    assert_eq!(always, r##"["main", "ctrl-q", "themes/dark", "1.0.2", "two words", "None", "", "#tag", "a\"b"]"##);
+
+    let required = to_string_pretty(&words, config.clone().quote_strings(QuoteStrings::WhenRequired)).unwrap();
+    assert_eq!(required, r##"[main, ctrl-q, themes/dark, "1.0.2", "two words", "None", "", "#tag", "a\"b"]"##);
+    assert_eq!(from_str::<Vec<String>>(&required).unwrap(), words);
+
+    let never = to_string_pretty(&words, config.clone().quote_strings(QuoteStrings::Never)).unwrap();
+    assert_eq!(never, r##"[main, ctrl-q, themes/dark, 1.0.2, "two words", "None", "", "#tag", "a\"b"]"##);
+    assert_eq!(from_str::<Vec<String>>(&never).unwrap(), words);
+
+    let map = BTreeMap::from([("theme", "dark")]);
+    let config = PrettyConfig::new().quote_strings(QuoteStrings::WhenRequired);
+    assert_eq!(to_string_pretty(&map, config.clone()).unwrap(), "{\n    theme: dark,\n}");
+    assert_eq!(to_string_pretty(&map, config.json_compatible(true)).unwrap(), "{\n    \"theme\": \"dark\"\n}");
+}
+
+#[test]
+fn test_value_formatter() {
+    #[derive(Serialize)]
+    struct Light {
+        colour: u32,
+        level: u8,
+        intensity: f64,
+        name: String,
+        mask: serde_bytes::ByteBuf,
+    }
+
+    struct Custom;
+
+    impl ValueFormatter for Custom {
+        fn write_uint(&mut self, writer: &mut dyn io::Write, value: u128, literal: &str) -> io::Result<()> {
+            match value > 0xffff {
+                true => write!(writer, "0x{:06x}", value),
+                false => writer.write_all(literal.as_bytes()),
+            }
+        }
+
+        fn write_f64(&mut self, writer: &mut dyn io::Write, value: f64, _: &str) -> io::Result<()> {
+            write!(writer, "{:.2}", value)
+        }
+
+        fn write_bytes(&mut self, writer: &mut dyn io::Write, _: &[u8], literal: &str) -> io::Result<()> {
+            write!(writer, "{} # mask", literal)
+        }
+    }
+
+    let light = Light {
+        colour: 0xff8800,
+        level: 3,
+        intensity: 1.0 / 3.0,
+        name: String::from("key \"light\""),
+        mask: serde_bytes::ByteBuf::from(vec![1, 2]),
+    };
+
+    let mut output = Vec::new();
+    let config = PrettyConfig::new().bytes_format(BytesFormat::Hex);
+    light.serialize(&mut Serializer::new(&mut output, Some(config.clone())).unwrap().formatter(Custom)).unwrap();
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "{\n    colour: 0xff8800,\n    level: 3,\n    intensity: 0.33,\n    name: \"key \\\"light\\\"\",\n    mask: x\"0102\" # mask,\n}",
+    );
+
+    let mut output = Vec::new();
+    light.serialize(&mut Serializer::new(&mut output, Some(config.clone())).unwrap().formatter(DefaultFormatter)).unwrap();
+    assert_eq!(String::from_utf8(output).unwrap(), to_string_pretty(&light, config).unwrap());
+}
+
+#[test]
+fn test_float_precision() {
+    let floats = (0.1 + 0.2, 1e-9, 1e20, 2.0f32);
+    assert_eq!(to_string(&floats).unwrap(), "(0.30000000000000004,1e-9,1e20,2)");
+
+    let auto = PrettyConfig::new().float_precision(3);
+    assert_eq!(to_string_pretty(&floats, auto.clone()).unwrap(), "(0.3, 1e-9, 1e20, 2)");
+    assert_eq!(to_string_pretty(&floats, auto.decimal_floats(true)).unwrap(), "(0.3, 1e-9, 1e20, 2.0)");
+
+    let fixed = PrettyConfig::new().float_style(FloatStyle::Fixed);
+    assert_eq!(to_string_pretty(&floats, fixed.clone()).unwrap(), "(0.30000000000000004, 0.000000001, 100000000000000000000, 2)");
+    assert_eq!(to_string_pretty(&floats, fixed.float_precision(2)).unwrap(), "(0.30, 0.00, 100000000000000000000.00, 2.00)");
+
+    let scientific = PrettyConfig::new().float_style(FloatStyle::Scientific).float_precision(1);
+    let s = to_string_pretty(&(1500.0, -0.25, f64::NAN), scientific).unwrap();
+    assert_eq!(s, "(1.5e3, -2.5e-1, NaN)");
+    assert_eq!(from_str::<(f64, f64)>("(1.5e3, -2.5e-1)"), Ok((1500.0, -0.25)));
+
+    // Rounding `MAX` up would overflow, the shortest text is kept instead.
+    let scientific = PrettyConfig::new().float_style(FloatStyle::Scientific);
+    for config in [PrettyConfig::new().float_precision(1), scientific.float_precision(0)] {
+        let s = to_string_pretty(&(f64::MAX, f32::MAX), config).unwrap();
+        let (double, single) = from_str::<(f64, f32)>(&s).unwrap();
+        assert!(double == f64::MAX && single.is_finite(), "{}", s);
+    }
+    let s = to_string_pretty(&f64::MAX, PrettyConfig::new().float_precision(1)).unwrap();
+    assert_eq!(s, "1.7976931348623157e308");
+}
+
+#[test]
+fn test_float_round_trip() {
+    let doubles = [0.1, 1.0 / 3.0, 5e-324, f64::MIN_POSITIVE, f64::MAX, -1.2345678901234568e17, 1e15, 1e-5];
+    for v in doubles {
+        let s = to_string(&v).unwrap();
+        assert_eq!(from_str::<f64>(&s).map(f64::to_bits), Ok(v.to_bits()), "{}", s);
+    }
+
+    let singles = [0.1f32, 16777217.0, f32::MIN_POSITIVE, f32::MAX, 3.4e-39];
+    for v in singles {
+        let s = to_string(&v).unwrap();
+        assert_eq!(from_str::<f32>(&s).map(f32::to_bits), Ok(v.to_bits()), "{}", s);
+    }
+
+    assert_eq!(to_string(&(1e15, 1e16, 1e-5, 1e-6, 0.1f32)).unwrap(), "(1000000000000000,1e16,0.00001,1e-6,0.1)");
+    let config = PrettyConfig::new().decimal_floats(true);
+    assert_eq!(to_string_pretty(&(1e20, 3.0, -0.0), config).unwrap(), "(1e20, 3.0, -0.0)");
+}
+
+#[test]
+fn test_integers() {
+    let ints = (i8::MIN, u16::MAX, i32::MIN, u64::MAX, i64::MIN, i128::MIN, u128::MAX, 0u8);
+    let expected = format!("({},{},{},{},{},{},{},0)", i8::MIN, u16::MAX, i32::MIN, u64::MAX, i64::MIN, i128::MIN, u128::MAX);
+    assert_eq!(to_string(&ints).unwrap(), expected);
+    assert_eq!(to_string(&vec![-1i64; 3]).unwrap(), "[-1,-1,-1]");
+}
+
+#[test]
+fn test_compact_containers() {
+    #[derive(Serialize)]
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    #[derive(Serialize)]
+    struct Shape {
+        name: &'static str,
+        points: Vec<Point>,
+        tags: BTreeMap<&'static str, u8>,
+    }
+
+    let shape = Shape {
+        name: "triangle",
+        points: vec![Point { x: 0, y: 0 }, Point { x: 4, y: 0 }, Point { x: 0, y: 3 }],
+        tags: BTreeMap::from([("layer", 2), ("order", 1)]),
+    };
+
+    let structs = PrettyConfig::new().compact_structs(true);
+    assert_eq!(
+        to_string_pretty(&shape, structs).unwrap(),
+        "{name: \"triangle\", points: [\n    {x: 0, y: 0},\n    {x: 4, y: 0},\n    {x: 0, y: 3},\n], tags: {\n    \"layer\": 2,\n    \"order\": 1,\n}}",
+    );
+    let maps = PrettyConfig::new().compact_maps(true);
+    assert!(to_string_pretty(&shape, maps).unwrap().ends_with("    tags: {\"layer\": 2, \"order\": 1},\n}"));
+
+    let short = PrettyConfig::new().compact_below_length(25);
+    assert_eq!(
+        to_string_pretty(&shape, short).unwrap(),
+        "{\n    name: \"triangle\",\n    points: [\n        {x: 0, y: 0},\n        {x: 4, y: 0},\n        {x: 0, y: 3},\n    ],\n    tags: {\"layer\": 2, \"order\": 1},\n}",
+    );
+    let long = PrettyConfig::new().compact_below_length(60);
+    assert_eq!(
+        to_string_pretty(&shape, long).unwrap(),
+        "{\n    name: \"triangle\",\n    points: [{x: 0, y: 0}, {x: 4, y: 0}, {x: 0, y: 3}],\n    tags: {\"layer\": 2, \"order\": 1},\n}",
+    );
+}
+
+#[test]
+fn test_max_width() {
+    #[derive(Serialize)]
+    struct Mesh {
+        name: &'static str,
+        vertices: Vec<(f32, f32)>,
+        indices: Vec<u16>,
+    }
+
+    let mesh = Mesh { name: "quad", vertices: vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)], indices: vec![0, 1, 2, 2, 3, 0] };
+
+    let s = to_string_pretty(&mesh, PrettyConfig::new().max_width(40)).unwrap();
+    assert_eq!(s, "{\n    name: \"quad\",\n    vertices: [\n        (0, 0),\n        (1, 0),\n        (1, 1),\n        (0, 1),\n    ],\n    indices: [0, 1, 2, 2, 3, 0],\n}");
+    assert!(s.lines().all(|line| line.len() <= 40));
+
+    let s = to_string_pretty(&mesh, PrettyConfig::new().max_width(50)).unwrap();
+    assert_eq!(s, "{\n    name: \"quad\",\n    vertices: [(0, 0), (1, 0), (1, 1), (0, 1)],\n    indices: [0, 1, 2, 2, 3, 0],\n}");
+
+    let s = to_string_pretty(&mesh, PrettyConfig::new().max_width(100)).unwrap();
+    assert_eq!(s, "{name: \"quad\", vertices: [(0, 0), (1, 0), (1, 1), (0, 1)], indices: [0, 1, 2, 2, 3, 0]}");
+}
+
+#[test]
+fn test_level_indentors() {
+    let nested = BTreeMap::from([("a", vec![BTreeMap::from([("b", 1)])])]);
+
+    let config = PrettyConfig::new().level_indentors(vec![String::from("  ")]);
+    assert_eq!(to_string_pretty(&nested, config).unwrap(), "{\n  \"a\": [\n      {\n          \"b\": 1,\n      },\n  ],\n}");
+
+    let config = PrettyConfig::new().indent_with(|level| "\t".repeat(level.min(2)));
+    assert_eq!(to_string_pretty(&nested, config).unwrap(), "{\n\t\"a\": [\n\t\t\t{\n\t\t\t\t\t\"b\": 1,\n\t\t\t},\n\t],\n}");
+
+    let error = to_string_pretty(&nested, PrettyConfig::new().indentor(String::from("--"))).unwrap_err();
+    assert_eq!(error, Error::InvalidConfig(String::from("`indentor` must be whitespace, not \"--\"")));
+    assert!(to_string_pretty(&nested, PrettyConfig::new().new_line(String::from(";"))).is_err());
+    assert!(to_string_pretty(&nested, PrettyConfig::new().indent_with(|_| String::from("."))).is_err());
+    assert!(to_string_pretty(&1, PrettyConfig::new().indentor(String::from("\t"))).is_ok());
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_space_after_colon_and_comma() {
+    let map = BTreeMap::from([("a", (1, 2)), ("b", (3, 4))]);
+    let config = PrettyConfig::new().depth_limit(0).space_after_colon(String::new());
+    assert_eq!(to_string_pretty(&map, config.clone()).unwrap(), "{\"a\":(1, 2), \"b\":(3, 4)}");
+    assert_eq!(to_string_pretty(&map, config.space_after_comma(String::from("  "))).unwrap(), "{\"a\":(1,  2),  \"b\":(3,  4)}");
+
+    // the old field and builder still set both, the new fields win where set
+    let mut config = PrettyConfig::new().depth_limit(0);
+    config.separator = String::new();
+    assert_eq!(to_string_pretty(&map, config.clone()).unwrap(), "{\"a\":(1,2),\"b\":(3,4)}");
+    assert_eq!(to_string_pretty(&map, config.space_after_comma(String::from(" "))).unwrap(), "{\"a\":(1, 2), \"b\":(3, 4)}");
+    let config = PrettyConfig::new().depth_limit(0).separator(String::from("\t"));
+    assert_eq!(to_string_pretty(&map, config.clone()).unwrap(), "{\"a\":\t(1,\t2),\t\"b\":\t(3,\t4)}");
+    let config = config.space_after_colon(String::from(" "));
+    assert_eq!(to_string_pretty(&map, config).unwrap(), "{\"a\": (1,\t2),\t\"b\": (3,\t4)}");
+}
+
+#[test]
+fn test_skip_defaults() {
+    #[derive(Debug, Default, PartialEq, Serialize, serde::Deserialize)]
+    #[serde(default)]
+    struct Window {
+        title: String,
+        size: (u32, u32),
+        fullscreen: bool,
+        scale: Option<f32>,
+        margin: Option<u8>,
+        tags: Vec<String>,
+        inner: Inner,
+    }
+
+    #[derive(Debug, Default, PartialEq, Serialize, serde::Deserialize)]
+    #[serde(default)]
+    struct Inner {
+        depth: u8,
+        name: String,
+    }
+
+    let window = Window {
+        title: String::from("main"),
+        margin: Some(0),
+        inner: Inner { depth: 2, name: String::new() },
+        ..Window::default()
+    };
+
+    let config = PrettyConfig::new().skip_defaults(true).compact_arrays(true);
+    let s = to_string_pretty(&window, config.clone()).unwrap();
+    assert_eq!(
+        s,
+        "{\n    title: \"main\",\n    size: (0, 0),\n    fullscreen: false,\n    margin: 0,\n    tags: [],\n    inner: {\n        depth: 2,\n        name: \"\",\n    },\n}"
+    );
+    assert_eq!(from_str::<Window>(&s), Ok(window));
+
+    // only `None` is left out, other values may not be the field's default
+    #[derive(Debug, PartialEq, Serialize, serde::Deserialize)]
+    struct Server {
+        #[serde(default = "port")]
+        port: u16,
+        #[serde(default)]
+        ratio: f32,
+    }
+
+    fn port() -> u16 {
+        8080
+    }
+
+    let server = Server { port: 0, ratio: 0.0 };
+    let s = to_string_pretty(&server, config).unwrap();
+    assert_eq!(from_str::<Server>(&s), Ok(server));
+
+    let reference = Window { title: String::from("main"), size: (800, 600), ..Window::default() };
+    let changed = Window { size: (1024, 600), fullscreen: true, inner: Inner { depth: 0, name: String::from("x") }, ..Window::default() };
+    let s = to_string(&SerializeDiff::new(&changed, &reference)).unwrap();
+    assert_eq!(s, "{title:\"\",size:(1024,600),fullscreen:true,inner:{name:\"x\"}}");
+    assert_eq!(from_str::<Window>(&s).map(|window| window.size), Ok((1024, 600)));
+    assert!(to_string(&SerializeDiff::new(&reference, &reference)).unwrap() == "{}");
+}
+
+#[test]
+fn test_serialize_options() {
+    #[derive(Serialize)]
+    struct Scene {
+        name: &'static str,
+        origin: Point,
+        marker: Marker,
+    }
+
+    #[derive(Serialize)]
+    struct Point(i32, i32);
+
+    #[derive(Serialize)]
+    struct Marker;
+
+    let scene = Scene { name: "intro", origin: Point(1, 2), marker: Marker };
+    assert_eq!(to_string(&scene).unwrap(), "{name:\"intro\",origin:(1,2),marker:()}");
+
+    let options = SerializeOptions::new().struct_names(true);
+    assert_eq!(options.to_string(&scene).unwrap(), "Scene{name:\"intro\",origin:Point(1,2),marker:Marker}");
+    assert_eq!(
+        options.to_string_pretty(&scene, PrettyConfig::new().depth_limit(0)).unwrap(),
+        "Scene{name: \"intro\", origin: Point(1, 2), marker: Marker}",
+    );
+
+    let options = options.compat(Compat::Ron);
+    assert_eq!(options.to_string(&scene).unwrap(), "Scene(name:\"intro\",origin:Point(1,2),marker:Marker)");
+}
+
+#[test]
+fn test_struct_names_filter() {
+    #[derive(Serialize)]
+    struct Scene {
+        root: Prefab,
+        origin: Point,
+    }
+
+    #[derive(Serialize)]
+    struct Prefab {
+        size: Point,
+    }
+
+    #[derive(Serialize)]
+    struct Point(i32, i32);
+
+    let scene = Scene { root: Prefab { size: Point(4, 4) }, origin: Point(0, 0) };
+    let config = PrettyConfig::new()
+        .depth_limit(0)
+        .struct_names_filter(|name| matches!(name, "Scene" | "Prefab"));
+    assert_eq!(
+        to_string_pretty(&scene, config.clone()).unwrap(),
+        "Scene{root: Prefab{size: (4, 4)}, origin: (0, 0)}",
+    );
+
+    // the filter replaces `struct_names`
+    let config = config.struct_names(true).struct_names_filter(|name| name == "Point");
+    assert_eq!(to_string_pretty(&scene, config).unwrap(), "{root: {size: Point(4, 4)}, origin: Point(0, 0)}");
+}
+
+#[test]
+fn test_enumerate_format() {
+    let value = vec![vec![1, 2], vec![3]];
+    let config = PrettyConfig::new().depth_limit(1).enumerate_arrays(true);
+    assert_eq!(to_string_pretty(&value, config.clone()).unwrap(), "[\n    /*[0]*/ [1, 2],\n    /*[1]*/ [3],\n]");
+
+    let leading = config.clone().enumerate_format(String::from("/* {index}: */ "));
+    assert_eq!(to_string_pretty(&value, leading).unwrap(), "[\n    /* 0: */ [1, 2],\n    /* 1: */ [3],\n]");
+
+    let trailing = config.enumerate_format(String::from("# {index}")).enumerate_trailing(true);
+    let s = to_string_pretty(&value, trailing).unwrap();
+    assert_eq!(s, "[\n    [1, 2], # 0\n    [3], # 1\n]");
+    assert_eq!(from_str::<Vec<Vec<i32>>>(&s), Ok(value));
+}
+
+#[test]
+fn test_depth_limit_error() {
+    let value = vec![vec![vec![1]], vec![]];
+    let config = PrettyConfig::new().depth_limit(2).depth_limit_error(true);
+    assert_eq!(to_string_pretty(&value, config.clone()), Err(Error::DepthLimitExceeded(2)));
+    assert_eq!(to_string_pretty(&value, config.clone().compact_arrays(true)), Err(Error::DepthLimitExceeded(2)));
+    assert_eq!(to_string_pretty(&value, config.clone().depth_limit(3)).unwrap(), "[\n    [\n        [\n            1,\n        ],\n    ],\n    [],\n]");
+
+    // written on one line without the error
+    assert_eq!(to_string_pretty(&value, config.depth_limit_error(false)).unwrap(), "[\n    [\n        [1],\n    ],\n    [],\n]");
+}
+
+#[test]
+fn test_detect_cycles() {
+    use std::{ cell::RefCell, rc::Rc };
+
+    #[derive(Serialize)]
+    struct Node {
+        name: &'static str,
+        next: Vec<Next>,
+    }
+
+    struct Next(Rc<RefCell<Node>>);
+
+    impl Serialize for Next {
+        fn serialize<S: ser::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+            SerializeShared::new(&self.0).serialize(serializer)
+        }
+    }
+
+    let leaf = Rc::new(RefCell::new(Node { name: "leaf", next: Vec::new() }));
+    let root = Node { name: "root", next: vec![Next(leaf.clone()), Next(leaf.clone())] };
+    let config = PrettyConfig::new().depth_limit(0).detect_cycles(true);
+    assert_eq!(
+        to_string_pretty(&root, config.clone()).unwrap(),
+        "{name: \"root\", next: [{name: \"leaf\", next: []}, {name: \"leaf\", next: []}]}",
+    );
+
+    leaf.borrow_mut().next.push(Next(leaf.clone()));
+    assert_eq!(to_string_pretty(&root, config), Err(Error::CycleDetected));
+
+    leaf.borrow_mut().next.clear();
+}
+
+#[test]
+fn test_presets() {
+    #[derive(Serialize)]
+    struct Item {
+        size: (u8, u8),
+        weight: f32,
+    }
+
+    let item = Item { size: (1, 2), weight: 3.0 };
+    assert_eq!(to_string_pretty(&item, PrettyConfig::compact()).unwrap(), "{size: (1, 2), weight: 3}");
+    assert_eq!(
+        to_string_pretty(&item, PrettyConfig::expanded()).unwrap(),
+        "{\n    size: (\n        1,\n        2,\n    ),\n    weight: 3,\n}",
+    );
+    assert_eq!(
+        to_string_pretty(&item, PrettyConfig::ron_like()).unwrap(),
+        "Item{\n    size: (1, 2),\n    weight: 3.0,\n}",
+    );
+
+    let config = PrettyConfig::new().enumerate_arrays(true).compact_arrays(true);
+    assert!(matches!(config.validate(), Err(Error::InvalidConfig(_))));
+    assert!(to_string_pretty(&item, config).is_err());
+    assert!(PrettyConfig::new().space_after_colon(String::from("=")).validate().is_err());
+}
+
+#[test]
+fn test_pretty_config_from_str() {
+    let (config, warnings) = PrettyConfig::from_str(
+        "# the style of this project
+        {
+            indentor: \"  \",
+            struct_name: true,
+            separator: \" \",
+            max_width: 80,
+        }",
+    )
+    .unwrap();
+
+    assert_eq!(config.indentor, "  ");
+    assert_eq!(config.max_width, Some(80));
+    assert!(!config.struct_names);
+
+    assert_eq!(warnings.len(), 2);
+    assert!(matches!(warnings[0].code, Error::NoSuchStructField { .. }));
+    assert_eq!(warnings[0].code.suggestion(), Some("struct_names"));
+    assert_eq!(warnings[0].position.line, 4);
+    assert_eq!(
+        warnings[1].code,
+        Error::RenamedOption {
+            found: String::from("separator"),
+            replacement: "`space_after_colon` and `space_after_comma`",
+        },
+    );
+
+    assert!(PrettyConfig::from_str("{ depth_limit: \"two\" }").is_err());
+}
+
+#[test]
+fn test_unit_parens() {
+    #[derive(Debug, PartialEq, Serialize, serde::Deserialize)]
+    struct Marker;
+
+    #[derive(Debug, PartialEq, Serialize, serde::Deserialize)]
+    enum State {
+        Idle,
+        Busy(u8),
+    }
+
+    let value = (Marker, State::Idle, State::Busy(1), Some(State::Idle));
+    let config = PrettyConfig::new().depth_limit(0).struct_names(true);
+    assert_eq!(to_string_pretty(&value, config.clone()).unwrap(), "(Marker, Idle, Busy(1), Idle)");
+
+    let s = to_string_pretty(&value, config.unit_parens(true)).unwrap();
+    assert_eq!(s, "(Marker(), Idle(), Busy(1), Idle())");
+    assert_eq!(from_str(&s), Ok(value));
+}
+
+#[test]
+fn test_explicit_some() {
+    #[derive(Debug, PartialEq, Serialize, serde::Deserialize)]
+    struct Something {
+        level: Option<Option<u8>>,
+    }
+
+    let value = vec![Some(Something { level: Some(None) }), None, Some(Something { level: Some(Some(2)) })];
+    let config = PrettyConfig::new().depth_limit(0).struct_names(true);
+    assert_eq!(
+        to_string_pretty(&value, config.clone()).unwrap(),
+        "[Something{level: None}, None, Something{level: 2}]",
+    );
+
+    let s = to_string_pretty(&value, config.explicit_some(true)).unwrap();
+    assert_eq!(s, "[Some(Something{level: Some(None)}), None, Some(Something{level: Some(Some(2))})]");
+    assert_eq!(from_str(&s), Ok(value));
+    assert_eq!(from_str("[Something{level: Some(2)}]"), Ok(vec![Some(Something { level: Some(Some(2)) })]));
+}
+
+#[test]
+fn test_unwrap_newtypes() {
+    #[derive(Debug, PartialEq, Serialize, serde::Deserialize)]
+    struct Handle(Id);
+
+    #[derive(Debug, PartialEq, Serialize, serde::Deserialize)]
+    struct Id(u32);
+
+    #[derive(Debug, PartialEq, Serialize, serde::Deserialize)]
+    struct Scene {
+        root: Handle,
+        parent: Option<Handle>,
+    }
+
+    let scene = Scene { root: Handle(Id(5)), parent: Some(Handle(Id(2))) };
+    let config = PrettyConfig::new().depth_limit(0).unwrap_newtypes(true);
+    let s = to_string_pretty(&scene, config).unwrap();
+    assert_eq!(s, "#![enable(unwrap_newtypes)]\n{root: 5, parent: 2}");
+    assert_eq!(from_str(&s), Ok(scene));
+
+    // the layers may still be written
+    let s = "#![enable(unwrap_newtypes)]\n{root: Handle(Id(5)), parent: Handle(2)}";
+    assert_eq!(from_str(s), Ok(Scene { root: Handle(Id(5)), parent: Some(Handle(Id(2))) }));
+}
+
+#[test]
+fn test_unwrap_variant_newtypes() {
+    #[derive(Debug, PartialEq, Serialize, serde::Deserialize)]
+    struct Circle {
+        radius: f32,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, serde::Deserialize)]
+    enum Shape {
+        Circle(Circle),
+        Dot(u8),
+    }
+
+    let shapes = vec![Shape::Circle(Circle { radius: 1.5 }), Shape::Dot(2)];
+    let config = PrettyConfig::new().depth_limit(0).struct_names(true).unwrap_variant_newtypes(true);
+    let s = to_string_pretty(&shapes, config).unwrap();
+    assert_eq!(s, "#![enable(unwrap_variant_newtypes)]\n[Circle{radius: 1.5}, Dot(2)]");
+    assert_eq!(from_str(&s), Ok(shapes));
+
+    let s = "#![enable(unwrap_variant_newtypes)]\n[Circle(radius: 1.5), Circle({radius: 2.5})]";
+    assert_eq!(
+        from_str(s),
+        Ok(vec![Shape::Circle(Circle { radius: 1.5 }), Shape::Circle(Circle { radius: 2.5 })]),
+    );
+}
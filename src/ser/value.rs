@@ -1,21 +1,162 @@
-use serde::ser::{Serialize, Serializer};
+use serde::ser::{ Serialize, SerializeMap, SerializeTuple, Serializer };
 
-use crate::value::{Number, Value};
+use crate::value::{ Map, Number, Ordered, Shape, Value, FIELD_TOKEN, STRUCT_TOKEN, VARIANT_TOKEN };
+#[cfg(feature = "arbitrary_precision")]
+use crate::value::NUMBER_TOKEN;
 
 impl Serialize for Value {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where S: Serializer {
+        Shaped(self, &Shape::Unknown).serialize(serializer)
+    }
+}
+
+impl Serialize for Ordered<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where S: Serializer {
+        Shaped(self.value, &self.shape).serialize(serializer)
+    }
+}
+
+impl Serialize for Map {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where S: Serializer {
+        Entries { map: self, shape: &Shape::Unknown, fields: false }.serialize(serializer)
+    }
+}
+
+impl Serialize for Number {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where S: Serializer {
         match *self {
+            Number::Float(ref f) => serializer.serialize_f64(f.get()),
+            #[cfg(not(feature = "integer128"))]
+            Number::Integer(i) => serializer.serialize_i64(i),
+            #[cfg(feature = "integer128")]
+            Number::Integer(i) => match (i64::try_from(i), u64::try_from(i)) {
+                (Ok(i), _) => serializer.serialize_i64(i),
+                (_, Ok(u)) => serializer.serialize_u64(u),
+                _ => serializer.serialize_i128(i),
+            },
+            #[cfg(feature = "arbitrary_precision")]
+            Number::Literal(ref literal) => serializer.serialize_newtype_struct(NUMBER_TOKEN, literal),
+        }
+    }
+}
+
+// A value along with the shape of the type it stands for, if known.
+struct Shaped<'a>(&'a Value, &'a Shape);
+
+impl Serialize for Shaped<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where S: Serializer {
+        let Shaped(value, shape) = *self;
+        match *value {
             Value::Bool(b) => serializer.serialize_bool(b),
+            Value::Bytes(ref b) => serializer.serialize_bytes(b),
             Value::Char(c) => serializer.serialize_char(c),
-            Value::Map(ref m) => Serialize::serialize(m, serializer),
-            Value::Number(Number::Float(ref f)) => serializer.serialize_f64(f.get()),
-            Value::Number(Number::Integer(i)) => serializer.serialize_i64(i),
-            Value::Option(Some(ref o)) => serializer.serialize_some(o.as_ref()),
+            Value::Map(ref map) => Entries { map, shape, fields: false }.serialize(serializer),
+            Value::Number(ref n) => n.serialize(serializer),
+            Value::Option(Some(ref o)) => serializer.serialize_some(&Shaped(o, shape)),
             Value::Option(None) => serializer.serialize_none(),
             Value::String(ref s) => serializer.serialize_str(s),
-            Value::Seq(ref s) => Serialize::serialize(s, serializer),
+            Value::Seq(ref s) => {
+                let element = match shape {
+                    Shape::Seq(element) => element,
+                    _ => &Shape::Unknown,
+                };
+
+                serializer.collect_seq(s.iter().map(|v| Shaped(v, element)))
+            }
+            Value::Struct { ref name, fields: ref map } => serializer.serialize_newtype_struct(
+                STRUCT_TOKEN,
+                &(name.as_deref().unwrap_or(""), Entries { map, shape, fields: true }),
+            ),
+            Value::Variant { ref name, ref content } if content.is_unit() => {
+                serializer.serialize_newtype_struct(VARIANT_TOKEN, &(name,))
+            }
+            Value::Variant { ref name, ref content } => {
+                serializer.serialize_newtype_struct(VARIANT_TOKEN, &(name, Content(content)))
+            }
             Value::Unit => serializer.serialize_unit(),
         }
     }
 }
+
+// Map entries, those declared by a struct shape first and in declaration
+// order. Struct fields that are strings are written as identifiers.
+struct Entries<'a> {
+    map: &'a Map,
+    shape: &'a Shape,
+    fields: bool,
+}
+
+impl Entries<'_> {
+    fn position(&self, key: &Value) -> Option<usize> {
+        match (self.shape, key) {
+            (Shape::Struct(declared), Value::String(key)) => declared.iter().position(|(field, _)| field == key),
+            _ => None,
+        }
+    }
+}
+
+impl Serialize for Entries<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where S: Serializer {
+        let mut entries: Vec<_> = self.map.iter().collect();
+        if let Shape::Struct(_) = self.shape {
+            entries.sort_by_key(|(key, _)| self.position(key).unwrap_or(usize::MAX));
+        }
+
+        let mut map = serializer.serialize_map(Some(self.map.len()))?;
+        for (key, value) in entries {
+            match key {
+                Value::String(field) if self.fields => map.serialize_key(&Field(field))?,
+                key => map.serialize_key(key)?,
+            }
+
+            let shape = match (self.shape, self.position(key)) {
+                (Shape::Struct(declared), Some(index)) => &declared[index].1,
+                (Shape::Map(value), _) => value,
+                _ => &Shape::Unknown,
+            };
+            map.serialize_value(&Shaped(value, shape))?;
+        }
+
+        map.end()
+    }
+}
+
+struct Field<'a>(&'a str);
+
+impl Serialize for Field<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where S: Serializer {
+        serializer.serialize_newtype_struct(FIELD_TOKEN, self.0)
+    }
+}
+
+// Variant contents always go between parentheses, a sequence of any length
+// but one is spread over them: `Name(a, b)` but `Name([a])`.
+struct Content<'a>(&'a Value);
+
+impl Serialize for Content<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where S: Serializer {
+        match self.0 {
+            Value::Seq(seq) if seq.len() != 1 => {
+                let mut tuple = serializer.serialize_tuple(seq.len())?;
+                for element in seq {
+                    tuple.serialize_element(element)?;
+                }
+
+                tuple.end()
+            }
+            value => {
+                let mut tuple = serializer.serialize_tuple(1)?;
+                tuple.serialize_element(value)?;
+                tuple.end()
+            }
+        }
+    }
+}
@@ -0,0 +1,72 @@
+// Vectorized scans behind the `simd` feature, taking 16 bytes at a time with
+// SSE2, which every x86_64 target has. Other targets, and the tail shorter
+// than a vector, take the scalar path. Comment ends are found with `memchr`,
+// which is vectorized already.
+
+use crate::parse::is_whitespace_char;
+
+const LANES: usize = 16;
+
+// The length of the run of whitespace that `bytes` starts with.
+pub(crate) fn whitespace_len(bytes: &[u8]) -> usize {
+    let mut i = 0;
+
+    #[cfg(target_arch = "x86_64")]
+    while i + LANES <= bytes.len() {
+        // SAFETY: the `LANES` bytes from `i` are in bounds.
+        let mask = unsafe { sse2::whitespace(bytes.as_ptr().add(i)) };
+        if mask != 0xFFFF {
+            return i + mask.trailing_ones() as usize;
+        }
+        i += LANES;
+    }
+
+    i + bytes[i..].iter().take_while(|&&b| is_whitespace_char(b)).count()
+}
+
+// The index of the first `"` or `\`, and whether the bytes before it are all
+// ASCII, so that they need no UTF-8 validation.
+pub(crate) fn string_end(bytes: &[u8]) -> Option<(usize, bool)> {
+    let mut ascii = true;
+    let mut i = 0;
+
+    #[cfg(target_arch = "x86_64")]
+    while i + LANES <= bytes.len() {
+        // SAFETY: the `LANES` bytes from `i` are in bounds.
+        let (ends, non_ascii) = unsafe { sse2::string(bytes.as_ptr().add(i)) };
+        if ends != 0 {
+            let end = ends.trailing_zeros();
+            ascii &= non_ascii & ((1 << end) - 1) == 0;
+            return Some((i + end as usize, ascii));
+        }
+        ascii &= non_ascii == 0;
+        i += LANES;
+    }
+
+    let end = i + memchr::memchr2(b'"', b'\\', &bytes[i..])?;
+    Some((end, ascii && bytes[i..end].is_ascii()))
+}
+
+#[cfg(target_arch = "x86_64")]
+mod sse2 {
+    use std::arch::x86_64::*;
+
+    // Bit `n` is set when byte `n` from `ptr` is whitespace.
+    pub(super) unsafe fn whitespace(ptr: *const u8) -> u32 {
+        let chunk = _mm_loadu_si128(ptr.cast());
+        let is = |b: u8| _mm_cmpeq_epi8(chunk, _mm_set1_epi8(b as i8));
+        let ws = _mm_or_si128(_mm_or_si128(is(b' '), is(b'\n')), _mm_or_si128(is(b'\t'), is(b'\r')));
+
+        _mm_movemask_epi8(ws) as u32
+    }
+
+    // Bits of the `"` and `\` bytes from `ptr`, and of the non-ASCII ones.
+    pub(super) unsafe fn string(ptr: *const u8) -> (u32, u32) {
+        let chunk = _mm_loadu_si128(ptr.cast());
+        let is = |b: u8| _mm_cmpeq_epi8(chunk, _mm_set1_epi8(b as i8));
+        let ends = _mm_or_si128(is(b'"'), is(b'\\'));
+
+        // the top bit of each byte, which only non-ASCII bytes have
+        (_mm_movemask_epi8(ends) as u32, _mm_movemask_epi8(chunk) as u32)
+    }
+}
@@ -0,0 +1,251 @@
+// Stores a `Vec` of structs as one array per field, for use with
+// `#[serde(with = "zmerald::soa")]`:
+//
+//     points: [Point { x: 1, y: 2 }, Point { x: 3, y: 4 }]
+//
+// is written as
+//
+//     points: { x: [1, 3], y: [2, 4] }
+//
+// which is smaller and faster to read for large numeric tables. Fields are
+// written straight from the rows, without copying them.
+
+use crate::value::{ Map, Value };
+use serde::de::{ DeserializeOwned, Deserializer };
+use serde::ser::{ self, Impossible, Serialize, SerializeSeq, SerializeStruct, Serializer };
+use std::fmt::Display;
+
+pub fn serialize<T, S>(rows: &[T], serializer: S) -> Result<S::Ok, S::Error>
+where T: Serialize, S: Serializer {
+    let fields = match rows.first() {
+        Some(row) => row.serialize(Fields(Vec::new())).map_err(ser::Error::custom)?,
+        None => Vec::new(),
+    };
+
+    let mut columns = serializer.serialize_struct("", fields.len())?;
+    for field in fields {
+        columns.serialize_field(field, &Column { rows, field })?;
+    }
+    columns.end()
+}
+
+pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Vec<T>, D::Error>
+where T: DeserializeOwned, D: Deserializer<'de> {
+    use serde::de::{ Deserialize, Error };
+
+    let mut columns = match Value::deserialize(deserializer)? {
+        Value::Map(columns) | Value::Struct { fields: columns, .. } => columns,
+        _ => return Err(D::Error::custom("expected a map of columns")),
+    };
+
+    let mut len = None;
+    let mut columns = columns
+        .iter_mut()
+        .map(|(field, column)| match std::mem::replace(column, Value::Unit) {
+            Value::Seq(values) => {
+                if *len.get_or_insert(values.len()) != values.len() {
+                    return Err(D::Error::custom(format!("column `{}` differs in length from the others", Name(field))));
+                }
+                Ok((field.clone(), values.into_iter()))
+            }
+            _ => Err(D::Error::custom(format!("column `{}` is not an array", Name(field)))),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    (0..len.unwrap_or(0))
+        .map(|_| {
+            let mut fields = Map::new();
+            for (field, values) in &mut columns {
+                fields.insert(field.clone(), values.next().unwrap_or(Value::Unit));
+            }
+
+            Value::Struct { name: None, fields }.into_rust().map_err(D::Error::custom)
+        })
+        .collect()
+}
+
+struct Name<'a>(&'a Value);
+
+impl Display for Name<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.0 {
+            Value::String(s) => f.write_str(s),
+            other => write!(f, "{:?}", other),
+        }
+    }
+}
+
+// One field of every row.
+struct Column<'a, T> {
+    rows: &'a [T],
+    field: &'static str,
+}
+
+impl<T: Serialize> Serialize for Column<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.rows.len()))?;
+        for row in self.rows {
+            seq.serialize_element(&Cell { row, field: self.field })?;
+        }
+        seq.end()
+    }
+}
+
+struct Cell<'a, T> {
+    row: &'a T,
+    field: &'static str,
+}
+
+impl<T: Serialize> Serialize for Cell<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.row.serialize(Pick { field: self.field, inner: serializer })
+    }
+}
+
+fn not_a_struct<E: ser::Error>() -> E {
+    E::custom("struct-of-arrays rows must be structs")
+}
+
+// Serializers that only accept a struct, any other value is an error.
+macro_rules! structs_only {
+    ($($method:ident($($arg:ty),*);)*) => {
+        $(
+            fn $method(self, $(_: $arg),*) -> Result<Self::Ok, Self::Error> {
+                Err(not_a_struct())
+            }
+        )*
+
+        fn serialize_some<U: ?Sized + Serialize>(self, _: &U) -> Result<Self::Ok, Self::Error> {
+            Err(not_a_struct())
+        }
+
+        fn serialize_newtype_struct<U: ?Sized + Serialize>(self, _: &'static str, _: &U) -> Result<Self::Ok, Self::Error> {
+            Err(not_a_struct())
+        }
+
+        fn serialize_newtype_variant<U: ?Sized + Serialize>(
+            self, _: &'static str, _: u32, _: &'static str, _: &U,
+        ) -> Result<Self::Ok, Self::Error> {
+            Err(not_a_struct())
+        }
+
+        fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+            Err(not_a_struct())
+        }
+
+        fn serialize_tuple(self, _: usize) -> Result<Self::SerializeTuple, Self::Error> {
+            Err(not_a_struct())
+        }
+
+        fn serialize_tuple_struct(self, _: &'static str, _: usize) -> Result<Self::SerializeTupleStruct, Self::Error> {
+            Err(not_a_struct())
+        }
+
+        fn serialize_tuple_variant(
+            self, _: &'static str, _: u32, _: &'static str, _: usize,
+        ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+            Err(not_a_struct())
+        }
+
+        fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+            Err(not_a_struct())
+        }
+
+        fn serialize_struct_variant(
+            self, _: &'static str, _: u32, _: &'static str, _: usize,
+        ) -> Result<Self::SerializeStructVariant, Self::Error> {
+            Err(not_a_struct())
+        }
+    };
+}
+
+macro_rules! struct_serializer {
+    () => {
+        type SerializeSeq = Impossible<Self::Ok, Self::Error>;
+        type SerializeTuple = Impossible<Self::Ok, Self::Error>;
+        type SerializeTupleStruct = Impossible<Self::Ok, Self::Error>;
+        type SerializeTupleVariant = Impossible<Self::Ok, Self::Error>;
+        type SerializeMap = Impossible<Self::Ok, Self::Error>;
+        type SerializeStructVariant = Impossible<Self::Ok, Self::Error>;
+
+        structs_only! {
+            serialize_bool(bool); serialize_i8(i8); serialize_i16(i16); serialize_i32(i32); serialize_i64(i64);
+            serialize_u8(u8); serialize_u16(u16); serialize_u32(u32); serialize_u64(u64);
+            serialize_f32(f32); serialize_f64(f64); serialize_char(char); serialize_str(&str);
+            serialize_bytes(&[u8]); serialize_none(); serialize_unit(); serialize_unit_struct(&'static str);
+            serialize_unit_variant(&'static str, u32, &'static str);
+        }
+    };
+}
+
+// Collects the field names of a row.
+struct Fields(Vec<&'static str>);
+
+impl Serializer for Fields {
+    type Ok = Vec<&'static str>;
+    type Error = crate::error::Error;
+    type SerializeStruct = Self;
+
+    struct_serializer!();
+
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<Self, Self::Error> {
+        Ok(self)
+    }
+}
+
+impl SerializeStruct for Fields {
+    type Ok = Vec<&'static str>;
+    type Error = crate::error::Error;
+
+    fn serialize_field<U: ?Sized + Serialize>(&mut self, key: &'static str, _: &U) -> Result<(), Self::Error> {
+        self.0.push(key);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(self.0)
+    }
+}
+
+// Serializes a single field of a row with the inner serializer.
+struct Pick<S> {
+    field: &'static str,
+    inner: S,
+}
+
+struct PickField<S: Serializer> {
+    field: &'static str,
+    inner: Option<S>,
+    ok: Option<S::Ok>,
+}
+
+impl<S: Serializer> Serializer for Pick<S> {
+    type Ok = S::Ok;
+    type Error = S::Error;
+    type SerializeStruct = PickField<S>;
+
+    struct_serializer!();
+
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<PickField<S>, S::Error> {
+        Ok(PickField { field: self.field, inner: Some(self.inner), ok: None })
+    }
+}
+
+impl<S: Serializer> SerializeStruct for PickField<S> {
+    type Ok = S::Ok;
+    type Error = S::Error;
+
+    fn serialize_field<U: ?Sized + Serialize>(&mut self, key: &'static str, value: &U) -> Result<(), S::Error> {
+        if key == self.field {
+            if let Some(inner) = self.inner.take() {
+                self.ok = Some(value.serialize(inner)?);
+            }
+        }
+        Ok(())
+    }
+
+    fn end(self) -> Result<S::Ok, S::Error> {
+        let field = self.field;
+        self.ok.ok_or_else(|| ser::Error::custom(format!("a row is missing field `{}`", field)))
+    }
+}
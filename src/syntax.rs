@@ -0,0 +1,92 @@
+// The rules the serializer follows to write strings and names, for tools that
+// produce documents by other means, such as templates.
+
+use crate::parse::{ is_ident_first_char, is_ident_other_char, is_keyword, is_reserved_char, is_whitespace_char };
+use std::borrow::Cow;
+
+// Whether `s` has to be a quoted string, rather than being written bare as a
+// field name or identifier. Keywords such as `true` need quoting, as bare they
+// mean something else.
+pub fn needs_quoting(s: &str) -> bool {
+    !is_identifier(s) || is_keyword(s)
+}
+
+// Whether `s` is read back as the same string when written without quotes,
+// as a typed string field reads `ctrl-q` or `1.0.2`.
+pub fn is_bare_word(s: &str) -> bool {
+    !s.is_empty()
+        && !is_keyword(s)
+        && !s.starts_with('#')
+        && !s.contains('"')
+        && !s.chars().any(char::is_control)
+        && !s.bytes().any(|b| is_reserved_char(b) || is_whitespace_char(b))
+}
+
+// Whether `s` is an identifier, maybe with `-`, `.` or `/` inside, such as
+// `ctrl-q` or `themes/dark`, which is written bare by
+// `QuoteStrings::WhenRequired`.
+pub fn is_safe_token(s: &str) -> bool {
+    let mut bytes = s.bytes();
+    is_bare_word(s)
+        && bytes.next().is_some_and(is_ident_first_char)
+        && bytes.all(|b| is_ident_other_char(b) || matches!(b, b'-' | b'.' | b'/'))
+}
+
+// The contents of the string literal for `s`, without the surrounding quotes:
+// quotes, backslashes and control characters escaped, everything else as is.
+// Borrowed when nothing needed escaping.
+pub fn escape_string(s: &str) -> Cow<'_, str> {
+    escape(s, '"', false)
+}
+
+// Like `escape_string`, with every non-ASCII character written as `\u{..}`,
+// for documents that must stay ASCII.
+pub fn escape_string_ascii(s: &str) -> Cow<'_, str> {
+    escape(s, '"', true)
+}
+
+// The contents of the char literal for `c`.
+pub(crate) fn escape_char(c: char, ascii: bool) -> String {
+    let mut escaped = String::new();
+    push_escaped(&mut escaped, c, '\'', ascii);
+
+    escaped
+}
+
+fn escape(s: &str, quote: char, ascii: bool) -> Cow<'_, str> {
+    if !s.chars().any(|c| needs_escape(c, quote, ascii)) {
+        return Cow::Borrowed(s);
+    }
+
+    let mut escaped = String::with_capacity(s.len() + 2);
+    for c in s.chars() {
+        push_escaped(&mut escaped, c, quote, ascii);
+    }
+
+    Cow::Owned(escaped)
+}
+
+fn needs_escape(c: char, quote: char, ascii: bool) -> bool {
+    c == quote || c == '\\' || c.is_control() || (ascii && !c.is_ascii())
+}
+
+fn push_escaped(escaped: &mut String, c: char, quote: char, ascii: bool) {
+    match c {
+        '\n' => escaped.push_str("\\n"),
+        '\r' => escaped.push_str("\\r"),
+        '\t' => escaped.push_str("\\t"),
+        '\0' => escaped.push_str("\\0"),
+        '\\' => escaped.push_str("\\\\"),
+        c if c == quote => {
+            escaped.push('\\');
+            escaped.push(c);
+        }
+        c if needs_escape(c, quote, ascii) => escaped.push_str(&format!("\\u{{{:x}}}", c as u32)),
+        c => escaped.push(c),
+    }
+}
+
+pub(crate) fn is_identifier(name: &str) -> bool {
+    let mut bytes = name.as_bytes().iter().cloned();
+    bytes.next().is_some_and(is_ident_first_char) && bytes.all(is_ident_other_char)
+}
@@ -0,0 +1,27 @@
+// Round-trip checks for crates that build documents from their own names and
+// values, e.g. field names read from a schema. Enabled by the `test-util`
+// feature.
+
+use crate::ser::{ to_string, to_string_pretty, PrettyConfig };
+use crate::value::{ Map, Value };
+
+// Whether `name` reads back unchanged when used as a struct name and as a
+// field name.
+pub fn roundtrip_identifier(name: &str) -> bool {
+    let mut fields = Map::new();
+    fields.insert(Value::from(name), Value::from(1));
+
+    roundtrip_value(&Value::Struct { name: Some(String::from(name)), fields })
+}
+
+// Whether `value` reads back unchanged, both in the compact and in the pretty
+// style.
+pub fn roundtrip_value(value: &Value) -> bool {
+    let config = PrettyConfig::new().struct_names(true);
+    let compact = to_string(value);
+    let pretty = to_string_pretty(value, config);
+
+    [compact, pretty].into_iter().all(|text| {
+        text.ok().and_then(|text| text.parse::<Value>().ok()).as_ref() == Some(value)
+    })
+}
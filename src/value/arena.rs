@@ -0,0 +1,237 @@
+// A `Value` for reading large documents without thousands of small
+// allocations: strings are borrowed from the input where they have no
+// escapes, and everything else goes into a caller's `Bump`, freed all at once
+// when it is dropped.
+
+use bumpalo::Bump;
+use bumpalo::collections::Vec as BumpVec;
+use serde::de::{ DeserializeSeed, Deserializer, EnumAccess, Error, MapAccess, SeqAccess, VariantAccess, Visitor };
+
+use std::fmt;
+
+#[cfg(feature = "arbitrary_precision")]
+use super::NUMBER_TOKEN;
+use super::{ Map, Number, Value, STRUCT_TOKEN, VALUE_TOKEN };
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(not(feature = "arbitrary_precision"), derive(Copy))]
+pub enum ValueRef<'a> {
+    Bool(bool),
+    Bytes(&'a [u8]),
+    Char(char),
+    // Entries in the order they were written, duplicate keys included.
+    Map(&'a [(ValueRef<'a>, ValueRef<'a>)]),
+    Number(Number),
+    // The text of a number that `Number::Literal` would hold.
+    #[cfg(feature = "arbitrary_precision")]
+    Literal(&'a str),
+    Option(Option<&'a ValueRef<'a>>),
+    String(&'a str),
+    Seq(&'a [ValueRef<'a>]),
+    Struct { name: Option<&'a str>, fields: &'a [(ValueRef<'a>, ValueRef<'a>)] },
+    Variant { name: &'a str, content: &'a ValueRef<'a> },
+    Unit,
+}
+
+impl<'a> ValueRef<'a> {
+    // The value of the last entry whose key is the string `key`, in a map or
+    // a struct.
+    pub fn get(&self, key: &str) -> Option<&'a ValueRef<'a>> {
+        match *self {
+            ValueRef::Map(entries) | ValueRef::Struct { fields: entries, .. } => {
+                entries.iter().rev().find(|(k, _)| k.as_str() == Some(key)).map(|(_, v)| v)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            ValueRef::Bool(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&'a str> {
+        match *self {
+            ValueRef::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    // Not for `ValueRef::Literal`, which `Number::parse_literal` reads.
+    pub fn as_number(&self) -> Option<&Number> {
+        match self {
+            ValueRef::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    pub fn as_seq(&self) -> Option<&'a [ValueRef<'a>]> {
+        match *self {
+            ValueRef::Seq(seq) => Some(seq),
+            _ => None,
+        }
+    }
+
+    // An owned copy, where the last of duplicate keys wins as reading a
+    // `Value` would.
+    pub fn to_value(&self) -> Value {
+        let map = |entries: &[(ValueRef, ValueRef)]| entries.iter().map(|(k, v)| (k.to_value(), v.to_value())).collect::<Map>();
+
+        match *self {
+            ValueRef::Bool(b) => Value::Bool(b),
+            ValueRef::Bytes(b) => Value::Bytes(b.to_vec()),
+            ValueRef::Char(c) => Value::Char(c),
+            ValueRef::Map(entries) => Value::Map(map(entries)),
+            ValueRef::Number(ref n) => Value::Number(Number::clone(n)),
+            #[cfg(feature = "arbitrary_precision")]
+            ValueRef::Literal(literal) => Value::Number(Number::Literal(literal.to_owned())),
+            ValueRef::Option(option) => Value::Option(option.map(|v| Box::new(v.to_value()))),
+            ValueRef::String(s) => Value::String(s.to_owned()),
+            ValueRef::Seq(seq) => Value::Seq(seq.iter().map(ValueRef::to_value).collect()),
+            ValueRef::Struct { name, fields } => Value::Struct { name: name.map(str::to_owned), fields: map(fields) },
+            ValueRef::Variant { name, content } => Value::Variant { name: name.to_owned(), content: Box::new(content.to_value()) },
+            ValueRef::Unit => Value::Unit,
+        }
+    }
+}
+
+// Reads a `ValueRef` into `arena`, see `de::from_str_in`.
+#[derive(Clone, Copy)]
+pub struct ArenaSeed<'a> {
+    arena: &'a Bump,
+}
+
+impl<'a> ArenaSeed<'a> {
+    pub fn new(arena: &'a Bump) -> Self {
+        ArenaSeed { arena }
+    }
+}
+
+impl<'a, 'de: 'a> DeserializeSeed<'de> for ArenaSeed<'a> {
+    type Value = ValueRef<'a>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error> where D: Deserializer<'de> {
+        deserializer.deserialize_newtype_struct(VALUE_TOKEN, self)
+    }
+}
+
+impl<'a, 'de: 'a> Visitor<'de> for ArenaSeed<'a> {
+    type Value = ValueRef<'a>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a zmerald value")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Self::Value, E> where E: Error {
+        Ok(ValueRef::Bool(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E> where E: Error {
+        Ok(ValueRef::Number(Number::new(v)))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E> where E: Error {
+        Ok(ValueRef::Number(Number::new(v)))
+    }
+
+    #[cfg(feature = "integer128")]
+    fn visit_i128<E>(self, v: i128) -> Result<Self::Value, E> where E: Error {
+        Ok(ValueRef::Number(Number::new(v)))
+    }
+
+    #[cfg(feature = "integer128")]
+    fn visit_u128<E>(self, v: u128) -> Result<Self::Value, E> where E: Error {
+        Ok(ValueRef::Number(Number::new(v)))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E> where E: Error {
+        Ok(ValueRef::Number(Number::new(v)))
+    }
+
+    fn visit_char<E>(self, v: char) -> Result<Self::Value, E> where E: Error {
+        Ok(ValueRef::Char(v))
+    }
+
+    fn visit_borrowed_str<E>(self, v: &'de str) -> Result<Self::Value, E> where E: Error {
+        Ok(ValueRef::String(v))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E> where E: Error {
+        Ok(ValueRef::String(self.arena.alloc_str(v)))
+    }
+
+    fn visit_borrowed_bytes<E>(self, v: &'de [u8]) -> Result<Self::Value, E> where E: Error {
+        Ok(ValueRef::Bytes(v))
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E> where E: Error {
+        Ok(ValueRef::Bytes(self.arena.alloc_slice_copy(v)))
+    }
+
+    fn visit_none<E>(self) -> Result<Self::Value, E> where E: Error {
+        Ok(ValueRef::Option(None))
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error> where D: Deserializer<'de> {
+        let value = self.deserialize(deserializer)?;
+        Ok(ValueRef::Option(Some(self.arena.alloc(value))))
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E> where E: Error {
+        Ok(ValueRef::Unit)
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error> where D: Deserializer<'de> {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error> where A: SeqAccess<'de> {
+        let mut vec = BumpVec::with_capacity_in(seq.size_hint().unwrap_or(0), self.arena);
+        while let Some(x) = seq.next_element_seed(self)? {
+            vec.push(x);
+        }
+
+        Ok(ValueRef::Seq(vec.into_bump_slice()))
+    }
+
+    fn visit_enum<A>(self, data: A) -> Result<Self::Value, A::Error> where A: EnumAccess<'de> {
+        let (name, variant) = data.variant_seed(self)?;
+        let Some(name) = name.as_str() else {
+            return Err(Error::custom("expected a variant name"));
+        };
+        let content = variant.newtype_variant_seed(self)?;
+
+        Ok(ValueRef::Variant { name, content: self.arena.alloc(content) })
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error> where A: MapAccess<'de> {
+        let mut entries = BumpVec::with_capacity_in(map.size_hint().unwrap_or(0), self.arena);
+        let mut name = None;
+
+        match map.next_key_seed(self)? {
+            #[cfg(feature = "arbitrary_precision")]
+            Some(ValueRef::String(NUMBER_TOKEN)) => {
+                let literal = map.next_value_seed(self)?;
+                return literal.as_str().map(ValueRef::Literal).ok_or_else(|| Error::custom("expected a number"));
+            }
+            Some(ValueRef::String(STRUCT_TOKEN)) => {
+                name = map.next_value_seed(self)?.as_str();
+            }
+            Some(key) => entries.push((key, map.next_value_seed(self)?)),
+            None => return Ok(ValueRef::Map(entries.into_bump_slice())),
+        }
+
+        while let Some(entry) = map.next_entry_seed(self, self)? {
+            entries.push(entry);
+        }
+
+        let entries = entries.into_bump_slice();
+        Ok(match name {
+            Some("") => ValueRef::Struct { name: None, fields: entries },
+            Some(name) => ValueRef::Struct { name: Some(name), fields: entries },
+            None => ValueRef::Map(entries),
+        })
+    }
+}
@@ -1,12 +1,31 @@
 use std::cmp::Ordering;
 use std::hash::{ Hasher, Hash };
 
-#[derive(Copy, Clone, Debug, PartialEq, PartialOrd, Eq, Hash, Ord)]
+use crate::parse::AnyNum;
+
+// The integer held by `Number::Integer`. With the `integer128` feature it is
+// an `i128`, so all of `u64` stays an integer rather than turning into a
+// float past `i64::MAX`.
+#[cfg(not(feature = "integer128"))]
+pub type Int = i64;
+#[cfg(feature = "integer128")]
+pub type Int = i128;
+
+#[derive(Clone, Debug, PartialEq, PartialOrd, Eq, Hash, Ord)]
+#[cfg_attr(not(feature = "arbitrary_precision"), derive(Copy))]
 pub enum Number {
-    Integer(i64),
+    Integer(Int),
     Float(Float),
+    // A number as it was written, for those an `i64` or `f64` can't hold
+    // exactly. It is only converted when its value is asked for, and compares
+    // by its text.
+    #[cfg(feature = "arbitrary_precision")]
+    Literal(String),
 }
 
+// Floats are equal, ordered and hashed by `f64::total_cmp` with all NaNs
+// taken to be one and the same value, so they make well behaved map keys:
+// `-0.0 < 0.0`, NaN equals NaN and is greater than everything else.
 #[derive(Copy, Clone, Debug)]
 pub struct Float(f64);
 
@@ -18,6 +37,18 @@ impl Float {
     pub fn get(self) -> f64 {
         self.0
     }
+
+    pub fn to_bits(self) -> u64 {
+        self.0.to_bits()
+    }
+
+    pub fn from_bits(bits: u64) -> Self {
+        Float(f64::from_bits(bits))
+    }
+
+    fn canonical(self) -> f64 {
+        if self.0.is_nan() { f64::NAN } else { self.0 }
+    }
 }
 
 impl Number {
@@ -29,18 +60,50 @@ impl Number {
         self.map_to(|i| i as f64, |f| f)
     }
 
-    pub fn as_f64(self) -> Option<f64> {
+    pub fn as_f64(&self) -> Option<f64> {
         self.map_to(|_| None, Some)
     }
 
-    pub fn as_i64(self) -> Option<i64> {
-        self.map_to(Some, |_| None)
+    // `Int` may or may not be an `i64`, depending on `integer128`.
+    #[allow(clippy::useless_conversion)]
+    pub fn as_i64(&self) -> Option<i64> {
+        self.map_to(|i| i64::try_from(i).ok(), |_| None)
     }
 
-    pub fn map_to<T>(self, integer_fn: impl FnOnce(i64) -> T, float_fn: impl FnOnce(f64) -> T) -> T {
-        match self {
+    pub fn as_u64(&self) -> Option<u64> {
+        self.map_to(|i| u64::try_from(i).ok(), |_| None)
+    }
+
+    pub fn map_to<T>(&self, integer_fn: impl FnOnce(Int) -> T, float_fn: impl FnOnce(f64) -> T) -> T {
+        match *self {
             Number::Integer(i) => integer_fn(i),
             Number::Float(Float(f)) => float_fn(f),
+            #[cfg(feature = "arbitrary_precision")]
+            Number::Literal(ref literal) => Number::parse_literal(literal).map_to(integer_fn, float_fn),
+        }
+    }
+
+    // Integers that don't fit an `i64` and malformed literals end up as
+    // floats, the latter as NaN.
+    #[cfg(feature = "arbitrary_precision")]
+    pub(crate) fn parse_literal(literal: &str) -> Number {
+        AnyNum::parse(literal).map_or(Number::from(f64::NAN), Number::from)
+    }
+}
+
+impl From<AnyNum> for Number {
+    fn from(num: AnyNum) -> Number {
+        match num {
+            AnyNum::F32(f) => Number::from(f64::from(f)),
+            AnyNum::F64(f) => Number::from(f),
+            AnyNum::I8(i) => Number::from(i64::from(i)),
+            AnyNum::U8(i) => Number::from(i64::from(i)),
+            AnyNum::I16(i) => Number::from(i64::from(i)),
+            AnyNum::U16(i) => Number::from(i64::from(i)),
+            AnyNum::I32(i) => Number::from(i64::from(i)),
+            AnyNum::U32(i) => Number::from(i64::from(i)),
+            AnyNum::I64(i) => Number::from(i),
+            AnyNum::U64(i) => Number::from(i),
         }
     }
 }
@@ -53,32 +116,43 @@ impl From<f64> for Number {
 
 impl From<i64> for Number {
     fn from(i: i64) -> Number {
-        Number::Integer(i)
+        Number::Integer(Int::from(i))
     }
 }
 
 impl From<i32> for Number {
     fn from(i: i32) -> Number {
-        Number::Integer(i64::from(i))
+        Number::Integer(Int::from(i))
     }
 }
 
-// The following number conversion checks if the integer fits losslessly into an i64, before
+// The following number conversions check if the integer fits losslessly into an `Int`, before
 // constructing a Number::Integer variant. If not, the conversion defaults to float.
 
 impl From<u64> for Number {
+    #[allow(clippy::unnecessary_fallible_conversions)]
     fn from(i: u64) -> Number {
-        if i <= std::i64::MAX as u64 {
-            Number::Integer(i as i64)
-        } else {
-            Number::new(i as f64)
-        }
+        Int::try_from(i).map_or_else(|_| Number::new(i as f64), Number::Integer)
+    }
+}
+
+#[cfg(feature = "integer128")]
+impl From<i128> for Number {
+    fn from(i: i128) -> Number {
+        Number::Integer(i)
+    }
+}
+
+#[cfg(feature = "integer128")]
+impl From<u128> for Number {
+    fn from(i: u128) -> Number {
+        Int::try_from(i).map_or_else(|_| Number::new(i as f64), Number::Integer)
     }
 }
 
 impl PartialEq for Float {
     fn eq(&self, other: &Self) -> bool {
-        self.0.is_nan() && other.0.is_nan() || self.0 == other.0
+        self.cmp(other) == Ordering::Equal
     }
 }
 
@@ -86,23 +160,18 @@ impl Eq for Float {}
 
 impl Hash for Float {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        state.write_u64(self.0 as u64);
+        state.write_u64(self.canonical().to_bits());
     }
 }
 
 impl PartialOrd for Float {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        match (self.0.is_nan(), other.0.is_nan()) {
-            (true, true) => Some(Ordering::Equal),
-            (true, false) => Some(Ordering::Less),
-            (false, true) => Some(Ordering::Greater),
-            _ => self.0.partial_cmp(&other.0),
-        }
+        Some(self.cmp(other))
     }
 }
 
 impl Ord for Float {
     fn cmp(&self, other: &Self) -> Ordering {
-        self.partial_cmp(other).expect("Bug: Contract violation")
+        self.canonical().total_cmp(&other.canonical())
     }
 }
\ No newline at end of file
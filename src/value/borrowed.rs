@@ -0,0 +1,282 @@
+// `&Value` as a deserializer, for typed views of a `Value` that is kept
+// around. It reads like `Value` itself, but hands out borrowed strings and
+// bytes and never copies the tree.
+
+use super::map::Iter;
+use super::{ visit_integer, Map, Number, Value, STRUCT_TOKEN, VALUE_TOKEN };
+
+use crate::error::{ Error, Result };
+use serde::de::value::{ BorrowedStrDeserializer, MapAccessDeserializer };
+use serde::de::{ DeserializeSeed, Deserializer, EnumAccess, MapAccess, SeqAccess, VariantAccess, Visitor };
+use serde::forward_to_deserialize_any;
+
+impl<'de> Deserializer<'de> for &'de Value {
+    type Error = Error;
+
+    forward_to_deserialize_any! {
+        bool f32 f64 char bytes
+        byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where V: Visitor<'de> {
+        match self {
+            Value::Bool(b) => visitor.visit_bool(*b),
+            Value::Bytes(b) => visitor.visit_borrowed_bytes(b),
+            Value::Char(c) => visitor.visit_char(*c),
+            Value::Map(m) | Value::Struct { fields: m, .. } => visitor.visit_map(MapRef::new(None, m)),
+            Value::Variant { name, content } => visitor.visit_enum(VariantRef { name, content: Content::Value(content) }),
+            Value::Number(Number::Float(f)) => visitor.visit_f64(f.get()),
+            Value::Number(Number::Integer(i)) => visit_integer(*i, visitor),
+            #[cfg(feature = "arbitrary_precision")]
+            Value::Number(Number::Literal(literal)) => Value::Number(Number::parse_literal(literal)).deserialize_any(visitor),
+            Value::Option(Some(o)) => visitor.visit_some(&**o),
+            Value::Option(None) => visitor.visit_none(),
+            Value::String(s) => visitor.visit_borrowed_str(s),
+            Value::Seq(seq) => visitor.visit_seq(SeqRef { seq: seq.iter() }),
+            Value::Unit => visitor.visit_unit(),
+        }
+    }
+
+    fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value>
+    where V: Visitor<'de> {
+        match self {
+            // Keeps the struct name, see `STRUCT_TOKEN`.
+            Value::Struct { name: struct_name, fields } if name == VALUE_TOKEN => {
+                visitor.visit_map(MapRef::new(Some(struct_name.as_deref().unwrap_or_default()), fields))
+            }
+            value if name == VALUE_TOKEN => value.deserialize_any(visitor),
+            value => visitor.visit_newtype_struct(value),
+        }
+    }
+
+    // `Name { .. }` also stands for a struct variant.
+    fn deserialize_enum<V>(self, _name: &'static str, _variants: &'static [&'static str], visitor: V) -> Result<V::Value>
+    where V: Visitor<'de> {
+        match self {
+            Value::Struct { name: Some(name), fields } => visitor.visit_enum(VariantRef { name, content: Content::Fields(fields) }),
+            value => value.deserialize_any(visitor),
+        }
+    }
+
+    // Bare words parse as unit variants, they still stand for strings when
+    // one is expected.
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value>
+    where V: Visitor<'de> {
+        match self {
+            Value::Variant { name, content } if content.is_unit() => visitor.visit_borrowed_str(name),
+            // Exact decimal types read the literal from a string.
+            #[cfg(feature = "arbitrary_precision")]
+            Value::Number(Number::Literal(literal)) => visitor.visit_borrowed_str(literal),
+            v => v.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
+    where V: Visitor<'de> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_i8<V>(self, visitor: V) -> Result<V::Value>
+    where V: Visitor<'de> {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_i16<V>(self, visitor: V) -> Result<V::Value>
+    where V: Visitor<'de> {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_i32<V>(self, visitor: V) -> Result<V::Value>
+    where V: Visitor<'de> {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_i64<V>(self, visitor: V) -> Result<V::Value>
+    where V: Visitor<'de> {
+        match self {
+            Value::Number(Number::Integer(i)) => visit_integer(*i, visitor),
+            #[cfg(feature = "arbitrary_precision")]
+            Value::Number(Number::Literal(literal)) => Value::Number(Number::parse_literal(literal)).deserialize_i64(visitor),
+            v => Err(Error::Message(format!("Expected a number, got {:?}", v))),
+        }
+    }
+
+    fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value>
+    where V: Visitor<'de> {
+        self.deserialize_u64(visitor)
+    }
+
+    fn deserialize_u16<V>(self, visitor: V) -> Result<V::Value>
+    where V: Visitor<'de> {
+        self.deserialize_u64(visitor)
+    }
+
+    fn deserialize_u32<V>(self, visitor: V) -> Result<V::Value>
+    where V: Visitor<'de> {
+        self.deserialize_u64(visitor)
+    }
+
+    // As for an owned `Value`, `Unit` is `None` and other values are `Some`.
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where V: Visitor<'de> {
+        match self {
+            Value::Option(Some(o)) => visitor.visit_some(&**o),
+            Value::Option(None) | Value::Unit => visitor.visit_none(),
+            v => visitor.visit_some(v),
+        }
+    }
+
+    #[cfg(feature = "integer128")]
+    fn deserialize_i128<V>(self, visitor: V) -> Result<V::Value>
+    where V: Visitor<'de> {
+        self.deserialize_any(visitor)
+    }
+
+    #[cfg(feature = "integer128")]
+    fn deserialize_u128<V>(self, visitor: V) -> Result<V::Value>
+    where V: Visitor<'de> {
+        self.deserialize_any(visitor)
+    }
+
+    fn deserialize_u64<V>(self, visitor: V) -> Result<V::Value>
+    where V: Visitor<'de> {
+        match self {
+            Value::Number(Number::Integer(i)) => match u64::try_from(*i) {
+                Ok(u) => visitor.visit_u64(u),
+                Err(_) => visit_integer(*i, visitor),
+            },
+            #[cfg(feature = "arbitrary_precision")]
+            Value::Number(Number::Literal(literal)) => Value::Number(Number::parse_literal(literal)).deserialize_u64(visitor),
+            v => Err(Error::Message(format!("Expected a number, got {:?}", v))),
+        }
+    }
+}
+
+struct SeqRef<'de> {
+    seq: std::slice::Iter<'de, Value>,
+}
+
+impl<'de> SeqAccess<'de> for SeqRef<'de> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+    where T: DeserializeSeed<'de> {
+        self.seq
+            .next()
+            .map_or(Ok(None), |v| seed.deserialize(v).map(Some))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.seq.len())
+    }
+}
+
+// The entries of `map`, after the struct name under `STRUCT_TOKEN` if there
+// is one.
+struct MapRef<'de> {
+    name: Option<&'de str>,
+    entries: Iter<'de>,
+    // Of the entry whose key was taken last.
+    value: Option<&'de Value>,
+}
+
+impl<'de> MapRef<'de> {
+    fn new(name: Option<&'de str>, map: &'de Map) -> Self {
+        MapRef { name, entries: map.iter(), value: None }
+    }
+}
+
+impl<'de> MapAccess<'de> for MapRef<'de> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+    where K: DeserializeSeed<'de> {
+        if self.name.is_some() {
+            return seed.deserialize(BorrowedStrDeserializer::<Error>::new(STRUCT_TOKEN)).map(Some);
+        }
+
+        match self.entries.next() {
+            Some((key, value)) => {
+                self.value = Some(value);
+                seed.deserialize(key).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+    where V: DeserializeSeed<'de> {
+        if let Some(name) = self.name.take() {
+            return seed.deserialize(BorrowedStrDeserializer::<Error>::new(name));
+        }
+
+        debug_assert!(self.value.is_some(), "value taken before its key");
+        match self.value.take() {
+            Some(value) => seed.deserialize(value),
+            None => Err(Error::Message(String::from("value taken before its key"))),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len() + usize::from(self.name.is_some()))
+    }
+}
+
+struct VariantRef<'de> {
+    name: &'de str,
+    content: Content<'de>,
+}
+
+// The fields of `Name { .. }` read as a struct variant stand for a map,
+// which is not in the tree to be borrowed.
+enum Content<'de> {
+    Value(&'de Value),
+    Fields(&'de Map),
+}
+
+impl<'de> EnumAccess<'de> for VariantRef<'de> {
+    type Error = Error;
+    type Variant = Content<'de>;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Content<'de>)>
+    where V: DeserializeSeed<'de> {
+        let variant = seed.deserialize(BorrowedStrDeserializer::<Error>::new(self.name))?;
+
+        Ok((variant, self.content))
+    }
+}
+
+impl<'de> VariantAccess<'de> for Content<'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        match self {
+            Content::Value(Value::Unit) => Ok(()),
+            Content::Value(v) => Err(Error::Message(format!("Expected a unit variant, got {:?}", v))),
+            Content::Fields(m) => Err(Error::Message(format!("Expected a unit variant, got {:?}", m))),
+        }
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+    where T: DeserializeSeed<'de> {
+        match self {
+            Content::Value(v) => seed.deserialize(v),
+            Content::Fields(m) => seed.deserialize(MapAccessDeserializer::new(MapRef::new(None, m))),
+        }
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value>
+    where V: Visitor<'de> {
+        match self {
+            Content::Value(v) => v.deserialize_any(visitor),
+            Content::Fields(m) => visitor.visit_map(MapRef::new(None, m)),
+        }
+    }
+
+    fn struct_variant<V>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value>
+    where V: Visitor<'de> {
+        self.tuple_variant(0, visitor)
+    }
+}
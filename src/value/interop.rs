@@ -0,0 +1,175 @@
+// Conversions to and from the values of `toml` and `serde_yaml`, for moving
+// configs over from those formats. Both lack struct names, which are dropped.
+// TOML also lacks `None` and `()`, which are left out of tables. Variants are
+// written as `{ Name = content }` in TOML and as YAML's `!Name content` tags.
+
+use super::{ Map, Number, Value };
+use crate::error::{ Error, Result };
+use serde_yaml::value::{ Tag, TaggedValue };
+use serde_yaml::Value as Yaml;
+use toml::Value as Toml;
+
+// Reads TOML text into a `Value`, dates and times as strings.
+pub fn from_toml_str(s: &str) -> Result<Value> {
+    s.parse::<toml::Table>().map(|table| Value::from(Toml::Table(table))).map_err(|e| Error::Message(e.to_string()))
+}
+
+// Writes `value` as TOML text, which must be a table at the top.
+pub fn to_toml_string(value: &Value) -> Result<String> {
+    match toml_value(value)? {
+        Some(Toml::Table(table)) => toml::to_string(&table).map_err(|e| Error::Message(e.to_string())),
+        _ => Err(Error::Message(String::from("TOML documents are tables"))),
+    }
+}
+
+// Reads YAML text into a `Value`.
+pub fn from_yaml_str(s: &str) -> Result<Value> {
+    serde_yaml::from_str::<Yaml>(s).map(Value::from).map_err(|e| Error::Message(e.to_string()))
+}
+
+// Writes `value` as YAML text.
+pub fn to_yaml_string(value: &Value) -> Result<String> {
+    serde_yaml::to_string(&Yaml::from(value)).map_err(|e| Error::Message(e.to_string()))
+}
+
+impl From<Toml> for Value {
+    fn from(toml: Toml) -> Self {
+        match toml {
+            Toml::String(s) => Value::String(s),
+            Toml::Integer(i) => Value::from(i),
+            Toml::Float(f) => Value::from(f),
+            Toml::Boolean(b) => Value::Bool(b),
+            Toml::Datetime(datetime) => Value::String(datetime.to_string()),
+            Toml::Array(array) => Value::Seq(array.into_iter().map(Value::from).collect()),
+            Toml::Table(table) => {
+                let mut map = Map::new();
+                for (key, value) in table {
+                    map.insert(Value::String(key), Value::from(value));
+                }
+
+                Value::Map(map)
+            }
+        }
+    }
+}
+
+// `None` for the values TOML cannot hold, an error where they cannot be left
+// out.
+fn toml_value(value: &Value) -> Result<Option<Toml>> {
+    let toml = match *value {
+        Value::Bool(b) => Toml::Boolean(b),
+        Value::Bytes(ref bytes) => Toml::Array(bytes.iter().map(|&b| Toml::Integer(i64::from(b))).collect()),
+        Value::Char(c) => Toml::String(c.to_string()),
+        Value::Map(ref map) | Value::Struct { fields: ref map, .. } => {
+            let mut table = toml::Table::new();
+            for (key, value) in map.iter() {
+                if let Some(value) = toml_value(value)? {
+                    table.insert(key_string(key), value);
+                }
+            }
+
+            Toml::Table(table)
+        }
+        Value::Number(ref n) => match n.as_i64() {
+            Some(i) => Toml::Integer(i),
+            None => Toml::Float(n.map_to(|i| i as f64, |f| f)),
+        },
+        Value::Option(Some(ref value)) => return toml_value(value),
+        Value::Option(None) | Value::Unit => return Ok(None),
+        Value::String(ref s) => Toml::String(s.clone()),
+        Value::Seq(ref seq) => {
+            let array = seq.iter().map(|value| {
+                toml_value(value)?.ok_or_else(|| Error::Message(String::from("TOML arrays cannot hold `None` or `()`")))
+            });
+
+            Toml::Array(array.collect::<Result<_>>()?)
+        }
+        Value::Variant { ref name, ref content } if content.is_unit() => Toml::String(name.clone()),
+        Value::Variant { ref name, ref content } => {
+            let mut table = toml::Table::new();
+            if let Some(content) = toml_value(content)? {
+                table.insert(name.clone(), content);
+            }
+
+            Toml::Table(table)
+        }
+    };
+
+    Ok(Some(toml))
+}
+
+impl From<Yaml> for Value {
+    fn from(yaml: Yaml) -> Self {
+        match yaml {
+            Yaml::Null => Value::Option(None),
+            Yaml::Bool(b) => Value::Bool(b),
+            Yaml::Number(n) => match (n.as_i64(), n.as_u64()) {
+                (Some(i), _) => Value::from(i),
+                (_, Some(u)) => Value::from(u),
+                _ => Value::from(n.as_f64().unwrap_or(f64::NAN)),
+            },
+            Yaml::String(s) => Value::String(s),
+            Yaml::Sequence(sequence) => Value::Seq(sequence.into_iter().map(Value::from).collect()),
+            Yaml::Mapping(mapping) => {
+                let mut map = Map::new();
+                for (key, value) in mapping {
+                    map.insert(Value::from(key), Value::from(value));
+                }
+
+                Value::Map(map)
+            }
+            Yaml::Tagged(tagged) => Value::Variant {
+                name: tagged.tag.to_string().trim_start_matches('!').to_string(),
+                content: Box::new(match tagged.value {
+                    Yaml::Null => Value::Unit,
+                    value => Value::from(value),
+                }),
+            },
+        }
+    }
+}
+
+impl From<&Value> for Yaml {
+    fn from(value: &Value) -> Self {
+        match *value {
+            Value::Bool(b) => Yaml::Bool(b),
+            Value::Bytes(ref bytes) => Yaml::Sequence(bytes.iter().map(|&b| Yaml::from(b)).collect()),
+            Value::Char(c) => Yaml::String(c.to_string()),
+            Value::Map(ref map) | Value::Struct { fields: ref map, .. } => {
+                Yaml::Mapping(map.iter().map(|(key, value)| (Yaml::from(key), Yaml::from(value))).collect())
+            }
+            Value::Number(ref n) => number(n),
+            Value::Option(Some(ref value)) => Yaml::from(&**value),
+            Value::Option(None) | Value::Unit => Yaml::Null,
+            Value::String(ref s) => Yaml::String(s.clone()),
+            Value::Seq(ref seq) => Yaml::Sequence(seq.iter().map(Yaml::from).collect()),
+            Value::Variant { ref name, ref content } if content.is_unit() => Yaml::String(name.clone()),
+            Value::Variant { ref name, ref content } => {
+                Yaml::Tagged(Box::new(TaggedValue { tag: Tag::new(name), value: Yaml::from(&**content) }))
+            }
+        }
+    }
+}
+
+impl From<Value> for Yaml {
+    fn from(value: Value) -> Self {
+        Yaml::from(&value)
+    }
+}
+
+fn number(n: &Number) -> Yaml {
+    match (n.as_i64(), n.as_u64()) {
+        (Some(i), _) => Yaml::from(i),
+        (_, Some(u)) => Yaml::from(u),
+        _ => Yaml::from(n.map_to(|i| i as f64, |f| f)),
+    }
+}
+
+// Table keys are strings, other keys are written as zmerald text.
+fn key_string(key: &Value) -> String {
+    match *key {
+        Value::String(ref s) => s.clone(),
+        Value::Char(c) => c.to_string(),
+        ref key => crate::ser::to_string(key).unwrap_or_default(),
+    }
+}
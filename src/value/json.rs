@@ -0,0 +1,90 @@
+// Conversions to and from `serde_json::Value`, for moving configs between the
+// two formats. JSON has no struct names, variants, chars or bytes, so going to
+// JSON keeps the data in the shape serde would give it: structs become objects,
+// variants `{ "Name": content }` and `None` and `()` become `null`. Keys come
+// out in the order of `serde_json::Map`, sorted unless serde_json's
+// `preserve_order` is on, `serde_transcode` keeps the order of the text.
+
+use super::{ Map, Number, Value };
+use crate::error::{ Error, Result };
+use serde_json::Value as Json;
+
+// Writes `value` as JSON text.
+pub fn to_json_string(value: &Value) -> Result<String> {
+    serde_json::to_string(&Json::from(value)).map_err(|e| Error::Message(e.to_string()))
+}
+
+// Reads JSON text into a `Value`.
+pub fn from_json_str(s: &str) -> Result<Value> {
+    serde_json::from_str::<Json>(s).map(Value::from).map_err(|e| Error::Message(e.to_string()))
+}
+
+impl From<Json> for Value {
+    fn from(json: Json) -> Self {
+        match json {
+            Json::Null => Value::Option(None),
+            Json::Bool(b) => Value::Bool(b),
+            Json::Number(n) => match (n.as_i64(), n.as_u64()) {
+                (Some(i), _) => Value::from(i),
+                (_, Some(u)) => Value::from(u),
+                _ => Value::from(n.as_f64().unwrap_or(f64::NAN)),
+            },
+            Json::String(s) => Value::String(s),
+            Json::Array(array) => Value::Seq(array.into_iter().map(Value::from).collect()),
+            Json::Object(object) => {
+                let mut map = Map::new();
+                for (key, value) in object {
+                    map.insert(Value::String(key), Value::from(value));
+                }
+
+                Value::Map(map)
+            }
+        }
+    }
+}
+
+impl From<&Value> for Json {
+    fn from(value: &Value) -> Self {
+        match *value {
+            Value::Bool(b) => Json::Bool(b),
+            Value::Bytes(ref bytes) => Json::Array(bytes.iter().map(|&b| Json::from(b)).collect()),
+            Value::Char(c) => Json::String(c.to_string()),
+            Value::Map(ref map) | Value::Struct { fields: ref map, .. } => {
+                Json::Object(map.iter().map(|(key, value)| (key_string(key), Json::from(value))).collect())
+            }
+            Value::Number(ref n) => number(n),
+            Value::Option(Some(ref value)) => Json::from(&**value),
+            Value::Option(None) | Value::Unit => Json::Null,
+            Value::String(ref s) => Json::String(s.clone()),
+            Value::Seq(ref seq) => Json::Array(seq.iter().map(Json::from).collect()),
+            Value::Variant { ref name, ref content } if content.is_unit() => Json::String(name.clone()),
+            Value::Variant { ref name, ref content } => {
+                Json::Object([(name.clone(), Json::from(&**content))].into_iter().collect())
+            }
+        }
+    }
+}
+
+impl From<Value> for Json {
+    fn from(value: Value) -> Self {
+        Json::from(&value)
+    }
+}
+
+fn number(n: &Number) -> Json {
+    match (n.as_i64(), n.as_u64()) {
+        (Some(i), _) => Json::from(i),
+        (_, Some(u)) => Json::from(u),
+        // Not a number or infinite floats have no JSON form.
+        _ => serde_json::Number::from_f64(n.map_to(|i| i as f64, |f| f)).map_or(Json::Null, Json::Number),
+    }
+}
+
+// Object keys must be strings, other keys are written as zmerald text.
+fn key_string(key: &Value) -> String {
+    match *key {
+        Value::String(ref s) => s.clone(),
+        Value::Char(c) => c.to_string(),
+        ref key => crate::ser::to_string(key).unwrap_or_default(),
+    }
+}
@@ -2,12 +2,13 @@ use super::Value;
 
 use crate::error::{ Error, Result };
 use std::cmp::Ordering;
+use std::collections::BTreeMap;
 use std::hash::{ Hasher, Hash };
 use std::ops::{ Index, IndexMut };
-use serde::{ Deserialize, Serialize };
+use serde::Deserialize;
 use serde::de::{ DeserializeSeed, MapAccess };
 
-#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[derive(Clone, Debug, Default, Deserialize)]
 #[serde(transparent)]
 pub struct Map(MapInner);
 
@@ -24,33 +25,107 @@ impl Map {
         self.0.len() == 0
     }
 
+    // Takes a `Value`, or a `str` standing for a `Value::String`.
+    pub fn get<K>(&self, key: &K) -> Option<&Value> where K: MapKey + ?Sized {
+        key.get_in(self)
+    }
+
+    pub fn get_mut(&mut self, key: &Value) -> Option<&mut Value> {
+        self.0.get_mut(key)
+    }
+
+    pub fn contains_key(&self, key: &Value) -> bool {
+        self.0.contains_key(key)
+    }
+
+    pub fn entry(&mut self, key: impl Into<Value>) -> Entry<'_> {
+        self.0.entry(key.into())
+    }
+
+    // New keys go last, an existing key keeps its place.
     pub fn insert(&mut self, key: Value, value: Value) -> Option<Value> {
         self.0.insert(key, value)
     }
 
+    // Keeps the order of the remaining entries, see `swap_remove` for a
+    // faster removal that doesn't.
     pub fn remove(&mut self, key: &Value) -> Option<Value> {
-        self.0.remove(key)
+        self.0.shift_remove(key)
+    }
+
+    // As `remove`, also handing back the key as it was stored.
+    pub fn remove_entry(&mut self, key: &Value) -> Option<(Value, Value)> {
+        self.0.shift_remove_entry(key)
+    }
+
+    // Moves the last entry into the place of the removed one.
+    pub fn swap_remove(&mut self, key: &Value) -> Option<Value> {
+        self.0.swap_remove(key)
+    }
+
+    // Moves the entries of `other` into this map as `insert` would, leaving
+    // `other` empty.
+    pub fn append(&mut self, other: &mut Map) {
+        self.0.append(&mut other.0)
+    }
+
+    pub fn retain(&mut self, keep: impl FnMut(&Value, &mut Value) -> bool) {
+        self.0.retain(keep)
+    }
+
+    pub fn sort_keys(&mut self) {
+        self.0.sort_keys()
+    }
+
+    // String keyed shorthands for the above.
+    pub fn get_str(&self, key: &str) -> Option<&Value> {
+        self.get(key)
+    }
+
+    pub fn insert_str(&mut self, key: &str, value: Value) -> Option<Value> {
+        self.insert(Value::String(key.to_owned()), value)
+    }
+
+    pub fn remove_str(&mut self, key: &str) -> Option<Value> {
+        self.remove(&Value::String(key.to_owned()))
     }
 
-    pub fn iter(&self) -> impl Iterator<Item = (&Value, &Value)> + DoubleEndedIterator {
+    pub fn iter(&self) -> Iter<'_> {
         self.0.iter()
     }
 
-    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&Value, &mut Value)> + DoubleEndedIterator {
+    pub fn iter_mut(&mut self) -> IterMut<'_> {
         self.0.iter_mut()
     }
 
-    pub fn keys(&self) -> impl Iterator<Item = &Value> + DoubleEndedIterator {
+    pub fn keys(&self) -> Keys<'_> {
         self.0.keys()
     }
 
-    pub fn values(&self) -> impl Iterator<Item = &Value> + DoubleEndedIterator {
+    pub fn values(&self) -> Values<'_> {
         self.0.values()
     }
 
-    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut Value> + DoubleEndedIterator {
+    pub fn values_mut(&mut self) -> ValuesMut<'_> {
         self.0.values_mut()
     }
+
+    pub fn into_keys(self) -> IntoKeys {
+        self.0.into_keys()
+    }
+
+    pub fn into_values(self) -> IntoValues {
+        self.0.into_values()
+    }
+
+    // The entries by key, for comparing and hashing maps whatever order they
+    // were written in.
+    fn sorted(&self) -> Vec<(&Value, &Value)> {
+        let mut entries = self.iter().collect::<Vec<_>>();
+        entries.sort_unstable_by(|a, b| a.0.cmp(b.0));
+
+        entries
+    }
 }
 
 impl FromIterator<(Value, Value)> for Map {
@@ -59,11 +134,45 @@ impl FromIterator<(Value, Value)> for Map {
     }
 }
 
+// New keys go last, as with `insert`.
+impl Extend<(Value, Value)> for Map {
+    fn extend<T: IntoIterator<Item = (Value, Value)>>(&mut self, iter: T) {
+        self.0.extend(iter)
+    }
+}
+
+impl IntoIterator for Map {
+    type Item = (Value, Value);
+    type IntoIter = IntoIter;
+
+    fn into_iter(self) -> IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Map {
+    type Item = (&'a Value, &'a Value);
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Iter<'a> {
+        self.0.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut Map {
+    type Item = (&'a Value, &'a mut Value);
+    type IntoIter = IterMut<'a>;
+
+    fn into_iter(self) -> IterMut<'a> {
+        self.0.iter_mut()
+    }
+}
+
 impl Eq for Map {}
 
 impl Hash for Map {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        self.iter().for_each(|x| x.hash(state));
+        self.sorted().hash(state);
     }
 }
 
@@ -83,28 +192,92 @@ impl IndexMut<&Value> for Map {
 
 impl Ord for Map {
     fn cmp(&self, other: &Map) -> Ordering {
-        self.iter().cmp(other.iter())
+        self.sorted().cmp(&other.sorted())
     }
 }
 
+// Maps are equal when their entries are, in any order, as they were when
+// `Map` was sorted by key.
 impl PartialEq for Map {
     fn eq(&self, other: &Map) -> bool {
-        self.iter().zip(other.iter()).all(|(a, b)| a == b)
+        self.len() == other.len() && self.iter().all(|(key, value)| other.0.get(key) == Some(value))
+    }
+}
+
+impl PartialEq<BTreeMap<String, Value>> for Map {
+    fn eq(&self, other: &BTreeMap<String, Value>) -> bool {
+        self.len() == other.len() && other.iter().all(|(key, value)| self.get(key.as_str()) == Some(value))
+    }
+}
+
+impl PartialEq<Map> for BTreeMap<String, Value> {
+    fn eq(&self, other: &Map) -> bool {
+        other == self
     }
 }
 
 impl PartialOrd for Map {
     fn partial_cmp(&self, other: &Map) -> Option<Ordering> {
-        self.iter().partial_cmp(other.iter())
+        Some(self.cmp(other))
     }
 }
 
-type MapInner = std::collections::BTreeMap<Value, Value>;
-// type MapInner = indexmap::IndexMap<Value, Value>;
+// Looks itself up in a map, see `Map::get`.
+pub trait MapKey {
+    fn get_in<'a>(&self, map: &'a Map) -> Option<&'a Value>;
+}
 
+impl MapKey for Value {
+    fn get_in<'a>(&self, map: &'a Map) -> Option<&'a Value> {
+        map.0.get(self)
+    }
+}
+
+impl MapKey for str {
+    fn get_in<'a>(&self, map: &'a Map) -> Option<&'a Value> {
+        map.0.get(&Value::String(self.to_owned()))
+    }
+}
+
+impl MapKey for String {
+    fn get_in<'a>(&self, map: &'a Map) -> Option<&'a Value> {
+        self.as_str().get_in(map)
+    }
+}
+
+// Entries keep the order they were inserted in, which for parsed documents is
+// the order they were written in.
+type MapInner = indexmap::IndexMap<Value, Value>;
+
+pub type Entry<'a> = indexmap::map::Entry<'a, Value, Value>;
+
+// Iterators over the entries in order.
+pub type Iter<'a> = indexmap::map::Iter<'a, Value, Value>;
+pub type IterMut<'a> = indexmap::map::IterMut<'a, Value, Value>;
+pub type IntoIter = indexmap::map::IntoIter<Value, Value>;
+pub type Keys<'a> = indexmap::map::Keys<'a, Value, Value>;
+pub type Values<'a> = indexmap::map::Values<'a, Value, Value>;
+pub type ValuesMut<'a> = indexmap::map::ValuesMut<'a, Value, Value>;
+pub type IntoKeys = indexmap::map::IntoKeys<Value, Value>;
+pub type IntoValues = indexmap::map::IntoValues<Value, Value>;
+
+// Hands out the entries of a map it owns, without copying them.
 pub struct MapAccessor {
-    pub keys: Vec<Value>,
-    pub values: Vec<Value>,
+    entries: std::iter::Chain<std::option::IntoIter<(Value, Value)>, IntoIter>,
+    // Of the entry whose key was taken last.
+    value: Option<Value>,
+}
+
+impl MapAccessor {
+    pub fn new(map: Map) -> Self {
+        MapAccessor { entries: None.into_iter().chain(map.0), value: None }
+    }
+
+    // `entry` comes before those of `map`, as the struct name does, see
+    // `STRUCT_TOKEN`.
+    pub(crate) fn with_first(entry: (Value, Value), map: Map) -> Self {
+        MapAccessor { entries: Some(entry).into_iter().chain(map.0), value: None }
+    }
 }
 
 impl<'de> MapAccess<'de> for MapAccessor {
@@ -112,18 +285,25 @@ impl<'de> MapAccess<'de> for MapAccessor {
 
     fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
     where K: DeserializeSeed<'de> {
-        // The `Vec` is reversed, so we can pop to get the originally first element
-        self.keys
-            .pop()
-            .map_or(Ok(None), |v| seed.deserialize(v).map(Some))
+        match self.entries.next() {
+            Some((key, value)) => {
+                self.value = Some(value);
+                seed.deserialize(key).map(Some)
+            }
+            None => Ok(None),
+        }
     }
 
     fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
     where V: DeserializeSeed<'de> {
-        // The `Vec` is reversed, so we can pop to get the originally first element
-        self.values
-            .pop()
-            .map(|v| seed.deserialize(v))
-            .expect("Contract violation")
+        debug_assert!(self.value.is_some(), "value taken before its key");
+        match self.value.take() {
+            Some(value) => seed.deserialize(value),
+            None => Err(Error::Message(String::from("value taken before its key"))),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.size_hint().0)
     }
 }
\ No newline at end of file
@@ -1,68 +1,410 @@
 mod map;
-pub use map::{ Map, MapAccessor };
+pub use map::{ Entry, IntoIter, IntoKeys, IntoValues, Iter, IterMut, Keys, Map, MapAccessor, MapKey, Values, ValuesMut };
 
 mod arithmetic;
-pub use arithmetic::Number;
+pub use arithmetic::{ Float, Int, Number };
 
 mod sequence;
 pub use sequence::Seq;
 
-use serde::de::{ DeserializeOwned, Deserializer, Visitor };
+mod size;
+
+mod variant;
+pub use variant::VariantAccessor;
+
+mod borrowed;
+
+mod order;
+pub use order::Ordered;
+pub(crate) use order::Shape;
+
+mod untagged;
+pub use untagged::Untagged;
+
+mod raw;
+pub use raw::RawValue;
+
+#[cfg(feature = "json")]
+mod json;
+#[cfg(feature = "json")]
+pub use json::{ from_json_str, to_json_string };
+#[cfg(feature = "interop")]
+mod interop;
+#[cfg(feature = "interop")]
+pub use interop::{ from_toml_str, from_yaml_str, to_toml_string, to_yaml_string };
+#[cfg(any(feature = "uuid", feature = "chrono"))]
+mod types;
+#[cfg(feature = "arena")]
+mod arena;
+#[cfg(feature = "arena")]
+pub use arena::{ ArenaSeed, ValueRef };
+
+use serde::de::{ Deserialize, DeserializeOwned, Deserializer, Visitor };
 use serde::forward_to_deserialize_any;
 
 use crate::error::{ Error, Result };
 use std::cmp::Eq;
 use std::hash::Hash;
+use std::ops::Index;
+use std::time::Duration;
+
+#[cfg(test)]
+mod tests;
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub enum Value {
     Bool(bool),
+    Bytes(Vec<u8>),
     Char(char),
     Map(Map),
     Number(Number),
     Option(Option<Box<Value>>),
     String(String),
     Seq(Vec<Value>),
+    // `Name { field: .. }`, or `{ field: .. }` when the keys are bare
+    // identifiers.
+    Struct { name: Option<String>, fields: Map },
+    // `Name` or `Name(..)`, several values between the parentheses are kept
+    // as a `Seq`.
+    Variant { name: String, content: Box<Value> },
     Unit,
 }
 
+// Private names used to carry struct and variant identity through serde,
+// which only deals in `&'static str` names.
+pub(crate) const VALUE_TOKEN: &str = "$zmerald::private::Value";
+pub(crate) const STRUCT_TOKEN: &str = "$zmerald::private::Struct";
+pub(crate) const VARIANT_TOKEN: &str = "$zmerald::private::Variant";
+pub(crate) const FIELD_TOKEN: &str = "$zmerald::private::Field";
+pub(crate) const RAW_VALUE_TOKEN: &str = "$zmerald::private::RawValue";
+#[cfg(feature = "arbitrary_precision")]
+pub(crate) const NUMBER_TOKEN: &str = "$zmerald::private::Number";
+
+// Returned by the `Index` impls for missing entries.
+static UNIT: Value = Value::Unit;
+
 impl Value {
     pub fn into_rust<T>(self) -> Result<T> where T: DeserializeOwned {
         T::deserialize(self)
     }
+
+    // Serializes with the fields of structs in `T` in declaration order rather
+    // than sorted, fields `T` doesn't know about follow the known ones.
+    pub fn ordered_like<'de, T>(&self) -> Ordered<'_> where T: Deserialize<'de> {
+        Ordered { value: self, shape: Shape::of::<T>() }
+    }
+
+    pub fn is_unit(&self) -> bool {
+        matches!(self, Value::Unit)
+    }
+
+    pub fn is_none(&self) -> bool {
+        matches!(self, Value::Option(None))
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Value::Bool(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn as_char(&self) -> Option<char> {
+        match *self {
+            Value::Char(c) => Some(c),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Value::Bytes(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    // A string such as `"1.5s"`, see `crate::duration`.
+    pub fn as_duration(&self) -> Option<Duration> {
+        self.as_str().and_then(crate::duration::parse)
+    }
+
+    pub fn as_number(&self) -> Option<&Number> {
+        match self {
+            Value::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        self.as_number().and_then(Number::as_i64)
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        self.as_number().and_then(Number::as_u64)
+    }
+
+    // Integers are converted, mirroring what a float field would accept.
+    pub fn as_f64(&self) -> Option<f64> {
+        self.as_number().map(|n| n.map_to(|i| i as f64, |f| f))
+    }
+
+    pub fn as_option(&self) -> Option<Option<&Value>> {
+        match self {
+            Value::Option(o) => Some(o.as_deref()),
+            _ => None,
+        }
+    }
+
+    pub fn as_seq(&self) -> Option<&Vec<Value>> {
+        match self {
+            Value::Seq(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_seq_mut(&mut self) -> Option<&mut Vec<Value>> {
+        match self {
+            Value::Seq(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_map(&self) -> Option<&Map> {
+        match self {
+            Value::Map(m) => Some(m),
+            _ => None,
+        }
+    }
+
+    pub fn as_map_mut(&mut self) -> Option<&mut Map> {
+        match self {
+            Value::Map(m) => Some(m),
+            _ => None,
+        }
+    }
+
+    pub fn is_struct(&self) -> bool {
+        matches!(self, Value::Struct { .. })
+    }
+
+    pub fn is_variant(&self) -> bool {
+        matches!(self, Value::Variant { .. })
+    }
+
+    // The struct or variant name, `None` for anything else and for
+    // anonymous structs.
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            Value::Struct { name, .. } => name.as_deref(),
+            Value::Variant { name, .. } => Some(name),
+            _ => None,
+        }
+    }
+
+    pub fn as_fields(&self) -> Option<&Map> {
+        match self {
+            Value::Struct { fields, .. } => Some(fields),
+            _ => None,
+        }
+    }
+
+    pub fn as_fields_mut(&mut self) -> Option<&mut Map> {
+        match self {
+            Value::Struct { fields, .. } => Some(fields),
+            _ => None,
+        }
+    }
+}
+
+impl Index<&str> for Value {
+    type Output = Value;
+
+    fn index(&self, key: &str) -> &Value {
+        self.as_map()
+            .or_else(|| self.as_fields())
+            .and_then(|m| m.get_str(key))
+            .unwrap_or(&UNIT)
+    }
+}
+
+impl Index<usize> for Value {
+    type Output = Value;
+
+    fn index(&self, index: usize) -> &Value {
+        self.as_seq()
+            .and_then(|s| s.get(index))
+            .unwrap_or(&UNIT)
+    }
+}
+
+macro_rules! from_number {
+    ($($ty:ty => $via:ty),*) => {
+        $(
+            impl From<$ty> for Value {
+                fn from(n: $ty) -> Value {
+                    Value::Number(Number::from(<$via>::from(n)))
+                }
+            }
+        )*
+    };
+}
+
+from_number!(i8 => i64, i16 => i64, i32 => i64, i64 => i64, u8 => i64, u16 => i64, u32 => i64, u64 => u64, f32 => f64, f64 => f64);
+#[cfg(feature = "integer128")]
+from_number!(i128 => i128, u128 => u128);
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Value {
+        Value::Bool(b)
+    }
+}
+
+impl From<char> for Value {
+    fn from(c: char) -> Value {
+        Value::Char(c)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Value {
+        Value::String(s.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Value {
+        Value::String(s)
+    }
+}
+
+impl From<Number> for Value {
+    fn from(n: Number) -> Value {
+        Value::Number(n)
+    }
+}
+
+impl From<Map> for Value {
+    fn from(m: Map) -> Value {
+        Value::Map(m)
+    }
+}
+
+impl From<()> for Value {
+    fn from(_: ()) -> Value {
+        Value::Unit
+    }
+}
+
+// `"1.5s"`, as `crate::duration` writes it.
+impl From<Duration> for Value {
+    fn from(duration: Duration) -> Value {
+        Value::String(crate::duration::format(duration))
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(o: Option<T>) -> Value {
+        Value::Option(o.map(|v| Box::new(v.into())))
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(v: Vec<T>) -> Value {
+        Value::Seq(v.into_iter().map(Into::into).collect())
+    }
+}
+
+// Integers are visited as the first of `i64`, `u64` and `i128` holding them.
+#[cfg(not(feature = "integer128"))]
+fn visit_integer<'de, V: Visitor<'de>>(i: Int, visitor: V) -> Result<V::Value> {
+    visitor.visit_i64(i)
+}
+
+#[cfg(feature = "integer128")]
+fn visit_integer<'de, V: Visitor<'de>>(i: Int, visitor: V) -> Result<V::Value> {
+    match (i64::try_from(i), u64::try_from(i)) {
+        (Ok(i), _) => visitor.visit_i64(i),
+        (_, Ok(u)) => visitor.visit_u64(u),
+        _ => visitor.visit_i128(i),
+    }
 }
 
 impl<'de> Deserializer<'de> for Value {
     type Error = Error;
 
     forward_to_deserialize_any! {
-        bool f32 f64 char str string bytes
-        byte_buf option unit unit_struct newtype_struct seq tuple
-        tuple_struct map struct enum identifier ignored_any
+        bool f32 f64 char bytes
+        byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
     }
 
     fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
     where V: Visitor<'de> {
         match self {
             Value::Bool(b) => visitor.visit_bool(b),
+            Value::Bytes(b) => visitor.visit_byte_buf(b),
             Value::Char(c) => visitor.visit_char(c),
-            Value::Map(m) => visitor.visit_map(MapAccessor {
-                keys: m.keys().cloned().rev().collect(),
-                values: m.values().cloned().rev().collect(),
-            }),
+            Value::Map(m) | Value::Struct { fields: m, .. } => visitor.visit_map(MapAccessor::new(m)),
+            Value::Variant { name, content } => visitor.visit_enum(VariantAccessor { name, content: *content }),
             Value::Number(Number::Float(ref f)) => visitor.visit_f64(f.get()),
-            Value::Number(Number::Integer(i)) => visitor.visit_i64(i),
+            Value::Number(Number::Integer(i)) => visit_integer(i, visitor),
+            #[cfg(feature = "arbitrary_precision")]
+            Value::Number(Number::Literal(ref literal)) => Value::Number(Number::parse_literal(literal)).deserialize_any(visitor),
             Value::Option(Some(o)) => visitor.visit_some(*o),
             Value::Option(None) => visitor.visit_none(),
             Value::String(s) => visitor.visit_string(s),
-            Value::Seq(mut seq) => {
-                seq.reverse();
-                visitor.visit_seq(Seq { seq })
-            }
+            Value::Seq(seq) => visitor.visit_seq(Seq::new(seq)),
             Value::Unit => visitor.visit_unit(),
         }
     }
 
+    fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value>
+    where V: Visitor<'de> {
+        match self {
+            // Keeps the struct name, see `STRUCT_TOKEN`.
+            Value::Struct { name: struct_name, fields } if name == VALUE_TOKEN => {
+                let name = (Value::String(STRUCT_TOKEN.to_string()), Value::String(struct_name.unwrap_or_default()));
+                visitor.visit_map(MapAccessor::with_first(name, fields))
+            }
+            value if name == VALUE_TOKEN => value.deserialize_any(visitor),
+            value => visitor.visit_newtype_struct(value),
+        }
+    }
+
+    // `Name { .. }` also stands for a struct variant.
+    fn deserialize_enum<V>(self, _name: &'static str, _variants: &'static [&'static str], visitor: V) -> Result<V::Value>
+    where V: Visitor<'de> {
+        match self {
+            Value::Struct { name: Some(name), fields } => visitor.visit_enum(VariantAccessor { name, content: Value::Map(fields) }),
+            value => value.deserialize_any(visitor),
+        }
+    }
+
+    // Bare words parse as unit variants, they still stand for strings when
+    // one is expected.
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value>
+    where V: Visitor<'de> {
+        match self {
+            Value::Variant { name, content } if content.is_unit() => visitor.visit_string(name),
+            // Exact decimal types read the literal from a string.
+            #[cfg(feature = "arbitrary_precision")]
+            Value::Number(Number::Literal(literal)) => visitor.visit_string(literal),
+            v => v.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
+    where V: Visitor<'de> {
+        self.deserialize_str(visitor)
+    }
+
     fn deserialize_i8<V>(self, visitor: V) -> Result<V::Value>
     where V: Visitor<'de> {
         self.deserialize_i64(visitor)
@@ -81,7 +423,9 @@ impl<'de> Deserializer<'de> for Value {
     fn deserialize_i64<V>(self, visitor: V) -> Result<V::Value>
     where V: Visitor<'de> {
         match self {
-            Value::Number(Number::Integer(i)) => visitor.visit_i64(i),
+            Value::Number(Number::Integer(i)) => visit_integer(i, visitor),
+            #[cfg(feature = "arbitrary_precision")]
+            Value::Number(Number::Literal(ref literal)) => Value::Number(Number::parse_literal(literal)).deserialize_i64(visitor),
             v => Err(Error::Message(format!("Expected a number, got {:?}", v))),
         }
     }
@@ -101,10 +445,38 @@ impl<'de> Deserializer<'de> for Value {
         self.deserialize_u64(visitor)
     }
 
+    // Like the text format, a value that is not an option is taken to be a
+    // `Some`. `Unit` stands for a missing value, as JSON's `null` does.
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where V: Visitor<'de> {
+        match self {
+            Value::Option(Some(o)) => visitor.visit_some(*o),
+            Value::Option(None) | Value::Unit => visitor.visit_none(),
+            v => visitor.visit_some(v),
+        }
+    }
+
+    #[cfg(feature = "integer128")]
+    fn deserialize_i128<V>(self, visitor: V) -> Result<V::Value>
+    where V: Visitor<'de> {
+        self.deserialize_any(visitor)
+    }
+
+    #[cfg(feature = "integer128")]
+    fn deserialize_u128<V>(self, visitor: V) -> Result<V::Value>
+    where V: Visitor<'de> {
+        self.deserialize_any(visitor)
+    }
+
     fn deserialize_u64<V>(self, visitor: V) -> Result<V::Value>
     where V: Visitor<'de> {
         match self {
-            Value::Number(Number::Integer(i)) => visitor.visit_u64(i as u64),
+            Value::Number(Number::Integer(i)) => match u64::try_from(i) {
+                Ok(u) => visitor.visit_u64(u),
+                Err(_) => visit_integer(i, visitor),
+            },
+            #[cfg(feature = "arbitrary_precision")]
+            Value::Number(Number::Literal(ref literal)) => Value::Number(Number::parse_literal(literal)).deserialize_u64(visitor),
             v => Err(Error::Message(format!("Expected a number, got {:?}", v))),
         }
     }
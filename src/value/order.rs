@@ -0,0 +1,285 @@
+use super::Value;
+
+use crate::error::{ Error, Result };
+use serde::de::{
+    Deserialize, DeserializeSeed, Deserializer, EnumAccess, IntoDeserializer, MapAccess,
+    SeqAccess, VariantAccess, Visitor,
+};
+
+// A `Value` serialized with the struct fields of a known type in declaration
+// order, see `Value::ordered_like`.
+pub struct Ordered<'a> {
+    pub(crate) value: &'a Value,
+    pub(crate) shape: Shape,
+}
+
+// The parts of a type that decide the field order: struct fields and where
+// structs are nested. Options and newtypes are transparent.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) enum Shape {
+    #[default]
+    Unknown,
+    Struct(Vec<(&'static str, Shape)>),
+    Seq(Box<Shape>),
+    Map(Box<Shape>),
+}
+
+impl Shape {
+    // Walks `T`'s `Deserialize` impl with placeholder data. A type rejecting a
+    // placeholder still leaves everything recorded up to that point.
+    pub(crate) fn of<'de, T>() -> Shape where T: Deserialize<'de> {
+        let mut shape = Shape::Unknown;
+        let _ = T::deserialize(Introspector { shape: &mut shape, stack: &mut Vec::new() });
+
+        shape
+    }
+}
+
+struct Introspector<'s> {
+    shape: &'s mut Shape,
+    // Structs and enums being walked, recursive types stop at the second visit.
+    stack: &'s mut Vec<&'static str>,
+}
+
+impl Introspector<'_> {
+    fn enter(&mut self, name: &'static str) -> Result<()> {
+        if self.stack.contains(&name) {
+            return Err(Error::Message(format!("Recursive type `{}`", name)));
+        }
+        self.stack.push(name);
+
+        Ok(())
+    }
+}
+
+macro_rules! placeholder {
+    ($($method:ident => $visit:ident($($value:expr)?),)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
+                visitor.$visit($($value)?)
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for Introspector<'_> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value> where V: Visitor<'de> {
+        Err(Error::Message("Self describing types have no known shape".to_owned()))
+    }
+
+    placeholder! {
+        deserialize_bool => visit_bool(false),
+        deserialize_i8 => visit_i8(0),
+        deserialize_i16 => visit_i16(0),
+        deserialize_i32 => visit_i32(0),
+        deserialize_i64 => visit_i64(0),
+        deserialize_u8 => visit_u8(0),
+        deserialize_u16 => visit_u16(0),
+        deserialize_u32 => visit_u32(0),
+        deserialize_u64 => visit_u64(0),
+        deserialize_f32 => visit_f32(0.0),
+        deserialize_f64 => visit_f64(0.0),
+        deserialize_char => visit_char('\0'),
+        deserialize_str => visit_str(""),
+        deserialize_string => visit_str(""),
+        deserialize_bytes => visit_bytes(&[]),
+        deserialize_byte_buf => visit_bytes(&[]),
+        deserialize_unit => visit_unit(),
+        deserialize_identifier => visit_str(""),
+        deserialize_ignored_any => visit_unit(),
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    where V: Visitor<'de> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    where V: Visitor<'de> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
+        *self.shape = Shape::Seq(Box::default());
+        let Shape::Seq(element) = self.shape else { unreachable!() };
+
+        visitor.visit_seq(Elements { shape: Some(element), remaining: 1, stack: self.stack })
+    }
+
+    fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
+        visitor.visit_seq(Elements { shape: None, remaining: len, stack: self.stack })
+    }
+
+    fn deserialize_tuple_struct<V>(self, _name: &'static str, len: usize, visitor: V) -> Result<V::Value>
+    where V: Visitor<'de> {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value> where V: Visitor<'de> {
+        *self.shape = Shape::Map(Box::default());
+        let Shape::Map(value) = self.shape else { unreachable!() };
+
+        visitor.visit_map(Entries { value, done: false, stack: self.stack })
+    }
+
+    fn deserialize_struct<V>(
+        mut self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where V: Visitor<'de> {
+        // Recursive structs still get their own fields in order.
+        *self.shape = Shape::Struct(fields.iter().map(|&field| (field, Shape::Unknown)).collect());
+        self.enter(name)?;
+        let Shape::Struct(fields) = &mut *self.shape else { unreachable!() };
+
+        let result = visitor.visit_map(Fields { fields, index: 0, stack: &mut *self.stack });
+        self.stack.pop();
+
+        result
+    }
+
+    fn deserialize_enum<V>(
+        mut self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where V: Visitor<'de> {
+        let Some(&variant) = variants.first() else {
+            return Err(Error::Message(format!("Enum `{}` has no variants", name)));
+        };
+        self.enter(name)?;
+
+        let result = visitor.visit_enum(Variant { variant, shape: &mut *self.shape, stack: &mut *self.stack });
+        self.stack.pop();
+        // Whatever the content recorded isn't the shape of the enum itself.
+        *self.shape = Shape::Unknown;
+
+        result
+    }
+}
+
+struct Elements<'s> {
+    // `None` for tuples, whose elements differ in shape.
+    shape: Option<&'s mut Shape>,
+    remaining: usize,
+    stack: &'s mut Vec<&'static str>,
+}
+
+impl<'de> SeqAccess<'de> for Elements<'_> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+    where T: DeserializeSeed<'de> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+
+        let mut unknown = Shape::Unknown;
+        let shape = self.shape.as_deref_mut().unwrap_or(&mut unknown);
+        seed.deserialize(Introspector { shape, stack: self.stack }).map(Some)
+    }
+}
+
+struct Entries<'s> {
+    value: &'s mut Shape,
+    done: bool,
+    stack: &'s mut Vec<&'static str>,
+}
+
+impl<'de> MapAccess<'de> for Entries<'_> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+    where K: DeserializeSeed<'de> {
+        if self.done {
+            return Ok(None);
+        }
+        self.done = true;
+
+        seed.deserialize(Introspector { shape: &mut Shape::Unknown, stack: self.stack }).map(Some)
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+    where V: DeserializeSeed<'de> {
+        seed.deserialize(Introspector { shape: self.value, stack: self.stack })
+    }
+}
+
+struct Fields<'s> {
+    fields: &'s mut [(&'static str, Shape)],
+    index: usize,
+    stack: &'s mut Vec<&'static str>,
+}
+
+impl<'de> MapAccess<'de> for Fields<'_> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+    where K: DeserializeSeed<'de> {
+        match self.fields.get(self.index) {
+            Some(&(field, _)) => seed.deserialize(IntoDeserializer::<Error>::into_deserializer(field)).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+    where V: DeserializeSeed<'de> {
+        let shape = &mut self.fields[self.index].1;
+        self.index += 1;
+
+        seed.deserialize(Introspector { shape, stack: self.stack })
+    }
+}
+
+// Enums are walked through their first variant only, to reach the types
+// behind it.
+struct Variant<'s> {
+    variant: &'static str,
+    shape: &'s mut Shape,
+    stack: &'s mut Vec<&'static str>,
+}
+
+impl<'de, 's> EnumAccess<'de> for Variant<'s> {
+    type Error = Error;
+    type Variant = Introspector<'s>;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Introspector<'s>)>
+    where V: DeserializeSeed<'de> {
+        let variant = seed.deserialize(IntoDeserializer::<Error>::into_deserializer(self.variant))?;
+
+        Ok((variant, Introspector { shape: self.shape, stack: self.stack }))
+    }
+}
+
+impl<'de> VariantAccess<'de> for Introspector<'_> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+    where T: DeserializeSeed<'de> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V>(self, len: usize, visitor: V) -> Result<V::Value>
+    where V: Visitor<'de> {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn struct_variant<V>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value>
+    where V: Visitor<'de> {
+        self.deserialize_struct("", fields, visitor)
+    }
+}
@@ -0,0 +1,96 @@
+// A value kept as the text it was written as, for sections of a document that
+// are read later or by someone else, such as the settings of a plugin:
+//
+//     #[derive(Deserialize, Serialize)]
+//     struct Config<'a> {
+//         name: String,
+//         #[serde(borrow)]
+//         plugin: RawValue<'a>,
+//     }
+//
+// Read from zmerald text, the value is checked but not built and borrows the
+// text. Written to zmerald, the text is copied as is. Other formats see a
+// string.
+
+use std::borrow::Cow;
+use std::fmt;
+
+use serde::de::{ self, Deserialize, Deserializer, IgnoredAny, Visitor };
+use serde::ser::{ Serialize, Serializer };
+
+use super::{ Value, RAW_VALUE_TOKEN };
+use crate::error::{ Error, SpannedResult };
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct RawValue<'a> {
+    text: Cow<'a, str>,
+}
+
+impl<'a> RawValue<'a> {
+    // Fails unless `text` is a single value.
+    pub fn new(text: &'a str) -> SpannedResult<Self> {
+        crate::from_str::<IgnoredAny>(text)?;
+
+        Ok(RawValue { text: Cow::Borrowed(text) })
+    }
+
+    pub fn from_string(text: String) -> SpannedResult<RawValue<'static>> {
+        crate::from_str::<IgnoredAny>(&text)?;
+
+        Ok(RawValue { text: Cow::Owned(text) })
+    }
+
+    pub fn get(&self) -> &str {
+        &self.text
+    }
+
+    pub fn into_owned(self) -> RawValue<'static> {
+        RawValue { text: Cow::Owned(self.text.into_owned()) }
+    }
+
+    // The extensions of the document the value came from don't apply.
+    pub fn parse<'de, T>(&'de self) -> SpannedResult<T> where T: Deserialize<'de> {
+        crate::from_str(&self.text)
+    }
+}
+
+impl fmt::Display for RawValue<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+impl Serialize for RawValue<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        serializer.serialize_newtype_struct(RAW_VALUE_TOKEN, &*self.text)
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for RawValue<'a> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: Deserializer<'de> {
+        deserializer.deserialize_newtype_struct(RAW_VALUE_TOKEN, RawValueVisitor)
+    }
+}
+
+struct RawValueVisitor;
+
+impl<'de> Visitor<'de> for RawValueVisitor {
+    type Value = RawValue<'de>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("any value")
+    }
+
+    // From zmerald text, see `Deserializer::deserialize_newtype_struct`.
+    fn visit_borrowed_str<E>(self, text: &'de str) -> Result<Self::Value, E> where E: de::Error {
+        Ok(RawValue { text: Cow::Borrowed(text) })
+    }
+
+    // From any other format, the value is written as zmerald text.
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error> where D: Deserializer<'de> {
+        let value = Value::deserialize(deserializer)?;
+        let text = crate::ser::to_string(&value).map_err(|e: Error| de::Error::custom(e))?;
+
+        Ok(RawValue { text: Cow::Owned(text) })
+    }
+}
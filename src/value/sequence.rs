@@ -3,8 +3,15 @@ use super::Value;
 use crate::error::{ Error, Result };
 use serde::de::{ DeserializeSeed, SeqAccess };
 
+// Hands out the elements of a `Vec` it owns, without copying them.
 pub struct Seq {
-    pub seq: Vec<Value>,
+    seq: std::vec::IntoIter<Value>,
+}
+
+impl Seq {
+    pub fn new(seq: Vec<Value>) -> Self {
+        Seq { seq: seq.into_iter() }
+    }
 }
 
 impl<'de> SeqAccess<'de> for Seq {
@@ -12,9 +19,12 @@ impl<'de> SeqAccess<'de> for Seq {
 
     fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
     where T: DeserializeSeed<'de> {
-        // The `Vec` is reversed, so we can pop to get the originally first element
         self.seq
-            .pop()
+            .next()
             .map_or(Ok(None), |v| seed.deserialize(v).map(Some))
     }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.seq.len())
+    }
 } 
\ No newline at end of file
@@ -0,0 +1,62 @@
+use super::Value;
+
+use crate::error::{ Error, Result };
+use std::mem::size_of;
+
+impl Value {
+    // Estimated number of bytes owned by this value, itself included. Map
+    // entries are counted as a key and a value, ignoring tree node overhead.
+    pub fn deep_size_of(&self) -> usize {
+        size_of::<Value>() + heap_size(self, usize::MAX).unwrap_or(usize::MAX)
+    }
+
+    pub fn try_clone_within_limit(&self, limit: usize) -> Result<Value> {
+        let heap_limit = limit.checked_sub(size_of::<Value>());
+
+        match heap_limit.and_then(|heap_limit| heap_size(self, heap_limit)) {
+            Some(_) => Ok(self.clone()),
+            None => Err(Error::SizeLimitExceeded(limit)),
+        }
+    }
+}
+
+// Walks the tree with an explicit stack so deeply nested values cannot
+// overflow the call stack, stopping as soon as `limit` is exceeded.
+fn heap_size(value: &Value, limit: usize) -> Option<usize> {
+    let mut size = 0usize;
+    let mut stack = vec![value];
+
+    while let Some(value) = stack.pop() {
+        size = size.saturating_add(match value {
+            Value::String(s) => s.capacity(),
+            Value::Bytes(b) => b.capacity(),
+            Value::Seq(seq) => {
+                stack.extend(seq);
+                seq.capacity() * size_of::<Value>()
+            }
+            Value::Map(map) => {
+                stack.extend(map.iter().flat_map(|(k, v)| [k, v]));
+                map.len() * 2 * size_of::<Value>()
+            }
+            Value::Struct { name, fields } => {
+                stack.extend(fields.iter().flat_map(|(k, v)| [k, v]));
+                name.as_ref().map_or(0, String::capacity) + fields.len() * 2 * size_of::<Value>()
+            }
+            Value::Variant { name, content } => {
+                stack.push(content);
+                name.capacity() + size_of::<Value>()
+            }
+            Value::Option(Some(inner)) => {
+                stack.push(inner);
+                size_of::<Value>()
+            }
+            _ => 0,
+        });
+
+        if size > limit {
+            return None;
+        }
+    }
+
+    Some(size)
+}
@@ -0,0 +1,823 @@
+use super::*;
+
+#[test]
+fn test_accessors() {
+    let value: Value = r#"{ "name": "zmerald", "pi": 3.5, "n": 4, "ok": true, "c": 'c', "list": [1, 2] }"#
+        .parse()
+        .unwrap();
+
+    assert_eq!(value["name"].as_str(), Some("zmerald"));
+    assert_eq!(value["pi"].as_f64(), Some(3.5));
+    assert_eq!(value["n"].as_i64(), Some(4));
+    assert_eq!(value["n"].as_u64(), Some(4));
+    assert_eq!(value["n"].as_f64(), Some(4.0));
+    assert_eq!(value["ok"].as_bool(), Some(true));
+    assert_eq!(value["c"].as_char(), Some('c'));
+    assert_eq!(value["list"][1].as_i64(), Some(2));
+    assert_eq!(value["list"].as_seq().map(Vec::len), Some(2));
+
+    assert_eq!(value["name"].as_i64(), None);
+    assert!(value.as_map().is_some());
+}
+
+#[test]
+fn test_index_missing() {
+    let value: Value = r#"{ "list": [1] }"#.parse().unwrap();
+
+    assert!(value["missing"].is_unit());
+    assert!(value["list"][5].is_unit());
+    assert!(value["list"]["not a map"].is_unit());
+    assert!(value[0].is_unit());
+}
+
+#[test]
+fn test_duplicate_keys_last_wins() {
+    let value: Value = r#"{
+        "list": [1],
+        "list": [2, 3],
+        "map": { "a": 1 },
+        "map": { "b": 2 },
+        "n": 1,
+        "n": 2,
+    }"#
+    .parse()
+    .unwrap();
+
+    assert_eq!(value["list"].as_seq().map(Vec::len), Some(2));
+    assert!(value["map"]["a"].is_unit());
+    assert_eq!(value["map"]["b"].as_i64(), Some(2));
+    assert_eq!(value["n"].as_i64(), Some(2));
+}
+
+#[test]
+fn test_deep_size_of() {
+    use std::mem::size_of;
+
+    let unit = size_of::<Value>();
+    assert_eq!(Value::Unit.deep_size_of(), unit);
+    assert_eq!(Value::String(String::with_capacity(10)).deep_size_of(), unit + 10);
+    assert_eq!(Value::Seq(vec![Value::Unit; 3]).deep_size_of(), unit + 3 * unit);
+
+    let value: Value = r#"{ "a": ["xyz"] }"#.parse().unwrap();
+    assert!(value.deep_size_of() > 4 * unit);
+    assert_eq!(value.try_clone_within_limit(value.deep_size_of()), Ok(value.clone()));
+    assert_eq!(
+        value.try_clone_within_limit(value.deep_size_of() - 1),
+        Err(Error::SizeLimitExceeded(value.deep_size_of() - 1))
+    );
+    assert!(value.try_clone_within_limit(0).is_err());
+}
+
+#[test]
+fn test_deep_size_of_pathological() {
+    let mut deep = Value::Unit;
+    for _ in 0..2_000 {
+        deep = Value::Option(Some(Box::new(Value::Seq(vec![deep]))));
+    }
+
+    let size = deep.deep_size_of();
+    assert!(size > 4_000 * std::mem::size_of::<Value>());
+    assert!(deep.try_clone_within_limit(1_024).is_err());
+
+    let wide = Value::Seq(vec![Value::String(String::from("wide")); 100_000]);
+    assert!(wide.try_clone_within_limit(1_024).is_err());
+    assert_eq!(wide.try_clone_within_limit(wide.deep_size_of()).map(|v| v.deep_size_of()), Ok(wide.deep_size_of()));
+}
+
+#[test]
+fn test_zmerald_macro() {
+    let name = "x";
+    let value = crate::zmerald!{
+        name: name,
+        list: [1, 2, -3,],
+        "quoted key": { on: true, off: false },
+        opt: Some([None, Some(1.5)]),
+        unit: (),
+        sum: 1 + 2,
+    };
+
+    let parsed: Value = r#"{
+        name: "x",
+        list: [1, 2, -3],
+        "quoted key": { on: true, off: false },
+        opt: Some([None, Some(1.5)]),
+        unit: (),
+        sum: 3,
+    }"#.parse().unwrap();
+    assert_eq!(value, parsed);
+    assert_eq!(crate::zmerald!(Point { x: 1 }), "Point { x: 1 }".parse().unwrap());
+    assert_eq!(crate::zmerald!({ "x": 1 }), r#"{ "x": 1 }"#.parse().unwrap());
+
+    assert_eq!(crate::zmerald!([]), Value::Seq(vec![]));
+    assert_eq!(crate::zmerald!({}), Value::Map(Map::new()));
+    assert_eq!(crate::zmerald!('c'), Value::Char('c'));
+    assert_eq!(crate::zmerald!(vec![1u8, 2]), Value::Seq(vec![Value::from(1), Value::from(2)]));
+}
+
+
+#[test]
+fn test_struct_and_variant_identity() {
+    let parse = |s: &str| s.parse::<Value>().unwrap();
+    let fields = |entries: &[(&str, Value)]| entries.iter().map(|(k, v)| (Value::from(*k), v.clone())).collect::<Map>();
+
+    assert_eq!(
+        parse("MyStruct{x:4}"),
+        Value::Struct { name: Some(String::from("MyStruct")), fields: fields(&[("x", Value::from(4))]) }
+    );
+    assert_eq!(parse("{ x: 4 }"), Value::Struct { name: None, fields: fields(&[("x", Value::from(4))]) });
+    assert_eq!(parse(r#"{ "x": 4 }"#), Value::Map(fields(&[("x", Value::from(4))])));
+    assert_eq!(parse("{ <x> 4 }"), Value::Map(fields(&[("x", Value::from(4))])));
+
+    let variant = |name: &str, content: Value| Value::Variant { name: String::from(name), content: Box::new(content) };
+    assert_eq!(parse("A"), variant("A", Value::Unit));
+    assert_eq!(parse("B(true)"), variant("B", Value::Bool(true)));
+    assert_eq!(parse("C(true, 3.5,)"), variant("C", Value::from(vec![Value::Bool(true), Value::from(3.5)])));
+    assert_eq!(parse("D([1])"), variant("D", Value::from(vec![1])));
+    assert_eq!(parse("r#true"), variant("true", Value::Unit));
+    assert_eq!(parse("Wrap(Inner { a: 1 })").name(), Some("Wrap"));
+    assert_eq!(parse("Wrap(Inner { a: 1 })")["a"], Value::Unit);
+
+    let value = parse(r#"Config { name: "x", kinds: [A, B(1), C(1, 2), D([1]), E { r#type: 1 }], "two words": { <k> Foo(1) } }"#);
+    assert_eq!(value.name(), Some("Config"));
+    assert_eq!(value["name"], Value::from("x"));
+
+    let s = crate::ser::to_string(&value).unwrap();
+    assert_eq!(s, r#"Config{name:"x",kinds:[A,B(1),C(1,2),D([1]),E{type:1}],"two words":{"k":Foo(1)}}"#);
+    assert_eq!(parse(&s), value);
+}
+
+#[test]
+fn test_struct_and_variant_into_rust() {
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    enum Kind {
+        A,
+        B(i32),
+        C(i32, i32),
+        D { a: i32 },
+    }
+
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    struct Config {
+        layout: String,
+        kinds: Vec<Kind>,
+    }
+
+    let value: Value = "Config { layout: us, kinds: [A, B(1), C(1, 2), D { a: 3 }] }".parse().unwrap();
+    assert_eq!(value.clone().into_rust::<Value>(), Ok(value.clone()));
+    assert_eq!(
+        value.into_rust(),
+        Ok(Config {
+            layout: String::from("us"),
+            kinds: vec![Kind::A, Kind::B(1), Kind::C(1, 2), Kind::D { a: 3 }],
+        })
+    );
+}
+
+#[test]
+fn test_deserialize_borrowed() {
+    use serde::Deserialize;
+    use std::collections::HashMap;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    enum Kind<'a> {
+        A,
+        B(i32),
+        C(i32, i32),
+        D { a: i32 },
+        E(#[serde(borrow)] &'a str),
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct View<'a> {
+        layout: &'a str,
+        #[serde(borrow)]
+        kinds: Vec<Kind<'a>>,
+        scale: Option<f32>,
+        #[serde(borrow)]
+        keys: HashMap<&'a str, u8>,
+    }
+
+    let value: Value = r#"View { layout: us, kinds: [A, B(1), C(1, 2), D { a: 3 }, E("x")], scale: 1.5, keys: { "q": 1 } }"#
+        .parse()
+        .unwrap();
+
+    // the same view can be taken repeatedly, borrowing from the value
+    for _ in 0..2 {
+        let view = View::deserialize(&value).unwrap();
+        assert_eq!(view.kinds, vec![Kind::A, Kind::B(1), Kind::C(1, 2), Kind::D { a: 3 }, Kind::E("x")]);
+        assert_eq!((view.layout, view.scale, view.keys["q"]), ("us", Some(1.5), 1));
+    }
+    assert_eq!(Value::deserialize(&value), Ok(value.clone()));
+    assert_eq!(Value::deserialize(&value["kinds"]), Ok(value["kinds"].clone()));
+    assert_eq!(<(i32, i32)>::deserialize(&Value::from(vec![1, 2])), Ok((1, 2)));
+    assert!(u8::deserialize(&Value::from("1")).is_err());
+}
+
+#[test]
+fn test_map_str_keys() {
+    let mut map = Map::new();
+    assert_eq!(map.insert_str("a", Value::from(1)), None);
+    assert_eq!(map.insert_str("a", Value::from(2)), Some(Value::from(1)));
+    assert_eq!(map.get_str("a"), Some(&Value::from(2)));
+    assert_eq!(map.get(&Value::from("a")), Some(&Value::from(2)));
+    assert_eq!(map.get_str("b"), None);
+
+    assert_eq!(map.remove_str("a"), Some(Value::from(2)));
+    assert!(map.is_empty());
+}
+
+#[test]
+fn test_ordered_like() {
+    use serde::Deserialize;
+    use std::collections::BTreeMap;
+
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    struct Key {
+        zeta: u8,
+        alpha: u8,
+    }
+
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    struct Layout {
+        name: String,
+        keys: Vec<Key>,
+        named: BTreeMap<String, Key>,
+        fallback: Option<Box<Layout>>,
+    }
+
+    let s = "{fallback:{extra:1,keys:[],name:uk,named:{}},keys:[{alpha:2,zeta:1}],name:us,named:{a:{alpha:4,zeta:3}}}";
+    let value: Value = s.parse().unwrap();
+
+    assert_eq!(crate::ser::to_string(&value).unwrap(), s);
+    assert_eq!(
+        crate::ser::to_string(&value.ordered_like::<Layout>()).unwrap(),
+        "{name:us,keys:[{zeta:1,alpha:2}],named:{a:{zeta:3,alpha:4}},fallback:{name:uk,keys:[],named:{},extra:1}}"
+    );
+}
+
+#[test]
+fn test_map_order_and_entries() {
+    let mut value: Value = "{ \"b\": 1, \"c\": 2, \"a\": 3 }".parse().unwrap();
+    let map = value.as_map_mut().unwrap();
+    assert_eq!(map.keys().map(|k| k.as_str().unwrap()).collect::<String>(), "bca");
+
+    *map.entry("d").or_insert(Value::from(0)) = Value::from(4);
+    *map.entry("b").or_insert(Value::from(0)) = Value::from(5);
+    assert!(map.contains_key(&Value::from("d")));
+    *map.get_mut(&Value::from("c")).unwrap() = Value::from(6);
+    assert_eq!(crate::ser::to_string(&value).unwrap(), r#"{"b":5,"c":6,"a":3,"d":4}"#);
+
+    let map = value.as_map_mut().unwrap();
+    assert_eq!(map.remove(&Value::from("b")), Some(Value::from(5)));
+    assert_eq!(crate::ser::to_string(map).unwrap(), r#"{"c":6,"a":3,"d":4}"#);
+    assert_eq!(map.swap_remove(&Value::from("c")), Some(Value::from(6)));
+    assert_eq!(crate::ser::to_string(map).unwrap(), r#"{"d":4,"a":3}"#);
+
+    map.insert_str("b", Value::from(2));
+    map.retain(|_, v| v.as_i64() != Some(4));
+    map.sort_keys();
+    assert_eq!(crate::ser::to_string(map).unwrap(), r#"{"a":3,"b":2}"#);
+}
+
+#[test]
+fn test_map_collection_traits() {
+    let parse = |s: &str| s.parse::<Value>().unwrap().as_map().unwrap().clone();
+    let text = |map: &Map| crate::ser::to_string(map).unwrap();
+
+    let mut map = parse(r#"{ "b": 1, "a": 2 }"#);
+    for (_, value) in &mut map {
+        *value = Value::from(value.as_i64().unwrap() * 10);
+    }
+    assert_eq!((&map).into_iter().map(|(k, _)| k.as_str().unwrap()).collect::<String>(), "ba");
+
+    map.extend([(Value::from("c"), Value::from(3)), (Value::from("b"), Value::from(4))]);
+    assert_eq!(text(&map), r#"{"b":4,"a":20,"c":3}"#);
+
+    let mut other = parse(r#"{ "d": 5, "a": 6 }"#);
+    map.append(&mut other);
+    assert!(other.is_empty());
+    assert_eq!(text(&map), r#"{"b":4,"a":6,"c":3,"d":5}"#);
+
+    assert_eq!(map.remove_entry(&Value::from("a")), Some((Value::from("a"), Value::from(6))));
+    assert_eq!(map.remove_entry(&Value::from("a")), None);
+    assert_eq!(map.clone().into_keys().collect::<Vec<_>>(), vec![Value::from("b"), Value::from("c"), Value::from("d")]);
+    assert_eq!(map.clone().into_values().collect::<Vec<_>>(), vec![Value::from(4), Value::from(3), Value::from(5)]);
+
+    let mut keys = String::new();
+    for (key, _) in map {
+        keys.push_str(key.as_str().unwrap());
+    }
+    assert_eq!(keys, "bcd");
+}
+
+#[test]
+fn test_map_equality() {
+    use std::collections::BTreeMap;
+
+    let parse = |s: &str| s.parse::<Value>().unwrap().as_map().unwrap().clone();
+
+    assert_ne!(parse(r#"{ "a": 1 }"#), parse(r#"{ "a": 1, "b": 2 }"#));
+    assert_ne!(parse(r#"{ "a": 1, "b": 2 }"#), parse(r#"{ "a": 1 }"#));
+    assert_ne!(parse("{}"), parse(r#"{ "a": 1 }"#));
+    assert_eq!(parse(r#"{ "a": 1, "b": 2 }"#), parse(r#"{ "a": 1, "b": 2 }"#));
+    let (ab, ba) = (parse(r#"{ "a": 1, "b": 2 }"#), parse(r#"{ "b": 2, "a": 1 }"#));
+    assert_eq!(ab, ba);
+    assert_eq!(ab.cmp(&ba), std::cmp::Ordering::Equal);
+    let state = std::collections::hash_map::RandomState::new();
+    let hash = |map: &crate::value::Map| std::hash::BuildHasher::hash_one(&state, map);
+    assert_eq!(hash(&ab), hash(&ba));
+    assert_ne!(ab, parse(r#"{ "a": 1, "b": 3 }"#));
+
+    let map = parse(r#"{ "b": 2, "a": [1] }"#);
+    assert_eq!(map.get("a"), Some(&Value::from(vec![1])));
+    assert_eq!(map.get(&String::from("b")), map.get(&Value::from("b")));
+    assert_eq!(map.get("c"), None);
+
+    let mut tree = BTreeMap::from([(String::from("a"), Value::from(vec![1])), (String::from("b"), Value::from(2))]);
+    assert_eq!(map, tree);
+    assert_eq!(tree, map);
+    tree.insert(String::from("c"), Value::Unit);
+    assert_ne!(map, tree);
+    assert_ne!(tree, map);
+    assert_ne!(parse(r#"{ "b": 2, "a": [1], 1: 3 }"#), BTreeMap::from([(String::from("a"), Value::from(vec![1])), (String::from("b"), Value::from(2))]));
+}
+
+#[test]
+fn test_deserialize_option() {
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct S {
+        a: Option<u32>,
+        b: Option<u32>,
+        c: Option<u32>,
+    }
+
+    // JSON's `null` is read into a `Value` as `Unit`, and stands for `None`
+    let value: Value = serde_json::from_str(r#"{ "a": null, "b": 1, "c": null }"#).unwrap();
+    let expected = S { a: None, b: Some(1), c: None };
+    assert_eq!(S::deserialize(&value), Ok(expected));
+    assert_eq!(S::deserialize(value), Ok(S { a: None, b: Some(1), c: None }));
+
+    let value: Value = "{ a: None, b: Some(1), c: 2 }".parse().unwrap();
+    assert_eq!(S::deserialize(value), Ok(S { a: None, b: Some(1), c: Some(2) }));
+}
+
+#[test]
+fn test_deserialize_consumes() {
+    use serde::de::{ Deserialize, MapAccess, SeqAccess };
+    use std::fmt;
+
+    // The keys or elements in order, and the size hint given before them.
+    #[derive(Debug, PartialEq)]
+    struct Seen(Option<usize>, Vec<Value>);
+
+    impl<'de> Deserialize<'de> for Seen {
+        fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error> where D: Deserializer<'de> {
+            struct SeenVisitor;
+
+            impl<'de> Visitor<'de> for SeenVisitor {
+                type Value = Seen;
+
+                fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    write!(f, "a map or a sequence")
+                }
+
+                fn visit_map<A>(self, mut map: A) -> std::result::Result<Seen, A::Error> where A: MapAccess<'de> {
+                    let mut seen = Seen(map.size_hint(), Vec::new());
+                    while let Some((key, _)) = map.next_entry::<Value, Value>()? {
+                        seen.1.push(key);
+                    }
+                    Ok(seen)
+                }
+
+                fn visit_seq<A>(self, mut seq: A) -> std::result::Result<Seen, A::Error> where A: SeqAccess<'de> {
+                    let mut seen = Seen(seq.size_hint(), Vec::new());
+                    while let Some(element) = seq.next_element()? {
+                        seen.1.push(element);
+                    }
+                    Ok(seen)
+                }
+            }
+
+            deserializer.deserialize_any(SeenVisitor)
+        }
+    }
+
+    let parse = |s: &str| s.parse::<Value>().unwrap();
+    let keys = |keys: &str| keys.chars().map(|c| Value::from(c.to_string())).collect();
+    assert_eq!(parse(r#"{ "b": 1, "c": 2, "a": 3 }"#).into_rust(), Ok(Seen(Some(3), keys("bca"))));
+    assert_eq!(parse("Point { y: 1, x: 2 }").into_rust(), Ok(Seen(Some(2), keys("yx"))));
+    assert_eq!(parse("[3, 1, 2]").into_rust(), Ok(Seen(Some(3), vec![Value::from(3), Value::from(1), Value::from(2)])));
+
+    // the struct name comes first when reading a `Value`
+    assert_eq!(Value::deserialize(parse("Point { y: 1, x: 2 }")), Ok(parse("Point { y: 1, x: 2 }")));
+}
+
+#[test]
+fn test_float_semantics() {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{ Hash, Hasher };
+
+    let hash = |f: f64| {
+        let mut hasher = DefaultHasher::new();
+        Value::from(f).hash(&mut hasher);
+        hasher.finish()
+    };
+
+    assert_ne!(hash(0.5), hash(0.7));
+    assert_ne!(Value::from(0.5), Value::from(0.7));
+    assert_eq!(hash(f64::NAN), hash(-f64::NAN));
+    assert_eq!(Value::from(f64::NAN), Value::from(-f64::NAN));
+
+    let mut floats = [f64::NAN, 1.0, f64::INFINITY, 0.0, -0.0, f64::NEG_INFINITY].map(Float::new);
+    floats.sort();
+    assert_eq!(floats.map(Float::to_bits), [f64::NEG_INFINITY, -0.0, 0.0, 1.0, f64::INFINITY, f64::NAN].map(f64::to_bits));
+    assert_eq!(Float::from_bits(2.5f64.to_bits()).get(), 2.5);
+
+    let mut map = Map::new();
+    map.insert(Value::from(0.5), Value::from("half"));
+    map.insert(Value::from(0.7), Value::from("more"));
+    assert_eq!(map.len(), 2);
+    assert_eq!(map[&Value::from(0.5)], Value::from("half"));
+}
+
+#[cfg(feature = "arbitrary_precision")]
+#[test]
+fn test_arbitrary_precision() {
+    let s = "{price:19.999999999999999999,big:123456789012345678901234567890,small:-7,hex:255}";
+    let value: Value = s.parse().unwrap();
+
+    assert_eq!(value["price"], Value::Number(Number::Literal(String::from("19.999999999999999999"))));
+    assert_eq!(crate::ser::to_string(&value).unwrap(), s);
+
+    assert_eq!(value["small"], Value::from(-7));
+    assert_eq!(value["small"].as_i64(), Some(-7));
+    assert_eq!(value["hex"].as_i64(), Some(255));
+    assert_eq!(value["big"].as_i64(), None);
+    assert_eq!(value["big"].as_f64(), Some(1.2345678901234568e29));
+
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    struct Prices {
+        price: String,
+        small: i8,
+        hex: u32,
+        big: f64,
+    }
+
+    assert_eq!(
+        value.into_rust(),
+        Ok(Prices { price: String::from("19.999999999999999999"), small: -7, hex: 255, big: 1.2345678901234568e29 })
+    );
+}
+
+#[test]
+fn test_roundtrip_util() {
+    use crate::test_util::{ roundtrip_identifier, roundtrip_value };
+
+    for name in ["a", "snake_case", "CamelCase", "_x1", "true", "None", "inf", "1a", "a-b"] {
+        assert!(roundtrip_identifier(name), "{}", name);
+    }
+    for name in ["", "a b", "a:b", "\"q\"", "é"] {
+        assert!(!roundtrip_identifier(name), "{}", name);
+    }
+
+    let value: Value = "{ a: [1, -2.5, \"s\", 'c'], b: true, <[1]> None, c: Point { x: 1 } }".parse().unwrap();
+    assert!(roundtrip_value(&value));
+}
+
+#[test]
+fn test_large_integers() {
+    let value = Value::from(u64::MAX);
+    assert_eq!(value.as_i64(), None);
+    assert_eq!(Value::from(7u64).as_u64(), Some(7));
+    assert_eq!(Value::from(-7).as_u64(), None);
+    assert!(Value::from(-7).into_rust::<u64>().is_err());
+
+    // Without `integer128` integers past `i64::MAX` become floats and lose
+    // precision, with it they stay exact.
+    #[cfg(not(feature = "integer128"))]
+    {
+        assert_eq!(value, Value::Number(Number::new(u64::MAX as f64)));
+        assert_eq!(value.as_u64(), None);
+    }
+    #[cfg(feature = "integer128")]
+    {
+        assert_eq!(value, Value::Number(Number::Integer(i128::from(u64::MAX))));
+        assert_eq!(value.as_u64(), Some(u64::MAX));
+        assert_eq!("18446744073709551615".parse::<Value>(), Ok(value.clone()));
+        assert_eq!(value.clone().into_rust::<u64>(), Ok(u64::MAX));
+        assert_eq!(crate::ser::to_string(&value).unwrap(), "18446744073709551615");
+
+        let huge = Value::from(i128::MIN);
+        assert_eq!(crate::ser::to_string(&huge).unwrap(), i128::MIN.to_string());
+        assert_eq!(huge.into_rust::<i128>(), Ok(i128::MIN));
+    }
+}
+
+#[test]
+fn test_untagged_struct_variants() {
+    use serde::de::Error as _;
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct Circle {
+        r: f32,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[serde(untagged)]
+    enum Derived {
+        Circle { r: f32 },
+        Point { x: i32, y: i32 },
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Shape {
+        Circle(Circle),
+        Point(Point),
+    }
+
+    impl<'de> Deserialize<'de> for Shape {
+        fn deserialize<D: Deserializer<'de>>(d: D) -> std::result::Result<Self, D::Error> {
+            Untagged::new(Value::deserialize(d)?)
+                .variant("Circle", Shape::Circle)
+                .variant("Point", Shape::Point)
+                .finish("Shape")
+                .map_err(D::Error::custom)
+        }
+    }
+
+    assert_eq!(crate::from_str("{ x: 1, y: 2 }"), Ok(Derived::Point { x: 1, y: 2 }));
+    assert_eq!(crate::from_str("Circle { r: 1.5 }"), Ok(Derived::Circle { r: 1.5 }));
+    assert_eq!(crate::from_str("{ x: 1, y: 2 }"), Ok(Shape::Point(Point { x: 1, y: 2 })));
+    assert_eq!(crate::from_str("{ r: 2 }"), Ok(Shape::Circle(Circle { r: 2.0 })));
+
+    let error = Untagged::new("{ x: 1 }".parse::<Value>().unwrap())
+        .variant("Circle", Shape::Circle)
+        .variant("Point", Shape::Point)
+        .finish("Shape")
+        .unwrap_err();
+    match &error {
+        Error::NoMatchingVariant { name: "Shape", errors } => {
+            assert_eq!(errors.iter().map(|(name, _)| *name).collect::<Vec<_>>(), ["Circle", "Point"]);
+        }
+        other => panic!("{:?}", other),
+    }
+    assert!(error.to_string().starts_with("Data did not match any variant of untagged enum `Shape`\n  `Circle`: "));
+    assert!(crate::from_str::<Shape>("{ x: 1 }").is_err());
+}
+
+#[test]
+fn test_comments() {
+    use crate::comments::{ self, NodeComments, Segment };
+    use crate::ser::PrettyConfig;
+
+    let source = "# app settings
+{
+    # the main window
+    window: {
+        width: 800, # px
+        height: 600,
+    },
+    tags: [
+        \"a\",
+        # second
+        \"b\", # trailing
+    ],
+}
+";
+    let (mut value, mut comments) = comments::from_str(source).unwrap();
+    let path = |keys: &[&str]| keys.iter().map(|k| Segment::Key(String::from(*k))).collect::<Vec<_>>();
+    assert_eq!(comments.get(&[]).unwrap().leading, vec![String::from(" app settings")]);
+    assert_eq!(comments.get(&path(&["window"])).unwrap().leading, vec![String::from(" the main window")]);
+    assert_eq!(comments.get(&path(&["window", "width"])).unwrap().trailing.as_deref(), Some(" px"));
+    let second = [Segment::Key(String::from("tags")), Segment::Index(1)];
+    assert_eq!(
+        comments.get(&second),
+        Some(&NodeComments { leading: vec![String::from(" second")], trailing: Some(String::from(" trailing")) })
+    );
+    assert_eq!(comments.iter().count(), 4);
+
+    // edits to the value keep the comments
+    value.as_fields_mut().unwrap().insert(Value::String(String::from("extra")), Value::Bool(true));
+    comments.insert(path(&["extra"]), NodeComments { leading: Vec::new(), trailing: Some(String::from(" new")) });
+    let output = comments::to_string_pretty(&value, &comments, PrettyConfig::new()).unwrap();
+    assert_eq!(
+        output,
+        source.replace("    ],\n}", "    ],\n    extra: true, # new\n}").trim_end()
+    );
+    assert_eq!(comments::from_str(&output).unwrap().1, comments);
+
+    // on one line there is nowhere to put them
+    let compact = comments::to_string_pretty(&value, &comments, PrettyConfig::new().depth_limit(0)).unwrap();
+    assert_eq!(compact.lines().count(), 2);
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_json_interop() {
+    use crate::value::{ from_json_str, to_json_string };
+
+    let json = r#"{"debug":false,"name":"benten","scale":1.5,"size":[800,600],"theme":null}"#;
+    let value = from_json_str(json).unwrap();
+    assert_eq!(value.as_map().unwrap().len(), 5);
+    assert_eq!(to_json_string(&value).unwrap(), json);
+
+    // and on to zmerald and back
+    let text = crate::ser::to_string(&value).unwrap();
+    assert_eq!(to_json_string(&text.parse().unwrap()).unwrap(), json);
+
+    let value: Value = "Config { window: Tiled(2), mode: Dark, tag: 'x', keys: { <1> \"one\" } }".parse().unwrap();
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&to_json_string(&value).unwrap()).unwrap(),
+        serde_json::json!({ "window": { "Tiled": 2 }, "mode": "Dark", "tag": "x", "keys": { "1": "one" } })
+    );
+    assert!(from_json_str("{").is_err());
+
+    // serde_json reads documents directly, with no types to guide it
+    let json: serde_json::Value = crate::from_str("Window { size: (800, 600), tags: [a, b], title: \"main\" }").unwrap();
+    assert_eq!(json, serde_json::json!({ "title": "main", "size": [800, 600], "tags": ["a", "b"] }));
+    assert_eq!(crate::ser::to_string(&json).unwrap(), "{\"size\":[800,600],\"tags\":[\"a\",\"b\"],\"title\":\"main\"}");
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_json_transcode() {
+    let json = r#"{"name":"benten","size":[800,600],"scale":1.5,"theme":null,"keys":{"q":"Quit"},"debug":false}"#;
+
+    let mut text = Vec::new();
+    let mut ser = crate::ser::Serializer::new(&mut text, None).unwrap();
+    serde_transcode::transcode(&mut serde_json::Deserializer::from_str(json), &mut ser).unwrap();
+    let text = String::from_utf8(text).unwrap();
+    assert_eq!(text, "{\"name\":\"benten\",\"size\":[800,600],\"scale\":1.5,\"theme\":(),\"keys\":{\"q\":\"Quit\"},\"debug\":false}");
+
+    // and back, in the same order
+    let mut back = Vec::new();
+    let mut de = crate::de::Deserializer::from_str(&text).unwrap();
+    serde_transcode::transcode(&mut de, &mut serde_json::Serializer::new(&mut back)).unwrap();
+    de.end().unwrap();
+    assert_eq!(String::from_utf8(back).unwrap(), json);
+}
+
+#[cfg(feature = "interop")]
+#[test]
+fn test_toml_yaml_interop() {
+    use crate::value::{ from_toml_str, from_yaml_str, to_toml_string, to_yaml_string };
+
+    let toml = "name = \"benten\"\nsize = [800, 600]\n\n[theme]\ndark = true\nscale = 1.5\n";
+    let value = from_toml_str(toml).unwrap();
+    assert_eq!(to_toml_string(&value).unwrap(), toml);
+    assert_eq!(crate::ser::to_string(&value).unwrap(), "{\"name\":\"benten\",\"size\":[800,600],\"theme\":{\"dark\":true,\"scale\":1.5}}");
+
+    let value: Value = "Config { mode: Tiled(2), theme: None, keys: { <1> Quit } }".parse().unwrap();
+    assert_eq!(to_toml_string(&value).unwrap(), "[mode]\nTiled = 2\n\n[keys]\n1 = \"Quit\"\n");
+    assert!(to_toml_string(&Value::Seq(vec![Value::Unit])).is_err());
+    assert!(from_toml_str("a = ").is_err());
+
+    let yaml = "name: benten\nsize:\n- 800\n- 600\ntheme: null\nmode: !Tiled 2\n";
+    let value = from_yaml_str(yaml).unwrap();
+    assert_eq!(value.as_map().unwrap().len(), 4);
+    assert_eq!(to_yaml_string(&value).unwrap(), yaml);
+    assert_eq!(crate::ser::to_string(&value).unwrap(), "{\"name\":\"benten\",\"size\":[800,600],\"theme\":None,\"mode\":Tiled(2)}");
+}
+
+#[cfg(all(feature = "uuid", feature = "chrono"))]
+#[test]
+fn test_uuid_chrono() {
+    use chrono::{ DateTime, TimeZone, Utc };
+    use uuid::Uuid;
+
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct Session {
+        id: Uuid,
+        started: DateTime<Utc>,
+    }
+
+    let id = Uuid::parse_str("67e55044-10b1-426f-9247-bb680e5fe0c8").unwrap();
+    let started = Utc.with_ymd_and_hms(2023, 1, 1, 12, 0, 0).unwrap();
+    let text = crate::ser::to_string(&Session { id, started }).unwrap();
+    assert_eq!(text, "{id:\"67e55044-10b1-426f-9247-bb680e5fe0c8\",started:\"2023-01-01T12:00:00Z\"}");
+    assert_eq!(crate::from_str(&text), Ok(Session { id, started }));
+
+    let value: Value = text.parse().unwrap();
+    assert_eq!(value["id"].as_uuid(), Some(id));
+    assert_eq!(value["started"].as_datetime(), Some(started.fixed_offset()));
+    assert_eq!(Value::from(id), value["id"]);
+    assert_eq!(Value::from(started), value["started"]);
+    assert_eq!(Value::from("soon").as_datetime(), None);
+}
+
+#[test]
+fn test_anchors() {
+    use crate::error::Error;
+    use crate::options::{ Limits, Options };
+
+    let source = "#![enable(anchors)]
+    {
+        base: &light Theme { fg: \"black\", bg: \"white\" },
+        panel: *light,
+        list: [&one 1, *one, \"*.txt\"],
+    }";
+    let allow = Options::new().extension("anchors");
+    let value: Value = allow.from_str(source).unwrap();
+    assert_eq!(value["panel"], value["base"]);
+    assert_eq!(value["panel"], "Theme { fg: \"black\", bg: \"white\" }".parse::<Value>().unwrap());
+    assert_eq!(value["list"], Value::Seq(vec![Value::from(1), Value::from(1), Value::from("*.txt")]));
+    assert!(crate::de::syntax_errors(source).is_empty());
+    assert!(source.parse::<crate::document::Document>().is_ok());
+
+    assert_eq!(
+        allow.from_str::<Value>("#![enable(anchors)] [*theme, &theme 1]").map_err(|e| e.code),
+        Err(Error::UndefinedAnchor(String::from("theme")))
+    );
+    // typed strings may still start with `*`
+    assert_eq!(allow.from_str("#![enable(anchors)] [*light]"), Ok(vec![String::from("*light")]));
+
+    // documents cannot enable it on their own
+    assert_eq!(source.parse::<Value>().map_err(|e| e.code), Err(Error::ExtensionNotAllowed(String::from("anchors"))));
+
+    // each label copies the one before ten times, 10^7 values from 300 bytes
+    let mut bomb = String::from("#![enable(anchors)] [&a0 1");
+    for level in 1..8 {
+        bomb.push_str(&format!(", &a{} [{}]", level, vec![format!("*a{}", level - 1); 10].join(", ")));
+    }
+    bomb.push(']');
+    assert_eq!(allow.from_str::<Value>(&bomb).map_err(|e| e.code), Err(Error::ExpansionTooLarge(1 << 20)));
+    let limits = Limits::new().expansion(2);
+    assert_eq!(allow.clone().limits(limits).from_str::<Value>("#![enable(anchors)] [&a 1, *a, *a]").map(|v| v.as_seq().map(Vec::len)), Ok(Some(3)));
+    assert_eq!(
+        allow.limits(Limits::new().expansion(1)).from_str::<Value>("#![enable(anchors)] [&a 1, *a, *a]").map_err(|e| e.code),
+        Err(Error::ExpansionTooLarge(1))
+    );
+}
+
+#[test]
+fn test_raw_value() {
+    use serde::{ Deserialize, Serialize };
+
+    #[derive(Debug, Deserialize, Serialize)]
+    struct Config<'a> {
+        name: String,
+        #[serde(borrow)]
+        plugin: RawValue<'a>,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Plugin {
+        level: u8,
+        tags: Vec<String>,
+    }
+
+    let source = "{ name: \"main\", plugin: Plugin { level: 2, tags: [\"a, b\", c] } }";
+    let config: Config = crate::from_str(source).unwrap();
+    assert_eq!(config.plugin.get(), "Plugin { level: 2, tags: [\"a, b\", c] }");
+    assert_eq!(config.plugin.parse(), Ok(Plugin { level: 2, tags: vec![String::from("a, b"), String::from("c")] }));
+    assert_eq!(crate::ser::to_string(&config).unwrap(), "{name:\"main\",plugin:Plugin { level: 2, tags: [\"a, b\", c] }}");
+
+    // read through another deserializer, the value is written out
+    let value: Value = crate::from_str(source).unwrap();
+    let owned = RawValue::deserialize(value["plugin"].clone()).unwrap();
+    assert_eq!(owned.parse::<Plugin>().unwrap().level, 2);
+
+    assert!(RawValue::new("[1, 2]").is_ok());
+    assert_eq!(RawValue::new("[1, 2").unwrap_err().code, Error::Eof);
+    assert!(RawValue::from_string(String::from("1 2")).is_err());
+}
+
+#[cfg(feature = "arena")]
+#[test]
+fn test_value_ref() {
+    use crate::value::ValueRef;
+
+    let arena = bumpalo::Bump::new();
+    let source = "Scene { name: \"main\", tags: [\"a\\tb\", c], light: Some(Point(1, 2.5)), bytes: x\"00ff\", b: Unit, n: None }";
+    let value = crate::de::from_str_in(source, &arena).unwrap();
+    assert_eq!(value.to_value(), source.parse::<Value>().unwrap());
+
+    // unescaped strings are borrowed from the input, the others are copied
+    let name = value.get("name").and_then(ValueRef::as_str).unwrap();
+    assert!(source.as_bytes().as_ptr_range().contains(&name.as_ptr()));
+    let tags = value.get("tags").and_then(ValueRef::as_seq).unwrap();
+    assert_eq!(tags[0].as_str(), Some("a\tb"));
+    assert!(!source.as_bytes().as_ptr_range().contains(&tags[0].as_str().unwrap().as_ptr()));
+    assert!(matches!(value, ValueRef::Struct { name: Some("Scene"), .. }));
+
+    // duplicate keys are kept, the last one wins in a `Value`
+    let value = crate::de::from_str_in("{ a: [1], a: [2] }", &arena).unwrap();
+    let ValueRef::Struct { fields: entries, .. } = value else { panic!("expected a struct, found {value:?}") };
+    assert_eq!(entries.len(), 2);
+    assert_eq!(value.get("a").and_then(ValueRef::as_seq).map(<[_]>::len), Some(1));
+    assert_eq!(value.to_value(), "{ a: [2] }".parse::<Value>().unwrap());
+
+    assert!(crate::de::from_str_in("[1, 2", &arena).is_err());
+}
@@ -0,0 +1,37 @@
+// Conversions between `Value` and the ids and timestamps of `uuid` and
+// `chrono`. Both are strings, as their `Serialize` impls write them:
+// `"67e55044-10b1-426f-9247-bb680e5fe0c8"` and `"2023-01-01T12:00:00Z"`.
+
+use super::Value;
+
+#[cfg(feature = "chrono")]
+use chrono::{ DateTime, FixedOffset, SecondsFormat, TimeZone };
+#[cfg(feature = "uuid")]
+use uuid::Uuid;
+
+impl Value {
+    #[cfg(feature = "uuid")]
+    pub fn as_uuid(&self) -> Option<Uuid> {
+        Uuid::parse_str(self.as_str()?).ok()
+    }
+
+    // An RFC 3339 timestamp, with the offset it was written with.
+    #[cfg(feature = "chrono")]
+    pub fn as_datetime(&self) -> Option<DateTime<FixedOffset>> {
+        DateTime::parse_from_rfc3339(self.as_str()?).ok()
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl From<Uuid> for Value {
+    fn from(id: Uuid) -> Value {
+        Value::String(id.hyphenated().to_string())
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl<Tz: TimeZone> From<DateTime<Tz>> for Value where Tz::Offset: std::fmt::Display {
+    fn from(time: DateTime<Tz>) -> Value {
+        Value::String(time.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+    }
+}
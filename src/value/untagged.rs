@@ -0,0 +1,45 @@
+use super::Value;
+use crate::error::{ Error, Result };
+use serde::de::DeserializeOwned;
+
+// Deserializes an untagged enum by trying its variants in order on a buffered
+// value. Unlike `#[serde(untagged)]`, the error tells why each variant did not
+// match:
+//
+//     impl<'de> Deserialize<'de> for Shape {
+//         fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+//             Untagged::new(Value::deserialize(d)?)
+//                 .variant("Circle", Shape::Circle)
+//                 .variant("Rect", Shape::Rect)
+//                 .finish("Shape")
+//                 .map_err(D::Error::custom)
+//         }
+//     }
+pub struct Untagged<T> {
+    value: Value,
+    found: Option<T>,
+    errors: Vec<(&'static str, Error)>,
+}
+
+impl<T> Untagged<T> {
+    pub fn new(value: Value) -> Self {
+        Untagged { value, found: None, errors: Vec::new() }
+    }
+
+    // Variants after the first one that matched are not tried.
+    pub fn variant<U, F>(mut self, name: &'static str, wrap: F) -> Self
+    where U: DeserializeOwned, F: FnOnce(U) -> T {
+        if self.found.is_none() {
+            match self.value.clone().into_rust() {
+                Ok(inner) => self.found = Some(wrap(inner)),
+                Err(e) => self.errors.push((name, e)),
+            }
+        }
+
+        self
+    }
+
+    pub fn finish(self, name: &'static str) -> Result<T> {
+        self.found.ok_or(Error::NoMatchingVariant { name, errors: self.errors })
+    }
+}
@@ -0,0 +1,47 @@
+use super::Value;
+
+use crate::error::{ Error, Result };
+use serde::de::{ DeserializeSeed, Deserializer, EnumAccess, VariantAccess, Visitor };
+
+pub struct VariantAccessor {
+    pub name: String,
+    pub content: Value,
+}
+
+impl<'de> EnumAccess<'de> for VariantAccessor {
+    type Error = Error;
+    type Variant = Value;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Value)>
+    where V: DeserializeSeed<'de> {
+        let variant = seed.deserialize(Value::String(self.name))?;
+
+        Ok((variant, self.content))
+    }
+}
+
+impl<'de> VariantAccess<'de> for Value {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        match self {
+            Value::Unit => Ok(()),
+            v => Err(Error::Message(format!("Expected a unit variant, got {:?}", v))),
+        }
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+    where T: DeserializeSeed<'de> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value>
+    where V: Visitor<'de> {
+        self.deserialize_any(visitor)
+    }
+
+    fn struct_variant<V>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value>
+    where V: Visitor<'de> {
+        self.deserialize_any(visitor)
+    }
+}